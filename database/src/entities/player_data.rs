@@ -1,7 +1,8 @@
 use sea_orm::entity::prelude::*;
 use serde::Serialize;
 
-/// Structure for player data stro
+/// Structure for the user settings stored for each player by the
+/// client such as class and character details
 #[derive(Serialize, Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
 #[sea_orm(table_name = "player_data")]
 pub struct Model {
@@ -1,5 +1,6 @@
 //! SeaORM Entity. Generated by sea-orm-codegen 0.9.3
 
+use chrono::NaiveDateTime;
 use sea_orm::entity::prelude::*;
 use serde::Serialize;
 
@@ -21,6 +22,9 @@ pub struct Model {
     /// Hashed password which is omitted from serialization
     #[serde(skip)]
     pub password: String,
+    /// The date the player was created. Players created before this
+    /// was recorded will have no value
+    pub created_at: Option<NaiveDateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
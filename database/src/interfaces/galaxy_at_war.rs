@@ -2,11 +2,12 @@ use crate::{
     entities::{galaxy_at_war, players},
     DbResult, GalaxyAtWar,
 };
-use chrono::Local;
+use chrono::{Duration, Local, NaiveDateTime};
 use sea_orm::{
     ActiveModelTrait,
     ActiveValue::{NotSet, Set},
-    DatabaseConnection, IntoActiveModel, ModelTrait,
+    ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, ModelTrait, QueryFilter,
+    QueryOrder, QuerySelect,
 };
 use std::cmp;
 
@@ -74,6 +75,79 @@ impl GalaxyAtWar {
         gaw_data.update(db).await
     }
 
+    /// Replaces the group values stored on the provided galaxy at war
+    /// model with the values provided. The values are clamped within
+    /// the minimum and maximum values and the last modified time is
+    /// updated to the current time.
+    ///
+    /// `db`     The database connection
+    /// `values` The new values for each group
+    pub async fn set_values(
+        self,
+        db: &DatabaseConnection,
+        values: (u16, u16, u16, u16, u16),
+    ) -> DbResult<galaxy_at_war::Model> {
+        let current_time = Local::now().naive_local();
+        let mut gaw_data = self.into_active_model();
+        gaw_data.last_modified = Set(current_time);
+        gaw_data.group_a = Set(Self::clamp_value(values.0));
+        gaw_data.group_b = Set(Self::clamp_value(values.1));
+        gaw_data.group_c = Set(Self::clamp_value(values.2));
+        gaw_data.group_d = Set(Self::clamp_value(values.3));
+        gaw_data.group_e = Set(Self::clamp_value(values.4));
+        gaw_data.update(db).await
+    }
+
+    /// Clamps the provided group value within the minimum and
+    /// maximum galaxy at war values
+    ///
+    /// `value` The value to clamp
+    pub(crate) fn clamp_value(value: u16) -> u16 {
+        value.clamp(Self::MIN_VALUE, Self::MAX_VALUE)
+    }
+
+    /// Applies the provided galaxy at war decay value to all the galaxy
+    /// at war models that have whole days of decay to apply. Models are
+    /// loaded and updated in batches of the provided size. Returns the
+    /// number of models that were decayed.
+    ///
+    /// `db`         The database connection
+    /// `decay`      The decay value
+    /// `batch_size` The number of models to load at a time
+    pub async fn decay_all(db: &DatabaseConnection, decay: f32, batch_size: u64) -> DbResult<u64> {
+        // Skip decaying if decay is non existent
+        if decay <= 0.0 {
+            return Ok(0);
+        }
+
+        let current_time = Local::now().naive_local();
+        let mut last_id = 0;
+        let mut decayed = 0;
+
+        loop {
+            let batch: Vec<Self> = galaxy_at_war::Entity::find()
+                .filter(galaxy_at_war::Column::Id.gt(last_id))
+                .order_by_asc(galaxy_at_war::Column::Id)
+                .limit(batch_size)
+                .all(db)
+                .await?;
+
+            let Some(last) = batch.last() else {
+                break;
+            };
+            last_id = last.id;
+
+            for value in batch {
+                if let Some(model) = value.decayed(decay, current_time) {
+                    model.update(db).await?;
+                    decayed += 1;
+                }
+            }
+        }
+
+        Ok(decayed)
+    }
+
     /// Applies the provided galaxy at war decay value to the provided
     /// galaxy at war model decreasing the values by the number of days
     /// that have passed.
@@ -82,30 +156,172 @@ impl GalaxyAtWar {
     /// `value` The galaxy at war model to decay
     /// `decay` The decay value
     async fn apply_decay(self, db: &DatabaseConnection, decay: f32) -> DbResult<Self> {
+        let current_time = Local::now().naive_local();
+        match self.decayed(decay, current_time) {
+            Some(value) => value.update(db).await,
+            None => Ok(self),
+        }
+    }
+
+    /// Creates the updated model for this galaxy at war model with the
+    /// provided decay value applied for each whole day that has passed
+    /// since it was last modified. The last modified time is moved forward
+    /// by the days that were applied so the same days aren't decayed again.
+    /// Returns None if there is no decay to apply.
+    ///
+    /// `decay`        The decay value
+    /// `current_time` The current time
+    fn decayed(
+        &self,
+        decay: f32,
+        current_time: NaiveDateTime,
+    ) -> Option<galaxy_at_war::ActiveModel> {
         // Skip decaying if decay is non existent
         if decay <= 0.0 {
-            return Ok(self);
+            return None;
         }
 
-        let current_time = Local::now().naive_local();
-        let days_passed = (current_time - self.last_modified).num_days() as f32;
-        let decay_value = (decay * days_passed * 100.0) as u16;
+        let days_passed = (current_time - self.last_modified).num_days();
+        if days_passed <= 0 {
+            return None;
+        }
+
+        let decay_value = (decay * days_passed as f32 * 100.0) as u16;
 
         // Apply decay while keeping minimum
-        let a = cmp::max(self.group_a - decay_value, Self::MIN_VALUE);
-        let b = cmp::max(self.group_b - decay_value, Self::MIN_VALUE);
-        let c = cmp::max(self.group_c - decay_value, Self::MIN_VALUE);
-        let d = cmp::max(self.group_d - decay_value, Self::MIN_VALUE);
-        let e = cmp::max(self.group_e - decay_value, Self::MIN_VALUE);
-
-        // Update stored copy
-        let mut value = self.into_active_model();
-        value.group_a = Set(a);
-        value.group_b = Set(b);
-        value.group_c = Set(c);
-        value.group_d = Set(d);
-        value.group_e = Set(e);
-
-        value.update(db).await
+        let apply = |value: u16| cmp::max(value.saturating_sub(decay_value), Self::MIN_VALUE);
+
+        let mut value = self.clone().into_active_model();
+        value.last_modified = Set(self.last_modified + Duration::days(days_passed));
+        value.group_a = Set(apply(self.group_a));
+        value.group_b = Set(apply(self.group_b));
+        value.group_c = Set(apply(self.group_c));
+        value.group_d = Set(apply(self.group_d));
+        value.group_e = Set(apply(self.group_e));
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{entities::galaxy_at_war, GalaxyAtWar, Player};
+    use chrono::{Duration, Local, NaiveDateTime};
+    use sea_orm::{
+        ActiveModelTrait,
+        ActiveValue::{NotSet, Set},
+        EntityTrait,
+    };
+
+    /// Tests that stale values are decayed towards the minimum without
+    /// underflowing and that the decayed days are only applied once
+    #[test]
+    fn test_decayed() {
+        let current_time = NaiveDateTime::default() + Duration::days(30);
+        let value = GalaxyAtWar {
+            id: 1,
+            player_id: 1,
+            last_modified: current_time - Duration::days(3) - Duration::hours(2),
+            group_a: 5500,
+            group_b: 5100,
+            group_c: 5000,
+            group_d: 10099,
+            group_e: 100,
+        };
+
+        let model = value.decayed(0.5, current_time).unwrap();
+        assert_eq!(model.group_a, Set(5350));
+        assert_eq!(model.group_b, Set(5000));
+        assert_eq!(model.group_c, Set(5000));
+        assert_eq!(model.group_d, Set(9949));
+        assert_eq!(model.group_e, Set(5000));
+        assert_eq!(model.last_modified, Set(current_time - Duration::hours(2)));
+
+        // Large decay values saturate rather than underflowing
+        let model = value.decayed(1000.0, current_time).unwrap();
+        assert_eq!(model.group_d, Set(5000));
+
+        // Values modified within the last day aren't decayed
+        let recent = GalaxyAtWar {
+            last_modified: current_time - Duration::hours(23),
+            ..value.clone()
+        };
+        assert!(recent.decayed(0.5, current_time).is_none());
+        assert!(value.decayed(0.0, current_time).is_none());
+    }
+
+    /// Tests that decaying all the models across multiple batches decays
+    /// the stale models clamping them to the minimum and leaves the fresh
+    /// models unchanged
+    #[tokio::test]
+    async fn test_decay_all() {
+        let db = crate::connect_memory().await;
+        let current_time = Local::now().naive_local();
+
+        let mut stale = Vec::new();
+        let mut fresh = Vec::new();
+        for index in 0..5 {
+            let player = Player::create(
+                &db,
+                format!("gaw{index}@test.com"),
+                format!("Gaw{index}"),
+                String::new(),
+                false,
+            )
+            .await
+            .unwrap();
+
+            let is_stale = index % 2 == 0;
+            let last_modified = if is_stale {
+                current_time - Duration::days(3) - Duration::hours(1)
+            } else {
+                current_time - Duration::hours(1)
+            };
+            let model = galaxy_at_war::ActiveModel {
+                id: NotSet,
+                player_id: Set(player.id),
+                last_modified: Set(last_modified),
+                group_a: Set(5500),
+                group_b: Set(5100),
+                group_c: Set(5000),
+                group_d: Set(10099),
+                group_e: Set(7000),
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+
+            if is_stale {
+                stale.push(model);
+            } else {
+                fresh.push(model);
+            }
+        }
+
+        // Batches of two so the models are spread across three batches
+        let decayed = GalaxyAtWar::decay_all(&db, 0.5, 2).await.unwrap();
+        assert_eq!(decayed, stale.len() as u64);
+
+        for model in stale {
+            let value = galaxy_at_war::Entity::find_by_id(model.id)
+                .one(&db)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(value.group_a, 5350);
+            assert_eq!(value.group_b, 5000);
+            assert_eq!(value.group_c, 5000);
+            assert_eq!(value.group_d, 9949);
+            assert_eq!(value.group_e, 6850);
+            assert_eq!(value.last_modified, model.last_modified + Duration::days(3));
+        }
+
+        for model in fresh {
+            let value = galaxy_at_war::Entity::find_by_id(model.id)
+                .one(&db)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(value, model);
+        }
     }
 }
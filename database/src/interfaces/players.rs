@@ -1,15 +1,65 @@
 use crate::{
-    entities::{player_data, players, PlayerData},
-    DbResult, Player,
+    entities::{galaxy_at_war, player_data, players, PlayerData},
+    DbResult, GalaxyAtWar, Player,
 };
+use chrono::{Local, NaiveDateTime};
 use sea_orm::{
+    sea_query::{Expr, LikeExpr},
     ActiveModelTrait,
     ActiveValue::{NotSet, Set},
-    ColumnTrait, CursorTrait, DatabaseConnection, DeleteResult, EntityTrait, IntoActiveModel,
-    ModelTrait, QueryFilter,
+    ColumnTrait, Condition, CursorTrait, DatabaseConnection, DeleteResult, EntityTrait,
+    IntoActiveModel, ModelTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
+    TransactionTrait,
 };
 use std::iter::Iterator;
 
+/// Details for a player being imported from another server
+pub struct ImportPlayer {
+    /// The player account email
+    pub email: String,
+    /// The player display name
+    pub display_name: String,
+    /// The hashed player password
+    pub password: String,
+    /// The galaxy at war values for the player if they
+    /// are being imported
+    pub galaxy_at_war: Option<(u16, u16, u16, u16, u16)>,
+    /// The date the player was created on the other server if
+    /// known otherwise the current date is used
+    pub created_at: Option<NaiveDateTime>,
+}
+
+/// The result of importing a single player
+pub enum ImportResult {
+    /// The player was created
+    Created(Player),
+    /// The player was skipped because the email is already in use
+    DuplicateEmail,
+    /// The player was skipped because the display name is already in use
+    DuplicateDisplayName,
+}
+
+/// Character used to escape wildcards within LIKE patterns
+const LIKE_ESCAPE: char = '\\';
+
+/// Creates a LIKE pattern matching values that contain the provided
+/// text. The LIKE wildcards within the text are escaped so that they
+/// are matched literally
+///
+/// `value` The text to match
+fn contains_pattern(value: &str) -> String {
+    let mut pattern = String::with_capacity(value.len() + 2);
+    pattern.push('%');
+    for char in value.chars() {
+        if matches!(char, '%' | '_') || char == LIKE_ESCAPE {
+            pattern.push(LIKE_ESCAPE);
+        }
+        pattern.push(char);
+    }
+    pattern.push('%');
+    pattern
+}
+
 impl Player {
     /// The length of player session tokens
     const TOKEN_LENGTH: usize = 128;
@@ -40,6 +90,42 @@ impl Player {
         Ok((values, is_more))
     }
 
+    /// Searches for the players whose display name or email contains the
+    /// provided query ordered by their ID. Returns the players within the
+    /// offset and count along with the total number of matching players
+    ///
+    /// `db`     The database connection
+    /// `query`  The text to search for
+    /// `offset` The number of rows to skip
+    /// `count`  The number of rows to collect
+    pub async fn search(
+        db: &DatabaseConnection,
+        query: &str,
+        offset: u64,
+        count: u64,
+    ) -> DbResult<(Vec<Self>, u64)> {
+        let pattern = contains_pattern(query);
+        let select = players::Entity::find().filter(
+            Condition::any()
+                .add(
+                    Expr::col(players::Column::DisplayName)
+                        .like(LikeExpr::str(&pattern).escape(LIKE_ESCAPE)),
+                )
+                .add(
+                    Expr::col(players::Column::Email)
+                        .like(LikeExpr::str(&pattern).escape(LIKE_ESCAPE)),
+                ),
+        );
+        let total = select.clone().count(db).await?;
+        let values = select
+            .order_by_asc(players::Column::Id)
+            .offset(offset)
+            .limit(count)
+            .all(db)
+            .await?;
+        Ok((values, total))
+    }
+
     /// Creates a new player with the proivded details and inserts
     /// it into the database
     ///
@@ -60,11 +146,104 @@ impl Player {
             display_name: Set(display_name),
             origin: Set(origin),
             password: Set(password),
+            created_at: Set(Some(Local::now().naive_local())),
             ..Default::default()
         };
         active_model.insert(db).await
     }
 
+    /// Creates all the provided players within a single transaction
+    /// along with their galaxy at war values. Players with emails or
+    /// display names that are already in use, including by players
+    /// earlier in the list, are skipped. Returns the result for each
+    /// of the provided players
+    ///
+    /// `db`      The database connection
+    /// `players` The players to create
+    pub async fn import(
+        db: &DatabaseConnection,
+        players: Vec<ImportPlayer>,
+    ) -> DbResult<Vec<ImportResult>> {
+        let txn = db.begin().await?;
+        let mut created = Vec::with_capacity(players.len());
+        for value in players {
+            let existing = players::Entity::find()
+                .filter(players::Column::Email.eq(&value.email))
+                .one(&txn)
+                .await?;
+            if existing.is_some() {
+                created.push(ImportResult::DuplicateEmail);
+                continue;
+            }
+
+            let existing = players::Entity::find()
+                .filter(players::Column::DisplayName.eq(&value.display_name))
+                .one(&txn)
+                .await?;
+            if existing.is_some() {
+                created.push(ImportResult::DuplicateDisplayName);
+                continue;
+            }
+
+            let player = players::ActiveModel {
+                email: Set(value.email),
+                display_name: Set(value.display_name),
+                origin: Set(false),
+                password: Set(value.password),
+                created_at: Set(Some(
+                    value
+                        .created_at
+                        .unwrap_or_else(|| Local::now().naive_local()),
+                )),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+
+            if let Some(values) = value.galaxy_at_war {
+                galaxy_at_war::ActiveModel {
+                    id: NotSet,
+                    player_id: Set(player.id),
+                    last_modified: Set(Local::now().naive_local()),
+                    group_a: Set(GalaxyAtWar::clamp_value(values.0)),
+                    group_b: Set(GalaxyAtWar::clamp_value(values.1)),
+                    group_c: Set(GalaxyAtWar::clamp_value(values.2)),
+                    group_d: Set(GalaxyAtWar::clamp_value(values.3)),
+                    group_e: Set(GalaxyAtWar::clamp_value(values.4)),
+                }
+                .insert(&txn)
+                .await?;
+            }
+
+            created.push(ImportResult::Created(player));
+        }
+        txn.commit().await?;
+        Ok(created)
+    }
+
+    /// Counts the total number of players
+    ///
+    /// `db` The database connection
+    pub async fn count(db: &DatabaseConnection) -> DbResult<u64> {
+        players::Entity::find().count(db).await
+    }
+
+    /// Counts the number of players that were created on or after
+    /// the provided date. Players without a known creation date are
+    /// not included
+    ///
+    /// `db`    The database connection
+    /// `since` The date to count players created from
+    pub async fn count_created_since(
+        db: &DatabaseConnection,
+        since: NaiveDateTime,
+    ) -> DbResult<u64> {
+        players::Entity::find()
+            .filter(players::Column::CreatedAt.gte(since))
+            .count(db)
+            .await
+    }
+
     /// Deletes the provided player
     ///
     /// `db` The database connection
@@ -215,6 +394,20 @@ impl Player {
         active.update(db).await
     }
 
+    /// Sets the display name of the player
+    ///
+    /// `db`           The database connection
+    /// `display_name` The new display name
+    pub async fn set_display_name(
+        self,
+        db: &DatabaseConnection,
+        display_name: String,
+    ) -> DbResult<Self> {
+        let mut active = self.into_active_model();
+        active.display_name = Set(display_name);
+        active.update(db).await
+    }
+
     /// Parses the challenge points value which is the second
     /// item in the completion list.
     pub async fn get_challenge_points(&self, db: &DatabaseConnection) -> Option<u32> {
@@ -224,6 +417,50 @@ impl Player {
         Some(value)
     }
 
+    /// Increases the challenge points value stored in the completion list
+    /// by the provided amount. The new value is only written if the stored
+    /// list hasn't changed since it was read otherwise the update is tried
+    /// again so that concurrent increments aren't lost. Returns the new
+    /// challenge points value or None if the player has no completion data.
+    ///
+    /// `db`     The database connection
+    /// `amount` The amount to increase by
+    pub async fn increase_challenge_points(
+        &self,
+        db: &DatabaseConnection,
+        amount: u32,
+    ) -> DbResult<Option<u32>> {
+        loop {
+            let data = self
+                .find_related(player_data::Entity)
+                .filter(player_data::Column::Key.eq("Completion"))
+                .one(db)
+                .await?;
+
+            let Some(data) = data else {
+                return Ok(None);
+            };
+
+            let mut parts: Vec<String> = data.value.split(',').map(String::from).collect();
+            let Some(part) = parts.get_mut(1) else {
+                return Ok(None);
+            };
+            let value = part.parse::<u32>().unwrap_or(0).saturating_add(amount);
+            *part = value.to_string();
+
+            let result = player_data::Entity::update_many()
+                .col_expr(player_data::Column::Value, Expr::value(parts.join(",")))
+                .filter(player_data::Column::Id.eq(data.id))
+                .filter(player_data::Column::Value.eq(data.value))
+                .exec(db)
+                .await?;
+
+            if result.rows_affected > 0 {
+                return Ok(Some(value));
+            }
+        }
+    }
+
     /// Attempts to find a player with the provided ID will return none
     /// if there was no players with that ID
     ///
@@ -283,6 +520,22 @@ impl Player {
             .map(|value| value.is_some())
     }
 
+    /// Checks whether the provided display name is taken by any
+    /// accounts in the database including origin accounts.
+    ///
+    /// `db`           The datbase instance
+    /// `display_name` The display name to check for
+    pub async fn is_display_name_taken(
+        db: &DatabaseConnection,
+        display_name: &str,
+    ) -> DbResult<bool> {
+        players::Entity::find()
+            .filter(players::Column::DisplayName.eq(display_name))
+            .one(db)
+            .await
+            .map(|value| value.is_some())
+    }
+
     /// Attempts to find a player by the provided session token
     ///
     /// `db`    The database instance
@@ -307,6 +560,21 @@ impl Player {
         Ok((player, token))
     }
 
+    /// Replaces the session token for the provided player with a newly
+    /// generated token so that the previous token can no longer be used
+    /// returning both the player model and the new session token
+    ///
+    /// `db`     The database instance
+    /// `gen_fn` Function for generating the new token
+    pub async fn rotate_token(
+        self,
+        db: &DatabaseConnection,
+        gen_fn: fn(usize) -> String,
+    ) -> DbResult<(Self, String)> {
+        let token = gen_fn(Self::TOKEN_LENGTH);
+        self.set_token(db, token).await
+    }
+
     /// Attempts to get the existing session token for the provided
     /// player or creates a new session token if there is not already
     /// one will return both the player model and session token
@@ -330,3 +598,15 @@ impl Player {
         Ok((self, token))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::contains_pattern;
+
+    #[test]
+    fn test_contains_pattern() {
+        assert_eq!(contains_pattern("Shep"), "%Shep%");
+        assert_eq!(contains_pattern("100%"), "%100\\%%");
+        assert_eq!(contains_pattern("a_b\\c"), "%a\\_b\\\\c%");
+    }
+}
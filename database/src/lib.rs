@@ -1,9 +1,11 @@
 use log::{debug, info};
 use migration::{Migrator, MigratorTrait};
-use sea_orm::Database as SeaDatabase;
+use sea_orm::{ConnectOptions, Database as SeaDatabase};
 use std::{
+    fmt::Display,
     fs::{create_dir_all, File},
     path::Path,
+    time::Duration,
 };
 
 mod entities;
@@ -16,6 +18,9 @@ pub use entities::{GalaxyAtWar, Player, PlayerData};
 // Re-exports of database types
 pub use sea_orm::DatabaseConnection;
 pub use sea_orm::DbErr;
+pub use sea_orm::RuntimeErr;
+pub use sea_orm::TransactionTrait;
+pub use sqlx::Error as SqlxError;
 
 /// Database error result type
 pub type DbResult<T> = Result<T, DbErr>;
@@ -29,20 +34,98 @@ pub enum DatabaseType {
     MySQL(String),
 }
 
+/// Options for the database connection pool. Options that are
+/// not set use the sea_orm defaults
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PoolOptions {
+    /// The maximum number of connections in the pool
+    pub max_connections: Option<u32>,
+    /// The minimum number of idle connections to keep in the pool
+    pub min_connections: Option<u32>,
+    /// The maximum time to wait when acquiring a connection
+    pub acquire_timeout: Option<Duration>,
+}
+
+impl PoolOptions {
+    /// Creates new pool options from the provided values where
+    /// zero values are treated as not set
+    ///
+    /// `max_connections` The maximum number of connections
+    /// `min_connections` The minimum number of idle connections
+    /// `acquire_timeout` The acquire timeout in seconds
+    pub fn new(max_connections: u32, min_connections: u32, acquire_timeout: u64) -> Self {
+        Self {
+            max_connections: (max_connections > 0).then_some(max_connections),
+            min_connections: (min_connections > 0).then_some(min_connections),
+            acquire_timeout: (acquire_timeout > 0).then(|| Duration::from_secs(acquire_timeout)),
+        }
+    }
+
+    /// Applies these pool options to the provided connect options
+    ///
+    /// `options` The connect options to apply to
+    fn apply(&self, options: &mut ConnectOptions) {
+        if let Some(value) = self.max_connections {
+            options.max_connections(value);
+        }
+        if let Some(value) = self.min_connections {
+            options.min_connections(value);
+        }
+        if let Some(value) = self.acquire_timeout {
+            options.acquire_timeout(value);
+        }
+    }
+}
+
+impl Display for PoolOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn value<T: Display>(value: Option<T>) -> String {
+            value
+                .map(|value| value.to_string())
+                .unwrap_or_else(|| "default".to_string())
+        }
+
+        write!(
+            f,
+            "Max connections: {}, Min connections: {}, Acquire timeout: {}",
+            value(self.max_connections),
+            value(self.min_connections),
+            value(
+                self.acquire_timeout
+                    .map(|value| format!("{}s", value.as_secs()))
+            )
+        )
+    }
+}
+
 /// Connects to the database returning a Database connection
 /// which allows accessing the database without accessing sea_orm
 ///
-/// `ty` The type of database to connect to
-pub async fn connect(ty: DatabaseType) -> DatabaseConnection {
+/// `ty`   The type of database to connect to
+/// `pool` The options for the connection pool
+pub async fn connect(ty: DatabaseType, pool: PoolOptions) -> DatabaseConnection {
     let url = match ty {
         DatabaseType::Sqlite(file) => init_sqlite(file),
         DatabaseType::MySQL(url) => url,
     };
-    let connection = SeaDatabase::connect(&url)
+    connect_url(url, pool).await
+}
+
+/// Connects to the database at the provided URL and runs
+/// the migrations
+///
+/// `url`  The database connection URL
+/// `pool` The options for the connection pool
+async fn connect_url(url: String, pool: PoolOptions) -> DatabaseConnection {
+    let mut options = ConnectOptions::new(url.clone());
+    pool.apply(&mut options);
+
+    let connection = SeaDatabase::connect(options)
         .await
         .expect("Unable to create database connection");
 
     info!("Connected to database: {url}");
+    info!("Database pool options ({pool})");
     debug!("Running migrations...");
 
     Migrator::up(&connection, None)
@@ -53,6 +136,20 @@ pub async fn connect(ty: DatabaseType) -> DatabaseConnection {
     connection
 }
 
+/// Connects to a new in-memory SQLite database with the migrations
+/// applied. Each connection is to a separate database which is discarded
+/// once the connection is dropped so this is used for tests that need
+/// a database
+pub async fn connect_memory() -> DatabaseConnection {
+    // A connection is kept open as the database is removed when
+    // its last connection is closed
+    let pool = PoolOptions {
+        min_connections: Some(1),
+        ..Default::default()
+    };
+    connect_url("sqlite::memory:".to_string(), pool).await
+}
+
 /// Initializes the SQLite database file at the provided
 /// file path ensuring that the parent directories and the
 /// database file itself exist. Appends the sqlite: prefix
@@ -71,3 +168,23 @@ fn init_sqlite(file: String) -> String {
     }
     format!("sqlite:{file}")
 }
+
+#[cfg(test)]
+mod test {
+    use super::PoolOptions;
+    use std::time::Duration;
+
+    #[test]
+    fn test_pool_options() {
+        assert_eq!(PoolOptions::new(0, 0, 0), PoolOptions::default());
+
+        let options = PoolOptions::new(32, 4, 10);
+        assert_eq!(options.max_connections, Some(32));
+        assert_eq!(options.min_connections, Some(4));
+        assert_eq!(options.acquire_timeout, Some(Duration::from_secs(10)));
+        assert_eq!(
+            options.to_string(),
+            "Max connections: 32, Min connections: 4, Acquire timeout: 10s"
+        );
+    }
+}
@@ -3,6 +3,7 @@ pub use sea_orm_migration::prelude::*;
 mod m20221015_142649_players_table;
 mod m20221015_153750_galaxy_at_war_table;
 mod m20221222_174733_player_data;
+mod m20230301_000000_players_created_at;
 
 pub struct Migrator;
 
@@ -13,6 +14,7 @@ impl MigratorTrait for Migrator {
             Box::new(m20221015_142649_players_table::Migration),
             Box::new(m20221015_153750_galaxy_at_war_table::Migration),
             Box::new(m20221222_174733_player_data::Migration),
+            Box::new(m20230301_000000_players_created_at::Migration),
         ]
     }
 }
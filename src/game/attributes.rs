@@ -0,0 +1,137 @@
+//! Limits on the size of game attribute maps sent by clients. Attributes
+//! are broadcast to every player in the game and included in every game
+//! snapshot so oversized maps are truncated before they are stored
+
+use super::AttrMap;
+use crate::utils::{env, types::GameID};
+use log::warn;
+
+/// Limits applied to game attributes. Limits of zero are treated
+/// as no limit
+pub struct AttributeLimits {
+    /// The maximum number of attributes a game can have
+    pub max_count: usize,
+    /// The maximum length of an attribute key
+    pub max_key_length: usize,
+    /// The maximum length of an attribute value
+    pub max_value_length: usize,
+}
+
+impl AttributeLimits {
+    /// Loads the attribute limits from the environment
+    pub fn from_env() -> Self {
+        Self {
+            max_count: env::from_env(env::GAME_MAX_ATTRIBUTES),
+            max_key_length: env::from_env(env::GAME_MAX_ATTRIBUTE_KEY_LENGTH),
+            max_value_length: env::from_env(env::GAME_MAX_ATTRIBUTE_VALUE_LENGTH),
+        }
+    }
+
+    /// Checks whether the provided key or value exceeds the
+    /// length limits
+    ///
+    /// `key`   The attribute key
+    /// `value` The attribute value
+    fn is_oversized(&self, key: &str, value: &str) -> bool {
+        (self.max_key_length > 0 && key.len() > self.max_key_length)
+            || (self.max_value_length > 0 && value.len() > self.max_value_length)
+    }
+
+    /// Truncates the provided attributes so that they are within the
+    /// limits when merged with the existing attributes of the game.
+    /// Attributes with oversized keys or values are dropped and new
+    /// keys past the maximum count are dropped. Existing keys can
+    /// always be updated. Logs a warning when anything is dropped.
+    ///
+    /// `game_id`    The ID of the game the attributes are for
+    /// `existing`   The existing attributes of the game
+    /// `attributes` The attributes to limit
+    pub fn apply(&self, game_id: GameID, existing: &AttrMap, attributes: AttrMap) -> AttrMap {
+        let mut count = existing.len();
+        let mut dropped = 0;
+        let mut output = AttrMap::with_capacity(attributes.len());
+
+        for (key, value) in attributes {
+            if self.is_oversized(&key, &value) {
+                dropped += 1;
+                continue;
+            }
+
+            let is_new = existing.get(&key).is_none() && output.get(&key).is_none();
+            if is_new {
+                if self.max_count > 0 && count >= self.max_count {
+                    dropped += 1;
+                    continue;
+                }
+                count += 1;
+            }
+
+            output.insert(key, value);
+        }
+
+        if dropped > 0 {
+            warn!(
+                "Dropped oversized game attributes (GID: {}, Dropped: {})",
+                game_id, dropped
+            );
+        }
+
+        output
+    }
+}
+
+impl Default for AttributeLimits {
+    fn default() -> Self {
+        Self {
+            max_count: env::GAME_MAX_ATTRIBUTES.1,
+            max_key_length: env::GAME_MAX_ATTRIBUTE_KEY_LENGTH.1,
+            max_value_length: env::GAME_MAX_ATTRIBUTE_VALUE_LENGTH.1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AttributeLimits;
+    use crate::game::AttrMap;
+
+    /// Tests that oversized attributes and attributes past the maximum
+    /// count are dropped while existing keys can still be updated
+    #[test]
+    fn test_apply() {
+        let limits = AttributeLimits {
+            max_count: 2,
+            max_key_length: 8,
+            max_value_length: 4,
+        };
+
+        let mut existing = AttrMap::default();
+        existing.insert("ME3map".to_string(), "map2".to_string());
+
+        let mut attributes = AttrMap::default();
+        attributes.insert("ME3map".to_string(), "map5".to_string());
+        attributes.insert("ME3privacyLong".to_string(), "OPEN".to_string());
+        attributes.insert("ME3diff".to_string(), "difficulty0".to_string());
+        attributes.insert("ME3enemy".to_string(), "e1".to_string());
+        attributes.insert("ME3extra".to_string(), "x".to_string());
+
+        let output = limits.apply(1, &existing, attributes);
+        assert_eq!(output.len(), 2);
+        assert_eq!(output.get("ME3map"), Some(&"map5".to_string()));
+        assert_eq!(output.get("ME3enemy"), Some(&"e1".to_string()));
+        assert_eq!(output.get("ME3privacyLong"), None);
+        assert_eq!(output.get("ME3diff"), None);
+        assert_eq!(output.get("ME3extra"), None);
+
+        // Zero limits are treated as no limit
+        let limits = AttributeLimits {
+            max_count: 0,
+            max_key_length: 0,
+            max_value_length: 0,
+        };
+        let mut attributes = AttrMap::default();
+        attributes.insert("ME3privacyLong".to_string(), "difficulty0".to_string());
+        let output = limits.apply(1, &existing, attributes);
+        assert_eq!(output.len(), 1);
+    }
+}
@@ -0,0 +1,73 @@
+//! Module for the game configuration which is loaded from the
+//! environment once when the server starts and shared between games
+
+use super::attributes::AttributeLimits;
+use crate::utils::env;
+use std::time::Duration;
+
+/// Configuration shared by every game
+pub struct GameConfig {
+    /// The key of the attribute used as the display label for
+    /// games. An empty key disables labels
+    pub label_attribute: String,
+    /// Whether players are only matched into games where their
+    /// NAT type is compatible with the host
+    pub nat_check: bool,
+    /// Mask of the bits that are allowed in game settings
+    pub setting_mask: u16,
+    /// Limits applied to the attributes of games
+    pub attribute_limits: AttributeLimits,
+    /// How long the slots of players who lost their connection are
+    /// held for. Zero when slots aren't held
+    pub reconnect_grace: Duration,
+    /// Whether only the host and admins are allowed to modify
+    /// games
+    pub enforce_admins: bool,
+    /// The max number of games to snapshot at once
+    pub snapshot_concurrency: usize,
+    /// Whether the matchmaking queue is checked against games again
+    /// when their attributes change
+    pub queue_on_attribute_change: bool,
+    /// How long players can rejoin the games they were in after
+    /// their session was lost. Zero when players can't rejoin
+    pub rejoin_window: Duration,
+    /// The largest max number of players that games can be
+    /// created with
+    pub max_players: usize,
+}
+
+impl GameConfig {
+    /// Loads the game configuration from the environment
+    /// variables falling back to the defaults
+    pub fn from_env() -> Self {
+        Self {
+            label_attribute: env::env(env::GAME_LABEL_ATTRIBUTE),
+            nat_check: env::from_env(env::NAT_CHECK),
+            setting_mask: env::from_env(env::GAME_SETTING_MASK),
+            attribute_limits: AttributeLimits::from_env(),
+            reconnect_grace: Duration::from_secs(env::from_env(env::RECONNECT_GRACE)),
+            enforce_admins: env::from_env(env::ENFORCE_GAME_ADMINS),
+            snapshot_concurrency: env::from_env(env::SNAPSHOT_CONCURRENCY),
+            queue_on_attribute_change: env::from_env(env::QUEUE_ON_ATTRIBUTE_CHANGE),
+            rejoin_window: Duration::from_secs(env::from_env(env::REJOIN_WINDOW)),
+            max_players: env::from_env(env::GAME_MAX_PLAYERS),
+        }
+    }
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            label_attribute: env::GAME_LABEL_ATTRIBUTE.1.to_string(),
+            nat_check: env::NAT_CHECK.1,
+            setting_mask: env::GAME_SETTING_MASK.1,
+            attribute_limits: AttributeLimits::default(),
+            reconnect_grace: Duration::from_secs(env::RECONNECT_GRACE.1),
+            enforce_admins: env::ENFORCE_GAME_ADMINS.1,
+            snapshot_concurrency: env::SNAPSHOT_CONCURRENCY.1,
+            queue_on_attribute_change: env::QUEUE_ON_ATTRIBUTE_CHANGE.1,
+            rejoin_window: Duration::from_secs(env::REJOIN_WINDOW.1),
+            max_players: env::GAME_MAX_PLAYERS.1,
+        }
+    }
+}
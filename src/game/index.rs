@@ -0,0 +1,97 @@
+//! Index of the games that each player is currently in so that
+//! a player's games can be found without checking every game
+
+use crate::utils::types::{GameID, PlayerID};
+use std::{
+    collections::HashMap,
+    sync::{PoisonError, RwLock},
+};
+
+/// Shared map of player IDs to the IDs of the games they are in. Players
+/// are able to be in multiple games at once when multi game is enabled.
+/// Games update the index as players are added and removed.
+#[derive(Default)]
+pub struct PlayerIndex {
+    /// Map of player IDs to the game IDs in the order they were joined
+    values: RwLock<HashMap<PlayerID, Vec<GameID>>>,
+}
+
+impl PlayerIndex {
+    /// Stores the game that the provided player has joined
+    ///
+    /// `player_id` The ID of the player
+    /// `game_id`   The ID of the game the player joined
+    pub fn insert(&self, player_id: PlayerID, game_id: GameID) {
+        // The index is left consistent by every update so a panic while
+        // holding the lock doesn't need to stop the index being used
+        let values = &mut *self.values.write().unwrap_or_else(PoisonError::into_inner);
+        let games = values.entry(player_id).or_default();
+        if !games.contains(&game_id) {
+            games.push(game_id);
+        }
+    }
+
+    /// Removes the provided game from the games of the provided player.
+    /// The other games the player is in are left untouched.
+    ///
+    /// `player_id` The ID of the player
+    /// `game_id`   The ID of the game the player left
+    pub fn remove(&self, player_id: PlayerID, game_id: GameID) {
+        let values = &mut *self.values.write().unwrap_or_else(PoisonError::into_inner);
+        if let Some(games) = values.get_mut(&player_id) {
+            games.retain(|value| *value != game_id);
+            if games.is_empty() {
+                values.remove(&player_id);
+            }
+        }
+    }
+
+    /// Finds the IDs of the games the provided player is in
+    ///
+    /// `player_id` The ID of the player
+    pub fn get(&self, player_id: PlayerID) -> Vec<GameID> {
+        let values = self.values.read().unwrap_or_else(PoisonError::into_inner);
+        values.get(&player_id).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PlayerIndex;
+    use std::{sync::Arc, thread};
+
+    /// Tests that players are indexed in each game they join and that
+    /// removing a player only applies to the game they left
+    #[test]
+    fn test_multiple_games() {
+        let index = PlayerIndex::default();
+        index.insert(1, 10);
+        index.insert(1, 11);
+        index.insert(1, 11);
+        assert_eq!(index.get(1), vec![10, 11]);
+
+        index.remove(1, 10);
+        assert_eq!(index.get(1), vec![11]);
+
+        index.remove(1, 11);
+        assert!(index.get(1).is_empty());
+    }
+
+    /// Tests that the index is still updated after a thread panicked
+    /// while holding its lock
+    #[test]
+    fn test_poisoned() {
+        let index = Arc::new(PlayerIndex::default());
+        let poison = index.clone();
+        thread::spawn(move || {
+            let _values = poison.values.write().unwrap();
+            panic!("Poisoning index lock");
+        })
+        .join()
+        .ok();
+        assert!(index.values.is_poisoned());
+
+        index.insert(1, 10);
+        assert_eq!(index.get(1), vec![10]);
+    }
+}
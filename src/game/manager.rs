@@ -1,18 +1,32 @@
 use super::{
-    player::GamePlayer, rules::RuleSet, Game, GameAddr, GameJoinableState, GameModifyAction,
-    GameSnapshot, RemovePlayerType,
+    config::GameConfig, index::PlayerIndex, models::MatchmakingFailed, player::GamePlayer,
+    rules::RuleSet, Game, GameAddr, GameJoinableState, GameModifyAction, GameSnapshot,
+    RemovePlayerType,
 };
-use crate::utils::types::{GameID, SessionID};
-use blaze_pk::types::TdfMap;
-use log::debug;
+use crate::{
+    state::GlobalState,
+    utils::{
+        components::{Components, GameManager},
+        env,
+        time::unix_millis,
+        types::{GameID, PlayerID, SessionID},
+    },
+};
+use blaze_pk::{packet::Packet, types::TdfMap};
+use log::{debug, warn};
+use serde::Serialize;
 use std::{
-    collections::{HashMap, VecDeque},
-    sync::atomic::{AtomicU32, Ordering},
-    time::SystemTime,
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
 };
 use tokio::{
-    sync::{Mutex, RwLock},
-    task::JoinSet,
+    sync::{oneshot, Mutex, Notify, RwLock},
+    task::{JoinHandle, JoinSet},
+    time::{interval, sleep},
 };
 
 /// Structure for managing games and the matchmaking queue
@@ -21,8 +35,82 @@ pub struct Games {
     games: RwLock<HashMap<GameID, GameAddr>>,
     /// Queue of players wanting to join games
     queue: Mutex<VecDeque<QueueEntry>>,
-    /// ID for the next game to create
+    /// Offset from the ID base of the next game to create
     id: AtomicU32,
+    /// The first ID that games are created with
+    id_base: GameID,
+    /// The number of IDs available from the ID base
+    id_span: u32,
+    /// The max number of entries in the matchmaking queue or
+    /// zero for no limit
+    max_queue: usize,
+    /// Map of player IDs to the games they were in when their
+    /// session was lost so they can be placed back on resume. This
+    /// is written while the session is dropped so that a quick resume
+    /// always sees the entry
+    rejoin: std::sync::Mutex<HashMap<PlayerID, RejoinEntry>>,
+    /// Map of player IDs to the game slots being held for them
+    /// while they are disconnected
+    held: Mutex<HashMap<PlayerID, HeldSlot>>,
+    /// History of the recently removed games
+    removed: Mutex<VecDeque<RemovedGame>>,
+    /// Whether the server is draining in which case no new games
+    /// can be created
+    draining: AtomicBool,
+    /// Notify for waiting until draining has started
+    drain_notify: Notify,
+    /// Index of the games each player is in
+    index: Arc<PlayerIndex>,
+    /// The configuration shared by every game
+    config: Arc<GameConfig>,
+}
+
+/// Errors that can occur when creating a new game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CreateGameError {
+    /// New games can't be created while the server is draining
+    Draining,
+    /// Every game ID in the configured range is in use
+    NoFreeId,
+}
+
+/// Structure for a game that was recently removed
+struct RemovedGame {
+    /// The ID of the removed game
+    game_id: GameID,
+    /// The time the game was removed (Milliseconds since unix epoch)
+    time: u64,
+}
+
+/// Structure for the games that a player was in before their
+/// session was lost
+struct RejoinEntry {
+    /// The IDs of the games the player was in
+    game_ids: Vec<GameID>,
+    /// The time the player was removed from the game
+    time: SystemTime,
+}
+
+/// Structure for the game slots that are being held for a player
+/// who lost their connection
+struct HeldSlot {
+    /// The IDs of the games the slots are in
+    game_ids: Vec<GameID>,
+    /// The ID of the session that was lost
+    session_id: SessionID,
+}
+
+impl RejoinEntry {
+    /// Checks whether this entry is still within the provided
+    /// rejoin window
+    ///
+    /// `window` The duration entries are valid for
+    fn is_valid(&self, window: Duration) -> bool {
+        self.time
+            .elapsed()
+            .map(|elapsed| elapsed < window)
+            .unwrap_or(false)
+    }
 }
 
 /// Structure for a entry in the matchmaking queue
@@ -34,28 +122,148 @@ struct QueueEntry {
     rules: RuleSet,
     /// The time that the queue entry was created at
     time: SystemTime,
+    /// The priority of the entry, higher priority entries are
+    /// matched before lower priority entries
+    priority: u8,
+    /// The queue position the player was last notified of or
+    /// zero if they haven't been notified yet
+    notified_position: usize,
+}
+
+/// Snapshot of an entry in the matchmaking queue for serialization
+#[derive(Serialize)]
+pub struct QueueEntrySnapshot {
+    /// The ID of the session that is queued
+    pub session_id: SessionID,
+    /// The ID of the queued player
+    pub player_id: PlayerID,
+    /// The display name of the queued player
+    pub display_name: String,
+    /// The number of seconds the player has been waiting
+    pub wait_time: u64,
+    /// Summary of the matchmaking rules for the entry
+    pub rules: String,
+    /// The matchmaking priority of the entry
+    pub priority: u8,
+}
+
+/// Aggregated statistics about the current games
+#[derive(Serialize)]
+pub struct GamesStats {
+    /// The total number of games
+    pub total: usize,
+    /// The number of games in each game state
+    pub states: BTreeMap<String, usize>,
+    /// The average number of players in each game
+    pub average_players: f32,
+}
+
+impl QueueEntry {
+    /// Takes a snapshot of this queue entry
+    fn snapshot(&self) -> QueueEntrySnapshot {
+        QueueEntrySnapshot {
+            session_id: self.player.addr.id,
+            player_id: self.player.player.id,
+            display_name: self.player.player.display_name.clone(),
+            wait_time: self.time.elapsed().unwrap_or_default().as_secs(),
+            rules: self.rules.to_string(),
+            priority: self.priority,
+        }
+    }
+
+    /// Compares the matching order of this entry with another entry.
+    /// Higher priority entries come first followed by the entries that
+    /// have been waiting the longest
+    ///
+    /// `other` The entry to compare with
+    fn match_order(&self, other: &QueueEntry) -> std::cmp::Ordering {
+        other
+            .priority
+            .cmp(&self.priority)
+            .then_with(|| self.time.cmp(&other.time))
+    }
+
+    /// Notifies the player of this entry that matchmaking has failed
+    fn notify_failed(&self) {
+        let player = &self.player;
+        let packet = Packet::notify(
+            Components::GameManager(GameManager::MatchmakingFailed),
+            MatchmakingFailed {
+                session_id: player.addr.id,
+                player_id: player.player.id,
+            },
+        );
+        player.addr.push(packet);
+    }
 }
 
 impl Default for Games {
     fn default() -> Self {
+        Self::new(1, 0, 0, GameConfig::default())
+    }
+}
+
+impl Games {
+    /// Creates a new games manager where game IDs are given out
+    /// starting from the provided base wrapping around once the
+    /// provided range of IDs has been used
+    ///
+    /// `id_base`   The first game ID (Zero is treated as one)
+    /// `id_range`  The number of IDs available or zero for all the IDs
+    /// `max_queue` The max matchmaking queue length or zero for no limit
+    /// `config`    The configuration shared by every game
+    pub fn new(id_base: GameID, id_range: u32, max_queue: usize, config: GameConfig) -> Self {
+        let id_base = id_base.max(1);
+        // The number of IDs between the base and the max ID
+        let available = GameID::MAX - id_base + 1;
+        let id_span = match id_range {
+            0 => available,
+            value => value.min(available),
+        };
         Self {
             games: Default::default(),
             queue: Default::default(),
-            id: AtomicU32::new(1),
+            id: AtomicU32::new(0),
+            id_base,
+            id_span,
+            max_queue,
+            rejoin: Default::default(),
+            held: Default::default(),
+            removed: Default::default(),
+            draining: AtomicBool::new(false),
+            drain_notify: Notify::new(),
+            index: Default::default(),
+            config: Arc::new(config),
         }
     }
-}
 
-impl Games {
+    /// The amount of time removed games are kept in the history for
+    const REMOVED_HISTORY: Duration = Duration::from_secs(60 * 10);
+
     /// Takes a snapshot of all the current games for serialization. Returns the list
     /// of snapshots obtained (May not equal the count) and a boolean value indicating
     /// if there are more snapshots in the next offset (For pagination).
     ///
     /// `offset` The number of games to skip from the start of the list
     /// `count`  The number of games to obtain snapshots of
-    pub async fn snapshot(&'static self, offset: usize, count: usize) -> (Vec<GameSnapshot>, bool) {
-        let mut join_set = JoinSet::new();
-        let (count, more) = {
+    pub async fn snapshot(&self, offset: usize, count: usize) -> (Vec<GameSnapshot>, bool) {
+        self.snapshot_with(offset, count, self.config.snapshot_concurrency)
+            .await
+    }
+
+    /// Takes a snapshot of the games in the provided page snapshotting
+    /// at most the provided number of games at once
+    ///
+    /// `offset`      The number of games to skip from the start of the list
+    /// `count`       The number of games to obtain snapshots of
+    /// `concurrency` The max number of games to snapshot at once
+    async fn snapshot_with(
+        &self,
+        offset: usize,
+        count: usize,
+        concurrency: usize,
+    ) -> (Vec<GameSnapshot>, bool) {
+        let (games, more) = {
             let games = &*self.games.read().await;
             // Obtained an order set of the keys from the games map
             let keys = {
@@ -67,32 +275,115 @@ impl Games {
             // Whether there is more keys that what was requested
             let more = keys.len() > offset + count;
 
-            // Collect the keys we will be using
-            let keys: Vec<GameID> = keys.into_iter().skip(offset).take(count).collect();
-            let keys_count = keys.len();
-
-            for key in keys {
-                let game = games.get(&key).cloned();
-                if let Some(game) = game {
-                    join_set.spawn(async move {
-                        let game = game;
-                        game.snapshot().await
-                    });
-                }
-            }
+            // Collect the games we will be using
+            let games: Vec<GameAddr> = keys
+                .into_iter()
+                .skip(offset)
+                .take(count)
+                .filter_map(|key| games.get(&key).cloned())
+                .collect();
 
-            (keys_count, more)
+            (games, more)
         };
 
-        // Start awaiting the snapshots that are being obtained
-        let mut snapshots = Vec::with_capacity(count);
-        while let Some(result) = join_set.join_next().await {
-            if let Ok(Some(snapshot)) = result {
-                snapshots.push(snapshot);
+        (Self::snapshot_games(games, concurrency).await, more)
+    }
+
+    /// Takes snapshots of the provided games in chunks so that at most
+    /// the provided number of snapshot tasks are running at once. The
+    /// snapshots are returned sorted by the game ID
+    ///
+    /// `games`       The games to snapshot
+    /// `concurrency` The max number of games to snapshot at once (Zero for no limit)
+    async fn snapshot_games(games: Vec<GameAddr>, concurrency: usize) -> Vec<GameSnapshot> {
+        let chunk_size = match concurrency {
+            0 => games.len().max(1),
+            value => value,
+        };
+
+        let mut snapshots = Vec::with_capacity(games.len());
+        for chunk in games.chunks(chunk_size) {
+            let mut join_set = JoinSet::new();
+            for game in chunk {
+                let game = game.clone();
+                join_set.spawn(async move { game.snapshot().await });
+            }
+            while let Some(result) = join_set.join_next().await {
+                if let Ok(Some(snapshot)) = result {
+                    snapshots.push(snapshot);
+                }
             }
         }
+        snapshots.sort_by_key(|snapshot| snapshot.id);
+        snapshots
+    }
+
+    /// Takes a snapshot of all the games that were modified at or after
+    /// the provided time sorted by the game ID
+    ///
+    /// `since` The time in milliseconds since the unix epoch
+    pub async fn snapshot_since(&self, since: u64) -> Vec<GameSnapshot> {
+        let games: Vec<GameAddr> = self.games.read().await.values().cloned().collect();
+        let mut snapshots = Self::snapshot_games(games, self.config.snapshot_concurrency).await;
+        snapshots.retain(|snapshot| snapshot.last_activity >= since);
+        snapshots
+    }
 
-        (snapshots, more)
+    /// Obtains the IDs of the games that were removed at or after the
+    /// provided time. Games are only kept in the removed history for a
+    /// short amount of time.
+    ///
+    /// `since` The time in milliseconds since the unix epoch
+    pub async fn removed_since(&self, since: u64) -> Vec<GameID> {
+        let removed = &*self.removed.lock().await;
+        removed
+            .iter()
+            .filter(|value| value.time >= since)
+            .map(|value| value.game_id)
+            .collect()
+    }
+
+    /// Adds the provided game to the removed games history removing
+    /// any entries that are older than the history duration
+    ///
+    /// `game_id` The ID of the removed game
+    async fn add_removed(&self, game_id: GameID) {
+        let now = unix_millis();
+        let oldest = now.saturating_sub(Self::REMOVED_HISTORY.as_millis() as u64);
+        let removed = &mut *self.removed.lock().await;
+        while removed.front().map_or(false, |value| value.time < oldest) {
+            removed.pop_front();
+        }
+        removed.push_back(RemovedGame { game_id, time: now });
+    }
+
+    /// Obtains statistics about the current games including the number
+    /// of games in each state and the average number of players
+    pub async fn stats(&self) -> GamesStats {
+        let snapshots = self.snapshot_since(0).await;
+        let total = snapshots.len();
+        let mut states: BTreeMap<String, usize> = BTreeMap::new();
+        let mut players = 0;
+        for snapshot in &snapshots {
+            *states.entry(format!("{:?}", snapshot.state)).or_default() += 1;
+            players += snapshot.players.len();
+        }
+        let average_players = if total > 0 {
+            players as f32 / total as f32
+        } else {
+            0.0
+        };
+        GamesStats {
+            total,
+            states,
+            average_players,
+        }
+    }
+
+    /// Obtains the number of games that are currently active
+    pub async fn count(&self) -> usize {
+        let games = &*self.games.read().await;
+        games.len()
     }
 
     /// Takes a snapshot of the game with the provided game ID
@@ -104,67 +395,384 @@ impl Games {
         game.snapshot().await
     }
 
+    /// Takes snapshots of the games that the player with the provided
+    /// ID is currently in ordered by when the player joined them
+    ///
+    /// `player_id` The ID of the player
+    pub async fn snapshot_player(&self, player_id: PlayerID) -> Vec<GameSnapshot> {
+        let mut snapshots = Vec::new();
+        for game_id in self.index.get(player_id) {
+            if let Some(snapshot) = self.snapshot_id(game_id).await {
+                snapshots.push(snapshot);
+            }
+        }
+        snapshots
+    }
+
+    /// Finds the IDs of the games the player with the provided ID is in
+    ///
+    /// `player_id` The ID of the player
+    pub fn player_games(&self, player_id: PlayerID) -> Vec<GameID> {
+        self.index.get(player_id)
+    }
+
+    /// Obtains the decoded game setup packet for the game with the
+    /// provided game ID for debugging
+    ///
+    /// `game_id` The ID of the game
+    pub async fn debug_setup(&self, game_id: GameID) -> Option<String> {
+        let games = &*self.games.read().await;
+        let game = games.get(&game_id)?;
+        game.debug_setup().await
+    }
+
+    /// Transfers host of the game with the provided ID to the player
+    /// with the provided ID. Returns None if the game doesn't exist
+    /// otherwise whether the player was in the game.
+    ///
+    /// `game_id`   The ID of the game
+    /// `player_id` The ID of the player to make host
+    pub async fn transfer_host(&self, game_id: GameID, player_id: PlayerID) -> Option<bool> {
+        let games = &*self.games.read().await;
+        let game = games.get(&game_id)?;
+        Some(game.transfer_host(player_id).await)
+    }
+
+    /// Executes the provided action on the game with the provided ID on
+    /// behalf of the player with the provided ID. Returns None if the game
+    /// doesn't exist otherwise whether the player was allowed to modify
+    /// the game.
+    ///
+    /// `game_id`   The ID of the game
+    /// `player_id` The ID of the player modifying the game
+    /// `action`    The action to execute
+    pub async fn modify_game_as(
+        &self,
+        game_id: GameID,
+        player_id: PlayerID,
+        action: GameModifyAction,
+    ) -> Option<bool> {
+        let requeue = self.is_requeue_action(&action);
+        let game = {
+            let games = &*self.games.read().await;
+            games.get(&game_id)?.clone()
+        };
+        let allowed = game.modify_as(player_id, action).await;
+        if allowed && requeue {
+            self.update_queue(game).await;
+        }
+        Some(allowed)
+    }
+
+    /// Closes the game with the provided ID removing all of its players
+    /// and removing the game from the games. Returns whether the game
+    /// existed
+    ///
+    /// `game_id` The ID of the game to close
+    pub async fn close_game(&self, game_id: GameID) -> bool {
+        // The game is removed first so that no players can join while closing
+        let game = {
+            let games = &mut *self.games.write().await;
+            games.remove(&game_id)
+        };
+        let Some(game) = game else {
+            return false;
+        };
+        game.close().await;
+        self.add_removed(game_id).await;
+        true
+    }
+
+    /// Starts draining the games. While draining no new games can be
+    /// created but the existing games are left to finish
+    pub fn start_draining(&self) {
+        if !self.draining.swap(true, Ordering::AcqRel) {
+            self.drain_notify.notify_one();
+        }
+    }
+
+    /// Checks whether the games are draining
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Acquire)
+    }
+
+    /// Waits until draining has started and then until all the
+    /// existing games have been removed or the provided timeout
+    /// has elapsed
+    ///
+    /// `timeout` The max time to wait for the games to finish
+    pub async fn wait_drained(&self, timeout: Duration) {
+        /// The interval between each check for remaining games
+        const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+        if !self.is_draining() {
+            self.drain_notify.notified().await;
+        }
+
+        let _ = tokio::time::timeout(timeout, async {
+            while self.count().await > 0 {
+                sleep(CHECK_INTERVAL).await;
+            }
+        })
+        .await;
+    }
+
     /// Creates a new game from the initial attributes and
     /// settings provided returning the Game ID of the created
     /// game. This also spawns a task to add the provided host
-    /// player to the game then update the games queue
+    /// player to the game then update the games queue. Returns
+    /// an error if the games are draining or every game ID is
+    /// in use
     ///
     /// `attributes` The initial game attributes
     /// `setting`    The initital game setting
     /// `host`       The host player
     pub async fn create_game(
-        &'static self,
+        self: &Arc<Self>,
         attributes: TdfMap<String, String>,
         setting: u16,
         host: GamePlayer,
-    ) -> u32 {
+    ) -> Result<GameID, CreateGameError> {
+        if self.is_draining() {
+            return Err(CreateGameError::Draining);
+        }
         let games = &mut *self.games.write().await;
-        let id = self.id.fetch_add(1, Ordering::AcqRel);
-        let game = Game::spawn(id, attributes, setting);
+        let id = self.next_id(games)?;
+        let limits = &self.config.attribute_limits;
+        let attributes = limits.apply(id, &TdfMap::default(), attributes);
+        let game = self.spawn_game(id, attributes, setting);
         games.insert(id, game.clone());
         game.send(GameModifyAction::AddPlayer(host));
-        tokio::spawn(self.update_queue(game));
-        id
+        self.spawn_update_queue(game);
+        Ok(id)
+    }
+
+    /// Creates a new game without a host player from the initial
+    /// attributes and settings provided returning the Game ID of the
+    /// created game. The first player to join the game becomes the
+    /// host. Empty games are removed by the idle sweep. Returns an
+    /// error if the games are draining or every game ID is in use
+    ///
+    /// `attributes` The initial game attributes
+    /// `setting`    The initital game setting
+    pub async fn create_empty_game(
+        self: &Arc<Self>,
+        attributes: TdfMap<String, String>,
+        setting: u16,
+    ) -> Result<GameID, CreateGameError> {
+        if self.is_draining() {
+            return Err(CreateGameError::Draining);
+        }
+        let games = &mut *self.games.write().await;
+        let id = self.next_id(games)?;
+        let limits = &self.config.attribute_limits;
+        let attributes = limits.apply(id, &TdfMap::default(), attributes);
+        let game = self.spawn_game(id, attributes, setting);
+        games.insert(id, game.clone());
+        self.spawn_update_queue(game);
+        Ok(id)
+    }
+
+    /// Spawns a new game that shares the player index and configuration
+    /// of these games. The max players of the game is taken from the
+    /// max players attribute if present
+    ///
+    /// `id`         The ID of the game
+    /// `attributes` The initial game attributes
+    /// `setting`    The initital game setting
+    fn spawn_game(&self, id: GameID, attributes: TdfMap<String, String>, setting: u16) -> GameAddr {
+        let max_players = requested_max_players(&attributes, self.config.max_players);
+        Game::spawn(
+            id,
+            attributes,
+            setting,
+            max_players,
+            self.index.clone(),
+            self.config.clone(),
+        )
+    }
+
+    /// Takes the next available game ID wrapping around to the ID base
+    /// once the end of the ID range is reached. IDs of games that still
+    /// exist are skipped. Returns an error if every ID in the range is
+    /// in use.
+    ///
+    /// `games` The current games
+    fn next_id(&self, games: &HashMap<GameID, GameAddr>) -> Result<GameID, CreateGameError> {
+        for _ in 0..self.id_span.min(games.len() as u32 + 1) {
+            let offset = self
+                .id
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |offset| {
+                    Some((offset + 1) % self.id_span)
+                })
+                .unwrap_or_default();
+            let id = self.id_base + offset;
+            if !games.contains_key(&id) {
+                return Ok(id);
+            }
+        }
+        warn!("Every game ID is in use, unable to create game");
+        Err(CreateGameError::NoFreeId)
+    }
+
+    /// Spawns the task that periodically removes games that have no
+    /// players and haven't had any activity within the configured
+    /// timeout.
+    pub fn start_idle_sweep(self: &Arc<Self>) {
+        /// The interval between each sweep for empty games
+        const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+        let timeout = Duration::from_secs(env::from_env(env::EMPTY_GAME_TIMEOUT));
+        if timeout.is_zero() {
+            return;
+        }
+
+        let this = self.clone();
+        tokio::spawn(async move {
+            let mut interval = interval(SWEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                let oldest = unix_millis().saturating_sub(timeout.as_millis() as u64);
+                let removed = this.remove_idle(oldest).await;
+                if !removed.is_empty() {
+                    debug!("Removed idle empty games (GIDs: {:?})", removed);
+                }
+            }
+        });
+    }
+
+    /// Removes all the games that have no players and haven't had any
+    /// activity since the provided time. Returns the IDs of the removed
+    /// games
+    ///
+    /// `oldest` The time in milliseconds since the unix epoch that empty
+    ///          games must have been active since to be kept
+    async fn remove_idle(&self, oldest: u64) -> Vec<GameID> {
+        let is_idle = |snapshot: &GameSnapshot| {
+            snapshot.players.is_empty() && snapshot.last_activity < oldest
+        };
+
+        let idle: Vec<GameID> = self
+            .snapshot_since(0)
+            .await
+            .into_iter()
+            .filter(is_idle)
+            .map(|snapshot| snapshot.id)
+            .collect();
+
+        let mut removed = Vec::with_capacity(idle.len());
+        {
+            let games = &mut *self.games.write().await;
+            for game_id in idle {
+                let Some(game) = games.get(&game_id) else {
+                    continue;
+                };
+                // Players may have joined the game before the write lock was
+                // aquired. Games that have stopped are removed
+                let still_idle = game
+                    .snapshot()
+                    .await
+                    .map_or(true, |snapshot| is_idle(&snapshot));
+                if still_idle {
+                    games.remove(&game_id);
+                    removed.push(game_id);
+                }
+            }
+        }
+        for game_id in &removed {
+            self.add_removed(*game_id).await;
+        }
+        removed
+    }
+
+    /// Spawns a task that updates the matchmaking queue for the
+    /// provided game
+    ///
+    /// `game` The game to update the queue with
+    fn spawn_update_queue(self: &Arc<Self>, game: GameAddr) {
+        let this = self.clone();
+        tokio::spawn(async move { this.update_queue(game).await });
     }
 
     /// Updates the matchmaking queue for the provided game. Will look through
     /// the queue checking if the player rules match the game attributes and if
-    /// they do then add them to the game.
+    /// they do then add them to the game. Entries are checked in order of their
+    /// priority then by how long they have been waiting.
     ///
     /// `game` The game to update to queue with
     async fn update_queue(&self, game: GameAddr) {
         let queue = &mut *self.queue.lock().await;
-        if !queue.is_empty() {
-            let mut unmatched = VecDeque::new();
-            while let Some(entry) = queue.pop_front() {
-                let join_state = game.check_joinable(Some(entry.rules.clone())).await;
-                match join_state {
-                    GameJoinableState::Full => {
-                        // If the game is not joinable push the entry back to the
-                        // front of the queue and early return
-                        queue.push_front(entry);
-                        return;
-                    }
-                    GameJoinableState::NotMatch => {
-                        // TODO: Check started time and timeout
-                        // player if they've been waiting too long
-                        unmatched.push_back(entry);
-                    }
-                    GameJoinableState::Joinable => {
-                        debug!(
-                            "Found player from queue adding them to the game (GID: {})",
-                            game.id
-                        );
-                        let time = SystemTime::now();
-                        let elapsed = time.duration_since(entry.time);
-                        if let Ok(elapsed) = elapsed {
-                            debug!("Matchmaking time elapsed: {}s", elapsed.as_secs())
-                        }
-                        game.send(GameModifyAction::AddPlayer(entry.player));
-                    }
+        if queue.is_empty() {
+            return;
+        }
+
+        let mut entries: Vec<Option<QueueEntry>> = queue.drain(..).map(Some).collect();
+
+        // Stable sort so entries with the same priority and time keep their order
+        let mut order: Vec<usize> = (0..entries.len()).collect();
+        order.sort_by(|a, b| match (&entries[*a], &entries[*b]) {
+            (Some(a), Some(b)) => a.match_order(b),
+            _ => std::cmp::Ordering::Equal,
+        });
+
+        for index in order {
+            let Some(entry) = &entries[index] else {
+                continue;
+            };
+            let join_state = game
+                .check_joinable(&entry.player, Some(entry.rules.clone()))
+                .await;
+            match join_state {
+                // If the game has no free slots stop checking
+                GameJoinableState::Full => break,
+                // Entries that need more slots than are free are skipped
+                // so that smaller entries behind them can still join
+                GameJoinableState::NotEnoughSlots
+                | GameJoinableState::NotMatch
+                | GameJoinableState::NatIncompatible => {
+                    // TODO: Check started time and timeout
+                    // player if they've been waiting too long
+                }
+                GameJoinableState::Joinable => {
+                    let Some(entry) = entries[index].take() else {
+                        continue;
+                    };
+                    debug!(
+                        "Found player from queue adding them to the game (GID: {})",
+                        game.id
+                    );
+                    let elapsed = entry.time.elapsed().unwrap_or_default();
+                    debug!("Matchmaking time elapsed: {}s", elapsed.as_secs());
+                    GlobalState::metrics().matchmaking.record_matched(elapsed);
+                    game.send(GameModifyAction::AddPlayer(entry.player));
                 }
             }
-            *queue = unmatched;
+        }
+
+        // Unmatched entries are placed back in their original order
+        queue.extend(entries.into_iter().flatten());
+
+        Self::notify_queue_positions(queue);
+    }
+
+    /// Sends each player in the provided queue whose position has
+    /// changed since they were last notified a message containing
+    /// their current position in the queue and how long they have
+    /// been waiting
+    ///
+    /// `queue` The matchmaking queue
+    fn notify_queue_positions(queue: &mut VecDeque<QueueEntry>) {
+        for (index, entry) in queue.iter_mut().enumerate() {
+            let position = index + 1;
+            if entry.notified_position == position {
+                continue;
+            }
+            entry.notified_position = position;
+            let elapsed = entry.time.elapsed().unwrap_or_default();
+            entry
+                .player
+                .addr
+                .send_message(queue_position_message(position, elapsed));
         }
     }
 
@@ -174,11 +782,12 @@ impl Games {
     ///
     /// `session` The session to get the game for
     /// `rules`   The rules the game must match to be valid
-    pub fn add_or_queue(&'static self, player: GamePlayer, rules: RuleSet) {
+    pub fn add_or_queue(self: &Arc<Self>, player: GamePlayer, rules: RuleSet) {
+        let this = self.clone();
         tokio::spawn(async move {
-            let games = &*self.games.read().await;
+            let games = &*this.games.read().await;
             for (id, game) in games.iter() {
-                let join_state = game.check_joinable(Some(rules.clone())).await;
+                let join_state = game.check_joinable(&player, Some(rules.clone())).await;
                 if let GameJoinableState::Joinable = join_state {
                     debug!("Found matching game (GID: {})", id);
                     game.send(GameModifyAction::AddPlayer(player));
@@ -186,54 +795,1258 @@ impl Games {
                 }
             }
 
-            let queue = &mut self.queue.lock().await;
-            queue.push_back(QueueEntry {
+            this.push_queue(QueueEntry {
                 player,
                 rules,
                 time: SystemTime::now(),
-            });
+                priority: 0,
+                notified_position: 0,
+            })
+            .await;
         });
     }
 
+    /// Attempts to add the provided player to the game with the provided
+    /// ID. Returns false without adding the player if the game no longer
+    /// exists or can't be joined
+    ///
+    /// `player`  The player joining the game
+    /// `game_id` The ID of the game to join
+    pub async fn join_game(&self, player: GamePlayer, game_id: GameID) -> bool {
+        // Clone the game so the lock isn't held while checking joinability
+        let game = self.games.read().await.get(&game_id).cloned();
+        let Some(game) = game else {
+            debug!(
+                "Player attempted to join missing game (PID: {}, GID: {})",
+                player.player.id, game_id
+            );
+            return false;
+        };
+        if let GameJoinableState::Joinable = game.check_joinable(&player, None).await {
+            debug!(
+                "Adding player to requested game (PID: {}, GID: {})",
+                player.player.id, game_id
+            );
+            game.send(GameModifyAction::AddPlayer(player));
+            true
+        } else {
+            debug!(
+                "Player attempted to join unjoinable game (PID: {}, GID: {})",
+                player.player.id, game_id
+            );
+            false
+        }
+    }
+
+    /// Pushes the provided entry to the back of the matchmaking queue.
+    /// When the queue is full the entry is refused and the player is
+    /// notified that matchmaking failed instead. Returns whether the
+    /// entry was queued
+    ///
+    /// `entry` The entry to queue
+    async fn push_queue(&self, entry: QueueEntry) -> bool {
+        let queue = &mut *self.queue.lock().await;
+        if self.max_queue != 0 && queue.len() >= self.max_queue {
+            debug!(
+                "Matchmaking queue is full, refused player (PID: {})",
+                entry.player.player.id
+            );
+            entry.notify_failed();
+            return false;
+        }
+        queue.push_back(entry);
+        Self::notify_queue_positions(queue);
+        true
+    }
+
+    /// Takes a snapshot of all the entries in the matchmaking queue
+    /// in the order they are queued
+    pub async fn queue_snapshot(&self) -> Vec<QueueEntrySnapshot> {
+        let queue = &*self.queue.lock().await;
+        queue.iter().map(QueueEntry::snapshot).collect()
+    }
+
+    /// Sets the matchmaking priority of the queue entry for the session
+    /// with the provided ID. Returns whether the session was queued
+    ///
+    /// `session_id` The ID of the queued session
+    /// `priority`   The new priority
+    pub async fn set_queue_priority(&self, session_id: SessionID, priority: u8) -> bool {
+        let queue = &mut *self.queue.lock().await;
+        match queue
+            .iter_mut()
+            .find(|entry| entry.player.addr.id == session_id)
+        {
+            Some(entry) => {
+                entry.priority = priority;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes all the entries from the matchmaking queue notifying each
+    /// of the players that their matchmaking has failed. Returns the
+    /// number of entries that were removed
+    pub async fn clear_queue(&self) -> usize {
+        let queue = &mut *self.queue.lock().await;
+        let count = queue.len();
+        for entry in queue.drain(..) {
+            entry.notify_failed();
+        }
+        GlobalState::metrics()
+            .matchmaking
+            .record_cleared(count as u64);
+        debug!("Cleared matchmaking queue (Count: {})", count);
+        count
+    }
+
     /// Spawns a new task that will execute the modify action on the game
     /// with the provided `game_id` once a read lock on games has been
     /// aquired
     ///
     /// `game_id` The ID of the game to modify
     /// `action`  The action to exectue
-    pub fn modify_game(&'static self, game_id: GameID, action: GameModifyAction) {
+    pub fn modify_game(self: &Arc<Self>, game_id: GameID, action: GameModifyAction) {
+        let this = self.clone();
         tokio::spawn(async move {
-            let games = self.games.read().await;
-            if let Some(game) = games.get(&game_id) {
+            let requeue = this.is_requeue_action(&action);
+            let game = {
+                let games = this.games.read().await;
+                let Some(game) = games.get(&game_id) else {
+                    return;
+                };
                 game.send(action);
+                game.clone()
+            };
+            if requeue {
+                this.update_queue(game).await;
             }
         });
     }
 
+    /// Checks whether the provided action changes the attributes of a
+    /// game in which case the matchmaking queue should be checked against
+    /// the game again as the queued players may now match the game
+    ///
+    /// `action` The action to check
+    fn is_requeue_action(&self, action: &GameModifyAction) -> bool {
+        self.config.queue_on_attribute_change
+            && matches!(
+                action,
+                GameModifyAction::SetAttributes(_)
+                    | GameModifyAction::ReplaceAttributes(_)
+                    | GameModifyAction::RemoveAttributes(_)
+            )
+    }
+
     /// Removes any sessions that have the ID provided from the
     /// matchmaking queue
     ///
     /// `sid` The session ID to remove
-    pub fn unqueue_session(&'static self, sid: SessionID) {
+    pub fn unqueue_session(self: &Arc<Self>, sid: SessionID) {
+        let this = self.clone();
+        tokio::spawn(async move { this.remove_queued(sid).await });
+    }
+
+    /// Removes any entries for the session with the provided ID from the
+    /// matchmaking queue recording them as cancelled. Returns the number
+    /// of entries removed
+    ///
+    /// `sid` The ID of the session to remove
+    async fn remove_queued(&self, sid: SessionID) -> usize {
+        let queue = &mut *self.queue.lock().await;
+        let count = queue.len();
+        queue.retain(|value| value.player.addr.id != sid);
+        let removed = count - queue.len();
+        if removed > 0 {
+            GlobalState::metrics()
+                .matchmaking
+                .record_cancelled(removed as u64);
+        }
+        removed
+    }
+
+    /// Spawns a task that removes the player from the game with the
+    /// provided ID returning the handle to the task
+    ///
+    /// `game_id` The ID of the game to remove the player from
+    /// `ty`      The type of player removal
+    pub fn remove_player(
+        self: &Arc<Self>,
+        game_id: GameID,
+        ty: RemovePlayerType,
+    ) -> JoinHandle<()> {
+        let this = self.clone();
+        tokio::spawn(async move { this.remove_player_now(game_id, ty).await })
+    }
+
+    /// Removes the player from the game with the provided ID removing
+    /// the game if there are no players left in it
+    ///
+    /// `game_id` The ID of the game to remove the player from
+    /// `ty`      The type of player removal
+    async fn remove_player_now(&self, game_id: GameID, ty: RemovePlayerType) {
+        let games = self.games.read().await;
+        if let Some(game) = games.get(&game_id) {
+            let is_empty = game.remove_player(ty).await;
+            if is_empty {
+                drop(games);
+                self.remove_empty(game_id).await;
+            }
+        }
+    }
+
+    /// Removes the player from the game with the provided ID on behalf
+    /// of the player with the provided ID removing the game if it becomes
+    /// empty. Returns None if the game doesn't exist otherwise whether
+    /// the player was allowed to remove the player
+    ///
+    /// `game_id`   The ID of the game
+    /// `player_id` The ID of the player removing the player
+    /// `ty`        The type of removal
+    pub async fn remove_player_as(
+        &self,
+        game_id: GameID,
+        player_id: PlayerID,
+        ty: RemovePlayerType,
+    ) -> Option<bool> {
+        let game = self.games.read().await.get(&game_id).cloned()?;
+        let (sender, reciever) = oneshot::channel();
+        let allowed = game
+            .modify_as(player_id, GameModifyAction::RemovePlayer(ty, sender))
+            .await;
+        if allowed && reciever.await.unwrap_or(false) {
+            self.remove_empty(game_id).await;
+        }
+        Some(allowed)
+    }
+
+    /// Removes the empty game with the provided ID from the games
+    ///
+    /// `game_id` The ID of the game
+    async fn remove_empty(&self, game_id: GameID) {
+        let removed = {
+            let games = &mut *self.games.write().await;
+            games.remove(&game_id).is_some()
+        };
+        if removed {
+            self.add_removed(game_id).await;
+        }
+    }
+
+    /// Stores the games that the provided player was in when their
+    /// session was lost so that they can be placed back into the
+    /// games if they resume their session within the rejoin window.
+    /// Expired entries are removed while storing.
+    ///
+    /// `player_id` The ID of the player
+    /// `game_ids`  The IDs of the games the player was in
+    pub fn set_rejoin(&self, player_id: PlayerID, game_ids: Vec<GameID>) {
+        let window = self.config.rejoin_window;
+        if window.is_zero() {
+            return;
+        }
+
+        let Ok(mut rejoin) = self.rejoin.lock() else {
+            return;
+        };
+        rejoin.retain(|_, entry| entry.is_valid(window));
+        rejoin.insert(
+            player_id,
+            RejoinEntry {
+                game_ids,
+                time: SystemTime::now(),
+            },
+        );
+    }
+
+    /// Removes the stored games that the provided player was in when
+    /// their session was lost returning it if it is still within the
+    /// rejoin window
+    ///
+    /// `player_id` The ID of the player
+    /// `window`    The duration entries are valid for
+    fn take_rejoin(&self, player_id: PlayerID, window: Duration) -> Option<RejoinEntry> {
+        let mut rejoin = self.rejoin.lock().ok()?;
+        rejoin
+            .remove(&player_id)
+            .filter(|entry| entry.is_valid(window))
+    }
+
+    /// Holds the game slots of the provided player after their session
+    /// was lost rather than removing them from the games. The player is
+    /// removed if they don't resume their session within the configured
+    /// grace period. Returns false if holding slots is disabled.
+    ///
+    /// `player_id`  The ID of the player
+    /// `game_ids`   The IDs of the games the player is in
+    /// `session_id` The ID of the session that was lost
+    pub fn hold_player(
+        self: &Arc<Self>,
+        player_id: PlayerID,
+        game_ids: Vec<GameID>,
+        session_id: SessionID,
+    ) -> bool {
+        let grace = self.config.reconnect_grace;
+        if grace.is_zero() {
+            return false;
+        }
+        let this = self.clone();
         tokio::spawn(async move {
-            let queue = &mut self.queue.lock().await;
-            queue.retain(|value| value.player.addr.id != sid);
+            this.hold_slot(player_id, game_ids, session_id).await;
+            sleep(grace).await;
+            this.expire_held(player_id, session_id).await;
         });
+        true
+    }
+
+    /// Stores the game slots being held for the provided player replacing
+    /// any slots that were previously held for them
+    ///
+    /// `player_id`  The ID of the player
+    /// `game_ids`   The IDs of the games the player is in
+    /// `session_id` The ID of the session that was lost
+    async fn hold_slot(&self, player_id: PlayerID, game_ids: Vec<GameID>, session_id: SessionID) {
+        let held = &mut *self.held.lock().await;
+        held.insert(
+            player_id,
+            HeldSlot {
+                game_ids,
+                session_id,
+            },
+        );
+    }
+
+    /// Removes the provided player from their games once the grace period
+    /// has passed if their slots are still being held for the session that
+    /// was lost. Returns whether the player was removed
+    ///
+    /// `player_id`  The ID of the player
+    /// `session_id` The ID of the session that was lost
+    async fn expire_held(&self, player_id: PlayerID, session_id: SessionID) -> bool {
+        let slot = {
+            let held = &mut *self.held.lock().await;
+            let is_session = held
+                .get(&player_id)
+                .map_or(false, |slot| slot.session_id == session_id);
+            if !is_session {
+                return false;
+            }
+            held.remove(&player_id)
+        };
+        let Some(slot) = slot else {
+            return false;
+        };
+
+        for game_id in slot.game_ids {
+            debug!(
+                "Reconnect grace expired, removing player (PID: {}, GID: {})",
+                player_id, game_id
+            );
+            self.remove_player_now(game_id, RemovePlayerType::Session(session_id))
+                .await;
+        }
+        true
+    }
+
+    /// Places the provided player back into the game slots that were
+    /// held for them. The player is given back if there were no slots
+    /// held for them or none of the games the slots were in still exist.
+    ///
+    /// `player` The player that resumed their session
+    async fn resume_held(&self, player: GamePlayer) -> Option<GamePlayer> {
+        let slot = match self.held.lock().await.remove(&player.player.id) {
+            Some(value) => value,
+            None => return Some(player),
+        };
+
+        let games = &*self.games.read().await;
+        let mut held = Vec::with_capacity(slot.game_ids.len());
+        for game_id in slot.game_ids {
+            match games.get(&game_id) {
+                Some(game) => held.push(game),
+                None => debug!(
+                    "Held slot game no longer exists (PID: {}, GID: {})",
+                    player.player.id, game_id
+                ),
+            }
+        }
+
+        let Some((last, rest)) = held.split_last() else {
+            return Some(player);
+        };
+        for game in rest {
+            debug!(
+                "Reconnecting player to held slot (PID: {}, GID: {})",
+                player.player.id, game.id
+            );
+            game.send(GameModifyAction::ReconnectPlayer(player.duplicate()));
+        }
+        debug!(
+            "Reconnecting player to held slot (PID: {}, GID: {})",
+            player.player.id, last.id
+        );
+        last.send(GameModifyAction::ReconnectPlayer(player));
+        None
     }
 
-    pub fn remove_player(&'static self, game_id: GameID, ty: RemovePlayerType) {
+    /// Attempts to place the provided player back into the games they
+    /// were in before their session was lost. Players that have game
+    /// slots being held are placed back into those slots. Otherwise
+    /// games that no longer exist or are full are skipped and nothing
+    /// is done if the rejoin window has passed.
+    ///
+    /// `player` The player to add back into their game
+    pub fn try_rejoin(self: &Arc<Self>, player: GamePlayer) {
+        let window = self.config.rejoin_window;
+        let this = self.clone();
         tokio::spawn(async move {
-            let games = self.games.read().await;
-            if let Some(game) = games.get(&game_id) {
-                let is_empty = game.remove_player(ty).await;
-                if is_empty {
-                    drop(games);
-
-                    // Remove the empty game
-                    let games = &mut *self.games.write().await;
-                    games.remove(&game_id);
+            let Some(player) = this.resume_held(player).await else {
+                return;
+            };
+            if window.is_zero() {
+                return;
+            }
+
+            let Some(entry) = this.take_rejoin(player.player.id, window) else {
+                return;
+            };
+
+            let games = &*this.games.read().await;
+            let mut joinable = Vec::with_capacity(entry.game_ids.len());
+            for game_id in entry.game_ids {
+                let Some(game) = games.get(&game_id) else {
+                    continue;
+                };
+                if let GameJoinableState::Joinable = game.check_joinable(&player, None).await {
+                    joinable.push(game);
                 }
             }
+
+            let Some((last, rest)) = joinable.split_last() else {
+                return;
+            };
+            for game in rest {
+                debug!(
+                    "Rejoining player to previous game (PID: {}, GID: {})",
+                    player.player.id, game.id
+                );
+                game.send(GameModifyAction::AddPlayer(player.duplicate()));
+            }
+            debug!(
+                "Rejoining player to previous game (PID: {}, GID: {})",
+                player.player.id, last.id
+            );
+            last.send(GameModifyAction::AddPlayer(player));
         });
     }
 }
+
+/// Game attribute that the host can use to request the max number of
+/// players allowed in the game when the game is created
+const MAX_PLAYERS_ATTRIBUTE: &str = "PR_maxPlayers";
+
+/// Finds the max number of players requested by the provided game
+/// attributes clamped to the provided ceiling. Games without the
+/// attribute or with an invalid value use the ceiling
+///
+/// `attributes` The initial game attributes
+/// `ceiling`    The largest max players allowed
+fn requested_max_players(attributes: &TdfMap<String, String>, ceiling: usize) -> usize {
+    attributes
+        .get(MAX_PLAYERS_ATTRIBUTE)
+        .and_then(|value| value.parse::<usize>().ok())
+        .filter(|value| *value > 0)
+        .map(|value| value.min(ceiling))
+        .unwrap_or(ceiling)
+}
+
+/// Creates the message sent to players in the matchmaking queue to
+/// inform them of their queue position
+///
+/// `position` The position in the queue (Starting at 1)
+/// `elapsed`  The time spent waiting in the queue
+fn queue_position_message(position: usize, elapsed: Duration) -> String {
+    format!(
+        "Matchmaking queue position: {} (Waiting {}s)",
+        position,
+        elapsed.as_secs()
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        requested_max_players, CreateGameError, Games, QueueEntry, RejoinEntry,
+        MAX_PLAYERS_ATTRIBUTE,
+    };
+    use crate::{
+        game::{
+            config::GameConfig,
+            models::{GameState, RemoveReason},
+            player::GamePlayer,
+            rules::RuleSet,
+            AttrMap, GameAddr, GameModifyAction, RemovePlayerType,
+        },
+        servers::main::session::{SessionAddr, SessionMessage},
+        state::GlobalState,
+        utils::{
+            components::{Components, GameManager},
+            models::NetData,
+            time::unix_millis,
+            types::GameID,
+        },
+    };
+    use blaze_pk::packet::PacketComponents;
+    use database::Player;
+    use std::{
+        collections::VecDeque,
+        sync::Arc,
+        time::{Duration, SystemTime},
+    };
+    use tokio::{sync::mpsc, time::sleep};
+
+    #[test]
+    fn test_rejoin_window() {
+        let window = Duration::from_secs(60);
+
+        let entry = RejoinEntry {
+            game_ids: vec![1],
+            time: SystemTime::now(),
+        };
+        assert!(entry.is_valid(window));
+
+        let entry = RejoinEntry {
+            game_ids: vec![1],
+            time: SystemTime::now() - Duration::from_secs(120),
+        };
+        assert!(!entry.is_valid(window));
+    }
+
+    /// Tests that rejoin entries are only stored when the rejoin window
+    /// from the game config is enabled
+    #[test]
+    fn test_set_rejoin() {
+        let games = Games::default();
+        games.set_rejoin(1, vec![1]);
+        assert!(games.take_rejoin(1, games.config.rejoin_window).is_some());
+
+        let config = GameConfig {
+            rejoin_window: Duration::ZERO,
+            ..Default::default()
+        };
+        let games = Games::new(1, 0, 0, config);
+        games.set_rejoin(1, vec![1]);
+        assert!(games.take_rejoin(1, Duration::from_secs(60)).is_none());
+    }
+
+    /// Creates a queue entry for a player with the provided ID returning
+    /// the entry and the receiver for messages sent to the player
+    fn queue_entry(id: u32) -> (QueueEntry, mpsc::UnboundedReceiver<SessionMessage>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let player = Player {
+            id,
+            email: format!("test{id}@test.com"),
+            display_name: format!("Test{id}"),
+            session_token: None,
+            origin: false,
+            password: String::new(),
+            created_at: None,
+        };
+        let entry = QueueEntry {
+            player: GamePlayer::new(
+                player,
+                NetData::default(),
+                SessionAddr::new(id, sender),
+                Default::default(),
+            ),
+            rules: RuleSet::new(Vec::new()),
+            time: SystemTime::now(),
+            priority: 0,
+            notified_position: 0,
+        };
+        (entry, receiver)
+    }
+
+    /// Tests that the max players requested by the creation attributes
+    /// is used for the game and is clamped to the ceiling from the
+    /// game config
+    #[tokio::test]
+    async fn test_requested_max_players() {
+        let ceiling = 8;
+        let config = GameConfig {
+            max_players: ceiling,
+            ..Default::default()
+        };
+        let games = Games::new(1, 0, 0, config);
+
+        for (requested, expected) in [("6", 6), ("100", 8), ("invalid", 8)] {
+            let mut attributes = AttrMap::default();
+            attributes.insert(MAX_PLAYERS_ATTRIBUTE.to_string(), requested.to_string());
+
+            let game = games.spawn_game(1, attributes, 0);
+            let snapshot = game.snapshot().await.unwrap();
+            assert_eq!(snapshot.max_players, expected);
+        }
+
+        assert_eq!(requested_max_players(&AttrMap::default(), ceiling), ceiling);
+    }
+
+    /// Creates a game player for the player with the provided ID on
+    /// a session with the provided session ID returning the player
+    /// and the receiver for messages sent to the session
+    fn game_player(
+        id: u32,
+        session_id: u32,
+    ) -> (GamePlayer, mpsc::UnboundedReceiver<SessionMessage>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let player = Player {
+            id,
+            email: format!("test{id}@test.com"),
+            display_name: format!("Test{id}"),
+            session_token: None,
+            origin: false,
+            password: String::new(),
+            created_at: None,
+        };
+        let player = GamePlayer::new(
+            player,
+            NetData::default(),
+            SessionAddr::new(session_id, sender),
+            Default::default(),
+        );
+        (player, receiver)
+    }
+
+    /// Creates a game with two players returning the games manager,
+    /// the address of the game and the receivers for the sessions of
+    /// the players
+    async fn held_game() -> (
+        Games,
+        GameAddr,
+        Vec<mpsc::UnboundedReceiver<SessionMessage>>,
+    ) {
+        let games = Games::default();
+        let game = games.spawn_game(1, AttrMap::default(), 0);
+        let mut sessions = Vec::new();
+        for id in 1..=2 {
+            let (player, session) = game_player(id, id);
+            game.send(GameModifyAction::AddPlayer(player));
+            sessions.push(session);
+        }
+        games.games.write().await.insert(1, game.clone());
+        (games, game, sessions)
+    }
+
+    /// Returns the session IDs of the players in the provided game
+    async fn game_sessions(game: &GameAddr) -> Vec<u32> {
+        let snapshot = game.snapshot().await.unwrap();
+        snapshot
+            .players
+            .iter()
+            .map(|player| player.session_id)
+            .collect()
+    }
+
+    /// Tests that a player resuming their session within the grace
+    /// period is placed back into their held slot
+    #[tokio::test]
+    async fn test_resume_within_grace() {
+        let (games, game, _sessions) = held_game().await;
+
+        games.hold_slot(2, vec![1], 2).await;
+        let (player, _session) = game_player(2, 3);
+        assert!(games.resume_held(player).await.is_none());
+
+        // The slot is kept after the grace period has passed
+        assert!(!games.expire_held(2, 2).await);
+        assert_eq!(game_sessions(&game).await, vec![1, 3]);
+    }
+
+    /// Tests that a player not resuming their session within the grace
+    /// period is removed from the game
+    #[tokio::test]
+    async fn test_resume_after_grace() {
+        let (games, game, _sessions) = held_game().await;
+
+        games.hold_slot(2, vec![1], 2).await;
+        assert!(games.expire_held(2, 2).await);
+        assert_eq!(game_sessions(&game).await, vec![1]);
+
+        // There is no longer a held slot for the player
+        let (player, _session) = game_player(2, 3);
+        assert!(games.resume_held(player).await.is_some());
+        assert_eq!(game_sessions(&game).await, vec![1]);
+    }
+
+    /// Tests that a player whose session was in multiple games is placed
+    /// back into the held slots of all the games
+    #[tokio::test]
+    async fn test_resume_multiple_games() {
+        let (games, game, _sessions) = held_game().await;
+        let other = games.spawn_game(2, AttrMap::default(), 0);
+        let (player, _other_session) = game_player(2, 2);
+        other.send(GameModifyAction::AddPlayer(player));
+        games.games.write().await.insert(2, other.clone());
+
+        games.hold_slot(2, vec![1, 2], 2).await;
+        let (player, _session) = game_player(2, 3);
+        assert!(games.resume_held(player).await.is_none());
+
+        assert_eq!(game_sessions(&game).await, vec![1, 3]);
+        assert_eq!(game_sessions(&other).await, vec![3]);
+    }
+
+    /// Tests that a player resuming their session is given back when
+    /// the game their slot was held in no longer exists
+    #[tokio::test]
+    async fn test_resume_removed_game() {
+        let (games, _game, _sessions) = held_game().await;
+
+        games.hold_slot(2, vec![1], 2).await;
+        games.games.write().await.remove(&1);
+
+        let (player, _session) = game_player(2, 3);
+        assert!(games.resume_held(player).await.is_some());
+        assert!(!games.expire_held(2, 2).await);
+    }
+
+    /// Receives the next message sent to a queued player
+    fn next_message(receiver: &mut mpsc::UnboundedReceiver<SessionMessage>) -> String {
+        loop {
+            match receiver.try_recv() {
+                Ok(SessionMessage::Message(message)) => return message,
+                Ok(_) => continue,
+                Err(_) => panic!("Expected message"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_queue_positions() {
+        let mut queue = VecDeque::new();
+        let (entry_a, mut receiver_a) = queue_entry(1);
+        let (entry_b, mut receiver_b) = queue_entry(2);
+        queue.push_back(entry_a);
+        queue.push_back(entry_b);
+
+        Games::notify_queue_positions(&mut queue);
+        assert!(next_message(&mut receiver_a).starts_with("Matchmaking queue position: 1 "));
+        assert!(next_message(&mut receiver_b).starts_with("Matchmaking queue position: 2 "));
+
+        // Players are not notified again while their position is unchanged
+        Games::notify_queue_positions(&mut queue);
+        assert!(receiver_a.try_recv().is_err());
+        assert!(receiver_b.try_recv().is_err());
+
+        // The first player is matched and removed from the queue
+        queue.pop_front();
+        Games::notify_queue_positions(&mut queue);
+        assert!(next_message(&mut receiver_b).starts_with("Matchmaking queue position: 1 "));
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_since() {
+        let games = Games::default();
+        {
+            let map = &mut *games.games.write().await;
+            map.insert(1, games.spawn_game(1, AttrMap::default(), 0));
+            map.insert(2, games.spawn_game(2, AttrMap::default(), 0));
+        }
+
+        sleep(Duration::from_millis(5)).await;
+        let since = unix_millis();
+        sleep(Duration::from_millis(5)).await;
+
+        // Only the second game is modified after the since time
+        if let Some(game) = games.games.read().await.get(&2) {
+            game.send(GameModifyAction::SetSetting(1));
+        }
+        games.add_removed(3).await;
+
+        let snapshots = games.snapshot_since(since).await;
+        let ids: Vec<u32> = snapshots.iter().map(|snapshot| snapshot.id).collect();
+        assert_eq!(ids, vec![2]);
+        assert_eq!(games.removed_since(since).await, vec![3]);
+        assert!(games.removed_since(unix_millis() + 1).await.is_empty());
+    }
+
+    /// Tests that games created without a host appear in the snapshots
+    /// and are removed by the idle sweep while empty
+    #[tokio::test]
+    async fn test_empty_game() {
+        let games = Arc::new(Games::default());
+        let mut attributes = AttrMap::default();
+        attributes.insert("ME3map".to_string(), "map2".to_string());
+
+        let id = games.create_empty_game(attributes, 0x11F).await.unwrap();
+        let snapshot = games.snapshot_id(id).await.unwrap();
+        assert_eq!(snapshot.id, id);
+        assert_eq!(snapshot.setting, 0x11F);
+        assert!(snapshot.players.is_empty());
+        let (snapshots, _) = games.snapshot(0, 20).await;
+        assert!(snapshots.iter().any(|snapshot| snapshot.id == id));
+
+        // Games are kept while they have been active since the cutoff
+        let last_activity = snapshot.last_activity;
+        assert!(games.remove_idle(last_activity).await.is_empty());
+        assert_eq!(games.remove_idle(last_activity + 1).await, vec![id]);
+        assert!(games.snapshot_id(id).await.is_none());
+        assert_eq!(games.removed_since(0).await, vec![id]);
+    }
+
+    /// Tests that pages of snapshots taken with a concurrency limit
+    /// smaller than the page are complete and in order
+    #[tokio::test]
+    async fn test_snapshot_concurrency() {
+        let games = Games::default();
+        {
+            let map = &mut *games.games.write().await;
+            for id in 1..=50 {
+                map.insert(id, games.spawn_game(id, AttrMap::default(), 0));
+            }
+        }
+
+        let (snapshots, more) = games.snapshot_with(5, 30, 4).await;
+        let ids: Vec<u32> = snapshots.iter().map(|snapshot| snapshot.id).collect();
+        assert_eq!(ids, (6..=35).collect::<Vec<u32>>());
+        assert!(more);
+
+        let (snapshots, more) = games.snapshot_with(40, 100, 3).await;
+        let ids: Vec<u32> = snapshots.iter().map(|snapshot| snapshot.id).collect();
+        assert_eq!(ids, (41..=50).collect::<Vec<u32>>());
+        assert!(!more);
+    }
+
+    /// Tests that game IDs start from the configured base and wrap
+    /// around once the range has been used
+    #[tokio::test]
+    async fn test_game_id_base() {
+        let games = Arc::new(Games::new(5000, 2, 0, GameConfig::default()));
+        let (host, _session) = game_player(1, 1);
+        let first = games.create_game(AttrMap::default(), 0, host).await;
+        let second = games.create_empty_game(AttrMap::default(), 0).await;
+        assert_eq!((first, second), (Ok(5000), Ok(5001)));
+
+        // The empty game is removed freeing its ID
+        let removed = games.remove_idle(unix_millis() + 1).await;
+        assert_eq!(removed, vec![5001]);
+
+        // IDs wrap around skipping the game that still exists
+        assert_eq!(
+            games.create_empty_game(AttrMap::default(), 0).await,
+            Ok(5001)
+        );
+
+        // No games are created once every ID is in use
+        assert_eq!(
+            games.create_empty_game(AttrMap::default(), 0).await,
+            Err(CreateGameError::NoFreeId)
+        );
+        assert_eq!(games.count().await, 2);
+
+        let games = Games::new(0, 0, 0, GameConfig::default());
+        assert_eq!(games.id_base, 1);
+        assert_eq!(games.id_span, u32::MAX);
+    }
+
+    /// Tests that the game stats count the games in each state
+    #[tokio::test]
+    async fn test_stats() {
+        let games = Arc::new(Games::default());
+        let stats = games.stats().await;
+        assert_eq!(stats.total, 0);
+        assert_eq!(stats.average_players, 0.0);
+
+        let (host, _session) = game_player(1, 1);
+        let first = games
+            .create_game(AttrMap::default(), 0, host)
+            .await
+            .unwrap();
+        games
+            .create_empty_game(AttrMap::default(), 0)
+            .await
+            .unwrap();
+        games
+            .create_empty_game(AttrMap::default(), 0)
+            .await
+            .unwrap();
+
+        {
+            let game = games.games.read().await;
+            game[&first].send(GameModifyAction::SetState(GameState::InGame));
+        }
+
+        let stats = games.stats().await;
+        assert_eq!(stats.total, 3);
+        assert_eq!(stats.states.get("Init"), Some(&2));
+        assert_eq!(stats.states.get("InGame"), Some(&1));
+        assert!((stats.average_players - 1.0 / 3.0).abs() < f32::EPSILON);
+    }
+
+    /// Tests that new games can't be created while draining but the
+    /// existing games are kept
+    #[tokio::test]
+    async fn test_draining() {
+        let games = Arc::new(Games::default());
+        let (host, _session) = game_player(1, 1);
+        let id = games
+            .create_game(AttrMap::default(), 0, host)
+            .await
+            .unwrap();
+
+        games.start_draining();
+        assert!(games.is_draining());
+        let (player, _player_session) = game_player(2, 2);
+        assert_eq!(
+            games.create_game(AttrMap::default(), 0, player).await,
+            Err(CreateGameError::Draining)
+        );
+        assert_eq!(
+            games.create_empty_game(AttrMap::default(), 0).await,
+            Err(CreateGameError::Draining)
+        );
+
+        assert!(games.snapshot_id(id).await.is_some());
+        assert_eq!(games.count().await, 1);
+
+        // Draining is finished after the timeout while games remain
+        games.wait_drained(Duration::from_millis(10)).await;
+        assert!(games.snapshot_id(id).await.is_some());
+    }
+
+    /// Tests that higher priority entries are matched before lower
+    /// priority entries that have been waiting longer
+    #[tokio::test]
+    async fn test_queue_priority() {
+        GlobalState::init_test().await;
+        let games = Games::default();
+        let game = games.spawn_game(1, AttrMap::default(), 0);
+        let mut sessions = Vec::new();
+        for id in 1..=3 {
+            let (player, session) = game_player(id, id);
+            game.send(GameModifyAction::AddPlayer(player));
+            sessions.push(session);
+        }
+
+        let (mut early, _receiver_early) = queue_entry(10);
+        early.time = SystemTime::now() - Duration::from_secs(30);
+        let (mut late, _receiver_late) = queue_entry(11);
+        late.priority = 1;
+        let (other, _receiver_other) = queue_entry(12);
+        {
+            let queue = &mut *games.queue.lock().await;
+            queue.push_back(early);
+            queue.push_back(late);
+            queue.push_back(other);
+        }
+
+        // The game only has one free slot left
+        games.update_queue(game.clone()).await;
+
+        let snapshot = game.snapshot().await.unwrap();
+        let ids: Vec<u32> = snapshot
+            .players
+            .iter()
+            .map(|player| player.player_id)
+            .collect();
+        assert_eq!(ids, vec![1, 2, 3, 11]);
+
+        // Unmatched entries keep their order
+        let queue = games.queue_snapshot().await;
+        let ids: Vec<u32> = queue.iter().map(|entry| entry.player_id).collect();
+        assert_eq!(ids, vec![10, 12]);
+    }
+
+    /// Tests that a party needing more slots than the game has free
+    /// doesn't stop smaller entries behind it from being matched
+    #[tokio::test]
+    async fn test_queue_skips_party() {
+        GlobalState::init_test().await;
+        let games = Games::default();
+        let game = games.spawn_game(1, AttrMap::default(), 0);
+        let mut sessions = Vec::new();
+        for id in 1..=3 {
+            let (player, session) = game_player(id, id);
+            game.send(GameModifyAction::AddPlayer(player));
+            sessions.push(session);
+        }
+
+        let (mut party, _receiver_party) = queue_entry(10);
+        party.rules = RuleSet::new(vec![("minFreeSlots".to_string(), "2".to_string())]);
+        let (solo, _receiver_solo) = queue_entry(11);
+        {
+            let queue = &mut *games.queue.lock().await;
+            queue.push_back(party);
+            queue.push_back(solo);
+        }
+
+        // The game only has one free slot left
+        games.update_queue(game.clone()).await;
+
+        let snapshot = game.snapshot().await.unwrap();
+        let ids: Vec<u32> = snapshot
+            .players
+            .iter()
+            .map(|player| player.player_id)
+            .collect();
+        assert_eq!(ids, vec![1, 2, 3, 11]);
+
+        let queue = games.queue_snapshot().await;
+        let ids: Vec<u32> = queue.iter().map(|entry| entry.player_id).collect();
+        assert_eq!(ids, vec![10]);
+    }
+
+    #[tokio::test]
+    async fn test_clear_queue() {
+        GlobalState::init_test().await;
+        let cleared = GlobalState::metrics().matchmaking.snapshot().cleared;
+        let games = Games::default();
+        let (entry_a, mut receiver_a) = queue_entry(1);
+        let (entry_b, mut receiver_b) = queue_entry(2);
+        {
+            let queue = &mut *games.queue.lock().await;
+            queue.push_back(entry_a);
+            queue.push_back(entry_b);
+        }
+
+        let snapshot = games.queue_snapshot().await;
+        let ids: Vec<u32> = snapshot.iter().map(|entry| entry.player_id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert_eq!(snapshot[0].display_name, "Test1");
+        assert_eq!(snapshot[0].rules, "Any");
+
+        assert_eq!(games.clear_queue().await, 2);
+        assert!(games.queue_snapshot().await.is_empty());
+        assert_eq!(
+            GlobalState::metrics().matchmaking.snapshot().cleared,
+            cleared + 2
+        );
+
+        // Each queued player is notified that matchmaking failed
+        for receiver in [&mut receiver_a, &mut receiver_b] {
+            let Ok(SessionMessage::Write(packet)) = receiver.try_recv() else {
+                panic!("Expected matchmaking failed notification");
+            };
+            assert_eq!(
+                Components::from_header(&packet.header),
+                Components::GameManager(GameManager::MatchmakingFailed)
+            );
+        }
+    }
+
+    /// Tests that changing the attributes of a game so that it matches
+    /// the rules of a queued player adds that player to the game
+    #[tokio::test]
+    async fn test_attributes_update_queue() {
+        GlobalState::init_test().await;
+        let games = Games::default();
+        let mut attributes = AttrMap::default();
+        attributes.insert("ME3map".to_string(), "map1".to_string());
+        let game = games.spawn_game(1, attributes, 0);
+        let (host, _session) = game_player(1, 1);
+        game.send(GameModifyAction::AddPlayer(host));
+        games.games.write().await.insert(1, game.clone());
+
+        let (mut entry, _receiver) = queue_entry(10);
+        entry.rules = RuleSet::new(vec![(
+            "ME3_gameMapMatchRule".to_string(),
+            "map2".to_string(),
+        )]);
+        games.queue.lock().await.push_back(entry);
+
+        // The queued player doesn't match the game yet
+        games.update_queue(game.clone()).await;
+        assert_eq!(games.queue_snapshot().await.len(), 1);
+
+        let mut attributes = AttrMap::default();
+        attributes.insert("ME3map".to_string(), "map2".to_string());
+        let allowed = games
+            .modify_game_as(1, 1, GameModifyAction::SetAttributes(attributes))
+            .await;
+        assert_eq!(allowed, Some(true));
+
+        assert!(games.queue_snapshot().await.is_empty());
+        let snapshot = game.snapshot().await.unwrap();
+        let ids: Vec<u32> = snapshot
+            .players
+            .iter()
+            .map(|player| player.player_id)
+            .collect();
+        assert_eq!(ids, vec![1, 10]);
+    }
+
+    /// Tests that closing a game notifies each of its players that they
+    /// were removed and removes the game from the snapshots
+    #[tokio::test]
+    async fn test_close_game() {
+        let games = Games::default();
+        let game = games.spawn_game(1, AttrMap::default(), 0);
+        games.games.write().await.insert(1, game.clone());
+
+        let mut receivers = Vec::new();
+        for id in 1..=2 {
+            let (entry, receiver) = queue_entry(id);
+            game.send(GameModifyAction::AddPlayer(entry.player));
+            receivers.push(receiver);
+        }
+        assert_eq!(game.snapshot().await.unwrap().players.len(), 2);
+
+        assert!(games.close_game(1).await);
+        assert!(!games.close_game(1).await);
+
+        for receiver in &mut receivers {
+            let mut removed = false;
+            while let Ok(message) = receiver.try_recv() {
+                let packets = match message {
+                    SessionMessage::Write(packet) => vec![packet],
+                    SessionMessage::WriteMany(packets) => packets,
+                    _ => continue,
+                };
+                removed |= packets.iter().any(|packet| {
+                    Components::from_header(&packet.header)
+                        == Components::GameManager(GameManager::PlayerRemoved)
+                });
+            }
+            assert!(removed);
+        }
+
+        assert!(games.snapshot_id(1).await.is_none());
+        assert_eq!(games.removed_since(0).await, vec![1]);
+    }
+
+    /// Tests that matched entries are recorded in the bucket for
+    /// the time they spent waiting and that cancelled entries are
+    /// recorded
+    #[tokio::test]
+    async fn test_matchmaking_metrics() {
+        GlobalState::init_test().await;
+        let metrics = &GlobalState::metrics().matchmaking;
+        let before = metrics.snapshot();
+
+        let games = Games::default();
+        let game = games.spawn_game(1, AttrMap::default(), 0);
+        let (host, _session) = game_player(1, 1);
+        game.send(GameModifyAction::AddPlayer(host));
+
+        let (mut entry, _receiver) = queue_entry(10);
+        entry.time = SystemTime::now() - Duration::from_secs(20);
+        games.queue.lock().await.push_back(entry);
+        games.update_queue(game).await;
+
+        let (entry, _receiver) = queue_entry(11);
+        games.queue.lock().await.push_back(entry);
+        assert_eq!(games.remove_queued(11).await, 1);
+        assert_eq!(games.remove_queued(11).await, 0);
+
+        let after = metrics.snapshot();
+        assert_eq!(after.under_15s, before.under_15s);
+        assert_eq!(after.under_60s, before.under_60s + 1);
+        assert_eq!(after.over_60s, before.over_60s);
+        assert_eq!(after.cancelled, before.cancelled + 1);
+    }
+
+    /// Tests that joining only adds the player when the game exists
+    /// and can be joined
+    #[tokio::test]
+    async fn test_join_game() {
+        let games = Games::default();
+        let game = games.spawn_game(1, AttrMap::default(), 0);
+        games.games.write().await.insert(1, game.clone());
+
+        let (entry, _receiver) = queue_entry(10);
+        assert!(!games.join_game(entry.player, 2).await);
+        assert_eq!(game_sessions(&game).await, Vec::<u32>::new());
+
+        let (entry, _receiver) = queue_entry(11);
+        assert!(games.join_game(entry.player, 1).await);
+        assert_eq!(game_sessions(&game).await, vec![11]);
+
+        // Games that were removed can't be joined
+        games.games.write().await.remove(&1);
+        let (entry, _receiver) = queue_entry(12);
+        assert!(!games.join_game(entry.player, 1).await);
+    }
+
+    /// Tests that entries are refused once the queue is full
+    #[tokio::test]
+    async fn test_max_queue() {
+        let games = Games::new(1, 0, 2, GameConfig::default());
+        let mut receivers = Vec::new();
+        for id in 1..=2 {
+            let (entry, receiver) = queue_entry(id);
+            assert!(games.push_queue(entry).await);
+            receivers.push(receiver);
+        }
+
+        let (entry, mut receiver) = queue_entry(3);
+        assert!(!games.push_queue(entry).await);
+
+        let queue = games.queue_snapshot().await;
+        let ids: Vec<u32> = queue.iter().map(|entry| entry.player_id).collect();
+        assert_eq!(ids, vec![1, 2]);
+
+        // The refused player is notified that matchmaking failed
+        let Ok(SessionMessage::Write(packet)) = receiver.try_recv() else {
+            panic!("Expected matchmaking failed notification");
+        };
+        assert_eq!(
+            Components::from_header(&packet.header),
+            Components::GameManager(GameManager::MatchmakingFailed)
+        );
+    }
+
+    /// Tests that the games a player is in can be found and that
+    /// players are no longer found once they are removed
+    #[tokio::test]
+    async fn test_snapshot_player() {
+        let games = Arc::new(Games::default());
+        let (host, _session) = game_player(1, 1);
+        let first = games
+            .create_game(AttrMap::default(), 0, host)
+            .await
+            .unwrap();
+        let (host, _session) = game_player(1, 1);
+        let second = games
+            .create_game(AttrMap::default(), 0, host)
+            .await
+            .unwrap();
+        // Snapshots are processed after the host has been added
+        assert!(games.snapshot_id(first).await.is_some());
+        assert!(games.snapshot_id(second).await.is_some());
+
+        let ids: Vec<GameID> = games
+            .snapshot_player(1)
+            .await
+            .iter()
+            .map(|snapshot| snapshot.id)
+            .collect();
+        assert_eq!(ids, vec![first, second]);
+        assert!(games.snapshot_player(2).await.is_empty());
+
+        games
+            .remove_player(first, RemovePlayerType::Player(1, RemoveReason::Generic))
+            .await
+            .unwrap();
+        assert_eq!(games.player_games(1), vec![second]);
+    }
+
+    /// Tests that attribute changes only requeue games when enabled
+    /// in the game config
+    #[test]
+    fn test_requeue_action() {
+        let action = GameModifyAction::SetAttributes(AttrMap::default());
+
+        let games = Games::default();
+        assert!(games.is_requeue_action(&action));
+        assert!(!games.is_requeue_action(&GameModifyAction::SetSetting(0)));
+
+        let config = GameConfig {
+            queue_on_attribute_change: false,
+            ..Default::default()
+        };
+        let games = Games::new(1, 0, 0, config);
+        assert!(!games.is_requeue_action(&action));
+    }
+}
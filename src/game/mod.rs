@@ -1,15 +1,27 @@
-use self::rules::RuleSet;
-use crate::utils::{
-    components::{Components, GameManager, UserSessions},
-    types::{GameID, GameSlot, PlayerID, SessionID},
+use self::{config::GameConfig, index::PlayerIndex, rules::RuleSet};
+use crate::{
+    servers::main::session::GameRole,
+    state::GlobalState,
+    utils::{
+        audit,
+        components::{Components, GameManager, Messaging, UserSessions},
+        models::NatType,
+        packet::append_packet_decoded,
+        time::unix_millis,
+        types::{GameID, GameSlot, PlayerID, SessionID},
+    },
 };
 use blaze_pk::{codec::Encodable, packet::Packet, types::TdfMap};
-use log::debug;
+use log::{debug, warn};
 use models::*;
 use player::{GamePlayer, GamePlayerSnapshot};
 use serde::Serialize;
+use std::{cell::Cell, cmp::Reverse, sync::Arc, time::Instant};
 use tokio::sync::{mpsc, oneshot};
 
+pub mod attributes;
+pub mod config;
+pub mod index;
 pub mod manager;
 pub mod models;
 pub mod player;
@@ -24,10 +36,27 @@ pub struct Game {
     pub setting: u16,
     /// The game attributes
     pub attributes: AttrMap,
+    /// The max number of players allowed in this game
+    pub max_players: usize,
     /// The list of players in this game
     pub players: Vec<GamePlayer>,
     /// The number of the next available slot
     pub next_slot: GameSlot,
+    /// The time the game was created at (Milliseconds since unix epoch)
+    pub created_at: u64,
+    /// The time the game was last modified at (Milliseconds since unix epoch)
+    pub last_activity: u64,
+    /// The IDs of players that have slots reserved in this game
+    pub reserved: Vec<PlayerID>,
+    /// The IDs of the players in the admin list of this game
+    admins: Vec<PlayerID>,
+    /// Whether a packet failed to send to a player because their
+    /// session was closed
+    has_closed: Cell<bool>,
+    /// Index of the games each player is in
+    index: Arc<PlayerIndex>,
+    /// The configuration shared by every game
+    config: Arc<GameConfig>,
     pub reciever: mpsc::UnboundedReceiver<GameModifyAction>,
 }
 
@@ -37,7 +66,43 @@ pub struct GameSnapshot {
     pub state: GameState,
     pub setting: u16,
     pub attributes: AttrMap,
+    /// Display label for the game taken from the label attribute
+    pub label: Option<String>,
+    /// The ID of the player that is hosting the game
+    pub host_id: Option<PlayerID>,
     pub players: Vec<GamePlayerSnapshot>,
+    pub created_at: u64,
+    pub last_activity: u64,
+    pub reserved: Vec<PlayerID>,
+    /// The max number of players allowed in the game
+    pub max_players: usize,
+}
+
+/// Compact snapshot of a game without the attributes or the player
+/// details for listing many games at once
+#[derive(Serialize)]
+pub struct CompactGameSnapshot {
+    pub id: GameID,
+    pub state: GameState,
+    /// The number of players in the game
+    pub player_count: usize,
+    /// The max number of players allowed in the game
+    pub max_players: usize,
+    /// Display label for the game taken from the label attribute
+    pub label: Option<String>,
+}
+
+impl GameSnapshot {
+    /// Converts this snapshot into the compact snapshot
+    pub fn compact(self) -> CompactGameSnapshot {
+        CompactGameSnapshot {
+            id: self.id,
+            state: self.state,
+            player_count: self.players.len(),
+            max_players: self.max_players,
+            label: self.label,
+        }
+    }
 }
 
 /// Attributes map type
@@ -69,11 +134,16 @@ impl GameAddr {
         reciever.await.unwrap_or(true)
     }
 
-    pub async fn check_joinable(&self, rules: Option<RuleSet>) -> GameJoinableState {
+    pub async fn check_joinable(
+        &self,
+        player: &GamePlayer,
+        rules: Option<RuleSet>,
+    ) -> GameJoinableState {
         let (sender, reciever) = oneshot::channel();
+        let details = JoinDetails::new(player);
         if self
             .sender
-            .send(GameModifyAction::CheckJoinable(rules, sender))
+            .send(GameModifyAction::CheckJoinable(details, rules, sender))
             .is_err()
         {
             return GameJoinableState::Full;
@@ -92,17 +162,96 @@ impl GameAddr {
         }
         reciever.await.ok()
     }
+
+    pub async fn transfer_host(&self, player_id: PlayerID) -> bool {
+        let (sender, reciever) = oneshot::channel();
+        if self
+            .sender
+            .send(GameModifyAction::TransferHost(player_id, sender))
+            .is_err()
+        {
+            return false;
+        }
+        reciever.await.unwrap_or(false)
+    }
+
+    /// Closes the game removing all of the players from the game and
+    /// waits until the players have been removed
+    pub async fn close(&self) {
+        let (sender, reciever) = oneshot::channel();
+        if self.sender.send(GameModifyAction::Close(sender)).is_err() {
+            return;
+        }
+        reciever.await.ok();
+    }
+
+    /// Executes the provided modify action on behalf of the player with
+    /// the provided ID. Returns whether the player was allowed to modify
+    /// the game
+    ///
+    /// `player_id` The ID of the player modifying the game
+    /// `action`    The action to execute
+    pub async fn modify_as(&self, player_id: PlayerID, action: GameModifyAction) -> bool {
+        let (sender, reciever) = oneshot::channel();
+        if self
+            .sender
+            .send(GameModifyAction::Authorized {
+                player_id,
+                action: Box::new(action),
+                sender,
+            })
+            .is_err()
+        {
+            return false;
+        }
+        reciever.await.unwrap_or(false)
+    }
+
+    pub async fn debug_setup(&self) -> Option<String> {
+        let (sender, reciever) = oneshot::channel();
+        if self
+            .sender
+            .send(GameModifyAction::DebugSetup(sender))
+            .is_err()
+        {
+            return None;
+        }
+        reciever.await.ok().flatten()
+    }
 }
 
 pub enum GameModifyAction {
     /// Adds a new player to the game
     AddPlayer(GamePlayer),
+    /// Places a player back into the slot that was held for them
+    /// after their session was lost
+    ReconnectPlayer(GamePlayer),
     /// Modify the state of the game
     SetState(GameState),
     /// Modify the setting of the game
     SetSetting(u16),
     /// Modify the attributes of the game
     SetAttributes(AttrMap),
+    /// Replace the attributes of the game
+    ReplaceAttributes(AttrMap),
+    /// Remove the attributes with the provided keys from the game.
+    /// Clients are sent the full resulting set of attributes
+    RemoveAttributes(Vec<String>),
+    /// Modify the list of players with reserved slots
+    SetReserved(Vec<PlayerID>),
+    /// Adds or removes a player in the game from the admin list
+    ModifyAdminList(PlayerID, AdminListOperation),
+    /// Update the display name of a player in the game
+    SetDisplayName {
+        player_id: PlayerID,
+        display_name: String,
+    },
+    /// Send a chat message to the players in the game from
+    /// either a player or the server when there is no sender
+    ChatMessage {
+        from: Option<PlayerID>,
+        text: String,
+    },
     /// Trigger a mesh connection update
     UpdateMeshConnection {
         session: SessionID,
@@ -112,13 +261,58 @@ pub enum GameModifyAction {
     /// Remove a player with a sender for responding with
     /// whether the game is empty now or not
     RemovePlayer(RemovePlayerType, oneshot::Sender<bool>),
+    /// Transfers host to the player with the provided ID with a sender
+    /// for responding with whether the player was in the game
+    TransferHost(PlayerID, oneshot::Sender<bool>),
+    /// Removes all the players from the game with a sender for
+    /// responding once the players have been removed
+    Close(oneshot::Sender<()>),
+    /// Executes the action on behalf of the player with the provided ID
+    /// if they are allowed to modify the game with a sender for responding
+    /// with whether the action was allowed
+    Authorized {
+        player_id: PlayerID,
+        action: Box<GameModifyAction>,
+        sender: oneshot::Sender<bool>,
+    },
 
-    /// Request for checking if the game is joinable optionally with
-    /// a ruleset for checking attributes against
-    CheckJoinable(Option<RuleSet>, oneshot::Sender<GameJoinableState>),
+    /// Request for checking if the game is joinable by the player with
+    /// the provided details optionally with a ruleset for checking attributes
+    /// against
+    CheckJoinable(
+        JoinDetails,
+        Option<RuleSet>,
+        oneshot::Sender<GameJoinableState>,
+    ),
 
     /// Requests a snapshot of the current game state
     Snapshot(oneshot::Sender<GameSnapshot>),
+
+    /// Requests the decoded contents of the game setup packet for
+    /// debugging purposes
+    DebugSetup(oneshot::Sender<Option<String>>),
+}
+
+/// Details about a player that are used when checking whether
+/// a game is joinable by that player
+#[derive(Clone, Copy)]
+pub struct JoinDetails {
+    /// The ID of the player
+    pub player_id: PlayerID,
+    /// The NAT type of the player if the player has network details
+    pub natt: Option<NatType>,
+}
+
+impl JoinDetails {
+    /// Creates the join details for the provided player
+    ///
+    /// `player` The player wanting to join
+    pub fn new(player: &GamePlayer) -> Self {
+        Self {
+            player_id: player.player.id,
+            natt: player.net.is_set.then_some(player.net.qos.natt),
+        }
+    }
 }
 
 pub enum GameJoinableState {
@@ -126,26 +320,32 @@ pub enum GameJoinableState {
     Joinable,
     /// Game is full
     Full,
+    /// The game has free slots but fewer than the player needs
+    NotEnoughSlots,
     /// The game doesn't match the provided rules
     NotMatch,
+    /// The NAT type of the player is not compatible with the host
+    NatIncompatible,
 }
 
 impl Game {
-    /// Constant for the maximum number of players allowed in
-    /// a game at one time. Used to determine a games full state
-    const MAX_PLAYERS: usize = 4;
+    /// The max number of characters in a chat message
+    const MAX_CHAT_LENGTH: usize = 256;
+    /// The name chat messages sent by the server are prefixed with
+    const SERVER_CHAT_NAME: &str = "Server";
 
-    pub fn spawn(id: GameID, attributes: AttrMap, setting: u16) -> GameAddr {
+    pub fn spawn(
+        id: GameID,
+        attributes: AttrMap,
+        setting: u16,
+        max_players: usize,
+        index: Arc<PlayerIndex>,
+        config: Arc<GameConfig>,
+    ) -> GameAddr {
         let (sender, reciever) = mpsc::unbounded_channel();
-        let game = Self {
-            id,
-            state: GameState::Init,
-            setting,
-            attributes,
-            players: Vec::new(),
-            next_slot: 0,
-            reciever,
-        };
+        let mut game = Self::new(id, attributes, setting, max_players, reciever);
+        game.index = index;
+        game.config = config;
         // Spawn the game processing loop
         tokio::spawn(async move {
             let mut game = game;
@@ -156,12 +356,72 @@ impl Game {
         GameAddr { id, sender }
     }
 
+    /// Creates a new game with the provided initial details
+    ///
+    /// `id`          The unique ID for the game
+    /// `attributes`  The initial game attributes
+    /// `setting`     The initial game setting
+    /// `max_players` The max number of players allowed in the game
+    /// `reciever`    The reciever for game modify actions
+    fn new(
+        id: GameID,
+        attributes: AttrMap,
+        setting: u16,
+        max_players: usize,
+        reciever: mpsc::UnboundedReceiver<GameModifyAction>,
+    ) -> Self {
+        let created_at = unix_millis();
+        audit::log_event("game_created", &[("game_id", &id)]);
+        Self {
+            id,
+            state: GameState::Init,
+            setting,
+            attributes,
+            max_players,
+            players: Vec::new(),
+            next_slot: 0,
+            created_at,
+            last_activity: created_at,
+            reserved: Vec::new(),
+            admins: Vec::new(),
+            has_closed: Cell::new(false),
+            index: Default::default(),
+            config: Default::default(),
+            reciever,
+        }
+    }
+
     fn handle(&mut self, action: GameModifyAction) {
+        // Actions that only read the game state don't count as activity
+        if !matches!(
+            action,
+            GameModifyAction::CheckJoinable(..)
+                | GameModifyAction::Snapshot(..)
+                | GameModifyAction::DebugSetup(..)
+        ) {
+            self.last_activity = unix_millis();
+        }
+
         match action {
             GameModifyAction::AddPlayer(player) => self.add_player(player),
+            GameModifyAction::ReconnectPlayer(player) => self.reconnect_player(player),
             GameModifyAction::SetState(state) => self.set_state(state),
             GameModifyAction::SetSetting(setting) => self.set_setting(setting),
             GameModifyAction::SetAttributes(attributes) => self.set_attributes(attributes),
+            GameModifyAction::ReplaceAttributes(attributes) => self.replace_attributes(attributes),
+            GameModifyAction::RemoveAttributes(keys) => self.remove_attributes(keys),
+            GameModifyAction::SetReserved(reserved) => self.set_reserved(reserved),
+            GameModifyAction::ModifyAdminList(target, operation) => {
+                // Only players within the game can be admins
+                if self.is_player_pid(target) {
+                    self.modify_admin_list(target, operation);
+                }
+            }
+            GameModifyAction::SetDisplayName {
+                player_id,
+                display_name,
+            } => self.set_display_name(player_id, display_name),
+            GameModifyAction::ChatMessage { from, text } => self.chat_message(from, text),
             GameModifyAction::UpdateMeshConnection {
                 session,
                 target,
@@ -171,29 +431,145 @@ impl Game {
                 let is_empty = self.remove_player(ty);
                 sender.send(is_empty).ok();
             }
-            GameModifyAction::CheckJoinable(rules, sender) => {
-                let join_state = self.check_joinable(rules);
+            GameModifyAction::TransferHost(player_id, sender) => {
+                let is_player = self.transfer_host(player_id);
+                sender.send(is_player).ok();
+            }
+            GameModifyAction::Close(sender) => {
+                self.close();
+                sender.send(()).ok();
+            }
+            GameModifyAction::Authorized {
+                player_id,
+                action,
+                sender,
+            } => {
+                let is_admin = self.is_admin(player_id);
+                if is_admin {
+                    self.handle(*action);
+                } else {
+                    warn!(
+                        "Rejected game modification from non admin (PID: {}, GID: {})",
+                        player_id, self.id
+                    );
+                }
+                sender.send(is_admin).ok();
+            }
+            GameModifyAction::CheckJoinable(details, rules, sender) => {
+                let join_state = self.check_joinable(details, rules);
                 sender.send(join_state).ok();
             }
             GameModifyAction::Snapshot(sender) => {
                 let snapshot = self.snapshot();
                 sender.send(snapshot).ok();
             }
+            GameModifyAction::DebugSetup(sender) => {
+                let value = self.debug_setup();
+                sender.send(value).ok();
+            }
         }
+
+        self.remove_closed_players();
     }
 
-    fn check_joinable(&self, rules: Option<RuleSet>) -> GameJoinableState {
-        let is_joinable = self.next_slot < Self::MAX_PLAYERS;
+    /// Removes any players whose sessions were found to be closed while
+    /// sending packets. Players are left in the game when reconnect grace
+    /// is enabled as their slots are being held for them.
+    fn remove_closed_players(&mut self) {
+        if !self.has_closed.take() || !self.config.reconnect_grace.is_zero() {
+            return;
+        }
+
+        let closed: Vec<SessionID> = self
+            .players
+            .iter()
+            .filter(|value| value.addr.is_closed())
+            .map(|value| value.addr.id)
+            .collect();
+        for session_id in closed {
+            debug!(
+                "Removing player with closed session (SID: {}, GID: {})",
+                session_id, self.id
+            );
+            self.remove_player(RemovePlayerType::Session(session_id));
+        }
+
+        // Sends to the removed players while removing don't need handling
+        self.has_closed.set(false);
+    }
+
+    fn check_joinable(&self, details: JoinDetails, rules: Option<RuleSet>) -> GameJoinableState {
+        let min_free_slots = rules.as_ref().map_or(1, |rules| rules.min_free_slots());
         if let Some(rules) = rules {
-            if !rules.matches(&self.attributes) {
+            if let Some(reason) = rules.explain(&self.attributes) {
+                debug!("Game doesn't match rules (GID: {}): {}", self.id, reason);
                 return GameJoinableState::NotMatch;
             }
         }
-        if is_joinable {
-            GameJoinableState::Joinable
-        } else {
-            GameJoinableState::Full
+        if !self.has_free_slot(details.player_id) {
+            return GameJoinableState::Full;
+        }
+        if min_free_slots > 1 && self.free_slots() < min_free_slots {
+            return GameJoinableState::NotEnoughSlots;
+        }
+        if self.config.nat_check && !self.is_nat_compatible(details.natt) {
+            debug!(
+                "Player NAT is incompatible with host (PID: {}, GID: {})",
+                details.player_id, self.id
+            );
+            return GameJoinableState::NatIncompatible;
+        }
+        GameJoinableState::Joinable
+    }
+
+    /// Checks whether a player with the provided NAT type is able to
+    /// connect to the host of this game. Strict NAT players are unable
+    /// to establish a mesh with strict NAT hosts. Missing network
+    /// details are treated as compatible.
+    ///
+    /// `natt` The NAT type of the joining player
+    fn is_nat_compatible(&self, natt: Option<NatType>) -> bool {
+        let host_natt = self
+            .players
+            .first()
+            .filter(|host| host.net.is_set)
+            .map(|host| host.net.qos.natt);
+        !matches!(
+            (host_natt, natt),
+            (Some(NatType::Strict), Some(NatType::Strict))
+        )
+    }
+
+    /// Checks whether there is a free slot for the player with the
+    /// provided ID. Reserved slots that haven't been filled yet are
+    /// only available to the players they are reserved for.
+    ///
+    /// `player_id` The ID of the player wanting a slot
+    fn has_free_slot(&self, player_id: PlayerID) -> bool {
+        if self.next_slot >= self.max_players {
+            return false;
+        }
+        if self.reserved.contains(&player_id) {
+            return true;
         }
+        let open_reserved = self
+            .reserved
+            .iter()
+            .filter(|id| !self.is_player_pid(**id))
+            .count();
+        self.next_slot + open_reserved < self.max_players
+    }
+
+    /// Counts the number of slots in this game that aren't filled
+    /// or reserved for a player
+    fn free_slots(&self) -> usize {
+        let open_reserved = self
+            .reserved
+            .iter()
+            .filter(|id| !self.is_player_pid(**id))
+            .count();
+        self.max_players
+            .saturating_sub(self.next_slot + open_reserved)
     }
 
     /// Takes a snapshot of the current game state for serialization
@@ -204,19 +580,52 @@ impl Game {
             state: self.state,
             setting: self.setting,
             attributes: self.attributes.clone(),
+            label: self.label(),
+            host_id: self.players.first().map(|host| host.player.id),
             players,
+            created_at: self.created_at,
+            last_activity: self.last_activity,
+            reserved: self.reserved.clone(),
+            max_players: self.max_players,
+        }
+    }
+
+    /// Finds the display label for this game from the value of the
+    /// configured label attribute. An empty attribute key disables
+    /// labels
+    fn label(&self) -> Option<String> {
+        let key = &self.config.label_attribute;
+        if key.is_empty() {
+            return None;
         }
+        self.attributes.get(key).cloned()
     }
 
     /// Writes the provided packet to all connected sessions.
     /// Does not wait for the write to complete just waits for
-    /// it to be placed into each sessions write buffers.
+    /// it to be placed into each sessions write buffers. Players
+    /// with closed sessions are removed after the current action.
     ///
     /// `packet` The packet to write
     fn push_all(&self, packet: &Packet) {
-        self.players
-            .iter()
-            .for_each(|value| value.addr.push(packet.clone()));
+        for value in &self.players {
+            if !value.addr.push(packet.clone()) {
+                self.has_closed.set(true);
+            }
+        }
+    }
+
+    /// Writes all the provided packets to every player in the game as
+    /// a single batch per player so that each session is only notified
+    /// once for the whole batch
+    ///
+    /// `packets` The packets to write
+    fn push_all_many(&self, packets: &[Packet]) {
+        for value in &self.players {
+            if !value.addr.push_many(packets.to_vec()) {
+                self.has_closed.set(true);
+            }
+        }
     }
 
     /// Sends a notification packet to all the connected session
@@ -249,6 +658,19 @@ impl Game {
     ///
     /// `setting` The new setting value
     fn set_setting(&mut self, setting: u16) {
+        // Strip any bits that aren't allowed in the setting
+        let mask = self.config.setting_mask;
+        let setting = if setting & !mask != 0 {
+            let sanitized = setting & mask;
+            warn!(
+                "Stripped disallowed bits from game setting (GID: {}, Value: {}, Sanitized: {})",
+                self.id, setting, sanitized
+            );
+            sanitized
+        } else {
+            setting
+        };
+
         debug!("Updating game setting (Value: {setting})");
 
         self.setting = setting;
@@ -268,6 +690,11 @@ impl Game {
     /// `attributes` The new attributes
     fn set_attributes(&mut self, attributes: AttrMap) {
         debug!("Updating game attributes");
+        let limits = &self.config.attribute_limits;
+        let attributes = limits.apply(self.id, &self.attributes, attributes);
+        if attributes.is_empty() {
+            return;
+        }
         let packet = Packet::notify(
             Components::GameManager(GameManager::GameAttribChange),
             AttributesChange {
@@ -279,6 +706,151 @@ impl Game {
         self.push_all(&packet);
     }
 
+    /// Replaces the current game attributes in the game data removing
+    /// any existing attributes and sends an update notification to all
+    /// connected clients notifying them of the new attributes
+    ///
+    /// `attributes` The new attributes
+    fn replace_attributes(&mut self, attributes: AttrMap) {
+        debug!("Replacing game attributes");
+        let limits = &self.config.attribute_limits;
+        let attributes = limits.apply(self.id, &AttrMap::default(), attributes);
+        let packet = Packet::notify(
+            Components::GameManager(GameManager::GameAttribChange),
+            AttributesChange {
+                id: self.id,
+                attributes: &attributes,
+            },
+        );
+        self.attributes = attributes;
+        self.push_all(&packet);
+    }
+
+    /// Removes the attributes with the provided keys from the game data.
+    /// Attribute change notifications are merged with the existing
+    /// attributes by clients so the full resulting set of attributes is
+    /// sent to all connected clients. No notification is sent if none
+    /// of the keys were present.
+    ///
+    /// `keys` The keys of the attributes to remove
+    fn remove_attributes(&mut self, keys: Vec<String>) {
+        debug!("Removing game attributes");
+        let mut removed = false;
+        for key in &keys {
+            removed |= self.attributes.remove(key).is_some();
+        }
+        if !removed {
+            return;
+        }
+
+        let packet = Packet::notify(
+            Components::GameManager(GameManager::GameAttribChange),
+            AttributesChange {
+                id: self.id,
+                attributes: &self.attributes,
+            },
+        );
+        self.push_all(&packet);
+    }
+
+    /// Sets the list of players that have slots reserved in this
+    /// game replacing any previous reservations
+    ///
+    /// `reserved` The IDs of the players to reserve slots for
+    fn set_reserved(&mut self, reserved: Vec<PlayerID>) {
+        debug!(
+            "Updating reserved slots (GID: {}, Count: {})",
+            self.id,
+            reserved.len()
+        );
+        self.reserved = reserved;
+    }
+
+    /// Updates the display name of the player with the provided ID and
+    /// sends the updated player details to the other players
+    ///
+    /// `player_id`    The ID of the player
+    /// `display_name` The new display name
+    fn set_display_name(&mut self, player_id: PlayerID, display_name: String) {
+        let Some(index) = self
+            .players
+            .iter()
+            .position(|value| value.player.id == player_id)
+        else {
+            return;
+        };
+        self.players[index].player.display_name = display_name;
+
+        let player = &self.players[index];
+        self.players
+            .iter()
+            .filter(|value| value.player.id != player_id)
+            .for_each(|value| player.write_updates(value));
+    }
+
+    /// Sends a chat message to the players in the game. Messages from
+    /// a player are prefixed with their display name and sent to the
+    /// other players. Messages without a sender are from the server and
+    /// are sent to every player. Messages from players that aren't in
+    /// the game or that are empty after sanitizing are ignored.
+    ///
+    /// `from` The ID of the player sending the message if any
+    /// `text` The text of the message
+    fn chat_message(&self, from: Option<PlayerID>, text: String) {
+        let name = match from {
+            Some(from) => {
+                let Some(sender) = self.players.iter().find(|value| value.player.id == from) else {
+                    return;
+                };
+                sender.player.display_name.as_str()
+            }
+            None => Self::SERVER_CHAT_NAME,
+        };
+        let Some(text) = Self::sanitize_chat(&text) else {
+            return;
+        };
+
+        debug!("Chat message in game (GID: {}, From: {:?})", self.id, from);
+
+        // Line terminator for the end of the message
+        let message = format!("{}: {}\n", name, text);
+        for player in &self.players {
+            let target = player.player.id;
+            if from == Some(target) {
+                continue;
+            }
+            let packet = Packet::notify(
+                Components::Messaging(Messaging::SendMessage),
+                ChatMessage {
+                    // Server messages are shown as coming from the player
+                    source: from.unwrap_or(target),
+                    target,
+                    message: &message,
+                },
+            );
+            if !player.addr.push(packet) {
+                self.has_closed.set(true);
+            }
+        }
+    }
+
+    /// Sanitizes the text of a chat message by removing any control
+    /// characters and trimming the surrounding whitespace. Messages
+    /// longer than the max chat length are truncated. Returns None
+    /// if the message is empty.
+    ///
+    /// `text` The text of the message
+    fn sanitize_chat(text: &str) -> Option<String> {
+        let text: String = text.chars().filter(|value| !value.is_control()).collect();
+        let text: String = text.trim().chars().take(Self::MAX_CHAT_LENGTH).collect();
+        let text = text.trim_end();
+        if text.is_empty() {
+            None
+        } else {
+            Some(text.to_string())
+        }
+    }
+
     /// Updates all the client details for the provided session.
     /// Tells each client to send session updates to the session
     /// and the session to send them as well.
@@ -299,6 +871,13 @@ impl Game {
         self.players.iter().any(|value| value.addr.id == sid)
     }
 
+    /// Finds the index of the player with the provided player ID
+    ///
+    /// `pid` The player ID
+    fn player_index(&self, pid: PlayerID) -> Option<usize> {
+        self.players.iter().position(|value| value.player.id == pid)
+    }
+
     /// Checks whether this game contains a player with the provided
     /// player ID
     ///
@@ -321,25 +900,123 @@ impl Game {
     ///
     /// `session` The session to add
     fn add_player(&mut self, mut player: GamePlayer) {
+        // Slots may have been filled or reserved since the player was
+        // checked as joinable
+        if !self.has_free_slot(player.player.id) {
+            debug!(
+                "No free slot for player (PID: {}, GID: {})",
+                player.player.id, self.id
+            );
+            return;
+        }
+
         let slot = self.aquire_slot();
         player.game_id = self.id;
+        player.joined_at = Instant::now();
 
+        // Packets are sent in the following order:
+        // - PlayerJoining to the other players
+        // - Session details of the joining player to the other players
+        //   and of the other players to the joining player
+        // - GameSetup to the joining player
+        // - SetSession for the joining player to all the players
         self.notify_player_joining(&player, slot);
         self.update_clients(&player);
         self.notify_game_setup(&player, slot);
 
-        player.addr.set_game(Some(self.id));
+        // The session may have been closed while the player was joining
+        if player.addr.is_closed() {
+            self.abort_add_player(&player);
+            return;
+        }
+
+        let role = if slot == 0 {
+            GameRole::Host
+        } else {
+            GameRole::Player
+        };
+        player.addr.set_game(self.id, role);
+        self.index.insert(player.player.id, self.id);
 
         let packet = player.create_set_session();
         self.push_all(&packet);
+        player.addr.push(packet);
+
+        audit::log_event(
+            "player_joined",
+            &[
+                ("game_id", &self.id),
+                ("player_id", &player.player.id),
+                ("slot", &slot),
+                ("ip", &player.net.groups.external.0),
+            ],
+        );
 
         self.players.push(player);
 
         debug!("Adding player complete");
     }
 
-    /// Notifies all the players in the game that a new player has
-    /// joined the game.
+    /// Replaces the disconnected player in the game that has the same
+    /// player ID as the provided player keeping their slot. The other
+    /// players are updated with the details of the new session.
+    ///
+    /// `player` The player from the resumed session
+    fn reconnect_player(&mut self, mut player: GamePlayer) {
+        let Some(index) = self
+            .players
+            .iter()
+            .position(|value| value.player.id == player.player.id)
+        else {
+            debug!(
+                "Held player no longer in game (PID: {}, GID: {})",
+                player.player.id, self.id
+            );
+            return;
+        };
+
+        player.game_id = self.id;
+        let role = if index == 0 {
+            GameRole::Host
+        } else {
+            GameRole::Player
+        };
+        player.addr.set_game(self.id, role);
+
+        self.players[index] = player;
+
+        let player = &self.players[index];
+        self.update_clients(player);
+        let packet = player.create_set_session();
+        self.push_all(&packet);
+
+        debug!(
+            "Reconnected player to game (PID: {}, GID: {})",
+            player.player.id, self.id
+        );
+    }
+
+    /// Aborts adding a player whose session was closed before they
+    /// finished joining. The acquired slot is released and the other
+    /// players are notified that the player was removed.
+    ///
+    /// `player` The player that was being added
+    fn abort_add_player(&mut self, player: &GamePlayer) {
+        debug!(
+            "Session closed while adding player (PID: {}, GID: {})",
+            player.player.id, self.id
+        );
+        self.notify_player_removed(player, RemoveReason::Generic);
+        self.release_slot();
+    }
+
+    /// Notifies all the other players in the game that a new player has
+    /// joined the game. The joining player isn't notified as they receive
+    /// the same details in the game setup and the host is skipped as
+    /// there are no other players to notify.
+    ///
+    /// `player` The player that is joining
+    /// `slot`   The slot the player is joining into
     fn notify_player_joining(&self, player: &GamePlayer, slot: GameSlot) {
         if slot == 0 {
             return;
@@ -349,7 +1026,6 @@ impl Game {
             PlayerJoining { slot, player },
         );
         self.push_all(&packet);
-        player.addr.push(packet);
     }
 
     /// Notifies the provided player that the game has been setup and
@@ -375,6 +1051,33 @@ impl Game {
         player.addr.push(packet);
     }
 
+    /// Creates the game setup packet that was sent to the most recently
+    /// joined player using the current game state and decodes it into
+    /// its string representation. Returns None if the game has no players
+    fn debug_setup(&mut self) -> Option<String> {
+        // The joining player isn't in the players list when the packet is created
+        let player = self.players.pop()?;
+        let ty = if self.players.is_empty() {
+            GameDetailsType::Created
+        } else {
+            GameDetailsType::Joined
+        };
+
+        let packet = Packet::notify(
+            Components::GameManager(GameManager::GameSetup),
+            GameDetails {
+                game: self,
+                player: &player,
+                ty,
+            },
+        );
+        self.players.push(player);
+
+        let mut output = String::new();
+        append_packet_decoded(&packet, &mut output);
+        Some(output.trim_start().to_string())
+    }
+
     /// Sets the state for the provided session notifying all
     /// the players that the players state has changed.
     ///
@@ -403,13 +1106,34 @@ impl Game {
         Some(old_state)
     }
 
-    /// Modifies the psudo admin list this list doesn't actually exist in
-    /// our implementation but we still need to tell the clients these
-    /// changes.
+    /// Checks whether the player with the provided ID is allowed to modify
+    /// the game. Only players within the game are allowed. When admins
+    /// aren't enforced every player in the game is allowed otherwise only
+    /// the host and players in the admin list are allowed
+    ///
+    /// `player_id` The ID of the player
+    fn is_admin(&self, player_id: PlayerID) -> bool {
+        let Some(index) = self.player_index(player_id) else {
+            return false;
+        };
+        !self.config.enforce_admins || index == 0 || self.admins.contains(&player_id)
+    }
+
+    /// Modifies the admin list of the game and notifies the clients of
+    /// the change. The admin list is only used to restrict who can modify
+    /// the game when admins are enforced.
     ///
     /// `target`    The player to target for the admin list
     /// `operation` Whether to add or remove the player from the admin list
-    fn modify_admin_list(&self, target: PlayerID, operation: AdminListOperation) {
+    fn modify_admin_list(&mut self, target: PlayerID, operation: AdminListOperation) {
+        match operation {
+            AdminListOperation::Add => {
+                if !self.admins.contains(&target) {
+                    self.admins.push(target);
+                }
+            }
+            AdminListOperation::Remove => self.admins.retain(|value| *value != target),
+        }
         let host_id = {
             let Some(host) = self.players.first() else {
                 return;
@@ -442,7 +1166,11 @@ impl Game {
             }
             PlayerState::Connecting => {
                 if self.is_player_sid(session) && self.is_player_pid(target) {
-                    self.set_player_state(session, PlayerState::Connected);
+                    let old_state = self.set_player_state(session, PlayerState::Connected);
+                    // Only the first connection made by the player is recorded
+                    if old_state != Some(PlayerState::Connected) {
+                        self.record_mesh_time(session);
+                    }
                     self.on_join_complete(session);
                     debug!("Connected player to game")
                 } else {
@@ -454,12 +1182,31 @@ impl Game {
         }
     }
 
+    /// Records the time taken for the player with the provided session
+    /// to establish their mesh connection since joining the game
+    ///
+    /// `session` The session that connected
+    fn record_mesh_time(&self, session: SessionID) {
+        let Some(player) = self.players.iter().find(|value| value.addr.id == session) else {
+            return;
+        };
+        let elapsed = player.joined_at.elapsed();
+        debug!(
+            "Player mesh connected (PID: {}, GID: {}, Time: {}ms)",
+            player.player.id,
+            self.id,
+            elapsed.as_millis()
+        );
+        GlobalState::metrics().mesh.record(elapsed);
+    }
+
     /// Handles informing the other players in the game when a player joining
     /// is complete (After the mesh connection is updated) and modifies the
-    /// admin list to include the newly added session
+    /// admin list to include the newly added session unless admins are
+    /// enforced
     ///
     /// `session` The session that completed joining
-    fn on_join_complete(&self, session: SessionID) {
+    fn on_join_complete(&mut self, session: SessionID) {
         let Some(player) = self.players.iter().find(|value| value.addr.id == session) else {
             return;
         };
@@ -471,7 +1218,10 @@ impl Game {
             },
         );
         self.push_all(&packet);
-        self.modify_admin_list(player.player.id, AdminListOperation::Add);
+        if !self.config.enforce_admins {
+            let player_id = player.player.id;
+            self.modify_admin_list(player_id, AdminListOperation::Add);
+        }
     }
 
     fn remove_player(&mut self, ty: RemovePlayerType) -> bool {
@@ -501,9 +1251,17 @@ impl Game {
             (player, index, reason, self.players.is_empty())
         };
 
-        player.addr.set_game(None);
+        player.addr.remove_game(self.id);
+        self.index.remove(player.player.id, self.id);
+        audit::log_event(
+            "player_removed",
+            &[
+                ("game_id", &self.id),
+                ("player_id", &player.player.id),
+                ("reason", &format!("{reason:?}")),
+            ],
+        );
         self.notify_player_removed(&player, reason);
-        self.notify_fetch_data(&player);
         self.modify_admin_list(player.player.id, AdminListOperation::Remove);
 
         // Possibly not needed
@@ -522,13 +1280,37 @@ impl Game {
         is_empty
     }
 
+    /// Removes all the players from the game notifying each player that
+    /// the game was destroyed. Players are removed starting from the last
+    /// slot and the host is not migrated as the game is being closed
+    fn close(&mut self) {
+        while let Some(player) = self.players.pop() {
+            player.addr.remove_game(self.id);
+            self.index.remove(player.player.id, self.id);
+            audit::log_event(
+                "player_removed",
+                &[
+                    ("game_id", &self.id),
+                    ("player_id", &player.player.id),
+                    ("reason", &format!("{:?}", RemoveReason::GameDestroyed)),
+                ],
+            );
+            self.notify_player_removed(&player, RemoveReason::GameDestroyed);
+            self.release_slot();
+        }
+        debug!("Closed game (GID: {})", self.id);
+    }
+
     /// Notifies all the session and the removed session that a
-    /// session was removed from the game.
+    /// session was removed from the game. The remaining sessions are
+    /// also told to fetch the player data for the removed session and
+    /// the removed session is told to fetch the extended data for all
+    /// the remaining sessions.
     ///
     /// `player`    The player that was removed
     /// `player_id` The player ID of the removed player
     fn notify_player_removed(&self, player: &GamePlayer, reason: RemoveReason) {
-        let packet = Packet::notify(
+        let removed_packet = Packet::notify(
             Components::GameManager(GameManager::PlayerRemoved),
             PlayerRemoved {
                 game_id: self.id,
@@ -536,51 +1318,104 @@ impl Game {
                 reason,
             },
         );
-        self.push_all(&packet);
-        player.addr.push(packet);
-    }
-
-    /// Notifies all the sessions in the game to fetch the player data
-    /// for the provided session and the session to fetch the extended
-    /// data for all the other sessions. Will early return if there
-    /// are no players left.
-    ///
-    /// `session`   The session to update with the other clients
-    /// `player_id` The player id of the session to update
-    fn notify_fetch_data(&self, player: &GamePlayer) {
-        let removed_packet = Packet::notify(
+        let fetch_packet = Packet::notify(
             Components::UserSessions(UserSessions::FetchExtendedData),
             FetchExtendedData {
                 player_id: player.player.id,
             },
         );
-        self.push_all(&removed_packet);
 
+        let mut packets = Vec::with_capacity(self.players.len() + 1);
+        packets.push(removed_packet.clone());
         for other_player in &self.players {
-            let packet = Packet::notify(
+            packets.push(Packet::notify(
                 Components::UserSessions(UserSessions::FetchExtendedData),
                 FetchExtendedData {
                     player_id: other_player.player.id,
                 },
-            );
-            player.addr.push(packet)
+            ));
         }
+
+        self.push_all_many(&[removed_packet, fetch_packet]);
+        player.addr.push_many(packets);
     }
 
     /// Attempts to migrate the host of this game if there are still players
-    /// left in the game.
+    /// left in the game. The player with the best connectivity is moved into
+    /// the host slot before migrating.
     fn try_migrate_host(&mut self) {
+        let index = self.find_best_host().unwrap_or(0);
+        self.migrate_host(index);
+    }
+
+    /// Transfers host to the player with the provided ID. The current
+    /// host stays in the game as a normal player. Returns whether the
+    /// player was in the game.
+    ///
+    /// `player_id` The ID of the player to make host
+    fn transfer_host(&mut self, player_id: PlayerID) -> bool {
+        let index = match self.player_index(player_id) {
+            Some(value) => value,
+            None => return false,
+        };
+        if index != 0 {
+            let old_host = &self.players[0];
+            old_host.addr.set_game(self.id, GameRole::Player);
+            self.migrate_host(index);
+        }
+        true
+    }
+
+    /// Migrates the host of this game to the player at the provided index
+    /// moving them into the host slot and notifying all the players.
+    ///
+    /// `index` The index of the player to migrate to
+    fn migrate_host(&mut self, index: usize) {
+        if index != 0 && index < self.players.len() {
+            let player = self.players.remove(index);
+            self.players.insert(0, player);
+        }
+
         self.set_state(GameState::HostMigration);
         debug!("Starting host migration (GID: {})", self.id);
         self.notify_migrate_start();
         self.set_state(GameState::InGame);
         self.notify_migrate_finish();
         let Some(new_host) = self.players.first() else { return; };
+        new_host.addr.set_game(self.id, GameRole::Host);
         self.update_clients(new_host);
+        audit::log_event(
+            "host_migrated",
+            &[("game_id", &self.id), ("host_id", &new_host.player.id)],
+        );
 
         debug!("Finished host migration (GID: {})", self.id);
     }
 
+    /// Finds the index of the player with the best connectivity to use as
+    /// the new host. Players with a less strict NAT type are preferred
+    /// followed by higher upstream bandwidth. Players without any network
+    /// data are only chosen if no other players have data in which case
+    /// the first player is used.
+    fn find_best_host(&self) -> Option<usize> {
+        self.players
+            .iter()
+            .enumerate()
+            .filter(|(_, player)| player.net.is_set)
+            .min_by_key(|(_, player)| {
+                let qos = &player.net.qos;
+                let nat_rank = match qos.natt {
+                    NatType::Open => 0,
+                    NatType::Moderate => 1,
+                    NatType::Sequential => 2,
+                    NatType::Strict => 3,
+                    NatType::Unknown(_) => 4,
+                };
+                (nat_rank, Reverse(qos.ubps))
+            })
+            .map(|(index, _)| index)
+    }
+
     /// Notifies all the sessions in this game that host migration has
     /// begun.
     ///
@@ -609,7 +1444,11 @@ impl Game {
 
 impl Drop for Game {
     fn drop(&mut self) {
-        debug!("Game has been dropped (GID: {})", self.id)
+        debug!("Game has been dropped (GID: {})", self.id);
+        for player in &self.players {
+            self.index.remove(player.player.id, self.id);
+        }
+        audit::log_event("game_closed", &[("game_id", &self.id)]);
     }
 }
 
@@ -618,3 +1457,863 @@ pub enum RemovePlayerType {
     Session(SessionID),
     Player(PlayerID, RemoveReason),
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        attributes::AttributeLimits,
+        config::GameConfig,
+        models::{AdminListOperation, FetchExtendedData, GameState, PlayerState, RemoveReason},
+        player::GamePlayer,
+        rules::RuleSet,
+        AttrMap, Game, GameJoinableState, GameModifyAction, JoinDetails, RemovePlayerType,
+    };
+    use crate::{
+        servers::main::session::{GameRole, SessionAddr, SessionMessage},
+        state::GlobalState,
+        utils::{
+            audit,
+            components::{Components, GameManager, Messaging, UserSessions},
+            logging::capture,
+            models::{NatType, NetData, QosNetworkData},
+        },
+    };
+    use blaze_pk::{packet::Packet, reader::TdfReader};
+    use database::Player;
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+    use tokio::sync::{mpsc, oneshot};
+
+    #[test]
+    fn test_last_activity() {
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(1, AttrMap::default(), 0, 4, reciever);
+        let created_at = game.created_at;
+        assert_eq!(game.last_activity, created_at);
+
+        game.last_activity = 0;
+        game.handle(GameModifyAction::SetState(GameState::InGame));
+
+        assert!(game.last_activity >= created_at);
+        assert_eq!(game.created_at, created_at);
+
+        let snapshot = game.snapshot();
+        assert_eq!(snapshot.created_at, created_at);
+        assert_eq!(snapshot.last_activity, game.last_activity);
+    }
+
+    /// Collection of the sessions of the players created for a test
+    /// which keeps the sessions open for the duration of the test
+    #[derive(Default)]
+    struct TestSessions(Vec<mpsc::UnboundedReceiver<SessionMessage>>);
+
+    impl TestSessions {
+        /// Creates a game player with the provided ID for testing
+        fn player(&mut self, id: u32) -> GamePlayer {
+            let (player, receiver) = test_player_session(id);
+            self.0.push(receiver);
+            player
+        }
+    }
+
+    /// Creates a game player with the provided ID for testing along
+    /// with the receiver for the messages sent to its session
+    fn test_player_session(id: u32) -> (GamePlayer, mpsc::UnboundedReceiver<SessionMessage>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let player = Player {
+            id,
+            email: format!("test{id}@test.com"),
+            display_name: format!("Test{id}"),
+            session_token: None,
+            origin: false,
+            password: String::new(),
+            created_at: None,
+        };
+        let player = GamePlayer::new(
+            player,
+            NetData::default(),
+            SessionAddr::new(id, sender),
+            Default::default(),
+        );
+        (player, receiver)
+    }
+
+    /// Takes the components of all the packets written to the session
+    /// of the provided receiver
+    fn written_components(
+        receiver: &mut mpsc::UnboundedReceiver<SessionMessage>,
+    ) -> Vec<Components> {
+        let mut components = Vec::new();
+        while let Ok(message) = receiver.try_recv() {
+            match message {
+                SessionMessage::Write(packet) => {
+                    components.push(Components::from_header(&packet.header))
+                }
+                SessionMessage::WriteMany(packets) => components.extend(
+                    packets
+                        .iter()
+                        .map(|packet| Components::from_header(&packet.header)),
+                ),
+                _ => {}
+            }
+        }
+        components
+    }
+
+    /// Tests the order of the packets sent to the players in the game
+    /// when a second player joins the game
+    #[test]
+    fn test_join_packet_order() {
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(1, AttrMap::default(), 0, 4, reciever);
+
+        let (host, mut host_session) = test_player_session(1);
+        game.add_player(host);
+        assert_eq!(
+            written_components(&mut host_session),
+            vec![
+                Components::GameManager(GameManager::GameSetup),
+                Components::UserSessions(UserSessions::SetSession),
+            ]
+        );
+
+        let (player, mut player_session) = test_player_session(2);
+        game.add_player(player);
+        assert_eq!(
+            written_components(&mut host_session),
+            vec![
+                Components::GameManager(GameManager::PlayerJoining),
+                Components::UserSessions(UserSessions::SessionDetails),
+                Components::UserSessions(UserSessions::UpdateExtendedDataAttribute),
+                Components::UserSessions(UserSessions::SetSession),
+            ]
+        );
+        assert_eq!(
+            written_components(&mut player_session),
+            vec![
+                Components::UserSessions(UserSessions::SessionDetails),
+                Components::UserSessions(UserSessions::UpdateExtendedDataAttribute),
+                Components::GameManager(GameManager::GameSetup),
+                Components::UserSessions(UserSessions::SetSession),
+            ]
+        );
+    }
+
+    /// Takes the roles from the set game messages sent to the session
+    /// of the provided receiver
+    fn set_game_roles(receiver: &mut mpsc::UnboundedReceiver<SessionMessage>) -> Vec<GameRole> {
+        let mut roles = Vec::new();
+        while let Ok(message) = receiver.try_recv() {
+            if let SessionMessage::SetGame(_, role) = message {
+                roles.push(role);
+            }
+        }
+        roles
+    }
+
+    /// Tests that the host of the game is given the host role and
+    /// the players that join after are given the player role
+    #[test]
+    fn test_join_roles() {
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(1, AttrMap::default(), 0, 4, reciever);
+
+        let (host, mut host_session) = test_player_session(1);
+        game.add_player(host);
+        let (player, mut player_session) = test_player_session(2);
+        game.add_player(player);
+
+        assert_eq!(set_game_roles(&mut host_session), vec![GameRole::Host]);
+        assert_eq!(set_game_roles(&mut player_session), vec![GameRole::Player]);
+    }
+
+    /// Tests that transferring host moves the player into the host slot
+    /// and notifies the players of the migration
+    #[test]
+    fn test_transfer_host() {
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(1, AttrMap::default(), 0, 4, reciever);
+        let mut sessions = Vec::new();
+        for id in 1..=3 {
+            let (player, session) = test_player_session(id);
+            game.add_player(player);
+            sessions.push(session);
+        }
+        for session in &mut sessions {
+            written_components(session);
+        }
+
+        assert!(!game.transfer_host(4));
+        assert!(game.transfer_host(3));
+
+        let ids: Vec<u32> = game.players.iter().map(|value| value.player.id).collect();
+        assert_eq!(ids, vec![3, 1, 2]);
+
+        let mut roles = Vec::new();
+        for (id, session) in (1..).zip(sessions.iter_mut()) {
+            let mut components = Vec::new();
+            while let Ok(message) = session.try_recv() {
+                match message {
+                    SessionMessage::SetGame(_, role) => roles.push((id, role)),
+                    SessionMessage::Write(packet) => {
+                        components.push(Components::from_header(&packet.header))
+                    }
+                    _ => {}
+                }
+            }
+            assert!(components.contains(&Components::GameManager(GameManager::HostMigrationStart)));
+            assert!(
+                components.contains(&Components::GameManager(GameManager::HostMigrationFinished))
+            );
+        }
+
+        // The old host becomes a normal player and the new host is updated
+        assert_eq!(roles, vec![(1, GameRole::Player), (3, GameRole::Host)]);
+    }
+
+    /// Tests that the host in the snapshot is updated when the host
+    /// leaves and the host is migrated to another player
+    #[test]
+    fn test_snapshot_host() {
+        let mut sessions = TestSessions::default();
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(1, AttrMap::default(), 0, 4, reciever);
+        assert_eq!(game.snapshot().host_id, None);
+
+        for id in 1..=3 {
+            game.add_player(sessions.player(id));
+        }
+        assert_eq!(game.snapshot().host_id, Some(1));
+
+        game.remove_player(RemovePlayerType::Player(1, RemoveReason::Generic));
+        let snapshot = game.snapshot();
+        assert_eq!(snapshot.host_id, Some(2));
+        assert_eq!(snapshot.players[0].player_id, 2);
+    }
+
+    /// Tests that when admins are enforced only the host is allowed to
+    /// change the game state
+    #[test]
+    fn test_enforce_admins() {
+        let mut sessions = TestSessions::default();
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(1, AttrMap::default(), 0, 4, reciever);
+        game.config = Arc::new(GameConfig {
+            enforce_admins: true,
+            ..Default::default()
+        });
+        game.add_player(sessions.player(1));
+        game.add_player(sessions.player(2));
+        let initial_state = game.state;
+
+        let (sender, mut result) = oneshot::channel();
+        game.handle(GameModifyAction::Authorized {
+            player_id: 2,
+            action: Box::new(GameModifyAction::SetState(GameState::InGame)),
+            sender,
+        });
+        assert_eq!(result.try_recv(), Ok(false));
+        assert_eq!(game.state, initial_state);
+
+        let (sender, mut result) = oneshot::channel();
+        game.handle(GameModifyAction::Authorized {
+            player_id: 1,
+            action: Box::new(GameModifyAction::SetState(GameState::InGame)),
+            sender,
+        });
+        assert_eq!(result.try_recv(), Ok(true));
+        assert_eq!(game.state, GameState::InGame);
+
+        // Players granted admin by the host are allowed
+        let (sender, mut result) = oneshot::channel();
+        game.handle(GameModifyAction::Authorized {
+            player_id: 1,
+            action: Box::new(GameModifyAction::ModifyAdminList(
+                2,
+                AdminListOperation::Add,
+            )),
+            sender,
+        });
+        assert_eq!(result.try_recv(), Ok(true));
+        let (sender, mut result) = oneshot::channel();
+        game.handle(GameModifyAction::Authorized {
+            player_id: 2,
+            action: Box::new(GameModifyAction::SetState(initial_state)),
+            sender,
+        });
+        assert_eq!(result.try_recv(), Ok(true));
+        assert_eq!(game.state, initial_state);
+
+        // Players outside the game are never allowed
+        game.config = Arc::new(GameConfig::default());
+        let (sender, mut result) = oneshot::channel();
+        game.handle(GameModifyAction::Authorized {
+            player_id: 3,
+            action: Box::new(GameModifyAction::SetState(GameState::InGame)),
+            sender,
+        });
+        assert_eq!(result.try_recv(), Ok(false));
+        assert_eq!(game.state, initial_state);
+    }
+
+    /// Tests that broadcasting several packets to a game sends each
+    /// player a single batch rather than a message per packet
+    #[test]
+    fn test_push_all_many() {
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(1, AttrMap::default(), 0, 4, reciever);
+        let mut sessions = Vec::new();
+        for id in 1..=2 {
+            let (player, session) = test_player_session(id);
+            game.add_player(player);
+            sessions.push(session);
+        }
+        for session in &mut sessions {
+            written_components(session);
+        }
+
+        let packets: Vec<Packet> = (1..=3)
+            .map(|player_id| {
+                Packet::notify(
+                    Components::UserSessions(UserSessions::FetchExtendedData),
+                    FetchExtendedData { player_id },
+                )
+            })
+            .collect();
+        game.push_all_many(&packets);
+
+        for session in &mut sessions {
+            let mut messages = Vec::new();
+            while let Ok(message) = session.try_recv() {
+                messages.push(message);
+            }
+            assert_eq!(messages.len(), 1);
+            assert!(matches!(
+                &messages[0],
+                SessionMessage::WriteMany(packets) if packets.len() == 3
+            ));
+        }
+    }
+
+    /// Tests that players whose sessions are closed are removed when
+    /// a packet is sent to all the players
+    #[test]
+    fn test_remove_closed_players() {
+        let mut sessions = TestSessions::default();
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(1, AttrMap::default(), 0, 4, reciever);
+        game.add_player(sessions.player(1));
+
+        let (sender, session) = mpsc::unbounded_channel();
+        let player = sessions.player(2);
+        game.add_player(GamePlayer::new(
+            player.player.clone(),
+            NetData::default(),
+            SessionAddr::new(2, sender),
+            Default::default(),
+        ));
+        assert_eq!(game.players.len(), 2);
+
+        // The session is closed after joining
+        drop(session);
+        game.handle(GameModifyAction::SetState(GameState::InGame));
+
+        let ids: Vec<u32> = game.players.iter().map(|value| value.player.id).collect();
+        assert_eq!(ids, vec![1]);
+    }
+
+    /// Tests that players whose sessions are closed are kept in the game
+    /// when reconnect grace is enabled
+    #[test]
+    fn test_keep_closed_players() {
+        let mut sessions = TestSessions::default();
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(1, AttrMap::default(), 0, 4, reciever);
+        game.config = Arc::new(GameConfig {
+            reconnect_grace: Duration::from_secs(30),
+            ..Default::default()
+        });
+        game.add_player(sessions.player(1));
+
+        let (sender, session) = mpsc::unbounded_channel();
+        let player = sessions.player(2);
+        game.add_player(GamePlayer::new(
+            player.player.clone(),
+            NetData::default(),
+            SessionAddr::new(2, sender),
+            Default::default(),
+        ));
+
+        drop(session);
+        game.handle(GameModifyAction::SetState(GameState::InGame));
+
+        let ids: Vec<u32> = game.players.iter().map(|value| value.player.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_reserved_slots() {
+        let mut sessions = TestSessions::default();
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(1, AttrMap::default(), 0, 4, reciever);
+        game.handle(GameModifyAction::SetReserved(vec![4]));
+
+        for id in 1..=3 {
+            game.add_player(sessions.player(id));
+        }
+        assert_eq!(game.players.len(), 3);
+
+        // The last slot is reserved so only the reserved player can join
+        assert!(matches!(
+            game.check_joinable(JoinDetails::new(&sessions.player(5)), None),
+            GameJoinableState::Full
+        ));
+        assert!(matches!(
+            game.check_joinable(JoinDetails::new(&sessions.player(4)), None),
+            GameJoinableState::Joinable
+        ));
+
+        game.add_player(sessions.player(5));
+        assert_eq!(game.players.len(), 3);
+
+        game.add_player(sessions.player(4));
+        assert_eq!(game.players.len(), 4);
+        assert!(matches!(
+            game.check_joinable(JoinDetails::new(&sessions.player(4)), None),
+            GameJoinableState::Full
+        ));
+    }
+
+    /// Tests that parties needing more slots than the game has
+    /// free are unable to join
+    #[test]
+    fn test_min_free_slots() {
+        let mut sessions = TestSessions::default();
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(1, AttrMap::default(), 0, 4, reciever);
+        for id in 1..=3 {
+            game.add_player(sessions.player(id));
+        }
+
+        let party = RuleSet::new(vec![("minFreeSlots".to_string(), "2".to_string())]);
+        assert_eq!(party.min_free_slots(), 2);
+        assert!(matches!(
+            game.check_joinable(JoinDetails::new(&sessions.player(4)), Some(party)),
+            GameJoinableState::NotEnoughSlots
+        ));
+
+        let single = RuleSet::new(Vec::new());
+        assert!(matches!(
+            game.check_joinable(JoinDetails::new(&sessions.player(4)), Some(single)),
+            GameJoinableState::Joinable
+        ));
+    }
+
+    #[test]
+    fn test_attributes() {
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut initial = AttrMap::default();
+        initial.insert("ME3map".to_string(), "map2".to_string());
+        initial.insert("ME3privacy".to_string(), "PUBLIC".to_string());
+        let mut game = Game::new(1, initial, 0, 4, reciever);
+
+        let mut attributes = AttrMap::default();
+        attributes.insert("ME3map".to_string(), "map5".to_string());
+        game.handle(GameModifyAction::SetAttributes(attributes));
+
+        let snapshot = game.snapshot();
+        assert_eq!(snapshot.attributes.get("ME3map"), Some(&"map5".to_string()));
+        assert_eq!(
+            snapshot.attributes.get("ME3privacy"),
+            Some(&"PUBLIC".to_string())
+        );
+
+        let mut attributes = AttrMap::default();
+        attributes.insert("ME3map".to_string(), "map7".to_string());
+        game.handle(GameModifyAction::ReplaceAttributes(attributes));
+
+        let snapshot = game.snapshot();
+        assert_eq!(snapshot.attributes.get("ME3map"), Some(&"map7".to_string()));
+        assert_eq!(snapshot.attributes.get("ME3privacy"), None);
+    }
+
+    /// Tests that replacing the attributes applies the attribute limits
+    #[test]
+    fn test_replace_attributes_limits() {
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(1, AttrMap::default(), 0, 4, reciever);
+        game.config = Arc::new(GameConfig {
+            attribute_limits: AttributeLimits {
+                max_count: 1,
+                max_key_length: 8,
+                max_value_length: 4,
+            },
+            ..Default::default()
+        });
+
+        let mut attributes = AttrMap::default();
+        attributes.insert("ME3map".to_string(), "map7".to_string());
+        attributes.insert("ME3privacyLong".to_string(), "OPEN".to_string());
+        attributes.insert("ME3enemy".to_string(), "enemy1".to_string());
+        attributes.insert("ME3diff".to_string(), "d1".to_string());
+        game.handle(GameModifyAction::ReplaceAttributes(attributes));
+
+        let snapshot = game.snapshot();
+        assert_eq!(snapshot.attributes.len(), 1);
+        assert_eq!(snapshot.attributes.get("ME3map"), Some(&"map7".to_string()));
+    }
+
+    /// Tests that the time taken for a joining player to connect
+    /// their mesh is recorded only the first time they connect
+    #[tokio::test]
+    async fn test_mesh_time() {
+        GlobalState::init_test().await;
+        let metrics = &GlobalState::metrics().mesh;
+
+        let mut sessions = TestSessions::default();
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(1, AttrMap::default(), 0, 4, reciever);
+        for id in 1..=3 {
+            game.add_player(sessions.player(id));
+        }
+        game.players[2].joined_at = Instant::now() - Duration::from_millis(50);
+        let count = metrics.snapshot().count;
+
+        // Connecting to each of the other players only records once
+        for target in [1, 2] {
+            game.handle(GameModifyAction::UpdateMeshConnection {
+                session: 3,
+                target,
+                state: PlayerState::Connecting,
+            });
+        }
+        assert_eq!(game.players[2].state, PlayerState::Connected);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.count, count + 1);
+        assert!(snapshot.max_time >= 50);
+    }
+
+    /// Tests that the label attribute is exposed as the snapshot label
+    #[test]
+    fn test_label() {
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(1, AttrMap::default(), 0, 4, reciever);
+        assert_eq!(game.snapshot().label, None);
+
+        let mut attributes = AttrMap::default();
+        attributes.insert("name".to_string(), "Friday Gold".to_string());
+        game.handle(GameModifyAction::SetAttributes(attributes));
+        assert_eq!(game.snapshot().label, Some("Friday Gold".to_string()));
+    }
+
+    /// Tests that the compact snapshot only contains the summary of
+    /// the game from the full snapshot
+    #[test]
+    fn test_compact_snapshot() {
+        let mut sessions = TestSessions::default();
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut attributes = AttrMap::default();
+        attributes.insert("name".to_string(), "Friday Gold".to_string());
+        let mut game = Game::new(1, attributes, 0, 4, reciever);
+        game.add_player(sessions.player(1));
+        game.add_player(sessions.player(2));
+
+        let full = serde_json::to_value(game.snapshot()).unwrap();
+        let compact = serde_json::to_value(game.snapshot().compact()).unwrap();
+
+        assert_eq!(compact["id"], full["id"]);
+        assert_eq!(compact["state"], full["state"]);
+        assert_eq!(compact["label"], full["label"]);
+        assert_eq!(compact["player_count"], 2);
+        assert_eq!(compact["max_players"], 4);
+        assert!(compact.get("attributes").is_none());
+        assert!(compact.get("players").is_none());
+    }
+
+    /// Takes the text of the chat messages written to the session of
+    /// the provided receiver
+    fn written_chat(receiver: &mut mpsc::UnboundedReceiver<SessionMessage>) -> Vec<String> {
+        let mut messages = Vec::new();
+        while let Ok(message) = receiver.try_recv() {
+            let SessionMessage::Write(packet) = message else {
+                continue;
+            };
+            let component = Components::from_header(&packet.header);
+            if component != Components::Messaging(Messaging::SendMessage) {
+                continue;
+            }
+            let mut reader = TdfReader::new(&packet.contents);
+            messages.push(reader.tag("NAME").unwrap());
+        }
+        messages
+    }
+
+    #[test]
+    fn test_chat_message() {
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(1, AttrMap::default(), 0, 4, reciever);
+
+        let mut sessions = Vec::new();
+        for id in 1..=3 {
+            let (player, session) = test_player_session(id);
+            game.add_player(player);
+            sessions.push(session);
+        }
+        sessions.iter_mut().for_each(|session| {
+            written_components(session);
+        });
+
+        // Messages from a player are sent to the other players
+        game.handle(GameModifyAction::ChatMessage {
+            from: Some(1),
+            text: "Hello\u{7}  ".to_string(),
+        });
+        assert!(written_chat(&mut sessions[0]).is_empty());
+        for session in &mut sessions[1..] {
+            assert_eq!(written_chat(session), vec!["Test1: Hello\n".to_string()]);
+        }
+
+        // Messages from the server are sent to every player
+        game.handle(GameModifyAction::ChatMessage {
+            from: None,
+            text: "Restarting soon".to_string(),
+        });
+        for session in &mut sessions {
+            assert_eq!(
+                written_chat(session),
+                vec!["Server: Restarting soon\n".to_string()]
+            );
+        }
+
+        // Empty messages and messages from players not in the game are ignored
+        game.handle(GameModifyAction::ChatMessage {
+            from: Some(1),
+            text: "\n\t ".to_string(),
+        });
+        game.handle(GameModifyAction::ChatMessage {
+            from: Some(9),
+            text: "Hello".to_string(),
+        });
+        for session in &mut sessions {
+            assert!(written_components(session).is_empty());
+        }
+
+        assert_eq!(
+            Game::sanitize_chat("Hello\u{7}  "),
+            Some("Hello".to_string())
+        );
+        let long = "a".repeat(Game::MAX_CHAT_LENGTH + 10);
+        assert_eq!(
+            Game::sanitize_chat(&long).map(|value| value.len()),
+            Some(Game::MAX_CHAT_LENGTH)
+        );
+        // Leading whitespace doesn't count towards the max length
+        let padded = format!("    {long}");
+        assert_eq!(
+            Game::sanitize_chat(&padded).map(|value| value.len()),
+            Some(Game::MAX_CHAT_LENGTH)
+        );
+    }
+
+    #[test]
+    fn test_remove_attributes() {
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut initial = AttrMap::default();
+        initial.insert("ME3map".to_string(), "map2".to_string());
+        initial.insert("ME3privacy".to_string(), "PUBLIC".to_string());
+        let mut game = Game::new(1, initial, 0, 4, reciever);
+
+        let (player, mut session) = test_player_session(1);
+        game.add_player(player);
+        written_components(&mut session);
+
+        game.handle(GameModifyAction::RemoveAttributes(vec![
+            "ME3privacy".to_string()
+        ]));
+
+        let snapshot = game.snapshot();
+        assert_eq!(snapshot.attributes.get("ME3map"), Some(&"map2".to_string()));
+        assert_eq!(snapshot.attributes.get("ME3privacy"), None);
+        assert_eq!(
+            written_components(&mut session),
+            vec![Components::GameManager(GameManager::GameAttribChange)]
+        );
+
+        // Removing keys that aren't present shouldn't notify clients
+        game.handle(GameModifyAction::RemoveAttributes(vec![
+            "ME3privacy".to_string()
+        ]));
+        assert!(written_components(&mut session).is_empty());
+    }
+
+    #[test]
+    fn test_audit_entries() {
+        let mut sessions = TestSessions::default();
+        capture::install();
+
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(9001, AttrMap::default(), 0, 4, reciever);
+        game.add_player(sessions.player(1));
+        game.remove_player(RemovePlayerType::Player(1, RemoveReason::Kick));
+        drop(game);
+
+        // Other tests may log audit entries for their own games
+        let entries: Vec<String> = capture::entries(audit::TARGET)
+            .into_iter()
+            .filter(|entry| entry.contains("game_id=9001"))
+            .collect();
+
+        assert_eq!(
+            entries,
+            vec![
+                "event=game_created game_id=9001",
+                "event=player_joined game_id=9001 player_id=1 slot=0 ip=127.0.0.1",
+                "event=player_removed game_id=9001 player_id=1 reason=Kick",
+                "event=game_closed game_id=9001",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_migrate_best_host() {
+        let mut sessions = TestSessions::default();
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(1, AttrMap::default(), 0, 4, reciever);
+
+        let qos = [
+            (NatType::Open, 500),
+            (NatType::Strict, 900),
+            (NatType::Moderate, 200),
+            (NatType::Open, 800),
+        ];
+        for (id, (natt, ubps)) in (1..).zip(qos) {
+            let mut player = sessions.player(id);
+            player.net = NetData {
+                qos: QosNetworkData {
+                    dbps: 0,
+                    natt,
+                    ubps,
+                },
+                is_set: true,
+                ..Default::default()
+            };
+            game.add_player(player);
+        }
+
+        // Removing the host should migrate to the open player with the most bandwidth
+        game.remove_player(RemovePlayerType::Player(1, RemoveReason::Generic));
+        assert_eq!(game.players[0].player.id, 4);
+
+        // Falls back to the first player when there is no network data
+        game.players
+            .iter_mut()
+            .for_each(|player| player.net.is_set = false);
+        game.remove_player(RemovePlayerType::Player(4, RemoveReason::Generic));
+        assert_eq!(game.players[0].player.id, 2);
+    }
+
+    /// Creates a game player with the provided ID and NAT type for testing
+    fn test_nat_player(sessions: &mut TestSessions, id: u32, natt: NatType) -> GamePlayer {
+        let mut player = sessions.player(id);
+        player.net = NetData {
+            qos: QosNetworkData {
+                dbps: 0,
+                natt,
+                ubps: 0,
+            },
+            is_set: true,
+            ..Default::default()
+        };
+        player
+    }
+
+    #[test]
+    fn test_nat_joinable() {
+        let mut sessions = TestSessions::default();
+
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(1, AttrMap::default(), 0, 4, reciever);
+        game.config = Arc::new(GameConfig {
+            nat_check: true,
+            ..Default::default()
+        });
+        game.add_player(test_nat_player(&mut sessions, 1, NatType::Strict));
+
+        let strict = JoinDetails::new(&test_nat_player(&mut sessions, 2, NatType::Strict));
+        assert!(matches!(
+            game.check_joinable(strict, None),
+            GameJoinableState::NatIncompatible
+        ));
+
+        let open = JoinDetails::new(&test_nat_player(&mut sessions, 3, NatType::Open));
+        assert!(matches!(
+            game.check_joinable(open, None),
+            GameJoinableState::Joinable
+        ));
+    }
+
+    #[test]
+    fn test_setting_mask() {
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(1, AttrMap::default(), 0, 4, reciever);
+        game.config = Arc::new(GameConfig {
+            setting_mask: 287,
+            ..Default::default()
+        });
+
+        game.handle(GameModifyAction::SetSetting(0x11F));
+        assert_eq!(game.setting, 0x11F);
+
+        game.handle(GameModifyAction::SetSetting(0xFFFF));
+        assert_eq!(game.setting, 287);
+    }
+
+    #[test]
+    fn test_add_closed_session() {
+        let mut sessions = TestSessions::default();
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut game = Game::new(1, AttrMap::default(), 0, 4, reciever);
+        game.add_player(sessions.player(1));
+
+        // Session is closed before the player finishes joining
+        let (sender, receiver) = mpsc::unbounded_channel();
+        drop(receiver);
+        let mut player = sessions.player(2);
+        player.addr = SessionAddr::new(2, sender);
+        game.add_player(player);
+
+        assert_eq!(game.players.len(), 1);
+        assert_eq!(game.next_slot, 1);
+
+        // The released slot is used by the next player
+        game.add_player(sessions.player(3));
+        assert_eq!(game.players.len(), 2);
+        assert_eq!(game.players[1].player.id, 3);
+        assert_eq!(game.next_slot, 2);
+    }
+
+    #[test]
+    fn test_debug_setup() {
+        let mut sessions = TestSessions::default();
+        let (_, reciever) = mpsc::unbounded_channel();
+        let mut attributes = AttrMap::default();
+        attributes.insert("ME3map".to_string(), "map2".to_string());
+        attributes.insert("ME3privacy".to_string(), "PUBLIC".to_string());
+        let mut game = Game::new(1, attributes, 0, 4, reciever);
+        assert!(game.debug_setup().is_none());
+
+        game.add_player(sessions.player(1));
+        game.add_player(sessions.player(2));
+
+        let output = game.debug_setup().unwrap();
+        assert!(output.contains("ME3map"));
+        assert!(output.contains("ME3privacy"));
+
+        // The game players are left unchanged
+        assert_eq!(game.players.len(), 2);
+        assert_eq!(game.players[1].player.id, 2);
+    }
+}
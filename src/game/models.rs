@@ -1,8 +1,12 @@
 use super::{player::GamePlayer, AttrMap, Game};
-use crate::utils::types::{GameID, GameSlot, PlayerID};
+use crate::utils::{
+    components::{Components, UserSessions},
+    types::{GameID, GameSlot, PlayerID, SessionID},
+};
 use blaze_pk::{
     codec::{Decodable, Encodable},
     error::DecodeResult,
+    packet::PacketComponents,
     reader::TdfReader,
     tag::TdfType,
     value_type,
@@ -216,6 +220,44 @@ impl Encodable for AttributesChange<'_> {
     }
 }
 
+/// Packet for chat messages sent between players in a game
+pub struct ChatMessage<'a> {
+    /// The ID of the player that sent the message
+    pub source: PlayerID,
+    /// The ID of the player receiving the message
+    pub target: PlayerID,
+    /// The message contents
+    pub message: &'a str,
+}
+
+impl Encodable for ChatMessage<'_> {
+    fn encode(&self, writer: &mut TdfWriter) {
+        let ref_value: (u16, u16) = Components::UserSessions(UserSessions::SetSession).values();
+        let source: (u16, u16, u32) = (ref_value.0, ref_value.1, self.source);
+        let target: (u16, u16, u32) = (ref_value.0, ref_value.1, self.target);
+
+        writer.tag_u8(b"FLAG", 0x1);
+        writer.tag_u8(b"MGID", 0x1);
+        writer.tag_str(b"NAME", self.message);
+        {
+            writer.tag_group(b"PYLD");
+            {
+                writer.tag_map_start(b"ATTR", TdfType::String, TdfType::String, 1);
+                writer.write_str("B0000");
+                writer.write_str("160");
+            }
+            writer.tag_u8(b"FLAG", 0x1);
+            writer.tag_u8(b"STAT", 0x0);
+            writer.tag_u8(b"TAG", 0x0);
+            writer.tag_value(b"TARG", &target);
+            writer.tag_u8(b"TYPE", 0x0);
+            writer.tag_group_end();
+        }
+        writer.tag_value(b"SRCE", &source);
+        writer.tag_zero(b"TIME");
+    }
+}
+
 pub struct PlayerJoining<'a> {
     /// The slot the player is joining into
     pub slot: GameSlot,
@@ -249,7 +291,7 @@ pub fn encode_game_data(writer: &mut TdfWriter, game: &Game, player: &GamePlayer
     writer.tag_value(b"ATTR", &game.attributes);
     {
         writer.tag_list_start(b"CAP", TdfType::VarInt, 2);
-        writer.write_u8(4);
+        writer.write_u32(game.max_players as u32);
         writer.write_u8(0);
     }
 
@@ -270,7 +312,7 @@ pub fn encode_game_data(writer: &mut TdfWriter, game: &Game, player: &GamePlayer
 
     writer.tag_u32(b"HSES", host_player.addr.id);
     writer.tag_zero(b"IGNO");
-    writer.tag_u8(b"MCAP", 4);
+    writer.tag_usize(b"MCAP", game.max_players);
     writer.tag_value(b"NQOS", &host_player.net.qos);
     writer.tag_zero(b"NRES");
     writer.tag_zero(b"NTOP");
@@ -418,6 +460,8 @@ pub enum RemoveReason {
     JoinTimeout,
     /// 0x1
     ConnectionLost,
+    // 0x4
+    GameDestroyed,
     // 0x6
     Generic,
     // 0x8
@@ -431,6 +475,7 @@ impl RemoveReason {
         match value {
             0 => Self::JoinTimeout,
             1 => Self::ConnectionLost,
+            4 => Self::GameDestroyed,
             6 => Self::Generic,
             8 => Self::Kick,
             value => Self::Unknown(value),
@@ -441,6 +486,7 @@ impl RemoveReason {
         match self {
             Self::JoinTimeout => 0,
             Self::ConnectionLost => 1,
+            Self::GameDestroyed => 4,
             Self::Generic => 6,
             Self::Kick => 8,
             Self::Unknown(value) => *value,
@@ -472,6 +518,25 @@ impl Encodable for PlayerRemoved {
     }
 }
 
+/// Notification sent to a player when their matchmaking session
+/// has ended without finding a game
+pub struct MatchmakingFailed {
+    /// The ID of the matchmaking session (The session ID of the player)
+    pub session_id: SessionID,
+    /// The ID of the player
+    pub player_id: PlayerID,
+}
+
+impl Encodable for MatchmakingFailed {
+    fn encode(&self, writer: &mut TdfWriter) {
+        writer.tag_u32(b"MAXF", 0);
+        writer.tag_u32(b"MSID", self.session_id);
+        // Matchmaking result (0x5 = Session terminated)
+        writer.tag_u8(b"RSLT", 0x5);
+        writer.tag_u32(b"USID", self.player_id);
+    }
+}
+
 pub struct FetchExtendedData {
     pub player_id: PlayerID,
 }
@@ -1,15 +1,17 @@
 use super::models::PlayerState;
 use crate::{
-    servers::main::session::SessionAddr,
+    servers::main::{config::SessionConfig, session::SessionAddr},
     utils::{
         components::{Components, UserSessions},
-        models::{NetData, UpdateExtDataAttr},
+        locale::{self, DEFAULT_SESSION_LOCALE},
+        models::{ConnectionQuality, NetData, UpdateExtDataAttr},
         types::{GameID, PlayerID, SessionID},
     },
 };
 use blaze_pk::{codec::Encodable, packet::Packet, tag::TdfType, writer::TdfWriter};
 use database::Player;
 use serde::Serialize;
+use std::{sync::Arc, time::Instant};
 
 pub struct GamePlayer {
     pub game_id: GameID,
@@ -21,6 +23,10 @@ pub struct GamePlayer {
     pub net: NetData,
     /// State of the game player
     pub state: PlayerState,
+    /// The time the player was added to the game
+    pub joined_at: Instant,
+    /// The configuration of the session the player is from
+    pub config: Arc<SessionConfig>,
 }
 
 /// Structure for taking a snapshot of the players current
@@ -32,6 +38,12 @@ pub struct GamePlayerSnapshot {
     pub player_id: PlayerID,
     pub display_name: String,
     pub net: NetData,
+    /// The quality of the player connection derived from their
+    /// network details
+    pub quality: ConnectionQuality,
+    /// Whether the session of the player has been lost and their
+    /// slot is being held for them to reconnect
+    pub disconnected: bool,
 }
 
 impl GamePlayer {
@@ -41,16 +53,35 @@ impl GamePlayer {
     /// `player` The session player
     /// `net`    The player networking details
     /// `addr`   The session address
-    pub fn new(player: Player, net: NetData, addr: SessionAddr) -> Self {
+    /// `config` The configuration of the session
+    pub fn new(
+        player: Player,
+        net: NetData,
+        addr: SessionAddr,
+        config: Arc<SessionConfig>,
+    ) -> Self {
         Self {
             player,
             addr,
             net,
             game_id: 1,
             state: PlayerState::Connecting,
+            joined_at: Instant::now(),
+            config,
         }
     }
 
+    /// Creates a new game player for the same session player which
+    /// hasn't been added to a game yet
+    pub fn duplicate(&self) -> Self {
+        Self::new(
+            self.player.clone(),
+            self.net.clone(),
+            self.addr.clone(),
+            self.config.clone(),
+        )
+    }
+
     /// Takes a snapshot of the current player state
     /// for serialization
     pub fn snapshot(&self) -> GamePlayerSnapshot {
@@ -59,6 +90,8 @@ impl GamePlayer {
             player_id: self.player.id,
             display_name: self.player.display_name.clone(),
             net: self.net.clone(),
+            quality: self.net.quality(),
+            disconnected: self.addr.is_closed(),
         }
     }
 
@@ -87,7 +120,7 @@ impl GamePlayer {
         writer.tag_empty_blob(b"BLOB");
         writer.tag_u8(b"EXID", 0);
         writer.tag_u32(b"GID", self.game_id);
-        writer.tag_u32(b"LOC", 0x64654445);
+        writer.tag_u32(b"LOC", self.locale());
         writer.tag_str(b"NAME", &self.player.display_name);
         writer.tag_u32(b"PID", self.player.id);
         self.net.tag_groups(b"PNET", writer);
@@ -101,10 +134,15 @@ impl GamePlayer {
         writer.tag_group_end();
     }
 
+    /// Obtains the locale reported for the player
+    fn locale(&self) -> u32 {
+        self.config.locale.unwrap_or(DEFAULT_SESSION_LOCALE)
+    }
+
     pub fn encode_data(&self, writer: &mut TdfWriter) {
         self.net.tag_groups(b"ADDR", writer);
-        writer.tag_str(b"BPS", "ea-sjc");
-        writer.tag_str_empty(b"CTY");
+        writer.tag_str(b"BPS", &self.config.ping_site);
+        locale::tag_country(writer, self.net.country.as_deref(), &self.config.country);
         writer.tag_var_int_list_empty(b"CVAR");
         {
             writer.tag_map_start(b"DMAP", TdfType::VarInt, TdfType::VarInt, 1);
@@ -112,10 +150,7 @@ impl GamePlayer {
             writer.write_u16(0x409a);
         }
         writer.tag_u16(b"HWFG", self.net.hardware_flags);
-        {
-            writer.tag_list_start(b"PSLM", TdfType::VarInt, 1);
-            writer.write_u32(0xfff0fff);
-        }
+        self.net.tag_pslm(writer);
         writer.tag_value(b"QDAT", &self.net.qos);
         writer.tag_u8(b"UATT", 0);
         writer.tag_list_start(b"ULST", TdfType::Triple, 1);
@@ -127,7 +162,7 @@ impl GamePlayer {
 impl Drop for GamePlayer {
     fn drop(&mut self) {
         // Clear player game when game player is dropped
-        self.addr.set_game(None)
+        self.addr.remove_game(self.game_id)
     }
 }
 
@@ -142,7 +177,7 @@ impl Encodable for PlayerUpdate<'_> {
 
         writer.tag_group(b"USER");
         writer.tag_u32(b"AID", self.player.player.id);
-        writer.tag_u32(b"ALOC", 0x64654445);
+        writer.tag_u32(b"ALOC", self.player.locale());
         writer.tag_empty_blob(b"EXBB");
         writer.tag_u8(b"EXID", 0);
         writer.tag_u32(b"ID", self.player.player.id);
@@ -162,3 +197,119 @@ impl Encodable for SetPlayer<'_> {
         writer.tag_u32(b"USID", self.player.player.id);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::GamePlayer;
+    use crate::{
+        servers::main::{config::SessionConfig, session::SessionAddr},
+        utils::{geo::Geolocator, models::NetData},
+    };
+    use blaze_pk::{reader::TdfReader, writer::TdfWriter};
+    use database::Player;
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        sync::Arc,
+    };
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_encode_ping_site() {
+        let (sender, _receiver) = mpsc::unbounded_channel();
+        let player = Player {
+            id: 1,
+            email: "test@test.com".to_string(),
+            display_name: "Test".to_string(),
+            session_token: None,
+            origin: false,
+            password: String::new(),
+            created_at: None,
+        };
+        let net = NetData {
+            pslm: Some(48),
+            ..Default::default()
+        };
+        let config = Arc::new(SessionConfig {
+            ping_site: "rs-lhr".to_string(),
+            ..Default::default()
+        });
+        let player = GamePlayer::new(player, net, SessionAddr::new(1, sender), config);
+
+        let mut writer = TdfWriter::default();
+        player.encode_data(&mut writer);
+
+        let mut reader = TdfReader::new(&writer.buffer);
+        let bps: String = reader.tag("BPS").unwrap();
+        assert_eq!(bps, "rs-lhr");
+        let pslm: Vec<u32> = reader.tag("PSLM").unwrap();
+        assert_eq!(pslm, vec![48]);
+    }
+
+    #[test]
+    fn test_encode_country() {
+        let (sender, _receiver) = mpsc::unbounded_channel();
+        let player = Player {
+            id: 1,
+            email: "test@test.com".to_string(),
+            display_name: "Test".to_string(),
+            session_token: None,
+            origin: false,
+            password: String::new(),
+            created_at: None,
+        };
+        let config = Arc::new(SessionConfig {
+            country: "NZ".to_string(),
+            ..Default::default()
+        });
+        let player = GamePlayer::new(
+            player,
+            NetData::default(),
+            SessionAddr::new(1, sender),
+            config,
+        );
+
+        let mut writer = TdfWriter::default();
+        player.encode_data(&mut writer);
+
+        let mut reader = TdfReader::new(&writer.buffer);
+        let country: String = reader.tag("CTY").unwrap();
+        assert_eq!(country, "NZ");
+    }
+
+    /// Geolocator that resolves every address to the same country
+    struct StubGeolocator;
+
+    impl Geolocator for StubGeolocator {
+        fn locate(&self, _ip: IpAddr) -> Option<String> {
+            Some("AU".to_string())
+        }
+    }
+
+    /// Tests that the resolved country is encoded instead of the
+    /// configured country
+    #[test]
+    fn test_encode_resolved_country() {
+        let (sender, _receiver) = mpsc::unbounded_channel();
+        let player = Player {
+            id: 1,
+            email: "test@test.com".to_string(),
+            display_name: "Test".to_string(),
+            session_token: None,
+            origin: false,
+            password: String::new(),
+            created_at: None,
+        };
+        let net = NetData {
+            country: StubGeolocator.locate(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            ..Default::default()
+        };
+        let player = GamePlayer::new(player, net, SessionAddr::new(1, sender), Default::default());
+
+        let mut writer = TdfWriter::default();
+        player.encode_data(&mut writer);
+
+        let mut reader = TdfReader::new(&writer.buffer);
+        let country: String = reader.tag("CTY").unwrap();
+        assert_eq!(country, "AU");
+    }
+}
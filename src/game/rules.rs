@@ -1,4 +1,5 @@
 use super::AttrMap;
+use std::fmt::{self, Display};
 
 /// Rulesets are fairly cheap to clone. Rule values are not usually
 /// very long.
@@ -10,6 +11,9 @@ pub struct RuleSet {
     enemy_rule: Option<String>,
     /// Difficulty rule provided in the matchmaking request
     difficulty_rule: Option<String>,
+    /// The number of free slots a game must have to be joined
+    /// so that parties aren't split across games
+    min_free_slots: usize,
 }
 
 impl RuleSet {
@@ -29,6 +33,9 @@ impl RuleSet {
     const DIFFICULTY_ATTR: &str = "ME3gameDifficulty";
     const DIFFICULTY_RULE: &str = "ME3_gameDifficultyRule";
 
+    /// Rule key for the minimum number of free slots
+    const MIN_FREE_SLOTS_RULE: &str = "minFreeSlots";
+
     /// Value for rules that have been abstained from matching
     /// when a rule is abstained it is ignored
     const ABSTAIN: &str = "abstain";
@@ -41,6 +48,7 @@ impl RuleSet {
         let mut map_rule: Option<String> = None;
         let mut enemy_rule: Option<String> = None;
         let mut difficulty_rule: Option<String> = None;
+        let mut min_free_slots: usize = 1;
 
         for (rule, value) in rules {
             if value == Self::ABSTAIN {
@@ -50,6 +58,9 @@ impl RuleSet {
                 Self::MAP_RULE => map_rule = Some(value),
                 Self::ENEMY_RULE => enemy_rule = Some(value),
                 Self::DIFFICULTY_RULE => difficulty_rule = Some(value),
+                Self::MIN_FREE_SLOTS_RULE => {
+                    min_free_slots = value.parse::<usize>().unwrap_or(1).max(1)
+                }
                 _ => {}
             }
         }
@@ -57,43 +68,168 @@ impl RuleSet {
             map_rule,
             enemy_rule,
             difficulty_rule,
+            min_free_slots,
         }
     }
 
+    /// The number of free slots a game must have for these rules
+    /// to join it
+    pub fn min_free_slots(&self) -> usize {
+        self.min_free_slots
+    }
+
     /// Checks if the rules provided in this rule set match the values in
-    /// the attributes map.
+    /// the attributes map. Returns a human readable explanation of the
+    /// first rule that doesn't match or None if all the rules match.
+    ///
+    /// `attributes` The attributes map to check for matches
+    pub fn explain(&self, attributes: &AttrMap) -> Option<String> {
+        self.find_mismatch(attributes)
+            .map(|value| value.to_string())
+    }
+
+    /// Finds the first rule in this rule set that doesn't match the
+    /// values in the attributes map.
     ///
     /// `attributes` The attributes map to check for matches
-    pub fn matches(&self, attributes: &AttrMap) -> bool {
+    fn find_mismatch<'a>(&'a self, attributes: &'a AttrMap) -> Option<RuleMismatch<'a>> {
         // Non public matches are unable to be matched
         if let Some(privacy) = attributes.get(Self::PRIVACY_ATTR) {
             if privacy != "PUBLIC" {
-                return false;
+                return Some(RuleMismatch {
+                    key: Self::PRIVACY_ATTR,
+                    expected: "PUBLIC",
+                    actual: privacy,
+                });
             }
         }
 
-        fn compare_rule(rule: Option<&String>, value: Option<&String>) -> bool {
-            rule.zip(value)
-                .map(|(a, b)| a.eq(b))
-                // Missing rules / attributes count as match and continue
-                .unwrap_or(true)
+        let rules = [
+            (Self::MAP_ATTR, &self.map_rule),
+            (Self::ENEMY_ATTR, &self.enemy_rule),
+            (Self::DIFFICULTY_ATTR, &self.difficulty_rule),
+        ];
+
+        for (key, rule) in rules {
+            // Missing rules / attributes count as match and continue
+            let (Some(expected), Some(actual)) = (rule, attributes.get(key)) else {
+                continue;
+            };
+            if expected != actual {
+                return Some(RuleMismatch {
+                    key,
+                    expected,
+                    actual,
+                });
+            }
         }
 
-        if !compare_rule(self.map_rule.as_ref(), attributes.get(Self::MAP_ATTR)) {
-            return false;
+        None
+    }
+}
+
+impl Display for RuleSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rules = [
+            (Self::MAP_ATTR, &self.map_rule),
+            (Self::ENEMY_ATTR, &self.enemy_rule),
+            (Self::DIFFICULTY_ATTR, &self.difficulty_rule),
+        ];
+
+        let mut is_empty = true;
+        for (key, rule) in rules {
+            let Some(rule) = rule else {
+                continue;
+            };
+            if !is_empty {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}={}", key, rule)?;
+            is_empty = false;
         }
 
-        if !compare_rule(self.enemy_rule.as_ref(), attributes.get(Self::ENEMY_ATTR)) {
-            return false;
+        if self.min_free_slots > 1 {
+            if !is_empty {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}={}", Self::MIN_FREE_SLOTS_RULE, self.min_free_slots)?;
+            is_empty = false;
         }
 
-        if !compare_rule(
-            self.difficulty_rule.as_ref(),
-            attributes.get(Self::DIFFICULTY_ATTR),
-        ) {
-            return false;
+        if is_empty {
+            f.write_str("Any")?;
         }
+        Ok(())
+    }
+}
+
+/// Details about a rule that didn't match a game attribute
+struct RuleMismatch<'a> {
+    /// The attribute key the rule was checked against
+    key: &'static str,
+    /// The value expected by the rule
+    expected: &'a str,
+    /// The actual value of the attribute
+    actual: &'a str,
+}
+
+impl Display for RuleMismatch<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Attribute {} expected {} but was {}",
+            self.key, self.expected, self.actual
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RuleSet;
+    use crate::game::AttrMap;
+
+    #[test]
+    fn test_explain() {
+        let rules = RuleSet::new(vec![
+            ("ME3_gameMapMatchRule".to_string(), "map2".to_string()),
+            ("ME3_gameDifficultyRule".to_string(), "abstain".to_string()),
+        ]);
+
+        let mut attributes = AttrMap::default();
+        attributes.insert("ME3map".to_string(), "map2".to_string());
+        attributes.insert("ME3gameDifficulty".to_string(), "difficulty3".to_string());
+        assert_eq!(rules.explain(&attributes), None);
+
+        attributes.insert("ME3map".to_string(), "map5".to_string());
+        assert_eq!(
+            rules.explain(&attributes).as_deref(),
+            Some("Attribute ME3map expected map2 but was map5")
+        );
+
+        attributes.insert("ME3privacy".to_string(), "PRIVATE".to_string());
+        assert_eq!(
+            rules.explain(&attributes).as_deref(),
+            Some("Attribute ME3privacy expected PUBLIC but was PRIVATE")
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let rules = RuleSet::new(vec![
+            ("ME3_gameMapMatchRule".to_string(), "map2".to_string()),
+            ("ME3_gameEnemyTypeRule".to_string(), "abstain".to_string()),
+            (
+                "ME3_gameDifficultyRule".to_string(),
+                "difficulty1".to_string(),
+            ),
+        ]);
+        assert_eq!(
+            rules.to_string(),
+            "ME3map=map2, ME3gameDifficulty=difficulty1"
+        );
+        assert_eq!(RuleSet::new(Vec::new()).to_string(), "Any");
 
-        true
+        let rules = RuleSet::new(vec![("minFreeSlots".to_string(), "3".to_string())]);
+        assert_eq!(rules.to_string(), "minFreeSlots=3");
     }
 }
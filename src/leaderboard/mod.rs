@@ -9,18 +9,41 @@ use crate::{
     },
 };
 use database::{DatabaseConnection, DbResult, Player};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock as StdRwLock},
+};
 use tokio::{sync::RwLock, task::JoinSet, try_join};
 
 pub mod models;
 
 /// Structure for storing the leaderboard values on the global
-/// state.
-#[derive(Default)]
+/// state. Leaderboards are stored in a registry keyed by name so
+/// that new leaderboards only need a definition to be registered
 pub struct Leaderboard {
-    /// Leaderboard entity group for n7 ratings
-    n7_group: RwLock<LeaderboardEntityGroup>,
-    /// Leaderboard entity group for challenge points
-    cp_group: RwLock<LeaderboardEntityGroup>,
+    /// The registered leaderboards keyed by their lowercase name
+    boards: StdRwLock<HashMap<String, Arc<RegisteredLeaderboard>>>,
+}
+
+/// A leaderboard registered on the registry along with its
+/// cached values
+pub struct RegisteredLeaderboard {
+    /// The definition of the leaderboard
+    pub definition: LeaderboardDefinition,
+    /// The cached entity group for the leaderboard
+    group: RwLock<LeaderboardEntityGroup>,
+}
+
+impl Default for Leaderboard {
+    fn default() -> Self {
+        let leaderboard = Self {
+            boards: Default::default(),
+        };
+        for definition in LeaderboardType::definitions() {
+            leaderboard.register(definition);
+        }
+        leaderboard
+    }
 }
 
 /// Different query types for querying the leaderboards
@@ -49,17 +72,29 @@ pub enum LeaderboardQuery {
 }
 
 impl Leaderboard {
-    /// Retrieves the lock to the leaderboard entity group for the
-    /// provided leaderboard type
+    /// Registers the provided leaderboard definition replacing any
+    /// existing leaderboard with the same name
     ///
-    /// `ty` The leaderboard type
-    fn get_type_lock(&self, ty: &LeaderboardType) -> &RwLock<LeaderboardEntityGroup> {
-        match ty {
-            LeaderboardType::N7Rating => &self.n7_group,
-            LeaderboardType::ChallengePoints => &self.cp_group,
+    /// `definition` The leaderboard definition
+    pub fn register(&self, definition: LeaderboardDefinition) {
+        let name = definition.name.to_ascii_lowercase();
+        let board = RegisteredLeaderboard {
+            group: RwLock::new(LeaderboardEntityGroup::new(definition.order)),
+            definition,
+        };
+        if let Ok(mut boards) = self.boards.write() {
+            boards.insert(name, Arc::new(board));
         }
     }
 
+    /// Finds the registered leaderboard with the provided name
+    ///
+    /// `name` The name of the leaderboard
+    pub fn find(&self, name: &str) -> Option<Arc<RegisteredLeaderboard>> {
+        let boards = self.boards.read().ok()?;
+        boards.get(&name.to_ascii_lowercase()).cloned()
+    }
+
     /// Updates the provided leaderboard type. If the contents are
     /// expired then they are computed again. Returns a cloned list of
     /// entires matching the provided query or None if the query was not
@@ -71,41 +106,110 @@ impl Leaderboard {
         ty: LeaderboardType,
         query: LeaderboardQuery,
     ) -> DbResult<Option<(Vec<LeaderboardEntry>, bool)>> {
-        let read_lock = self.get_type_lock(&ty);
+        match self.find(ty.name()) {
+            Some(board) => board.get(GlobalState::database(), query).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Updates the provided leaderboard type. If the contents are
+    /// expired then they are computed again. Returns the total number
+    /// of entities present in the leaderboard type
+    ///
+    /// `ty` The leaderboard type
+    pub async fn get_size(&self, ty: LeaderboardType) -> DbResult<usize> {
+        match self.find(ty.name()) {
+            Some(board) => board.get_size(GlobalState::database()).await,
+            None => Ok(0),
+        }
+    }
+
+    /// Increases the challenge points of the provided player in the
+    /// database and moves their entry to its new position in the cached
+    /// challenge points leaderboard. Expired leaderboards are left alone
+    /// as they will be recomputed on the next query. Returns the new
+    /// challenge points value or None if the player had no value.
+    ///
+    /// `db`     The database connection
+    /// `player` The player to increase the challenge points for
+    /// `amount` The amount to increase by
+    pub async fn increase_challenge_points(
+        &self,
+        db: &DatabaseConnection,
+        player: &Player,
+        amount: u32,
+    ) -> DbResult<Option<u32>> {
+        let value = player.increase_challenge_points(db, amount).await?;
+        if let (Some(value), Some(board)) =
+            (value, self.find(LeaderboardType::ChallengePoints.name()))
+        {
+            board
+                .update_entry(player.id, player.display_name.clone(), value)
+                .await;
+        }
+        Ok(value)
+    }
+}
+
+impl RegisteredLeaderboard {
+    /// Updates the leaderboard values if they are expired by computing
+    /// them again. Returns a cloned list of entires matching the provided
+    /// query or None if the query was not valid
+    ///
+    /// `db`    The database connection
+    /// `query` The query to resolve
+    pub async fn get(
+        &self,
+        db: &DatabaseConnection,
+        query: LeaderboardQuery,
+    ) -> DbResult<Option<(Vec<LeaderboardEntry>, bool)>> {
         // Check the cached value to see if its valid
         {
-            let entity = &*read_lock.read().await;
+            let entity = &*self.group.read().await;
             if entity.is_valid() {
                 return Ok(Self::resolve_query(entity, query));
             }
         }
 
-        let ranking = self.compute_rankings(&ty).await?;
-        let entity = &mut *self.get_type_lock(&ty).write().await;
+        let ranking = self.compute_rankings(db).await?;
+        let entity = &mut *self.group.write().await;
         entity.update(ranking);
         Ok(Self::resolve_query(entity, query))
     }
-    /// Updates the provided leaderboard type. If the contents are
-    /// expired then they are computed again. Returns the total number
-    /// of entities present in the leaderboard type
+
+    /// Updates the leaderboard values if they are expired by computing
+    /// them again. Returns the total number of entities present in the
+    /// leaderboard
     ///
-    /// `ty` The leaderboard type
-    pub async fn get_size(&self, ty: LeaderboardType) -> DbResult<usize> {
-        let read_lock = self.get_type_lock(&ty);
+    /// `db` The database connection
+    pub async fn get_size(&self, db: &DatabaseConnection) -> DbResult<usize> {
         // Check the cached value to see if its valid
         {
-            let entity = &*read_lock.read().await;
+            let entity = &*self.group.read().await;
             if entity.is_valid() {
                 return Ok(entity.values.len());
             }
         }
 
-        let ranking = self.compute_rankings(&ty).await?;
-        let entity = &mut *self.get_type_lock(&ty).write().await;
+        let ranking = self.compute_rankings(db).await?;
+        let entity = &mut *self.group.write().await;
         entity.update(ranking);
         Ok(entity.values.len())
     }
 
+    /// Updates the value of a single player in the leaderboard if
+    /// the leaderboard is currently valid
+    ///
+    /// `player_id`   The ID of the player
+    /// `player_name` The name of the player
+    /// `value`       The new value for the player
+    async fn update_entry(&self, player_id: PlayerID, player_name: String, value: u32) {
+        let entity = &mut *self.group.write().await;
+        if entity.is_valid() {
+            entity.update_entry(player_id, player_name, value);
+        }
+    }
+
     /// Resolves the query based on the provided entity group
     /// cloning any values that are needed returning a list of
     /// entires and a boolean for whether there are more entries
@@ -162,16 +266,15 @@ impl Leaderboard {
         }
     }
 
-    /// Computes the ranking values for the provided `ty` this consists of
+    /// Computes the ranking values for this leaderboard this consists of
     /// streaming the values from the database in chunks of 20, processing the
     /// chunks converting them into entries then sorting the entries based
     /// on their value.
     ///
-    /// `ty` The leaderboard type
-    async fn compute_rankings(&self, ty: &LeaderboardType) -> DbResult<Vec<LeaderboardEntry>> {
+    /// `db` The database connection
+    async fn compute_rankings(&self, db: &DatabaseConnection) -> DbResult<Vec<LeaderboardEntry>> {
         // The amount of players to process in each database request
         const BATCH_COUNT: u64 = 20;
-        let db = GlobalState::database();
         let mut offset = 0;
         let mut values: Vec<LeaderboardEntry> = Vec::new();
         loop {
@@ -180,15 +283,20 @@ impl Leaderboard {
                 break;
             }
             let mut join_set = JoinSet::new();
-            match ty {
-                LeaderboardType::N7Rating => {
+            match &self.definition.source {
+                LeaderboardSource::N7Rating => {
                     for player in players {
-                        join_set.spawn(Self::compute_n7_player(db, player));
+                        join_set.spawn(Self::compute_n7_player(db.clone(), player));
                     }
                 }
-                LeaderboardType::ChallengePoints => {
+                LeaderboardSource::PlayerData { key, index } => {
                     for player in players {
-                        join_set.spawn(Self::compute_cp_player(db, player));
+                        join_set.spawn(Self::compute_data_player(
+                            db.clone(),
+                            player,
+                            key.clone(),
+                            *index,
+                        ));
                     }
                 }
             }
@@ -206,14 +314,11 @@ impl Leaderboard {
             offset += BATCH_COUNT;
         }
         // Sort the values based on their value
-        values.sort_by(|a, b| a.value.cmp(&b.value).reverse());
+        let order = self.definition.order;
+        values.sort_by(|a, b| order.compare(a.value, b.value));
 
         // Apply the new rank order
-        let mut rank = 1;
-        for value in values.iter_mut() {
-            value.rank = rank;
-            rank += 1;
-        }
+        rank_entries(&mut values);
 
         Ok(values)
     }
@@ -224,12 +329,13 @@ impl Leaderboard {
     /// `db`     The database connection
     /// `player` The player to compute
     async fn compute_n7_player(
-        db: &DatabaseConnection,
+        db: DatabaseConnection,
         player: Player,
     ) -> DbResult<LeaderboardEntry> {
         let mut total_promotions = 0;
         let mut total_level: u32 = 0;
-        let (classes, characters) = try_join!(player.get_classes(db), player.get_characters(db),)?;
+        let (classes, characters) =
+            try_join!(player.get_classes(&db), player.get_characters(&db),)?;
 
         let classes: Vec<_> = classes
             .into_iter()
@@ -259,20 +365,135 @@ impl Leaderboard {
             // Rank is not computed yet at this stage
             rank: 0,
             value: rating,
+            tied: false,
         })
     }
 
-    async fn compute_cp_player(
-        db: &DatabaseConnection,
+    /// Computes the value for the provided player from the value at
+    /// `index` of the comma seperated list stored in the player data
+    /// under `key`. Missing or invalid values are treated as zero
+    ///
+    /// `db`     The database connection
+    /// `player` The player to compute
+    /// `key`    The player data key
+    /// `index`  The index of the value within the list
+    async fn compute_data_player(
+        db: DatabaseConnection,
         player: Player,
+        key: String,
+        index: usize,
     ) -> DbResult<LeaderboardEntry> {
-        let value = player.get_challenge_points(db).await.unwrap_or(0);
+        let value = player
+            .get_data(&db, &key)
+            .await?
+            .and_then(|data| data.value.split(',').nth(index)?.parse().ok())
+            .unwrap_or(0);
         Ok(LeaderboardEntry {
             player_id: player.id,
             player_name: player.display_name,
             // Rank is not computed yet at this stage
             rank: 0,
             value,
+            tied: false,
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{models::*, Leaderboard, LeaderboardQuery};
+    use database::Player;
+
+    #[tokio::test]
+    async fn test_register_leaderboard() {
+        let db = database::connect_memory().await;
+
+        for (index, value) in ["3,40", "1,10", "2,25"].into_iter().enumerate() {
+            let player = Player::create(
+                &db,
+                format!("{}@test.com", index),
+                format!("Player{}", index),
+                String::new(),
+                false,
+            )
+            .await
+            .unwrap();
+            player
+                .set_data(&db, "Deaths".to_string(), value.to_string())
+                .await
+                .unwrap();
+        }
+
+        let leaderboard = Leaderboard::default();
+        assert!(leaderboard.find("deaths").is_none());
+        // Built in leaderboards are registered by default
+        assert!(leaderboard.find("n7").is_some());
+        assert!(leaderboard.find("CP").is_some());
+
+        leaderboard.register(LeaderboardDefinition {
+            name: "Deaths".to_string(),
+            title: "Deaths".to_string(),
+            source: LeaderboardSource::PlayerData {
+                key: "Deaths".to_string(),
+                index: 1,
+            },
+            order: LeaderboardOrder::Descending,
+        });
+
+        let board = leaderboard.find("deaths").unwrap();
+        let (values, more) = board
+            .get(
+                &db,
+                LeaderboardQuery::Normal {
+                    start: 0,
+                    count: 10,
+                },
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!more);
+
+        let ranks: Vec<(String, usize, u32)> = values
+            .into_iter()
+            .map(|value| (value.player_name, value.rank, value.value))
+            .collect();
+        assert_eq!(
+            ranks,
+            vec![
+                ("Player0".to_string(), 1, 40),
+                ("Player2".to_string(), 2, 25),
+                ("Player1".to_string(), 3, 10),
+            ]
+        );
+    }
+
+    /// Tests that concurrent challenge point increases are all applied
+    #[tokio::test]
+    async fn test_concurrent_challenge_points() {
+        let db = database::connect_memory().await;
+        let player = Player::create(
+            &db,
+            "cp@test.com".to_string(),
+            "Player".to_string(),
+            String::new(),
+            false,
+        )
+        .await
+        .unwrap();
+        player
+            .set_data(&db, "Completion".to_string(), "22,100,0".to_string())
+            .await
+            .unwrap();
+
+        let increase = || player.increase_challenge_points(&db, 5);
+        let results = tokio::join!(increase(), increase(), increase(), increase());
+        for result in [results.0, results.1, results.2, results.3] {
+            assert!(result.unwrap().is_some());
+        }
+
+        assert_eq!(player.get_challenge_points(&db).await, Some(120));
+        let data = player.get_data(&db, "Completion").await.unwrap().unwrap();
+        assert_eq!(data.value, "22,120,0");
+    }
+}
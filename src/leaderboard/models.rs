@@ -1,6 +1,7 @@
 use crate::utils::types::PlayerID;
 use serde::{Deserialize, Serialize};
 use std::{
+    cmp::Ordering,
     fmt::Display,
     time::{Duration, SystemTime},
 };
@@ -12,10 +13,14 @@ pub struct LeaderboardEntry {
     pub player_id: PlayerID,
     /// The name of the player this entry is for
     pub player_name: String,
-    /// The ranking of this entry (Position in the leaderboard)
+    /// The ranking of this entry (Position in the leaderboard). Entries
+    /// with equal values share the same rank
     pub rank: usize,
     /// The value this ranking is based on
     pub value: u32,
+    /// Whether another entry has the same value as this entry
+    #[serde(default)]
+    pub tied: bool,
 }
 
 /// Structure for a group of leaderboard entities ranked based
@@ -26,14 +31,13 @@ pub struct LeaderboardEntityGroup {
     pub values: Vec<LeaderboardEntry>,
     /// The time at which this entity group will become expired
     pub expires: SystemTime,
+    /// The order the values in this group are ranked in
+    pub order: LeaderboardOrder,
 }
 
 impl Default for LeaderboardEntityGroup {
     fn default() -> Self {
-        Self {
-            values: Vec::with_capacity(0),
-            expires: SystemTime::now(),
-        }
+        Self::new(LeaderboardOrder::default())
     }
 }
 
@@ -41,6 +45,17 @@ impl LeaderboardEntityGroup {
     /// Leaderboard contents are cached for 1 hour
     const LIFETIME: Duration = Duration::from_secs(60 * 60);
 
+    /// Creates a new expired entity group ranked in the provided order
+    ///
+    /// `order` The order to rank values in
+    pub fn new(order: LeaderboardOrder) -> Self {
+        Self {
+            values: Vec::with_capacity(0),
+            expires: SystemTime::now(),
+            order,
+        }
+    }
+
     pub fn is_valid(&self) -> bool {
         let now = SystemTime::now();
         now.lt(&self.expires)
@@ -50,6 +65,118 @@ impl LeaderboardEntityGroup {
         self.expires = SystemTime::now() + Self::LIFETIME;
         self.values = values;
     }
+
+    /// Updates the value of a single entry moving it to its new position
+    /// in the ranking without recomputing the entire group. The entry is
+    /// added if the player is not already ranked.
+    ///
+    /// `player_id`   The ID of the player
+    /// `player_name` The name of the player
+    /// `value`       The new value for the player
+    pub fn update_entry(&mut self, player_id: PlayerID, player_name: String, value: u32) {
+        let old_index = self
+            .values
+            .iter()
+            .position(|entry| entry.player_id == player_id);
+        if let Some(index) = old_index {
+            self.values.remove(index);
+        }
+
+        let order = self.order;
+        let new_index = self
+            .values
+            .partition_point(|entry| order.compare(entry.value, value).is_le());
+        self.values.insert(
+            new_index,
+            LeaderboardEntry {
+                player_id,
+                player_name,
+                rank: 0,
+                value,
+                tied: false,
+            },
+        );
+
+        // Ties can change the ranks of entries outside of the moved range
+        rank_entries(&mut self.values);
+    }
+}
+
+/// Assigns the ranks of the provided entries using competition ranking
+/// where entries with equal values share the same rank and the rank of
+/// the following entry skips the shared positions (1, 2, 2, 4). The
+/// entries must already be sorted in ranking order
+///
+/// `values` The sorted entries to rank
+pub fn rank_entries(values: &mut [LeaderboardEntry]) {
+    for index in 0..values.len() {
+        let value = values[index].value;
+        let tied_previous = index > 0 && values[index - 1].value == value;
+        let tied_next = values
+            .get(index + 1)
+            .map(|next| next.value == value)
+            .unwrap_or(false);
+
+        values[index].rank = if tied_previous {
+            values[index - 1].rank
+        } else {
+            index + 1
+        };
+        values[index].tied = tied_previous || tied_next;
+    }
+}
+
+/// The order that leaderboard values are ranked in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LeaderboardOrder {
+    /// Highest values are ranked first
+    #[default]
+    Descending,
+}
+
+impl LeaderboardOrder {
+    /// Compares the two values returning the ordering that
+    /// would rank them correctly
+    ///
+    /// `a` The first value
+    /// `b` The second value
+    pub fn compare(&self, a: u32, b: u32) -> Ordering {
+        match self {
+            Self::Descending => b.cmp(&a),
+        }
+    }
+}
+
+/// The source that the value for each player on a leaderboard
+/// is computed from
+#[derive(Debug, Clone)]
+pub enum LeaderboardSource {
+    /// The N7 rating computed from the player classes and characters
+    N7Rating,
+    /// A numeric value stored in the player data. The value at `index`
+    /// of the comma seperated list stored under `key` is used with
+    /// missing values counted as zero
+    PlayerData {
+        /// The player data key
+        key: String,
+        /// The index of the value within the comma seperated list
+        index: usize,
+    },
+}
+
+/// Definition of a leaderboard that can be registered on the
+/// leaderboard registry
+#[derive(Debug, Clone)]
+pub struct LeaderboardDefinition {
+    /// The name the leaderboard is registered under. Names are
+    /// matched without case sensitivity
+    pub name: String,
+    /// The title displayed for the leaderboard
+    pub title: String,
+    /// The source of the value for each player
+    pub source: LeaderboardSource,
+    /// The order values are ranked in
+    pub order: LeaderboardOrder,
 }
 
 /// Type of leaderboard entity
@@ -68,18 +195,36 @@ impl Display for LeaderboardType {
 }
 
 impl LeaderboardType {
-    /// Attempts to parse the leaderboard type from the provided value
-    ///
-    /// `value` The value to attempt to parse from
-    pub fn try_parse(value: &str) -> Option<LeaderboardType> {
-        if value.eq_ignore_ascii_case("n7") {
-            Some(LeaderboardType::N7Rating)
-        } else if value.eq_ignore_ascii_case("cp") {
-            Some(LeaderboardType::ChallengePoints)
-        } else {
-            None
+    /// Returns the name the leaderboard for this type is
+    /// registered under
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::N7Rating => "n7",
+            Self::ChallengePoints => "cp",
         }
     }
+
+    /// Returns the definitions for the built in leaderboards
+    pub fn definitions() -> [LeaderboardDefinition; 2] {
+        [
+            LeaderboardDefinition {
+                name: Self::N7Rating.name().to_string(),
+                title: Self::N7Rating.to_string(),
+                source: LeaderboardSource::N7Rating,
+                order: LeaderboardOrder::Descending,
+            },
+            LeaderboardDefinition {
+                name: Self::ChallengePoints.name().to_string(),
+                title: Self::ChallengePoints.to_string(),
+                // Challenge points are the second value of the completion list
+                source: LeaderboardSource::PlayerData {
+                    key: "Completion".to_string(),
+                    index: 1,
+                },
+                order: LeaderboardOrder::Descending,
+            },
+        ]
+    }
 }
 
 impl From<String> for LeaderboardType {
@@ -91,3 +236,86 @@ impl From<String> for LeaderboardType {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{rank_entries, LeaderboardEntityGroup, LeaderboardEntry};
+
+    fn entry(player_id: u32, rank: usize, value: u32) -> LeaderboardEntry {
+        LeaderboardEntry {
+            player_id,
+            player_name: format!("Player {}", player_id),
+            rank,
+            value,
+            tied: false,
+        }
+    }
+
+    #[test]
+    fn test_update_entry() {
+        let mut group = LeaderboardEntityGroup::default();
+        group.update(vec![
+            entry(1, 1, 500),
+            entry(2, 2, 400),
+            entry(3, 3, 300),
+            entry(4, 4, 200),
+            entry(5, 5, 100),
+        ]);
+        // Mark the untouched entries so a full rebuild would be noticed
+        group.values[0].player_name = "Unchanged".to_string();
+
+        group.update_entry(4, "Player 4".to_string(), 450);
+
+        let order: Vec<(u32, usize)> = group
+            .values
+            .iter()
+            .map(|value| (value.player_id, value.rank))
+            .collect();
+        assert_eq!(order, vec![(1, 1), (4, 2), (2, 3), (3, 4), (5, 5)]);
+        assert_eq!(group.values[0].player_name, "Unchanged");
+        assert_eq!(group.values[1].value, 450);
+
+        // New players are added at their ranked position
+        group.update_entry(6, "Player 6".to_string(), 250);
+        let ids: Vec<u32> = group.values.iter().map(|value| value.player_id).collect();
+        assert_eq!(ids, vec![1, 4, 2, 3, 6, 5]);
+        assert_eq!(group.values[4].rank, 5);
+        assert_eq!(group.values[5].rank, 6);
+    }
+
+    /// Tests that entries with equal values share a rank and that the
+    /// rank of the following entry skips the shared positions
+    #[test]
+    fn test_rank_ties() {
+        let mut values = vec![
+            entry(1, 0, 500),
+            entry(2, 0, 400),
+            entry(3, 0, 400),
+            entry(4, 0, 300),
+        ];
+        rank_entries(&mut values);
+
+        let ranks: Vec<(u32, usize, bool)> = values
+            .iter()
+            .map(|value| (value.player_id, value.rank, value.tied))
+            .collect();
+        assert_eq!(
+            ranks,
+            vec![(1, 1, false), (2, 2, true), (3, 2, true), (4, 4, false)]
+        );
+
+        // Updating an entry to tie with another shares its rank
+        let mut group = LeaderboardEntityGroup::default();
+        group.update(values);
+        group.update_entry(4, "Player 4".to_string(), 500);
+        let ranks: Vec<(u32, usize, bool)> = group
+            .values
+            .iter()
+            .map(|value| (value.player_id, value.rank, value.tied))
+            .collect();
+        assert_eq!(
+            ranks,
+            vec![(1, 1, true), (4, 1, true), (2, 3, true), (3, 3, true)]
+        );
+    }
+}
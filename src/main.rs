@@ -1,12 +1,14 @@
 use dotenvy::dotenv;
-use log::info;
+use log::{error, info};
 use servers::*;
 use state::GlobalState;
-use tokio::signal;
-use utils::{constants::VERSION, env, logging};
+use std::{path::Path, time::Duration};
+use tokio::{select, signal, task::JoinSet, time::sleep};
+use utils::{constants::VERSION, decay, env, logging};
 
 mod game;
 mod leaderboard;
+mod metrics;
 mod retriever;
 mod servers;
 mod state;
@@ -20,30 +22,85 @@ async fn main() {
     // Initialize logging
     logging::setup();
 
+    // Replay a packet capture instead of starting the server when
+    // started with --replay <file>
+    let mut args = std::env::args().skip(1);
+    if let (Some("--replay"), Some(path)) = (args.next().as_deref(), args.next()) {
+        if let Err(err) = main::replay::run(Path::new(&path)).await {
+            error!("Failed to read capture file: {}", err);
+        }
+        return;
+    }
+
     info!("Starting Pocket Relay v{}", VERSION);
 
+    // Check the configuration before anything is bound
+    if env::from_env(env::VALIDATE_ENV) {
+        let problems = env::validate();
+        if !problems.is_empty() {
+            for problem in &problems {
+                error!("Invalid configuration: {}", problem);
+            }
+            error!(
+                "Found {} configuration problem(s), not starting",
+                problems.len()
+            );
+            std::process::exit(1);
+        }
+    }
+
     logging::log_connection_urls().await;
 
     // Initialize global state
     GlobalState::init().await;
 
+    // Start removing games that are left empty
+    GlobalState::games().start_idle_sweep();
+
+    // Start periodically decaying galaxy at war values
+    decay::start_decay_job();
+
+    let mut servers = JoinSet::new();
+
     // Spawn redirector in its own task
-    tokio::spawn(redirector::start_server());
+    servers.spawn(redirector::start_server());
 
     if env::from_env(env::MITM_ENABLED) {
         // Start the MITM server
-        tokio::spawn(mitm::start_server());
+        servers.spawn(mitm::start_server());
     } else {
         // Spawn QOS server in its own task
-        tokio::spawn(qos::start_server());
+        servers.spawn(qos::start_server());
         // Spawn the HTTP server in its own task
-        tokio::spawn(http::start_server());
+        servers.spawn(http::start_server());
         // Spawn the Main server in its own task
-        tokio::spawn(main::start_server());
+        servers.spawn(main::start_server());
         // Spawn the Telemetry server in its own task
-        tokio::spawn(telemetry::start_server());
+        servers.spawn(telemetry::start_server());
+    }
+
+    // Wait for either a shutdown signal, for any of the servers to stop
+    // or for draining to complete
+    let drain_timeout = Duration::from_secs(env::from_env(env::DRAIN_TIMEOUT));
+    select! {
+        _ = signal::ctrl_c() => {}
+        _ = GlobalState::games().wait_drained(drain_timeout) => {
+            info!("Server finished draining");
+        }
+        Some(result) = servers.join_next() => {
+            match result {
+                Ok(Err(err)) => error!("Server stopped unexpectedly: {}", err),
+                Err(err) => error!("Server task failed: {}", err),
+                Ok(Ok(_)) => {}
+            }
+        }
     }
 
-    signal::ctrl_c().await.ok();
     info!("Shutting down...");
+
+    // Notify the connected sessions that the server is shutting down
+    if GlobalState::sessions().shutdown() > 0 {
+        // Give the sessions time to write the notification
+        sleep(Duration::from_millis(500)).await;
+    }
 }
@@ -0,0 +1,87 @@
+//! Metrics for the time players spend waiting in the matchmaking
+//! queue grouped into buckets of wait times
+
+use serde::Serialize;
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Counters for the number of matchmaking queue entries within each
+/// bucket of wait times
+#[derive(Default)]
+pub struct MatchmakingMetrics {
+    /// Entries matched in under 5 seconds
+    under_5s: AtomicU64,
+    /// Entries matched in under 15 seconds
+    under_15s: AtomicU64,
+    /// Entries matched in under 60 seconds
+    under_60s: AtomicU64,
+    /// Entries matched after 60 seconds or more
+    over_60s: AtomicU64,
+    /// Entries removed when the queue was cleared
+    cleared: AtomicU64,
+    /// Entries removed because the player cancelled matchmaking
+    cancelled: AtomicU64,
+}
+
+/// Serializable snapshot of the matchmaking metrics
+#[derive(Serialize)]
+pub struct MatchmakingMetricsSnapshot {
+    /// Entries matched in under 5 seconds
+    pub under_5s: u64,
+    /// Entries matched in under 15 seconds
+    pub under_15s: u64,
+    /// Entries matched in under 60 seconds
+    pub under_60s: u64,
+    /// Entries matched after 60 seconds or more
+    pub over_60s: u64,
+    /// Entries removed when the queue was cleared
+    pub cleared: u64,
+    /// Entries removed because the player cancelled matchmaking
+    pub cancelled: u64,
+}
+
+impl MatchmakingMetrics {
+    /// Records a queue entry that was matched with a game after
+    /// waiting for the provided time
+    ///
+    /// `elapsed` The time spent waiting in the queue
+    pub fn record_matched(&self, elapsed: Duration) {
+        let bucket = match elapsed.as_secs() {
+            0..=4 => &self.under_5s,
+            5..=14 => &self.under_15s,
+            15..=59 => &self.under_60s,
+            _ => &self.over_60s,
+        };
+        bucket.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records queue entries that were removed when the queue
+    /// was cleared
+    ///
+    /// `count` The number of entries removed
+    pub fn record_cleared(&self, count: u64) {
+        self.cleared.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Records queue entries that were removed because the player
+    /// cancelled matchmaking or their session ended
+    ///
+    /// `count` The number of entries removed
+    pub fn record_cancelled(&self, count: u64) {
+        self.cancelled.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Takes a snapshot of the current metric values
+    pub fn snapshot(&self) -> MatchmakingMetricsSnapshot {
+        MatchmakingMetricsSnapshot {
+            under_5s: self.under_5s.load(Ordering::Relaxed),
+            under_15s: self.under_15s.load(Ordering::Relaxed),
+            under_60s: self.under_60s.load(Ordering::Relaxed),
+            over_60s: self.over_60s.load(Ordering::Relaxed),
+            cleared: self.cleared.load(Ordering::Relaxed),
+            cancelled: self.cancelled.load(Ordering::Relaxed),
+        }
+    }
+}
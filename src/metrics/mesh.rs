@@ -0,0 +1,56 @@
+//! Metrics for the time taken for players joining games to establish
+//! their mesh connection with the other players
+
+use serde::Serialize;
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Counters for the mesh connection times. Times are stored in
+/// milliseconds.
+#[derive(Default)]
+pub struct MeshMetrics {
+    /// The number of mesh connections that completed
+    count: AtomicU64,
+    /// The total time spent establishing mesh connections
+    total_time: AtomicU64,
+    /// The longest time spent establishing a single mesh connection
+    max_time: AtomicU64,
+}
+
+/// Serializable snapshot of the mesh connection metrics
+#[derive(Serialize)]
+pub struct MeshMetricsSnapshot {
+    /// The number of mesh connections that completed
+    pub count: u64,
+    /// The average connection time in milliseconds
+    pub average_time: u64,
+    /// The longest connection time in milliseconds
+    pub max_time: u64,
+}
+
+impl MeshMetrics {
+    /// Records the time taken for a player to establish their
+    /// mesh connection after being added to a game
+    ///
+    /// `elapsed` The time taken
+    pub fn record(&self, elapsed: Duration) {
+        let time = elapsed.as_millis() as u64;
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total_time.fetch_add(time, Ordering::Relaxed);
+        self.max_time.fetch_max(time, Ordering::Relaxed);
+    }
+
+    /// Takes a snapshot of the current metric values
+    pub fn snapshot(&self) -> MeshMetricsSnapshot {
+        let count = self.count.load(Ordering::Relaxed);
+        let total_time = self.total_time.load(Ordering::Relaxed);
+        let average_time = if count > 0 { total_time / count } else { 0 };
+        MeshMetricsSnapshot {
+            count,
+            average_time,
+            max_time: self.max_time.load(Ordering::Relaxed),
+        }
+    }
+}
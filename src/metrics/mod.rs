@@ -0,0 +1,35 @@
+//! Module for collecting runtime metrics about the server such as
+//! the number of packets handled and the time taken to handle them
+
+use self::{matchmaking::MatchmakingMetrics, mesh::MeshMetrics, packets::PacketMetrics};
+use crate::{env, servers::main::routed_components};
+
+pub mod matchmaking;
+pub mod mesh;
+pub mod packets;
+
+/// Structure for storing the different metrics collected by the
+/// server on the global state.
+pub struct Metrics {
+    /// Metrics for packets handled by the main server
+    pub packets: PacketMetrics,
+    /// Metrics for the time taken to establish mesh connections
+    pub mesh: MeshMetrics,
+    /// Metrics for the time spent waiting in the matchmaking queue
+    pub matchmaking: MatchmakingMetrics,
+}
+
+impl Metrics {
+    /// Creates a new metrics structure using the configuration
+    /// from the environment variables
+    pub fn new() -> Self {
+        Self {
+            packets: PacketMetrics::new(
+                env::from_env(env::METRICS_IGNORE_PINGS),
+                routed_components(),
+            ),
+            mesh: MeshMetrics::default(),
+            matchmaking: MatchmakingMetrics::default(),
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! Metrics for the packets handled by the main server. Each component
+//! with a handler has its own set of counters which are created up front
+//! so that recording a packet only updates atomic counters without any
+//! locking.
+
+use crate::utils::components::{self, Components};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Name used in the snapshots for packets of components without handlers
+const UNHANDLED_NAME: &str = "Unhandled";
+
+/// Structure for storing the packet metrics for each component
+pub struct PacketMetrics {
+    /// Whether ping packets should be excluded from the metrics
+    ignore_pings: bool,
+    /// Map of the components to their metrics
+    values: HashMap<Components, PacketMetric>,
+    /// Metrics for packets of components without handlers
+    unhandled: PacketMetric,
+}
+
+/// Counters for a specific component. Times are stored in
+/// microseconds.
+#[derive(Default)]
+struct PacketMetric {
+    /// The total number of packets handled
+    count: AtomicU64,
+    /// The number of packets that resulted in an error
+    errors: AtomicU64,
+    /// The total time spent handling packets
+    total_time: AtomicU64,
+    /// The longest time spent handling a single packet
+    max_time: AtomicU64,
+}
+
+/// Serializable snapshot of the metrics for a single component
+#[derive(Serialize)]
+pub struct PacketMetricSnapshot {
+    /// The name of the component
+    pub component: String,
+    /// The total number of packets handled
+    pub count: u64,
+    /// The number of packets that resulted in an error
+    pub errors: u64,
+    /// The average handling time in microseconds
+    pub average_time: u64,
+    /// The longest handling time in microseconds
+    pub max_time: u64,
+}
+
+impl PacketMetric {
+    /// Records a single packet on this metric
+    ///
+    /// `time`     The time taken in microseconds
+    /// `is_error` Whether the packet resulted in an error
+    fn record(&self, time: u64, is_error: bool) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        if is_error {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        self.total_time.fetch_add(time, Ordering::Relaxed);
+        self.max_time.fetch_max(time, Ordering::Relaxed);
+    }
+
+    /// Takes a snapshot of the current metric values if any packets
+    /// have been recorded
+    ///
+    /// `component` The name of the component these metrics are for
+    fn snapshot(&self, component: String) -> Option<PacketMetricSnapshot> {
+        let count = self.count.load(Ordering::Relaxed);
+        if count == 0 {
+            return None;
+        }
+        let total_time = self.total_time.load(Ordering::Relaxed);
+        Some(PacketMetricSnapshot {
+            component,
+            count,
+            errors: self.errors.load(Ordering::Relaxed),
+            average_time: total_time / count,
+            max_time: self.max_time.load(Ordering::Relaxed),
+        })
+    }
+}
+
+impl PacketMetrics {
+    /// Creates a new empty set of packet metrics with counters for
+    /// each of the provided components
+    ///
+    /// `ignore_pings` Whether to exclude ping packets from the metrics
+    /// `components`   The components that have handlers
+    pub fn new(ignore_pings: bool, components: Vec<Components>) -> Self {
+        Self {
+            ignore_pings,
+            values: components
+                .into_iter()
+                .map(|component| (component, PacketMetric::default()))
+                .collect(),
+            unhandled: PacketMetric::default(),
+        }
+    }
+
+    /// Checks whether the provided component should be excluded from
+    /// the metrics. Ping and SuspendUserPing are sent constantly by
+    /// every client so they are excluded unless configured otherwise.
+    ///
+    /// `component` The component to check
+    fn is_ignored(&self, component: &Components) -> bool {
+        self.ignore_pings
+            && (Components::Util(components::Util::Ping).eq(component)
+                || Components::Util(components::Util::SuspendUserPing).eq(component))
+    }
+
+    /// Records a handled packet for the provided component
+    ///
+    /// `component` The component of the handled packet
+    /// `elapsed`   The time taken to handle the packet
+    /// `is_error`  Whether handling resulted in an error
+    pub fn record(&self, component: Components, elapsed: Duration, is_error: bool) {
+        if self.is_ignored(&component) {
+            return;
+        }
+
+        let time = elapsed.as_micros() as u64;
+        self.values
+            .get(&component)
+            .unwrap_or(&self.unhandled)
+            .record(time, is_error);
+    }
+
+    /// Takes a snapshot of all the recorded metrics sorted by
+    /// the component name
+    pub fn snapshot(&self) -> Vec<PacketMetricSnapshot> {
+        let mut snapshots: Vec<PacketMetricSnapshot> = self
+            .values
+            .iter()
+            .filter_map(|(component, metric)| metric.snapshot(format!("{:?}", component)))
+            .collect();
+        snapshots.sort_by(|a, b| a.component.cmp(&b.component));
+        snapshots.extend(self.unhandled.snapshot(UNHANDLED_NAME.to_string()));
+        snapshots
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PacketMetrics, UNHANDLED_NAME};
+    use crate::utils::components::{Components, UserSessions, Util};
+    use std::time::Duration;
+
+    #[test]
+    fn test_record() {
+        let component = || Components::UserSessions(UserSessions::ResumeSession);
+        let metrics = PacketMetrics::new(true, vec![component()]);
+
+        metrics.record(component(), Duration::from_micros(100), false);
+        metrics.record(component(), Duration::from_micros(300), true);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.len(), 1);
+
+        let value = &snapshot[0];
+        assert_eq!(value.count, 2);
+        assert_eq!(value.errors, 1);
+        assert_eq!(value.average_time, 200);
+        assert_eq!(value.max_time, 300);
+    }
+
+    /// Tests that packets for components without handlers are
+    /// recorded together
+    #[test]
+    fn test_record_unhandled() {
+        let metrics = PacketMetrics::new(true, Vec::new());
+        metrics.record(
+            Components::UserSessions(UserSessions::ResumeSession),
+            Duration::from_micros(100),
+            true,
+        );
+        metrics.record(Components::Unknown(0x99, 0x1), Duration::ZERO, true);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot[0].component, UNHANDLED_NAME);
+        assert_eq!(snapshot[0].count, 2);
+        assert_eq!(snapshot[0].errors, 2);
+    }
+
+    #[test]
+    fn test_ignore_pings() {
+        let pings = || {
+            vec![
+                Components::Util(Util::Ping),
+                Components::Util(Util::SuspendUserPing),
+            ]
+        };
+        let metrics = PacketMetrics::new(true, pings());
+        metrics.record(Components::Util(Util::Ping), Duration::ZERO, false);
+        metrics.record(
+            Components::Util(Util::SuspendUserPing),
+            Duration::ZERO,
+            false,
+        );
+        assert!(metrics.snapshot().is_empty());
+
+        let metrics = PacketMetrics::new(false, pings());
+        metrics.record(Components::Util(Util::Ping), Duration::ZERO, false);
+        assert_eq!(metrics.snapshot().len(), 1);
+    }
+}
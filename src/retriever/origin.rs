@@ -1,6 +1,6 @@
 use super::{
     models::{OriginLoginRequest, OriginLoginResponse, SettingsResponse},
-    RetSession, Retriever,
+    RetSession, Retriever, RetrieverResult,
 };
 use crate::utils::components::{Authentication, Components, Util};
 use blaze_pk::types::TdfMap;
@@ -21,18 +21,18 @@ pub struct OriginFlow {
 
 impl OriginFlow {
     /// Authenticates with the official servers using the provided token. Will
-    /// return Origin details if the authentication process went without error
+    /// return Origin details if the authentication process went without error.
+    /// Tokens rejected by the official servers result in a packet error.
     ///
     /// `token` The token to authenticate with
-    pub async fn authenticate(&mut self, token: String) -> Option<OriginDetails> {
+    pub async fn authenticate(&mut self, token: String) -> RetrieverResult<OriginDetails> {
         let value = self
             .session
             .request::<OriginLoginRequest, OriginLoginResponse>(
                 Components::Authentication(Authentication::OriginLogin),
                 OriginLoginRequest { token },
             )
-            .await
-            .ok()?;
+            .await?;
 
         let details = OriginDetails {
             email: value.email,
@@ -43,7 +43,7 @@ impl OriginFlow {
             "Retrieved origin details (Name: {}, Email: {})",
             &details.display_name, &details.email
         );
-        Some(details)
+        Ok(details)
     }
 
     /// Loads the user settings from the official server. Must be called after
@@ -0,0 +1,161 @@
+//! Access logging for the HTTP API. When enabled each authenticated
+//! API request is logged as a JSON line to a separate logging target
+//! which is written to its own log file. Request bodies are never
+//! included in the entries.
+
+use super::token::TokenId;
+use crate::{env, utils::time::unix_millis};
+use axum::{
+    extract::{OriginalUri, State},
+    http::Request,
+    middleware::Next,
+    response::Response,
+};
+use log::info;
+use serde::Serialize;
+use std::time::Instant;
+
+/// The logging target used for access log entries
+pub const TARGET: &str = "access";
+
+/// Whether access logging is enabled. This is read once when the
+/// router is created and provided as the state of the layer
+#[derive(Clone, Copy)]
+pub struct AccessLog(pub bool);
+
+impl AccessLog {
+    /// Reads whether access logging is enabled from the environment
+    pub fn from_env() -> Self {
+        Self(env::from_env(env::API_ACCESS_LOG))
+    }
+}
+
+/// Structure of a single access log entry
+#[derive(Serialize)]
+struct AccessEntry<'a> {
+    /// The time the request was received in milliseconds since the
+    /// unix epoch
+    timestamp: u64,
+    /// The ID of the token used to authenticate the request
+    token_id: Option<u32>,
+    /// The request method
+    method: &'a str,
+    /// The full path of the request
+    path: &'a str,
+    /// The status code of the response
+    status: u16,
+    /// The time taken to handle the request in milliseconds
+    duration: u64,
+}
+
+/// Middleware layer function for logging access entries for requests
+/// when access logging is enabled. This layer must be applied inside
+/// the token auth layer for the token ID to be present.
+///
+/// `enabled` Whether access logging is enabled
+/// `req`     The request to handle
+/// `next`    The next layer to use
+pub async fn access_log_layer<T>(
+    State(AccessLog(enabled)): State<AccessLog>,
+    req: Request<T>,
+    next: Next<T>,
+) -> Response {
+    if !enabled {
+        return next.run(req).await;
+    }
+
+    let timestamp = unix_millis();
+    let start = Instant::now();
+
+    let token_id = req.extensions().get::<TokenId>().map(|value| value.0);
+    let method = req.method().clone();
+    // Nested routers strip their prefix from the request URI so the
+    // original URI is used when available
+    let path = req
+        .extensions()
+        .get::<OriginalUri>()
+        .map(|value| value.0.path().to_string())
+        .unwrap_or_else(|| req.uri().path().to_string());
+
+    let res = next.run(req).await;
+
+    let entry = AccessEntry {
+        timestamp,
+        token_id,
+        method: method.as_str(),
+        path: &path,
+        status: res.status().as_u16(),
+        duration: start.elapsed().as_millis() as u64,
+    };
+
+    match serde_json::to_string(&entry) {
+        Ok(value) => info!(target: TARGET, "{}", value),
+        Err(err) => info!(target: TARGET, "Failed to serialize access entry: {}", err),
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::{access_log_layer, AccessLog, TARGET};
+    use crate::{
+        env,
+        servers::http::{middleware::token::token_auth_layer, stores::token::TokenStore},
+        utils::logging::capture,
+    };
+    use axum::{middleware, routing::get, Extension, Router};
+    use serde_json::Value;
+    use std::{net::SocketAddr, sync::Arc};
+
+    #[tokio::test]
+    async fn test_access_log_entry() {
+        capture::install();
+
+        let store = Arc::new(TokenStore::default());
+        let (token, _) = store
+            .authenticate(&env::env(env::API_USERNAME), &env::env(env::API_PASSWORD))
+            .await
+            .unwrap();
+        let token_id = store.list_tokens().await[0].id;
+
+        let router = Router::new()
+            .nest(
+                "/api/access-test",
+                Router::new().route("/value", get(|| async { "value" })),
+            )
+            .layer(middleware::from_fn_with_state(
+                AccessLog(true),
+                access_log_layer,
+            ))
+            .layer(middleware::from_fn(token_auth_layer))
+            .layer(Extension(store));
+
+        let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .serve(router.into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        let res = reqwest::Client::new()
+            .get(format!("http://{}/api/access-test/value?secret=1", addr))
+            .header("X-Token", token)
+            .send()
+            .await
+            .unwrap();
+        assert!(res.status().is_success());
+
+        let entries: Vec<Value> = capture::entries(TARGET)
+            .iter()
+            .filter_map(|entry| serde_json::from_str::<Value>(entry).ok())
+            .filter(|entry| entry["path"] == "/api/access-test/value")
+            .collect();
+        assert_eq!(entries.len(), 1);
+
+        let entry = &entries[0];
+        assert!(entry["timestamp"].as_u64().is_some());
+        assert_eq!(entry["token_id"], token_id);
+        assert_eq!(entry["method"], "GET");
+        assert_eq!(entry["status"], 200);
+        assert!(entry["duration"].as_u64().is_some());
+    }
+}
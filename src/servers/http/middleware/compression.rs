@@ -0,0 +1,199 @@
+use crate::env;
+use axum::{
+    body::{boxed, Full, HttpBody},
+    http::{header, HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use flate2::{
+    write::{GzEncoder, ZlibEncoder},
+    Compression,
+};
+use std::io::{self, Write};
+
+/// Encodings that responses can be compressed with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    /// Finds the preferred encoding from the provided Accept-Encoding
+    /// header value. Gzip is preferred over deflate and encodings with
+    /// a quality value of zero are ignored.
+    ///
+    /// `value` The Accept-Encoding header value
+    fn from_accept(value: &str) -> Option<Self> {
+        let mut encoding = None;
+        for part in value.split(',') {
+            let mut parts = part.split(';').map(|value| value.trim());
+            let name = parts.next().unwrap_or_default();
+            let disabled = parts.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|value| value.parse::<f32>().ok())
+                    .map(|value| value <= 0.0)
+                    .unwrap_or(false)
+            });
+            if disabled {
+                continue;
+            }
+
+            if name.eq_ignore_ascii_case("gzip") {
+                return Some(Self::Gzip);
+            } else if name.eq_ignore_ascii_case("deflate") {
+                encoding = Some(Self::Deflate);
+            }
+        }
+        encoding
+    }
+
+    /// Compresses the provided bytes using this encoding
+    ///
+    /// `bytes` The bytes to compress
+    fn encode(&self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            }
+            Self::Deflate => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            }
+        }
+    }
+
+    /// Returns the Content-Encoding header value for this encoding
+    fn header_value(&self) -> HeaderValue {
+        HeaderValue::from_static(match self {
+            Self::Gzip => "gzip",
+            Self::Deflate => "deflate",
+        })
+    }
+}
+
+/// Middleware layer function for compressing responses that are larger
+/// than the configured threshold when the client accepts a supported
+/// encoding
+///
+/// `req`  The request to handle
+/// `next` The next layer to use
+pub async fn compression_layer<T>(req: Request<T>, next: Next<T>) -> Response {
+    if !env::from_env(env::HTTP_COMPRESSION) {
+        return next.run(req).await;
+    }
+
+    let encoding = req
+        .headers()
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .and_then(Encoding::from_accept);
+
+    let res = next.run(req).await;
+    match encoding {
+        Some(encoding) => {
+            let threshold = env::from_env(env::HTTP_COMPRESSION_THRESHOLD);
+            compress_response(res, encoding, threshold).await
+        }
+        None => res,
+    }
+}
+
+/// Compresses the body of the provided response if the size of the body
+/// is known and is at least the provided threshold. Responses that are
+/// already encoded or streamed are left unchanged.
+///
+/// `res`       The response to compress
+/// `encoding`  The encoding to compress with
+/// `threshold` The minimum body size to compress
+async fn compress_response(res: Response, encoding: Encoding, threshold: u64) -> Response {
+    if res.headers().contains_key(header::CONTENT_ENCODING) {
+        return res;
+    }
+
+    let size = match res.body().size_hint().exact() {
+        Some(size) if size >= threshold => size,
+        _ => return res,
+    };
+
+    let (mut parts, mut body) = res.into_parts();
+    let mut bytes = Vec::with_capacity(size as usize);
+    while let Some(chunk) = body.data().await {
+        match chunk {
+            Ok(chunk) => bytes.extend_from_slice(&chunk),
+            Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+
+    let compressed = match encoding.encode(&bytes) {
+        Ok(value) => value,
+        Err(_) => return Response::from_parts(parts, boxed(Full::from(bytes))),
+    };
+
+    let headers = &mut parts.headers;
+    headers.insert(header::CONTENT_ENCODING, encoding.header_value());
+    headers.remove(header::CONTENT_LENGTH);
+    headers.append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    Response::from_parts(parts, boxed(Full::from(compressed)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{compress_response, Encoding};
+    use axum::{
+        body::HttpBody,
+        http::header,
+        response::{IntoResponse, Response},
+    };
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    /// Collects the body of the provided response
+    async fn body_bytes(res: Response) -> Vec<u8> {
+        let mut body = res.into_body();
+        let mut bytes = Vec::new();
+        while let Some(chunk) = body.data().await {
+            bytes.extend_from_slice(&chunk.unwrap());
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_from_accept() {
+        assert_eq!(
+            Encoding::from_accept("deflate, gzip;q=1.0"),
+            Some(Encoding::Gzip)
+        );
+        assert_eq!(
+            Encoding::from_accept("gzip;q=0, deflate"),
+            Some(Encoding::Deflate)
+        );
+        assert_eq!(Encoding::from_accept("br"), None);
+    }
+
+    #[tokio::test]
+    async fn test_compress_response() {
+        let content = "leaderboard entry ".repeat(200);
+
+        // Large responses are compressed when an encoding is accepted
+        let res = compress_response(content.clone().into_response(), Encoding::Gzip, 1024).await;
+        assert_eq!(res.headers()[header::CONTENT_ENCODING], "gzip");
+        let compressed = body_bytes(res).await;
+        assert!(compressed.len() < content.len());
+
+        let mut decoded = String::new();
+        GzDecoder::new(compressed.as_slice())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, content);
+
+        // Responses below the threshold are left uncompressed
+        let res = compress_response("small".into_response(), Encoding::Gzip, 1024).await;
+        assert!(res.headers().get(header::CONTENT_ENCODING).is_none());
+        assert_eq!(body_bytes(res).await, b"small");
+    }
+}
@@ -1,3 +1,7 @@
+/// Middleware functions for logging API access entries
+pub mod access;
+/// Middleware functions for compressing responses
+pub mod compression;
 /// Middleware functions related to CORS implementation
 pub mod cors;
 /// Middleware functions an enums related to token authentication
@@ -11,11 +11,15 @@ use std::{fmt::Display, sync::Arc};
 const TOKEN_HEADER: &str = "X-Token";
 
 /// Guarding middleware layer for ensuring that requests have a valid
-/// authentication token in the X-Token header.
+/// authentication token in the X-Token header. The ID of the token is
+/// added to the request extensions.
 ///
 /// `req`  The request to handle
 /// `next` The next layer to use
-pub async fn token_auth_layer<T>(req: Request<T>, next: Next<T>) -> Result<Response, TokenError> {
+pub async fn token_auth_layer<T>(
+    mut req: Request<T>,
+    next: Next<T>,
+) -> Result<Response, TokenError> {
     // Obtain the token store from the extensions
     let store = req
         .extensions()
@@ -30,13 +34,21 @@ pub async fn token_auth_layer<T>(req: Request<T>, next: Next<T>) -> Result<Respo
         .map(|value| value.to_owned())
         .ok_or(TokenError::MissingToken)?;
 
-    if store.is_valid_token(&token).await {
-        Ok(next.run(req).await)
-    } else {
-        Err(TokenError::InvalidToken)
-    }
+    let id = store
+        .validate_token(&token)
+        .await
+        .ok_or(TokenError::InvalidToken)?;
+
+    // Store the token ID for use by the inner layers
+    req.extensions_mut().insert(TokenId(id));
+    Ok(next.run(req).await)
 }
 
+/// Request extension containing the ID of the token that was used
+/// to authenticate the request
+#[derive(Clone, Copy)]
+pub struct TokenId(pub u32);
+
 /// Error type used by the token checking middleware to handle
 /// different errors and create error respones based on them
 pub enum TokenError {
@@ -1,6 +1,6 @@
-use std::net::SocketAddr;
+use std::{io, net::SocketAddr};
 
-use crate::env;
+use crate::{env, utils::net::bind_address};
 use axum::Server;
 use log::{error, info};
 
@@ -8,16 +8,46 @@ mod ext;
 mod middleware;
 mod routes;
 mod stores;
+mod tls;
 
 /// Starts the HTTP server
-pub async fn start_server() {
+pub async fn start_server() -> io::Result<()> {
     let port = env::from_env(env::HTTP_PORT);
     info!("Starting HTTP Server on (Port: {port})");
 
     let router = routes::router();
-    let addr: SocketAddr = ([0, 0, 0, 0], port).into();
-    if let Err(err) = Server::bind(&addr).serve(router.into_make_service()).await {
-        error!("Failed to bind HTTP server (Port: {}): {:?}", port, err);
-        panic!();
+    let addr: SocketAddr = SocketAddr::new(bind_address(), port);
+
+    // Serve over TLS instead when a certificate is configured
+    match tls::config_from_env() {
+        Ok(Some(config)) => {
+            info!("Using TLS for HTTP server");
+            return tls::serve(addr, router, config).await.map_err(|err| {
+                error!("Failed to bind HTTP server (Port: {}): {:?}", port, err);
+                err
+            });
+        }
+        Ok(None) => {}
+        Err(err) => {
+            error!("Failed to load HTTP server TLS configuration: {}", err);
+            return Err(err);
+        }
+    }
+
+    let server = match Server::try_bind(&addr) {
+        Ok(value) => value,
+        Err(err) => {
+            error!("Failed to bind HTTP server (Port: {}): {:?}", port, err);
+            return Err(io::Error::new(io::ErrorKind::AddrInUse, err));
+        }
+    };
+
+    if let Err(err) = server.serve(router.into_make_service()).await {
+        error!(
+            "Error while serving HTTP server (Port: {}): {:?}",
+            port, err
+        );
+        return Err(io::Error::new(io::ErrorKind::Other, err));
     }
+    Ok(())
 }
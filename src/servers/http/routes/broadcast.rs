@@ -0,0 +1,36 @@
+//! This module contains routes for broadcasting messages to all
+//! the sessions connected to the main server
+
+use crate::state::GlobalState;
+use axum::{routing::post, Json, Router};
+use serde::{Deserialize, Serialize};
+
+/// Router function creates a new router with all the underlying
+/// routes for this file.
+///
+/// Prefix: /api/broadcast
+pub fn router() -> Router {
+    Router::new().route("/", post(broadcast_message))
+}
+
+/// Request structure for broadcasting a message
+#[derive(Deserialize)]
+struct BroadcastRequest {
+    /// The message to broadcast
+    message: String,
+}
+
+/// Response from broadcasting a message
+#[derive(Serialize)]
+struct BroadcastResponse {
+    /// The number of sessions the message was sent to
+    count: usize,
+}
+
+/// Route for broadcasting a message to all the connected sessions
+///
+/// `req` The broadcast request
+async fn broadcast_message(Json(req): Json<BroadcastRequest>) -> Json<BroadcastResponse> {
+    let count = GlobalState::sessions().broadcast(&req.message);
+    Json(BroadcastResponse { count })
+}
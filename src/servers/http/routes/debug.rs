@@ -0,0 +1,77 @@
+//! This modules contains routes for debugging the server such as
+//! tracing the packets of individual sessions
+
+use crate::{servers::main::handled_components, state::GlobalState, utils::types::SessionID};
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Serialize;
+
+/// Router function creates a new router with all the underlying
+/// routes for this file.
+///
+/// Prefix: /api/debug
+pub fn router() -> Router {
+    Router::new()
+        .route("/routes", get(list_routes))
+        .route("/session/:session_id/trace", post(trace_session))
+        .route("/session/:session_id/untrace", post(untrace_session))
+}
+
+/// Error type used when the session requested is not connected
+struct SessionNotFound;
+
+/// Response for the list of main server routes
+#[derive(Serialize)]
+struct RoutesResponse {
+    /// The names of the components that have handlers. Packets for
+    /// any other components fall through to the missing handler error
+    handled: Vec<String>,
+}
+
+/// Route for listing the components that the main server has
+/// handlers registered for
+async fn list_routes() -> Json<RoutesResponse> {
+    Json(RoutesResponse {
+        handled: handled_components(),
+    })
+}
+
+/// Route for enabling tracing on the session with the provided ID.
+/// Traced sessions log every packet in full along with its raw bytes
+///
+/// `session_id` The ID of the session
+async fn trace_session(Path(session_id): Path<SessionID>) -> Result<StatusCode, SessionNotFound> {
+    set_traced(session_id, true)
+}
+
+/// Route for disabling tracing on the session with the provided ID
+///
+/// `session_id` The ID of the session
+async fn untrace_session(Path(session_id): Path<SessionID>) -> Result<StatusCode, SessionNotFound> {
+    set_traced(session_id, false)
+}
+
+/// Sets whether the session with the provided ID is traced
+///
+/// `session_id` The ID of the session
+/// `traced`     Whether the session should be traced
+fn set_traced(session_id: SessionID, traced: bool) -> Result<StatusCode, SessionNotFound> {
+    if !GlobalState::sessions().set_traced(session_id, traced) {
+        return Err(SessionNotFound);
+    }
+    Ok(StatusCode::OK)
+}
+
+/// IntoResponse implementation for SessionNotFound to allow it to be
+/// used within the result type as a error response
+impl IntoResponse for SessionNotFound {
+    #[inline]
+    fn into_response(self) -> Response {
+        (StatusCode::NOT_FOUND, "SessionNotFound").into_response()
+    }
+}
@@ -1,12 +1,21 @@
-use crate::{game::GameSnapshot, state::GlobalState, utils::types::GameID};
+use crate::{
+    game::{
+        manager::{CreateGameError, GamesStats},
+        AttrMap, CompactGameSnapshot, GameModifyAction, GameSnapshot,
+    },
+    state::GlobalState,
+    utils::types::{GameID, PlayerID},
+};
 use axum::{
     extract::{Path, Query},
     http::StatusCode,
     response::{IntoResponse, Response},
-    routing::get,
+    routing::{get, patch, post, put},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tokio::join;
 
 /// Router function creates a new router with all the underlying
 /// routes for this file.
@@ -14,8 +23,17 @@ use serde::{Deserialize, Serialize};
 /// Prefix: /api/games
 pub fn router() -> Router {
     Router::new()
-        .route("/", get(get_games))
-        .route("/:id", get(get_game))
+        .route("/", get(get_games).post(create_game))
+        .route("/stats", get(get_stats))
+        .route("/:id", get(get_game).delete(close_game))
+        .route("/:id/debug", get(get_game_debug))
+        .route(
+            "/:id/attributes",
+            patch(set_attributes).delete(remove_attributes),
+        )
+        .route("/:id/reserved", put(set_reserved))
+        .route("/:id/host/:player_id", post(transfer_host))
+        .route("/:id/chat", post(send_chat))
 }
 
 /// The query structure for a players query
@@ -28,6 +46,38 @@ struct GamesQuery {
     /// of 255 entries to prevent server strain from querying the
     /// entire list of leaderboard entries
     count: Option<u8>,
+    /// Optional time in milliseconds since the unix epoch. When
+    /// provided only the games modified since this time are returned
+    /// along with the games removed since this time
+    since: Option<u64>,
+    /// Whether to return compact snapshots that don't include
+    /// the attributes or player details
+    #[serde(default)]
+    compact: bool,
+}
+
+/// List of game snapshots in either the full or compact form
+#[derive(Serialize)]
+#[serde(untagged)]
+enum GamesList {
+    /// Full game snapshots
+    Full(Vec<GameSnapshot>),
+    /// Compact game snapshots
+    Compact(Vec<CompactGameSnapshot>),
+}
+
+impl GamesList {
+    /// Creates the list of games in the compact form if requested
+    ///
+    /// `games`   The game snapshots
+    /// `compact` Whether to use the compact form
+    fn new(games: Vec<GameSnapshot>, compact: bool) -> Self {
+        if compact {
+            Self::Compact(games.into_iter().map(GameSnapshot::compact).collect())
+        } else {
+            Self::Full(games)
+        }
+    }
 }
 
 /// Response from the players endpoint which contains a list of
@@ -35,28 +85,91 @@ struct GamesQuery {
 #[derive(Serialize)]
 struct GamesResponse {
     /// The list of players retrieved
-    games: Vec<GameSnapshot>,
+    games: GamesList,
     /// Whether there is more players left in the database
     more: bool,
+    /// The IDs of the games removed since the requested time. Only
+    /// present when a since time was provided
+    #[serde(skip_serializing_if = "Option::is_none")]
+    removed: Option<Vec<GameID>>,
 }
 
 /// Route for retrieving a list of all the games that are currently running.
-/// Will take a snapshot of all the games.
+/// Will take a snapshot of all the games. When a since time is provided
+/// only the games that have changed or been removed since then are
+/// included and the offset and count are ignored.
 ///
 /// `query` The query containing the offset and count
 async fn get_games(Query(query): Query<GamesQuery>) -> Json<GamesResponse> {
     /// The default number of games to return in a leaderboard response
     const DEFAULT_COUNT: u8 = 20;
 
+    let games = GlobalState::games();
+    if let Some(since) = query.since {
+        let (snapshots, removed) = join!(games.snapshot_since(since), games.removed_since(since));
+        return Json(GamesResponse {
+            games: GamesList::new(snapshots, query.compact),
+            more: false,
+            removed: Some(removed),
+        });
+    }
+
     let count: usize = query.count.unwrap_or(DEFAULT_COUNT) as usize;
 
     // Calculate the start and ending indexes
     let start_index: usize = query.offset * count;
 
     // Retrieve the game snapshots
-    let (games, more) = GlobalState::games().snapshot(start_index, count).await;
+    let (games, more) = games.snapshot(start_index, count).await;
+
+    Json(GamesResponse {
+        games: GamesList::new(games, query.compact),
+        more,
+        removed: None,
+    })
+}
+
+/// Route for retrieving statistics about the current games including
+/// the number of games in each state and the average player count
+async fn get_stats() -> Json<GamesStats> {
+    Json(GlobalState::games().stats().await)
+}
+
+/// Request structure for creating a new game without a host
+#[derive(Deserialize)]
+struct CreateGameRequest {
+    /// The initial attributes of the game
+    #[serde(default)]
+    attributes: BTreeMap<String, String>,
+    /// The initial setting of the game
+    #[serde(default)]
+    setting: u16,
+}
+
+/// Response structure for a created game
+#[derive(Serialize)]
+struct CreateGameResponse {
+    /// The ID of the created game
+    id: GameID,
+}
+
+/// Route for creating a new game that has no host player for use by
+/// bots and tooling. The first player to join the game becomes the
+/// host and the game is removed by the idle sweep while empty
+///
+/// `req` The create game request
+async fn create_game(
+    Json(req): Json<CreateGameRequest>,
+) -> Result<Json<CreateGameResponse>, CreateGameError> {
+    let mut attributes = AttrMap::with_capacity(req.attributes.len());
+    for (key, value) in req.attributes {
+        attributes.insert(key, value);
+    }
 
-    Json(GamesResponse { games, more })
+    let id = GlobalState::games()
+        .create_empty_game(attributes, req.setting)
+        .await?;
+    Ok(Json(CreateGameResponse { id }))
 }
 
 /// Error type used when a game with a specific ID was requested
@@ -74,6 +187,156 @@ async fn get_game(Path(game_id): Path<GameID>) -> Result<Json<GameSnapshot>, Gam
     Ok(Json(games))
 }
 
+/// Route for closing the game with a specific game ID. All the players
+/// in the game are removed and notified before the game is removed
+///
+/// `game_id` The ID of the game
+async fn close_game(Path(game_id): Path<GameID>) -> Result<StatusCode, GameNotFound> {
+    if !GlobalState::games().close_game(game_id).await {
+        return Err(GameNotFound);
+    }
+    Ok(StatusCode::OK)
+}
+
+/// Route for retrieving the decoded contents of the game setup packet
+/// that was sent to the most recently joined player of the game with a
+/// specific game ID. Used for debugging protocol issues.
+///
+/// `game_id` The ID of the game
+async fn get_game_debug(Path(game_id): Path<GameID>) -> Result<String, GameNotFound> {
+    GlobalState::games()
+        .debug_setup(game_id)
+        .await
+        .ok_or(GameNotFound)
+}
+
+/// The query structure for modifying game attributes
+#[derive(Deserialize)]
+struct AttributesQuery {
+    /// Whether the existing attributes should be replaced rather
+    /// than extended with the new attributes
+    #[serde(default)]
+    replace: bool,
+}
+
+/// Route for modifying the attributes of the game with a specific game ID.
+/// By default the new attributes are added to the existing attributes
+/// unless the replace query flag is set.
+///
+/// `game_id`    The ID of the game
+/// `query`      The query containing the replace flag
+/// `attributes` The attributes to set
+async fn set_attributes(
+    Path(game_id): Path<GameID>,
+    Query(query): Query<AttributesQuery>,
+    Json(attributes): Json<BTreeMap<String, String>>,
+) -> Result<StatusCode, GameNotFound> {
+    let games = GlobalState::games();
+    if games.snapshot_id(game_id).await.is_none() {
+        return Err(GameNotFound);
+    }
+
+    let mut map = AttrMap::with_capacity(attributes.len());
+    for (key, value) in attributes {
+        map.insert(key, value);
+    }
+
+    let action = if query.replace {
+        GameModifyAction::ReplaceAttributes(map)
+    } else {
+        GameModifyAction::SetAttributes(map)
+    };
+    games.modify_game(game_id, action);
+    Ok(StatusCode::OK)
+}
+
+/// Route for removing the attributes with the provided keys from the
+/// game with a specific game ID
+///
+/// `game_id` The ID of the game
+/// `keys`    The keys of the attributes to remove
+async fn remove_attributes(
+    Path(game_id): Path<GameID>,
+    Json(keys): Json<Vec<String>>,
+) -> Result<StatusCode, GameNotFound> {
+    let games = GlobalState::games();
+    if games.snapshot_id(game_id).await.is_none() {
+        return Err(GameNotFound);
+    }
+
+    games.modify_game(game_id, GameModifyAction::RemoveAttributes(keys));
+    Ok(StatusCode::OK)
+}
+
+/// Request structure for setting the reserved slots of a game
+#[derive(Deserialize)]
+struct ReservedRequest {
+    /// The IDs of the players to reserve slots for
+    reserved: Vec<PlayerID>,
+}
+
+/// Route for replacing the list of players that have slots reserved
+/// in the game with a specific game ID
+///
+/// `game_id` The ID of the game
+/// `req`     The reserved slots request
+async fn set_reserved(
+    Path(game_id): Path<GameID>,
+    Json(req): Json<ReservedRequest>,
+) -> Result<StatusCode, GameNotFound> {
+    let games = GlobalState::games();
+    if games.snapshot_id(game_id).await.is_none() {
+        return Err(GameNotFound);
+    }
+    games.modify_game(game_id, GameModifyAction::SetReserved(req.reserved));
+    Ok(StatusCode::OK)
+}
+
+/// Error type used when transferring host to a player that isn't
+/// within the game
+struct PlayerNotInGame;
+
+/// Route for transferring host of the game with a specific game ID
+/// to a player that is already within the game
+///
+/// `game_id`   The ID of the game
+/// `player_id` The ID of the player to make host
+async fn transfer_host(Path((game_id, player_id)): Path<(GameID, PlayerID)>) -> Response {
+    match GlobalState::games().transfer_host(game_id, player_id).await {
+        Some(true) => StatusCode::OK.into_response(),
+        Some(false) => PlayerNotInGame.into_response(),
+        None => GameNotFound.into_response(),
+    }
+}
+
+/// Request structure for sending a chat message
+#[derive(Deserialize)]
+struct ChatRequest {
+    /// The text of the message
+    text: String,
+}
+
+/// Route for sending a chat message from the server to all the
+/// players in the game with a specific game ID
+///
+/// `game_id` The ID of the game
+/// `req`     The chat request
+async fn send_chat(
+    Path(game_id): Path<GameID>,
+    Json(req): Json<ChatRequest>,
+) -> Result<StatusCode, GameNotFound> {
+    let games = GlobalState::games();
+    games.snapshot_id(game_id).await.ok_or(GameNotFound)?;
+    games.modify_game(
+        game_id,
+        GameModifyAction::ChatMessage {
+            from: None,
+            text: req.text,
+        },
+    );
+    Ok(StatusCode::OK)
+}
+
 /// IntoResponse implementation for GameNotFound to allow it to be
 /// used within the result type as a error response
 impl IntoResponse for GameNotFound {
@@ -82,3 +345,25 @@ impl IntoResponse for GameNotFound {
         (StatusCode::NOT_FOUND, "GameNotFound").into_response()
     }
 }
+
+/// IntoResponse implementation for PlayerNotInGame to allow it to be
+/// used as a error response
+impl IntoResponse for PlayerNotInGame {
+    #[inline]
+    fn into_response(self) -> Response {
+        (StatusCode::NOT_FOUND, "PlayerNotInGame").into_response()
+    }
+}
+
+/// IntoResponse implementation for CreateGameError to allow it to be
+/// used within the result type as a error response
+impl IntoResponse for CreateGameError {
+    #[inline]
+    fn into_response(self) -> Response {
+        let message = match self {
+            Self::Draining => "ServerDraining",
+            Self::NoFreeId => "NoFreeGameId",
+        };
+        (StatusCode::SERVICE_UNAVAILABLE, message).into_response()
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    leaderboard::{models::*, Leaderboard},
+    leaderboard::{models::LeaderboardEntry, Leaderboard},
     servers::http::ext::ErrorStatusCode,
     state::GlobalState,
     utils::types::PlayerID,
@@ -55,6 +55,8 @@ struct LeaderboardQuery {
 /// from a leaderboard request
 #[derive(Serialize)]
 struct LeaderboardResponse {
+    /// The display title of the leaderboard
+    title: String,
     /// The entries retrieved at the provided offset
     entries: Vec<LeaderboardEntry>,
     /// Whether there is more entries past the provided offset
@@ -70,10 +72,10 @@ async fn get_leaderboard(
     Path(name): Path<String>,
     Query(query): Query<LeaderboardQuery>,
 ) -> Result<Json<LeaderboardResponse>, LeaderboardError> {
-    let ty: LeaderboardType =
-        LeaderboardType::try_parse(&name).ok_or(LeaderboardError::UnknownLeaderboard)?;
-
     let leaderboard: &Leaderboard = GlobalState::leaderboard();
+    let board = leaderboard
+        .find(&name)
+        .ok_or(LeaderboardError::UnknownLeaderboard)?;
 
     /// The default number of entries to return in a leaderboard response
     const DEFAULT_COUNT: u8 = 40;
@@ -83,16 +85,20 @@ async fn get_leaderboard(
     // Calculate the start and ending indexes
     let start: usize = query.offset * count;
 
-    let (entries, more) = leaderboard
+    let (entries, more) = board
         .get(
-            ty,
+            GlobalState::database(),
             crate::leaderboard::LeaderboardQuery::Normal { start, count },
         )
         .await
         .map_err(|_| LeaderboardError::ServerError)?
         .unwrap_or_default();
 
-    let response = LeaderboardResponse { entries, more };
+    let response = LeaderboardResponse {
+        title: board.definition.title.clone(),
+        entries,
+        more,
+    };
 
     Ok(Json(response))
 }
@@ -105,12 +111,13 @@ async fn get_leaderboard(
 async fn get_player_ranking(
     Path((name, player_id)): Path<(String, PlayerID)>,
 ) -> Result<Json<LeaderboardEntry>, LeaderboardError> {
-    let ty: LeaderboardType =
-        LeaderboardType::try_parse(&name).ok_or(LeaderboardError::UnknownLeaderboard)?;
     let leaderboard: &Leaderboard = GlobalState::leaderboard();
-    let (mut values, _) = leaderboard
+    let board = leaderboard
+        .find(&name)
+        .ok_or(LeaderboardError::UnknownLeaderboard)?;
+    let (mut values, _) = board
         .get(
-            ty,
+            GlobalState::database(),
             crate::leaderboard::LeaderboardQuery::Filtered { player_id },
         )
         .await
@@ -0,0 +1,82 @@
+//! This modules contains routes for inspecting and managing the
+//! matchmaking queue
+
+use crate::{game::manager::QueueEntrySnapshot, state::GlobalState, utils::types::SessionID};
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, put},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+/// Router function creates a new router with all the underlying
+/// routes for this file.
+///
+/// Prefix: /api/matchmaking
+pub fn router() -> Router {
+    Router::new()
+        .route("/queue", get(get_queue).delete(clear_queue))
+        .route("/queue/:session_id/priority", put(set_priority))
+}
+
+/// Route for retrieving the entries in the matchmaking queue in
+/// the order they are queued
+async fn get_queue() -> Json<Vec<QueueEntrySnapshot>> {
+    let queue = GlobalState::games().queue_snapshot().await;
+    Json(queue)
+}
+
+/// Response from clearing the matchmaking queue
+#[derive(Serialize)]
+struct ClearQueueResponse {
+    /// The number of entries removed from the queue
+    removed: usize,
+}
+
+/// Route for removing all the entries from the matchmaking queue.
+/// The removed players are notified that matchmaking has failed.
+async fn clear_queue() -> Json<ClearQueueResponse> {
+    let removed = GlobalState::games().clear_queue().await;
+    Json(ClearQueueResponse { removed })
+}
+
+/// Request structure for setting the priority of a queue entry
+#[derive(Deserialize)]
+struct PriorityRequest {
+    /// The new priority for the queue entry
+    priority: u8,
+}
+
+/// Error type used when the session requested is not in the
+/// matchmaking queue
+struct QueueEntryNotFound;
+
+/// Route for setting the matchmaking priority of the queue entry for
+/// the session with the provided ID. Higher priority entries are
+/// matched before entries that have been waiting longer
+///
+/// `session_id` The ID of the queued session
+/// `req`        The priority request
+async fn set_priority(
+    Path(session_id): Path<SessionID>,
+    Json(req): Json<PriorityRequest>,
+) -> Result<StatusCode, QueueEntryNotFound> {
+    let found = GlobalState::games()
+        .set_queue_priority(session_id, req.priority)
+        .await;
+    if !found {
+        return Err(QueueEntryNotFound);
+    }
+    Ok(StatusCode::OK)
+}
+
+/// IntoResponse implementation for QueueEntryNotFound to allow it to be
+/// used within the result type as a error response
+impl IntoResponse for QueueEntryNotFound {
+    #[inline]
+    fn into_response(self) -> Response {
+        (StatusCode::NOT_FOUND, "QueueEntryNotFound").into_response()
+    }
+}
@@ -0,0 +1,41 @@
+//! This modules contains routes that handle serving the metrics
+//! collected by the server
+
+use crate::{
+    metrics::{
+        matchmaking::MatchmakingMetricsSnapshot, mesh::MeshMetricsSnapshot,
+        packets::PacketMetricSnapshot,
+    },
+    state::GlobalState,
+};
+use axum::{routing::get, Json, Router};
+
+/// Router function creates a new router with all the underlying
+/// routes for this file.
+///
+/// Prefix: /api/metrics
+pub fn router() -> Router {
+    Router::new()
+        .route("/packets", get(get_packet_metrics))
+        .route("/mesh", get(get_mesh_metrics))
+        .route("/matchmaking", get(get_matchmaking_metrics))
+}
+
+/// Route for retrieving the packet metrics for each component
+/// that has been handled by the main server
+async fn get_packet_metrics() -> Json<Vec<PacketMetricSnapshot>> {
+    let metrics = GlobalState::metrics().packets.snapshot();
+    Json(metrics)
+}
+
+/// Route for retrieving the metrics for the time taken for players
+/// joining games to establish their mesh connections
+async fn get_mesh_metrics() -> Json<MeshMetricsSnapshot> {
+    Json(GlobalState::metrics().mesh.snapshot())
+}
+
+/// Route for retrieving the number of players matched from the
+/// matchmaking queue grouped by how long they waited
+async fn get_matchmaking_metrics() -> Json<MatchmakingMetricsSnapshot> {
+    Json(GlobalState::metrics().matchmaking.snapshot())
+}
@@ -1,18 +1,30 @@
 use super::{
-    middleware::{cors::cors_layer, token::token_auth_layer},
+    middleware::{
+        access::{access_log_layer, AccessLog},
+        compression::compression_layer,
+        cors::cors_layer,
+        token::token_auth_layer,
+    },
     stores::token::TokenStore,
 };
 use crate::env;
-use axum::{middleware, Router};
+use axum::{middleware, routing::get, Router};
 
+mod broadcast;
+mod debug;
 mod games;
 mod gaw;
 mod leaderboard;
+mod matchmaking;
+mod metrics;
 mod players;
 mod content;
 mod qos;
 mod server;
+mod sessions;
+mod stats;
 mod token;
+mod tokens;
 
 /// Function for configuring the provided service config with all the
 /// application routes.
@@ -35,14 +47,30 @@ fn api_router() -> Router {
             .nest("/games", games::router())
             // Players routing
             .nest("/players", players::router())
+            // Metrics routing
+            .nest("/metrics", metrics::router())
+            // Matchmaking routing
+            .nest("/matchmaking", matchmaking::router())
+            // Broadcast routing
+            .nest("/broadcast", broadcast::router())
+            // Sessions routing
+            .nest("/sessions", sessions::router())
+            // Token management routing
+            .nest("/tokens", tokens::router())
+            // Debugging routing
+            .nest("/debug", debug::router())
+            // Log access entries for authenticated requests
+            .layer(middleware::from_fn_with_state(
+                AccessLog::from_env(),
+                access_log_layer,
+            ))
             // Apply the token auth middleware
             .layer(middleware::from_fn(token_auth_layer))
             // Routes that require token store access but arent protected
             .nest("/token", token::router())
-            // Provide token store to API routes
-            .layer(TokenStore::extension())
             // Non protected API routes
             .nest("/leaderboard", leaderboard::router())
+            .nest("/stats", stats::router())
     } else {
         // If the API is disable a default empty router is added
         Router::new()
@@ -50,6 +78,11 @@ fn api_router() -> Router {
     // Even when the API is disabled the server route must still
     // be applied otherwise clients won't be able to check the server
     .nest("/server", server::router())
+    .route("/version", get(server::server_version))
+    // Provide token store to API routes
+    .layer(TokenStore::extension())
+    // Large API responses are compressed when the client supports it
+    .layer(middleware::from_fn(compression_layer))
     // CORS middleware is applied to all API routes to allow browser access
     .layer(middleware::from_fn(cors_layer))
 }
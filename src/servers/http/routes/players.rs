@@ -1,18 +1,32 @@
 use crate::{
+    game::GameSnapshot,
+    leaderboard::{
+        models::{LeaderboardEntry, LeaderboardType},
+        LeaderboardQuery,
+    },
     servers::http::ext::ErrorStatusCode,
     state::GlobalState,
-    utils::{hashing::hash_password, types::PlayerID, validate::is_email},
+    utils::{
+        hashing::hash_password,
+        random::generate_random_string,
+        types::PlayerID,
+        validate::{is_display_name, is_email, BlockedNames},
+    },
 };
 use axum::{
     extract::{Path, Query},
     http::StatusCode,
     response::{IntoResponse, Response},
-    routing::get,
+    routing::{get, post},
     Json, Router,
 };
-use database::{DatabaseConnection, DbErr, GalaxyAtWar, Player, PlayerData};
+use chrono::NaiveDateTime;
+use database::{
+    interfaces::players::{ImportPlayer, ImportResult},
+    DatabaseConnection, DbErr, GalaxyAtWar, Player, PlayerData,
+};
 use serde::{ser::SerializeMap, Deserialize, Serialize};
-use std::fmt::Display;
+use std::{collections::BTreeMap, fmt::Display};
 
 /// Router function creates a new router with all the underlying
 /// routes for this file.
@@ -21,9 +35,13 @@ use std::fmt::Display;
 pub fn router() -> Router {
     Router::new()
         .route("/", get(get_players).post(create_player))
+        .route("/import", post(import_players))
         .route(
             "/:id",
-            get(get_player).put(modify_player).delete(delete_player),
+            get(get_player)
+                .put(modify_player)
+                .patch(rename_player)
+                .delete(delete_player),
         )
         .route("/:id/data", get(all_data))
         .route(
@@ -31,6 +49,12 @@ pub fn router() -> Router {
             get(get_data).put(set_data).delete(delete_data),
         )
         .route("/:id/galaxy_at_war", get(get_player_gaw))
+        .route("/:id/gaw", get(get_player_gaw).put(set_player_gaw))
+        .route("/:id/profile", get(get_player_profile))
+        .route("/:id/challenge_points", post(increase_challenge_points))
+        .route("/:id/network", get(get_player_network))
+        .route("/:id/game", get(get_player_game))
+        .route("/:id/rotate-token", post(rotate_player_token))
 }
 
 /// Enum for errors that could occur when accessing any of
@@ -48,6 +72,12 @@ enum PlayersError {
     ServerError,
     /// Requested class could not be found
     DataNotFound,
+    /// The provided display name was already in use
+    DisplayNameTaken,
+    /// The provided display name was not a valid display name
+    InvalidDisplayName,
+    /// The player is not connected to the main server
+    PlayerNotOnline,
 }
 
 /// Type alias for players result responses which wraps the provided type in
@@ -76,6 +106,13 @@ struct PlayersQuery {
     /// 255 to prevent the database having to do any larger
     /// queries
     count: Option<u8>,
+    /// Text to search for within the player display names and
+    /// emails. All players are listed when not provided
+    query: Option<String>,
+    /// Whether to include the email addresses of players found
+    /// by searching
+    #[serde(default)]
+    include_email: bool,
 }
 
 /// Response from the players endpoint which contains a list of
@@ -88,21 +125,100 @@ struct PlayersResponse {
     more: bool,
 }
 
+/// Response from the players endpoint when searching which contains
+/// the matching players and the total number of matches
+#[derive(Serialize)]
+struct PlayerSearchResponse {
+    /// The list of matching players retrieved
+    players: Vec<PlayerSearchResult>,
+    /// The total number of players matching the search
+    total: u64,
+    /// Whether there is more matching players after
+    more: bool,
+}
+
+/// Player found when searching players
+#[derive(Serialize)]
+struct PlayerSearchResult {
+    /// The ID of the player
+    id: PlayerID,
+    /// The display name of the player
+    display_name: String,
+    /// The email of the player which is omitted unless requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<String>,
+    /// Whether the player is an Origin account
+    origin: bool,
+}
+
+/// Players list response which is either the full list of players
+/// or the results of a search when a query is provided
+#[derive(Serialize)]
+#[serde(untagged)]
+enum PlayersList {
+    /// List of all the players
+    All(PlayersResponse),
+    /// Players matching a search query
+    Search(PlayerSearchResponse),
+}
+
 /// Route for retrieving a list of players from the database. The
 /// offset value if used to know how many rows to skip and count
-/// is the number of rows to collect. Offset = offset * count
+/// is the number of rows to collect. Offset = offset * count. When
+/// a search query is provided only players whose display name or
+/// email contains the query are listed
 ///
 /// `query` The query containing the offset and count values
-async fn get_players(Query(query): Query<PlayersQuery>) -> PlayersResult<PlayersResponse> {
+async fn get_players(Query(query): Query<PlayersQuery>) -> PlayersResult<PlayersList> {
     const DEFAULT_COUNT: u8 = 20;
     const DEFAULT_OFFSET: u16 = 0;
 
     let db = GlobalState::database();
     let count = query.count.unwrap_or(DEFAULT_COUNT);
     let offset = query.offset as u64 * count as u64;
+
+    if let Some(search) = query.query.as_deref() {
+        let response =
+            search_players(db, search, offset, count as u64, query.include_email).await?;
+        return Ok(Json(PlayersList::Search(response)));
+    }
+
     let (players, more) = Player::all(db, offset, count as u64).await?;
 
-    Ok(Json(PlayersResponse { players, more }))
+    Ok(Json(PlayersList::All(PlayersResponse { players, more })))
+}
+
+/// Searches for the players whose display name or email contains the
+/// provided query. Emails are omitted from the results unless requested
+///
+/// `db`            The database connection
+/// `query`         The text to search for
+/// `offset`        The number of rows to skip
+/// `count`         The number of rows to collect
+/// `include_email` Whether to include the player emails
+async fn search_players(
+    db: &DatabaseConnection,
+    query: &str,
+    offset: u64,
+    count: u64,
+    include_email: bool,
+) -> Result<PlayerSearchResponse, PlayersError> {
+    let (players, total) = Player::search(db, query, offset, count).await?;
+    let more = offset + (players.len() as u64) < total;
+    let players = players
+        .into_iter()
+        .map(|player| PlayerSearchResult {
+            id: player.id,
+            display_name: player.display_name,
+            email: include_email.then_some(player.email),
+            origin: player.origin,
+        })
+        .collect();
+    Ok(PlayerSearchResponse {
+        players,
+        total,
+        more,
+    })
 }
 
 /// Route for retrieving a player from the database with an ID that
@@ -162,10 +278,14 @@ async fn modify_player(
         None
     };
 
-    // Ignore the display name field if it has not changed
-    let display_name = req
-        .display_name
-        .filter(|value| value.ne(&player.display_name));
+    let display_name = match req.display_name {
+        Some(display_name) => {
+            let blocked = GlobalState::blocked_names();
+            check_display_name(db, blocked, &player, display_name).await?
+        }
+        None => None,
+    };
+    let renamed = display_name.is_some();
 
     // Hash the password value if it is present
     let password = if let Some(password) = req.password.as_ref() {
@@ -179,9 +299,93 @@ async fn modify_player(
         .update_http(db, email, display_name, req.origin, password)
         .await?;
 
+    if renamed {
+        for addr in GlobalState::sessions().find_player_sessions(player.id) {
+            addr.set_display_name(player.display_name.clone());
+        }
+    }
+
+    Ok(Json(player))
+}
+
+/// Request structure for a request to rename a player
+#[derive(Deserialize)]
+struct RenamePlayerRequest {
+    /// The new display name for the player
+    display_name: String,
+}
+
+/// Route for changing the display name of the player with the provided
+/// ID. Any sessions authenticated as the player are updated with the
+/// new display name.
+///
+/// `path` The route path with the ID for the player to rename
+/// `req`  The request body
+async fn rename_player(
+    Path(player_id): Path<PlayerID>,
+    Json(req): Json<RenamePlayerRequest>,
+) -> PlayersResult<Player> {
+    let db = GlobalState::database();
+    let player: Player = find_player(db, player_id).await?;
+    let blocked = GlobalState::blocked_names();
+    let player = set_display_name(db, blocked, player, req.display_name).await?;
+
+    for addr in GlobalState::sessions().find_player_sessions(player.id) {
+        addr.set_display_name(player.display_name.clone());
+    }
+
     Ok(Json(player))
 }
 
+/// Validates the provided display name and ensures that it isn't
+/// already in use before updating the display name of the player
+///
+/// `db`           The database connection
+/// `blocked`      The blocked display names
+/// `player`       The player to update
+/// `display_name` The new display name
+async fn set_display_name(
+    db: &DatabaseConnection,
+    blocked: &BlockedNames,
+    player: Player,
+    display_name: String,
+) -> Result<Player, PlayersError> {
+    match check_display_name(db, blocked, &player, display_name).await? {
+        Some(display_name) => Ok(player.set_display_name(db, display_name).await?),
+        None => Ok(player),
+    }
+}
+
+/// Checks that the provided display name is valid, isn't blocked and
+/// isn't already in use by another player. Returns None when the
+/// display name is the same as the current display name
+///
+/// `db`           The database connection
+/// `blocked`      The blocked display names
+/// `player`       The player being renamed
+/// `display_name` The new display name
+async fn check_display_name(
+    db: &DatabaseConnection,
+    blocked: &BlockedNames,
+    player: &Player,
+    display_name: String,
+) -> Result<Option<String>, PlayersError> {
+    if !is_display_name(&display_name) || blocked.is_blocked(&display_name) {
+        return Err(PlayersError::InvalidDisplayName);
+    }
+
+    // Ignore unchanged display names
+    if display_name == player.display_name {
+        return Ok(None);
+    }
+
+    if Player::is_display_name_taken(db, &display_name).await? {
+        return Err(PlayersError::DisplayNameTaken);
+    }
+
+    Ok(Some(display_name))
+}
+
 /// Request structure for a request to create a new player
 #[derive(Deserialize)]
 struct CreatePlayerRequest {
@@ -207,11 +411,160 @@ async fn create_player(Json(req): Json<CreatePlayerRequest>) -> PlayersResult<Pl
     if exists {
         return Err(PlayersError::EmailTaken);
     }
+    let display_name = req.display_name;
+    if !is_display_name(&display_name) || GlobalState::blocked_names().is_blocked(&display_name) {
+        return Err(PlayersError::InvalidDisplayName);
+    }
     let password = hash_password(&req.password).map_err(|_| PlayersError::ServerError)?;
-    let player: Player = Player::create(db, email, req.display_name, password, false).await?;
+    let player: Player = Player::create(db, email, display_name, password, false).await?;
     Ok(Json(player))
 }
 
+/// Request structure for a single player within an import request
+#[derive(Deserialize)]
+struct ImportPlayerRequest {
+    /// The email address of the player
+    email: String,
+    /// The display name of the player
+    display_name: String,
+    /// The already hashed password for the player
+    password_hash: Option<String>,
+    /// The plain text password for the player used when no
+    /// password hash is provided
+    password: Option<String>,
+    /// The galaxy at war values for the player
+    galaxy_at_war: Option<SetGalaxyAtWarRequest>,
+    /// The unix timestamp in seconds of when the player was
+    /// created on the other server
+    created_at: Option<i64>,
+}
+
+/// The result of importing a single player
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+enum ImportStatus {
+    /// The player was created
+    Created,
+    /// The player was skipped because the email was already in use
+    Duplicate,
+    /// The player was skipped because the display name was already in use
+    DuplicateDisplayName,
+    /// The player was skipped because the email was not valid
+    InvalidEmail,
+    /// The player was skipped because the display name was not valid
+    /// or contained a blocked name
+    InvalidDisplayName,
+    /// The player was skipped because neither a password or a
+    /// password hash was provided
+    MissingPassword,
+}
+
+/// Response structure for the result of importing a single player
+#[derive(Serialize)]
+struct ImportPlayerResult {
+    /// The email of the imported player
+    email: String,
+    /// The result of the import
+    status: ImportStatus,
+    /// The ID of the created player if one was created
+    player_id: Option<PlayerID>,
+}
+
+/// Route for importing a list of players from another server. The
+/// players are created in a single transaction skipping any players
+/// with emails that are already in use. Returns the result for each
+/// of the provided players in the same order
+///
+/// `req` The list of players to import
+async fn import_players(
+    Json(req): Json<Vec<ImportPlayerRequest>>,
+) -> PlayersResult<Vec<ImportPlayerResult>> {
+    let db = GlobalState::database();
+    let blocked = GlobalState::blocked_names();
+    let results = import_player_list(db, blocked, req).await?;
+    Ok(Json(results))
+}
+
+/// Validates and hashes the passwords of the provided players before
+/// importing all the valid players into the database
+///
+/// `db`      The database connection
+/// `blocked` The blocked display names
+/// `players` The list of players to import
+async fn import_player_list(
+    db: &DatabaseConnection,
+    blocked: &BlockedNames,
+    players: Vec<ImportPlayerRequest>,
+) -> Result<Vec<ImportPlayerResult>, PlayersError> {
+    let mut results = Vec::with_capacity(players.len());
+    let mut imports = Vec::with_capacity(players.len());
+
+    for player in players {
+        let status = if !is_email(&player.email) {
+            Some(ImportStatus::InvalidEmail)
+        } else if !is_display_name(&player.display_name) || blocked.is_blocked(&player.display_name)
+        {
+            Some(ImportStatus::InvalidDisplayName)
+        } else if player.password_hash.is_none() && player.password.is_none() {
+            Some(ImportStatus::MissingPassword)
+        } else {
+            None
+        };
+
+        results.push(ImportPlayerResult {
+            email: player.email.clone(),
+            status: status.unwrap_or(ImportStatus::Created),
+            player_id: None,
+        });
+
+        if status.is_some() {
+            continue;
+        }
+
+        let password = match (player.password_hash, player.password) {
+            (Some(hash), _) => hash,
+            (None, Some(password)) => {
+                hash_password(&password).map_err(|_| PlayersError::ServerError)?
+            }
+            // Players without a password are skipped above
+            (None, None) => continue,
+        };
+
+        imports.push(ImportPlayer {
+            email: player.email,
+            display_name: player.display_name,
+            password,
+            galaxy_at_war: player.galaxy_at_war.map(|value| {
+                (
+                    value.group_a,
+                    value.group_b,
+                    value.group_c,
+                    value.group_d,
+                    value.group_e,
+                )
+            }),
+            created_at: player
+                .created_at
+                .and_then(|value| NaiveDateTime::from_timestamp_opt(value, 0)),
+        });
+    }
+
+    let mut created = Player::import(db, imports).await?.into_iter();
+    for result in results
+        .iter_mut()
+        .filter(|result| result.status == ImportStatus::Created)
+    {
+        match created.next() {
+            Some(ImportResult::Created(player)) => result.player_id = Some(player.id),
+            Some(ImportResult::DuplicateDisplayName) => {
+                result.status = ImportStatus::DuplicateDisplayName
+            }
+            Some(ImportResult::DuplicateEmail) | None => result.status = ImportStatus::Duplicate,
+        }
+    }
+
+    Ok(results)
+}
+
 /// Route for deleting a player using its Player ID
 ///
 /// `path` The route path with the ID for the player to find
@@ -222,6 +575,30 @@ async fn delete_player(Path(player_id): Path<PlayerID>) -> Result<Response, Play
     Ok(StatusCode::OK.into_response())
 }
 
+/// Response structure for rotating the session token of a player
+#[derive(Serialize)]
+struct RotateTokenResponse {
+    /// The number of sessions that were disconnected because they
+    /// were authenticated as the player
+    disconnected: usize,
+}
+
+/// Route for replacing the session token of the player with the provided
+/// ID so that the previous token can no longer be used to resume a
+/// session. Any sessions authenticated as the player are disconnected.
+///
+/// `path` The route path with the ID for the player
+async fn rotate_player_token(
+    Path(player_id): Path<PlayerID>,
+) -> PlayersResult<RotateTokenResponse> {
+    let db = GlobalState::database();
+    let player: Player = find_player(db, player_id).await?;
+    player.rotate_token(db, generate_random_string).await?;
+
+    let disconnected = GlobalState::sessions().kick_player(player_id);
+    Ok(Json(RotateTokenResponse { disconnected }))
+}
+
 /// Structure wrapping a vec of player data in order to make
 /// it serializable without requiring a hashmap
 struct PlayerDataMap(Vec<PlayerData>);
@@ -304,6 +681,218 @@ async fn get_player_gaw(Path(player_id): Path<PlayerID>) -> PlayersResult<Galaxy
     Ok(Json(galax_at_war))
 }
 
+/// Request structure for replacing the galaxy at war values of a player
+#[derive(Deserialize)]
+struct SetGalaxyAtWarRequest {
+    /// The first group value
+    group_a: u16,
+    /// The second group value
+    group_b: u16,
+    /// The third group value
+    group_c: u16,
+    /// The fourth group value
+    group_d: u16,
+    /// The fifth group value
+    group_e: u16,
+}
+
+/// Route for replacing the galaxy at war values for the player with
+/// the provided {id}. Values outside the valid range are clamped.
+///
+/// `path` The route path with the ID for the player
+/// `req`  The new galaxy at war values
+async fn set_player_gaw(
+    Path(player_id): Path<PlayerID>,
+    Json(req): Json<SetGalaxyAtWarRequest>,
+) -> PlayersResult<GalaxyAtWar> {
+    let db = GlobalState::database();
+    let player = find_player(db, player_id).await?;
+    let galaxy_at_war = GalaxyAtWar::find_or_create(db, &player, 0.0).await?;
+    let galaxy_at_war = galaxy_at_war
+        .set_values(
+            db,
+            (
+                req.group_a,
+                req.group_b,
+                req.group_c,
+                req.group_d,
+                req.group_e,
+            ),
+        )
+        .await?;
+    Ok(Json(galaxy_at_war))
+}
+
+/// Request structure for a request to increase the challenge points
+/// of a player
+#[derive(Deserialize)]
+struct IncreaseChallengePointsRequest {
+    /// The amount of challenge points to add
+    amount: u32,
+}
+
+/// Response structure for increasing the challenge points of a player
+#[derive(Serialize)]
+struct ChallengePointsResponse {
+    /// The new challenge points value for the player
+    value: u32,
+}
+
+/// Route for increasing the challenge points of the player with the
+/// provided {id}. The leaderboard entry for the player is updated in
+/// place rather than recomputing the leaderboard.
+///
+/// `path` The route path with the ID for the player
+/// `req`  The request body
+async fn increase_challenge_points(
+    Path(player_id): Path<PlayerID>,
+    Json(req): Json<IncreaseChallengePointsRequest>,
+) -> PlayersResult<ChallengePointsResponse> {
+    let db = GlobalState::database();
+    let player = find_player(db, player_id).await?;
+    let value = GlobalState::leaderboard()
+        .increase_challenge_points(db, &player, req.amount)
+        .await?
+        .ok_or(PlayersError::DataNotFound)?;
+    Ok(Json(ChallengePointsResponse { value }))
+}
+
+/// The query structure for a player profile query
+#[derive(Deserialize)]
+struct ProfileQuery {
+    /// Whether to include the email address of the player
+    #[serde(default)]
+    include_email: bool,
+}
+
+/// Structure for the full profile of a player composed of the
+/// player details, galaxy at war data and leaderboard rankings
+#[derive(Serialize)]
+struct PlayerProfile {
+    /// The ID of the player
+    id: PlayerID,
+    /// The display name of the player
+    display_name: String,
+    /// The email of the player which is omitted unless requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<String>,
+    /// Whether the player is an Origin account
+    origin: bool,
+    /// The galaxy at war data for the player
+    galaxy_at_war: GalaxyAtWar,
+    /// The N7 rating leaderboard entry for the player
+    n7: Option<LeaderboardEntry>,
+    /// The challenge points leaderboard entry for the player
+    cp: Option<LeaderboardEntry>,
+}
+
+impl PlayerProfile {
+    /// Creates a new player profile from the provided values
+    ///
+    /// `player`        The player the profile is for
+    /// `galaxy_at_war` The galaxy at war data for the player
+    /// `n7`            The N7 rating leaderboard entry
+    /// `cp`            The challenge points leaderboard entry
+    /// `include_email` Whether to include the player email
+    fn new(
+        player: Player,
+        galaxy_at_war: GalaxyAtWar,
+        n7: Option<LeaderboardEntry>,
+        cp: Option<LeaderboardEntry>,
+        include_email: bool,
+    ) -> Self {
+        Self {
+            id: player.id,
+            display_name: player.display_name,
+            email: include_email.then_some(player.email),
+            origin: player.origin,
+            galaxy_at_war,
+            n7,
+            cp,
+        }
+    }
+}
+
+/// Finds the leaderboard entry of the provided type for the
+/// player with the provided ID
+///
+/// `ty`        The leaderboard type
+/// `player_id` The ID of the player
+async fn find_ranking(
+    ty: LeaderboardType,
+    player_id: PlayerID,
+) -> Result<Option<LeaderboardEntry>, PlayersError> {
+    let values = GlobalState::leaderboard()
+        .get(ty, LeaderboardQuery::Filtered { player_id })
+        .await?;
+    Ok(values.and_then(|(mut values, _)| values.pop()))
+}
+
+/// Route for retrieving the full profile for a provided player
+/// matches the provided {id}
+///
+/// `path`  The route path with the ID for the player to find the profile for
+/// `query` The query containing whether to include the email
+async fn get_player_profile(
+    Path(player_id): Path<PlayerID>,
+    Query(query): Query<ProfileQuery>,
+) -> PlayersResult<PlayerProfile> {
+    let db = GlobalState::database();
+    let player = find_player(db, player_id).await?;
+    // Zero decay is used so that viewing the profile doesn't modify the data
+    let galaxy_at_war = GalaxyAtWar::find_or_create(db, &player, 0.0).await?;
+    let n7 = find_ranking(LeaderboardType::N7Rating, player_id).await?;
+    let cp = find_ranking(LeaderboardType::ChallengePoints, player_id).await?;
+    Ok(Json(PlayerProfile::new(
+        player,
+        galaxy_at_war,
+        n7,
+        cp,
+        query.include_email,
+    )))
+}
+
+/// Response structure for the network diagnostics of a player
+#[derive(Serialize)]
+struct PlayerNetworkResponse {
+    /// The latency in milliseconds to each of the Quality of Service
+    /// regions as last reported by the client
+    latency: BTreeMap<String, u32>,
+}
+
+/// Route for retrieving the network diagnostics reported by the
+/// session of the player with the provided {id}
+///
+/// `path` The route path with the ID for the player
+async fn get_player_network(
+    Path(player_id): Path<PlayerID>,
+) -> PlayersResult<PlayerNetworkResponse> {
+    let latency = GlobalState::sessions()
+        .find_player_latency(player_id)
+        .ok_or(PlayersError::PlayerNotOnline)?;
+    Ok(Json(PlayerNetworkResponse { latency }))
+}
+
+/// Response structure for the games a player is currently in
+#[derive(Serialize)]
+struct PlayerGamesResponse {
+    /// Snapshots of the games ordered by when the player joined them
+    games: Vec<GameSnapshot>,
+}
+
+/// Route for finding the games that the player with the provided {id}
+/// is currently in. Responds with no content when the player isn't
+/// in any games
+///
+/// `path` The route path with the ID for the player
+async fn get_player_game(Path(player_id): Path<PlayerID>) -> Response {
+    let games = GlobalState::games().snapshot_player(player_id).await;
+    if games.is_empty() {
+        return StatusCode::NO_CONTENT.into_response();
+    }
+    Json(PlayerGamesResponse { games }).into_response()
+}
+
 /// Display implementation for the PlayersError type. Only the PlayerNotFound
 /// error has a custom message. All other errors use "Internal Server Error"
 impl Display for PlayersError {
@@ -319,7 +908,11 @@ impl ErrorStatusCode for PlayersError {
         match self {
             Self::DataNotFound => StatusCode::NOT_FOUND,
             Self::PlayerNotFound => StatusCode::NOT_FOUND,
-            Self::EmailTaken | Self::InvalidEmail => StatusCode::BAD_REQUEST,
+            Self::PlayerNotOnline => StatusCode::NOT_FOUND,
+            Self::EmailTaken
+            | Self::InvalidEmail
+            | Self::DisplayNameTaken
+            | Self::InvalidDisplayName => StatusCode::BAD_REQUEST,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -341,3 +934,302 @@ impl IntoResponse for PlayersError {
         (self.status_code(), self.to_string()).into_response()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        check_display_name, import_player_list, search_players, set_display_name,
+        ImportPlayerRequest, ImportStatus, PlayerProfile, PlayersError, SetGalaxyAtWarRequest,
+    };
+    use crate::utils::validate::BlockedNames;
+    use chrono::NaiveDateTime;
+    use database::{GalaxyAtWar, Player};
+
+    #[test]
+    fn test_profile() {
+        let player = Player {
+            id: 1,
+            email: "test@test.com".to_string(),
+            display_name: "Test".to_string(),
+            session_token: None,
+            origin: false,
+            password: String::new(),
+            created_at: None,
+        };
+        let galaxy_at_war = GalaxyAtWar {
+            id: 1,
+            player_id: 1,
+            last_modified: NaiveDateTime::default(),
+            group_a: 5100,
+            group_b: 5200,
+            group_c: 5300,
+            group_d: 5400,
+            group_e: 5500,
+        };
+
+        let profile = PlayerProfile::new(player.clone(), galaxy_at_war.clone(), None, None, false);
+        let value = serde_json::to_value(&profile).unwrap();
+        assert!(value.get("email").is_none());
+        assert_eq!(value["display_name"], "Test");
+        assert_eq!(value["galaxy_at_war"]["group_a"], 5100);
+        assert_eq!(value["galaxy_at_war"]["group_e"], 5500);
+
+        let profile = PlayerProfile::new(player, galaxy_at_war, None, None, true);
+        let value = serde_json::to_value(&profile).unwrap();
+        assert_eq!(value["email"], "test@test.com");
+    }
+
+    #[tokio::test]
+    async fn test_set_display_name() {
+        let db = database::connect_memory().await;
+
+        let player = Player::create(
+            &db,
+            "a@test.com".to_string(),
+            "PlayerA".to_string(),
+            String::new(),
+            false,
+        )
+        .await
+        .unwrap();
+        Player::create(
+            &db,
+            "b@test.com".to_string(),
+            "PlayerB".to_string(),
+            String::new(),
+            false,
+        )
+        .await
+        .unwrap();
+
+        let blocked = BlockedNames::default();
+        let player = set_display_name(&db, &blocked, player, "Renamed".to_string())
+            .await
+            .unwrap();
+        assert_eq!(player.display_name, "Renamed");
+
+        let result = set_display_name(&db, &blocked, player.clone(), "PlayerB".to_string()).await;
+        assert!(matches!(result, Err(PlayersError::DisplayNameTaken)));
+
+        let result = set_display_name(&db, &blocked, player.clone(), "<Invalid>".to_string()).await;
+        assert!(matches!(result, Err(PlayersError::InvalidDisplayName)));
+
+        // Checks used by the modify route
+        let result = check_display_name(&db, &blocked, &player, "PlayerB".to_string()).await;
+        assert!(matches!(result, Err(PlayersError::DisplayNameTaken)));
+
+        let result = check_display_name(&db, &blocked, &player, "Renamed".to_string()).await;
+        assert!(matches!(result, Ok(None)));
+
+        let result = check_display_name(&db, &blocked, &player, "Other".to_string()).await;
+        assert!(matches!(result, Ok(Some(value)) if value == "Other"));
+
+        let blocked = BlockedNames::new("badword");
+        let result = check_display_name(&db, &blocked, &player, "BadWord1".to_string()).await;
+        assert!(matches!(result, Err(PlayersError::InvalidDisplayName)));
+    }
+
+    #[tokio::test]
+    async fn test_import_players() {
+        let db = database::connect_memory().await;
+
+        Player::create(
+            &db,
+            "existing@test.com".to_string(),
+            "Existing".to_string(),
+            String::new(),
+            false,
+        )
+        .await
+        .unwrap();
+
+        let request = |email: &str, display_name: &str| ImportPlayerRequest {
+            email: email.to_string(),
+            display_name: display_name.to_string(),
+            password_hash: Some("hash".to_string()),
+            password: None,
+            galaxy_at_war: None,
+            created_at: None,
+        };
+
+        let mut imported = request("imported@test.com", "Imported");
+        imported.galaxy_at_war = Some(SetGalaxyAtWarRequest {
+            group_a: 6000,
+            group_b: 6100,
+            group_c: 6200,
+            group_d: 6300,
+            group_e: 6400,
+        });
+
+        let mut no_password = request("nopassword@test.com", "NoPassword");
+        no_password.password_hash = None;
+
+        let blocked = BlockedNames::new("badword");
+        let results = import_player_list(
+            &db,
+            &blocked,
+            vec![
+                imported,
+                request("existing@test.com", "Duplicate"),
+                request("other@test.com", "Other"),
+                request("taken@test.com", "Existing"),
+                request("repeated@test.com", "Other"),
+                no_password,
+                request("blocked@test.com", "Player_BadWord"),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let statuses: Vec<&ImportStatus> = results.iter().map(|result| &result.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                &ImportStatus::Created,
+                &ImportStatus::Duplicate,
+                &ImportStatus::Created,
+                &ImportStatus::DuplicateDisplayName,
+                &ImportStatus::DuplicateDisplayName,
+                &ImportStatus::MissingPassword,
+                &ImportStatus::InvalidDisplayName,
+            ]
+        );
+        assert!(results[1].player_id.is_none());
+        assert!(results[3].player_id.is_none());
+        assert!(Player::by_email(&db, "nopassword@test.com", false)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(Player::by_email(&db, "blocked@test.com", false)
+            .await
+            .unwrap()
+            .is_none());
+
+        let player = Player::by_email(&db, "imported@test.com", false)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(results[0].player_id, Some(player.id));
+        assert_eq!(player.password, "hash");
+
+        let galaxy_at_war = GalaxyAtWar::find_or_create(&db, &player, 0.0)
+            .await
+            .unwrap();
+        assert_eq!(galaxy_at_war.group_a, 6000);
+        assert_eq!(galaxy_at_war.group_e, 6400);
+
+        assert!(Player::by_email(&db, "other@test.com", false)
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_search_players() {
+        let db = database::connect_memory().await;
+
+        for (email, display_name) in [
+            ("shep@test.com", "Shepard"),
+            ("garrus@test.com", "Garrus"),
+            ("other@test.com", "ShepardTwo"),
+            ("tali@test.com", "Tali"),
+        ] {
+            Player::create(
+                &db,
+                email.to_string(),
+                display_name.to_string(),
+                String::new(),
+                false,
+            )
+            .await
+            .unwrap();
+        }
+
+        // Display names are matched by substring and emails are redacted
+        let response = search_players(&db, "epar", 0, 20, false).await.unwrap();
+        let names: Vec<&str> = response
+            .players
+            .iter()
+            .map(|player| player.display_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Shepard", "ShepardTwo"]);
+        assert_eq!(response.total, 2);
+        assert!(!response.more);
+        assert!(response.players.iter().all(|player| player.email.is_none()));
+
+        let response = search_players(&db, "garrus@", 0, 20, true).await.unwrap();
+        assert_eq!(response.players.len(), 1);
+        assert_eq!(
+            response.players[0].email.as_deref(),
+            Some("garrus@test.com")
+        );
+
+        // Pages are bounded by the offset and count
+        let response = search_players(&db, "test.com", 1, 2, false).await.unwrap();
+        let names: Vec<&str> = response
+            .players
+            .iter()
+            .map(|player| player.display_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Garrus", "ShepardTwo"]);
+        assert_eq!(response.total, 4);
+        assert!(response.more);
+
+        let response = search_players(&db, "test.com", 4, 2, false).await.unwrap();
+        assert!(response.players.is_empty());
+        assert_eq!(response.total, 4);
+        assert!(!response.more);
+
+        // Wildcards within the query are matched literally
+        Player::create(
+            &db,
+            "under@test.com".to_string(),
+            "Under_Score".to_string(),
+            String::new(),
+            false,
+        )
+        .await
+        .unwrap();
+        let response = search_players(&db, "_", 0, 20, false).await.unwrap();
+        assert_eq!(response.total, 1);
+        assert_eq!(response.players[0].display_name, "Under_Score");
+        let response = search_players(&db, "%", 0, 20, false).await.unwrap();
+        assert_eq!(response.total, 0);
+    }
+
+    #[tokio::test]
+    async fn test_galaxy_at_war_values() {
+        let db = database::connect_memory().await;
+
+        let player = Player::create(
+            &db,
+            "gaw@test.com".to_string(),
+            "PlayerGaw".to_string(),
+            String::new(),
+            false,
+        )
+        .await
+        .unwrap();
+
+        let galaxy_at_war = GalaxyAtWar::find_or_create(&db, &player, 0.0)
+            .await
+            .unwrap();
+        let galaxy_at_war = galaxy_at_war
+            .set_values(&db, (0, 6000, 7000, 8000, u16::MAX))
+            .await
+            .unwrap();
+
+        // Values outside the valid range are clamped
+        assert_eq!(galaxy_at_war.group_a, 5000);
+        assert_eq!(galaxy_at_war.group_b, 6000);
+        assert_eq!(galaxy_at_war.group_e, 10099);
+
+        // Reading the existing values doesn't modify them
+        let existing = GalaxyAtWar::find_or_create(&db, &player, 0.0)
+            .await
+            .unwrap();
+        assert_eq!(existing.group_a, 5000);
+        assert_eq!(existing.group_c, 7000);
+        assert_eq!(existing.group_e, 10099);
+    }
+}
@@ -1,16 +1,49 @@
 //! This modules contains routes that handle serving information
 //! about the server such as the version and services running
 
-use crate::utils::constants;
-use axum::{routing::get, Json, Router};
+use crate::{
+    env,
+    servers::http::middleware::{
+        access::{access_log_layer, AccessLog},
+        token::token_auth_layer,
+    },
+    state::GlobalState,
+    utils::{constants, models::Port, net::public_address},
+};
+use axum::{
+    http::StatusCode,
+    middleware,
+    routing::{get, post},
+    Json, Router,
+};
+use log::info;
 use serde::Serialize;
+use std::{net::Ipv4Addr, time::Duration};
 
 /// Router function creates a new router with all the underlying
 /// routes for this file.
 ///
 /// Prefix: /api/server
 pub fn router() -> Router {
-    Router::new().route("/", get(server_details))
+    let router = Router::new()
+        .route("/", get(server_details))
+        .route("/address", get(server_address))
+        .route("/status", get(server_status));
+
+    // Admin routes are only available when the API is enabled
+    if env::from_env(env::API) {
+        router.merge(
+            Router::new()
+                .route("/drain", post(drain_server))
+                .route_layer(middleware::from_fn_with_state(
+                    AccessLog::from_env(),
+                    access_log_layer,
+                ))
+                .route_layer(middleware::from_fn(token_auth_layer)),
+        )
+    } else {
+        router
+    }
 }
 
 /// Response detailing the information about this Pocket Relay server
@@ -31,3 +64,235 @@ async fn server_details() -> Json<ServerDetails> {
         version: constants::VERSION,
     })
 }
+
+/// Response detailing the version of the server along with the
+/// versions of the protocol that it is compatible with
+#[derive(Serialize)]
+pub struct ServerVersion {
+    /// The server version
+    version: &'static str,
+    /// The git commit hash the server was built from if known
+    git_hash: Option<&'static str>,
+    /// The protocol compatibility details
+    protocol: ProtocolVersion,
+}
+
+/// The versions of the Blaze protocol reported to clients
+#[derive(Serialize)]
+pub struct ProtocolVersion {
+    /// The Blaze server version
+    blaze: &'static str,
+    /// The server SRC version
+    src: &'static str,
+    /// The client versions that are allowed to connect. Empty
+    /// when all versions are allowed
+    client_versions: Vec<String>,
+}
+
+/// Route for retrieving the server version along with the build
+/// and protocol compatibility details
+pub async fn server_version() -> Json<ServerVersion> {
+    let client_versions = env::env(env::CLIENT_VERSIONS)
+        .split(',')
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_string())
+        .collect();
+    Json(ServerVersion {
+        version: constants::VERSION,
+        git_hash: constants::GIT_HASH,
+        protocol: ProtocolVersion {
+            blaze: constants::BLAZE_VERSION,
+            src: constants::SRC_VERSION,
+            client_versions,
+        },
+    })
+}
+
+/// Response detailing the external address of the server along
+/// with the ports of the servers
+#[derive(Serialize)]
+struct ServerAddress {
+    /// The public address of the server if it could be found
+    address: Option<Ipv4Addr>,
+    /// The ports used by the servers
+    ports: ServerPorts,
+}
+
+/// The ports for the different servers
+#[derive(Serialize)]
+struct ServerPorts {
+    /// Port of the main server
+    main: Port,
+    /// Port of the quality of service server
+    qos: Port,
+    /// Port of the telemetry server
+    telemetry: Port,
+}
+
+impl ServerAddress {
+    /// Creates the server address response from the provided address
+    /// using the ports from the environment variables
+    ///
+    /// `address` The public address of the server
+    fn new(address: Option<Ipv4Addr>) -> Self {
+        Self {
+            address,
+            ports: ServerPorts {
+                main: env::from_env(env::MAIN_PORT),
+                qos: env::from_env(env::QOS_PORT),
+                telemetry: env::from_env(env::TELEMETRY_PORT),
+            },
+        }
+    }
+}
+
+/// Route for retrieving the public address the server believes it
+/// has which is used for networking between players along with the
+/// ports of the servers.
+async fn server_address() -> Json<ServerAddress> {
+    Json(ServerAddress::new(public_address().await))
+}
+
+/// Response detailing the current status of the server
+#[derive(Serialize)]
+struct ServerStatus {
+    /// The server version
+    version: &'static str,
+    /// The number of seconds since the server was started
+    uptime: u64,
+    /// The services that are active
+    services: ServerServices,
+    /// The galaxy at war configuration
+    galaxy_at_war: GalaxyAtWarConfig,
+    /// The current counts
+    counts: ServerCounts,
+    /// Whether the server is draining
+    draining: bool,
+}
+
+/// The services that are enabled on the server
+#[derive(Serialize)]
+struct ServerServices {
+    /// Whether the official server retriever is available
+    retriever: bool,
+    /// Whether the server is running in MITM mode
+    mitm: bool,
+    /// Whether the API is enabled
+    api: bool,
+    /// Whether the quality of service server is running
+    qos: bool,
+}
+
+/// The galaxy at war configuration of the server
+#[derive(Serialize)]
+struct GalaxyAtWarConfig {
+    /// The daily decay rate of galaxy at war values
+    daily_decay: f32,
+    /// Whether promotions are included in galaxy at war
+    promotions: bool,
+}
+
+/// The counts of active resources on the server
+#[derive(Serialize)]
+struct ServerCounts {
+    /// The number of connected sessions
+    sessions: usize,
+    /// The number of active games
+    games: usize,
+}
+
+impl ServerStatus {
+    /// Creates the server status from the provided uptime and counts
+    /// using the configuration from the environment variables
+    ///
+    /// `uptime`    The time since the server was started
+    /// `retriever` Whether the retriever is available
+    /// `counts`    The current counts
+    /// `draining`  Whether the server is draining
+    fn new(uptime: Duration, retriever: bool, counts: ServerCounts, draining: bool) -> Self {
+        let mitm = env::from_env(env::MITM_ENABLED);
+        Self {
+            version: constants::VERSION,
+            uptime: uptime.as_secs(),
+            services: ServerServices {
+                retriever,
+                mitm,
+                api: env::from_env(env::API),
+                qos: !mitm,
+            },
+            galaxy_at_war: GalaxyAtWarConfig {
+                daily_decay: env::from_env(env::GAW_DAILY_DECAY),
+                promotions: env::from_env(env::GAW_PROMOTIONS),
+            },
+            counts,
+            draining,
+        }
+    }
+}
+
+/// Route for retrieving the status of the server including the
+/// version, uptime, enabled services and current counts
+async fn server_status() -> Json<ServerStatus> {
+    let games = GlobalState::games();
+    let counts = ServerCounts {
+        sessions: GlobalState::sessions().count(),
+        games: games.count().await,
+    };
+    Json(ServerStatus::new(
+        GlobalState::uptime(),
+        GlobalState::retriever().is_some(),
+        counts,
+        games.is_draining(),
+    ))
+}
+
+/// Route for starting to drain the server. New connections and
+/// new games are refused while the existing games are left to
+/// finish before the server exits
+async fn drain_server() -> StatusCode {
+    let games = GlobalState::games();
+    if !games.is_draining() {
+        info!("Server is draining (Games: {})", games.count().await);
+        games.start_draining();
+    }
+    StatusCode::OK
+}
+
+#[cfg(test)]
+mod test {
+    use super::{server_version, ServerAddress, ServerCounts, ServerStatus};
+    use crate::utils::constants;
+    use std::{net::Ipv4Addr, time::Duration};
+
+    #[test]
+    fn test_server_address() {
+        let response = ServerAddress::new(Some(Ipv4Addr::new(203, 0, 113, 7)));
+        let value = serde_json::to_value(&response).unwrap();
+        assert_eq!(value["address"], "203.0.113.7");
+        assert_eq!(value["ports"]["main"], 14219);
+    }
+
+    #[tokio::test]
+    async fn test_server_version() {
+        let response = server_version().await.0;
+        let value = serde_json::to_value(&response).unwrap();
+        assert_eq!(value["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(value["protocol"]["blaze"], constants::BLAZE_VERSION);
+    }
+
+    #[test]
+    fn test_server_status() {
+        let counts = ServerCounts {
+            sessions: 3,
+            games: 1,
+        };
+        let status = ServerStatus::new(Duration::from_secs(90), false, counts, false);
+        let value = serde_json::to_value(&status).unwrap();
+        assert_eq!(value["version"], constants::VERSION);
+        assert_eq!(value["uptime"], 90);
+        assert_eq!(value["services"]["retriever"], false);
+        assert_eq!(value["counts"]["sessions"], 3);
+        assert_eq!(value["draining"], false);
+    }
+}
@@ -0,0 +1,34 @@
+//! This module contains routes for inspecting the sessions that are
+//! connected to the main server
+
+use crate::{servers::main::sessions::SessionSummary, state::GlobalState};
+use axum::{routing::get, Json, Router};
+use serde::Serialize;
+
+/// Router function creates a new router with all the underlying
+/// routes for this file.
+///
+/// Prefix: /api/sessions
+pub fn router() -> Router {
+    Router::new().route("/", get(list_sessions))
+}
+
+/// Response containing the connected sessions
+#[derive(Serialize)]
+struct SessionsResponse {
+    /// The summaries of the connected sessions
+    sessions: Vec<SessionSummary>,
+}
+
+/// Route for listing summaries of all the sessions connected to the
+/// main server along with the game each authenticated player is in
+async fn list_sessions() -> Json<SessionsResponse> {
+    let games = GlobalState::games();
+    let mut sessions = GlobalState::sessions().summaries();
+    for session in &mut sessions {
+        if let Some(player_id) = session.player_id {
+            session.game_ids = games.player_games(player_id);
+        }
+    }
+    Json(SessionsResponse { sessions })
+}
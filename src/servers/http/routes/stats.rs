@@ -0,0 +1,158 @@
+//! This module contains routes for retrieving general statistics
+//! about the server such as player counts
+
+use crate::{servers::http::ext::ErrorStatusCode, state::GlobalState};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::{Duration, Local, NaiveDateTime};
+use database::{DatabaseConnection, DbErr, Player};
+use log::error;
+use serde::Serialize;
+use std::fmt::Display;
+
+/// Router function creates a new router with all the underlying
+/// routes for this file.
+///
+/// Prefix: /api/stats
+pub fn router() -> Router {
+    Router::new().route("/players", get(get_player_stats))
+}
+
+/// Error type used in stats routes
+#[derive(Debug)]
+enum StatsError {
+    /// Some server error occurred like a database failure
+    ServerError,
+}
+
+/// Response structure for the player statistics
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct PlayerStats {
+    /// The total number of registered players
+    total: u64,
+    /// The number of players registered in the last 24 hours
+    last_day: u64,
+    /// The number of players registered in the last 7 days
+    last_week: u64,
+    /// The number of players registered in the last 30 days
+    last_month: u64,
+    /// The number of players that are currently online
+    online: usize,
+}
+
+/// Route for retrieving the number of registered players, how many
+/// players registered recently and the number of players online
+async fn get_player_stats() -> Result<Json<PlayerStats>, StatsError> {
+    let db = GlobalState::database();
+    let online = GlobalState::sessions().player_count();
+    let now = Local::now().naive_local();
+    let stats = player_stats(db, now, online).await?;
+    Ok(Json(stats))
+}
+
+/// Counts the total number of players and the number of players
+/// registered within each of the periods before the provided date
+///
+/// `db`     The database connection
+/// `now`    The date the periods are relative to
+/// `online` The number of players that are currently online
+async fn player_stats(
+    db: &DatabaseConnection,
+    now: NaiveDateTime,
+    online: usize,
+) -> Result<PlayerStats, DbErr> {
+    Ok(PlayerStats {
+        total: Player::count(db).await?,
+        last_day: Player::count_created_since(db, now - Duration::days(1)).await?,
+        last_week: Player::count_created_since(db, now - Duration::days(7)).await?,
+        last_month: Player::count_created_since(db, now - Duration::days(30)).await?,
+        online,
+    })
+}
+
+/// Display implementation for the StatsError this will be displayed
+/// as the error response message.
+impl Display for StatsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Error status code implementation for the different error
+/// status codes of each error
+impl ErrorStatusCode for StatsError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::ServerError => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// From implementation for converting database errors into stats
+/// errors logging the underlying database error
+impl From<DbErr> for StatsError {
+    fn from(err: DbErr) -> Self {
+        error!("Failed to count players for stats: {err:?}");
+        StatsError::ServerError
+    }
+}
+
+/// IntoResponse implementation for StatsError to allow it to be
+/// used within the result type as a error response
+impl IntoResponse for StatsError {
+    #[inline]
+    fn into_response(self) -> Response {
+        (self.status_code(), self.to_string()).into_response()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{player_stats, PlayerStats};
+    use chrono::{Duration, NaiveDate};
+    use database::{interfaces::players::ImportPlayer, Player};
+
+    #[tokio::test]
+    async fn test_player_stats() {
+        let db = database::connect_memory().await;
+
+        let now = NaiveDate::from_ymd_opt(2023, 3, 1)
+            .and_then(|date| date.and_hms_opt(12, 0, 0))
+            .unwrap();
+
+        // Players registered 1 hour, 3 days, 10 days and 60 days ago
+        let players = [
+            Duration::hours(1),
+            Duration::days(3),
+            Duration::days(10),
+            Duration::days(60),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(index, age)| ImportPlayer {
+            email: format!("player{index}@test.com"),
+            display_name: format!("Player{index}"),
+            password: String::new(),
+            galaxy_at_war: None,
+            created_at: Some(now - age),
+        })
+        .collect();
+        Player::import(&db, players).await.unwrap();
+
+        let stats = player_stats(&db, now, 2).await.unwrap();
+        assert_eq!(
+            stats,
+            PlayerStats {
+                total: 4,
+                last_day: 1,
+                last_week: 2,
+                last_month: 3,
+                online: 2,
+            }
+        );
+    }
+}
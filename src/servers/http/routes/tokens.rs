@@ -0,0 +1,59 @@
+//! This module contains routes for viewing and revoking the
+//! authentication tokens that have been issued
+
+use crate::servers::http::stores::token::{TokenInfo, TokenStore};
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get},
+    Extension, Json, Router,
+};
+use std::sync::Arc;
+
+/// Router function creates a new router with all the underlying
+/// routes for this file.
+///
+/// Prefix: /api/tokens
+pub fn router() -> Router {
+    Router::new()
+        .route("/", get(list_tokens))
+        .route("/:id", delete(revoke_token))
+}
+
+/// Error type used when attempting to revoke a token that
+/// doesn't exist
+struct TokenNotFound;
+
+/// Route for listing the details of all the issued tokens. The
+/// tokens themselves are not included in the response
+///
+/// `token_store` The token store to list the tokens from
+async fn list_tokens(Extension(token_store): Extension<Arc<TokenStore>>) -> Json<Vec<TokenInfo>> {
+    Json(token_store.list_tokens().await)
+}
+
+/// Route for revoking the token with the provided ID. Revoked
+/// tokens are rejected on future requests
+///
+/// `id`          The ID of the token to revoke
+/// `token_store` The token store to revoke the token from
+async fn revoke_token(
+    Path(id): Path<u32>,
+    Extension(token_store): Extension<Arc<TokenStore>>,
+) -> Result<Response, TokenNotFound> {
+    if token_store.remove_token_id(id).await {
+        Ok(StatusCode::OK.into_response())
+    } else {
+        Err(TokenNotFound)
+    }
+}
+
+/// IntoResponse implementation for TokenNotFound to allow it to be
+/// used within the result type as a error response
+impl IntoResponse for TokenNotFound {
+    #[inline]
+    fn into_response(self) -> Response {
+        (StatusCode::NOT_FOUND, "TokenNotFound").into_response()
+    }
+}
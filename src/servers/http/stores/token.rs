@@ -1,9 +1,11 @@
 use crate::env;
 use crate::utils::random::generate_random_string;
 use axum::Extension;
+use serde::Serialize;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::Mutex;
 
 /// Structure of a store which stores tokens along with
@@ -11,9 +13,47 @@ use tokio::sync::Mutex;
 /// validity of session tokens
 #[derive(Default)]
 pub struct TokenStore {
-    /// Hash map of tokens mapped to the time that they will
-    /// become expired at.
-    tokens: Mutex<HashMap<String, SystemTime>>,
+    /// Hash map of tokens mapped to the data for that token
+    tokens: Mutex<HashMap<String, TokenData>>,
+    /// The ID to use for the next created token
+    next_id: AtomicU32,
+}
+
+/// Data stored for each token in the token store
+struct TokenData {
+    /// Unique ID for the token which can be exposed without
+    /// exposing the token itself
+    id: u32,
+    /// The time the token was created at
+    created: SystemTime,
+    /// The time the token was last used at
+    last_used: SystemTime,
+    /// The time the token will become expired at
+    expiry: SystemTime,
+}
+
+/// Serializable details about a token which doesn't include
+/// the token itself. Times are seconds since the unix epoch
+#[derive(Serialize)]
+pub struct TokenInfo {
+    /// The unique ID of the token
+    pub id: u32,
+    /// The time the token was created at
+    pub created: u64,
+    /// The time the token was last used at
+    pub last_used: u64,
+    /// The time the token will expire at
+    pub expiry_time: u64,
+}
+
+/// Converts the provided system time into the number of seconds
+/// since the unix epoch
+///
+/// `time` The time to convert
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs()
 }
 
 impl TokenStore {
@@ -29,15 +69,18 @@ impl TokenStore {
     /// The length of randomly generated token to create
     const TOKEN_LENGTH: usize = 64;
 
-    /// Checks if the provided token is valid. If the token is
-    /// expired then it is removed from the token store
+    /// Checks if the provided token is valid returning the ID of the
+    /// token if it is. If the token is expired then it is removed from
+    /// the token store. Valid tokens have their last used time updated
     ///
     /// `token` The token to check the validity of
-    pub async fn is_valid_token(&self, token: &str) -> bool {
+    pub async fn validate_token(&self, token: &str) -> Option<u32> {
         let tokens = &mut *self.tokens.lock().await;
         let now = SystemTime::now();
-        tokens.retain(|_, value| now.lt(value));
-        tokens.contains_key(token)
+        tokens.retain(|_, value| now.lt(&value.expiry));
+        let data = tokens.get_mut(token)?;
+        data.last_used = now;
+        Some(data.id)
     }
 
     /// Removes the provided token from the map of tokens.
@@ -48,14 +91,44 @@ impl TokenStore {
         tokens.remove(token);
     }
 
+    /// Removes the token with the provided ID from the map of
+    /// tokens. Returns whether a token was removed
+    ///
+    /// `id` The ID of the token to remove
+    pub async fn remove_token_id(&self, id: u32) -> bool {
+        let tokens = &mut *self.tokens.lock().await;
+        let length = tokens.len();
+        tokens.retain(|_, value| value.id != id);
+        tokens.len() != length
+    }
+
     /// Finds the expiry time for the provided token if it
     /// exists in the tokens map.
     ///
     /// `token` The token to find the expiry time for
     pub async fn get_token_expiry(&self, token: &str) -> Option<SystemTime> {
         let tokens = &*self.tokens.lock().await;
-        let expiry_time = tokens.get(token);
-        expiry_time.copied()
+        tokens.get(token).map(|value| value.expiry)
+    }
+
+    /// Lists the details of all the tokens that haven't expired
+    /// sorted by their token ID
+    pub async fn list_tokens(&self) -> Vec<TokenInfo> {
+        let tokens = &mut *self.tokens.lock().await;
+        let now = SystemTime::now();
+        tokens.retain(|_, value| now.lt(&value.expiry));
+
+        let mut values: Vec<TokenInfo> = tokens
+            .values()
+            .map(|value| TokenInfo {
+                id: value.id,
+                created: unix_seconds(value.created),
+                last_used: unix_seconds(value.last_used),
+                expiry_time: unix_seconds(value.expiry),
+            })
+            .collect();
+        values.sort_by_key(|value| value.id);
+        values
     }
 
     /// Attempts to authenticate a session with the provided username and password.
@@ -85,8 +158,41 @@ impl TokenStore {
             }
         }
 
-        let expiry_time = SystemTime::now() + Self::EXPIRY_TIME;
-        tokens.insert(token.clone(), expiry_time);
+        let now = SystemTime::now();
+        let expiry_time = now + Self::EXPIRY_TIME;
+        let id = self.next_id.fetch_add(1, Ordering::AcqRel) + 1;
+        tokens.insert(
+            token.clone(),
+            TokenData {
+                id,
+                created: now,
+                last_used: now,
+                expiry: expiry_time,
+            },
+        );
         Some((token, expiry_time))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::TokenStore;
+    use crate::env;
+
+    #[tokio::test]
+    async fn test_revoke_token() {
+        let store = TokenStore::default();
+        let (token, _) = store
+            .authenticate(&env::env(env::API_USERNAME), &env::env(env::API_PASSWORD))
+            .await
+            .unwrap();
+        assert!(store.validate_token(&token).await.is_some());
+
+        let tokens = store.list_tokens().await;
+        assert_eq!(tokens.len(), 1);
+
+        assert!(store.remove_token_id(tokens[0].id).await);
+        assert!(store.validate_token(&token).await.is_none());
+        assert!(store.list_tokens().await.is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+//! Module for the session configuration which is loaded from the
+//! environment once when the server starts and shared between sessions
+
+use super::{entitlements, models::auth::Entitlement};
+use crate::utils::{
+    env::{self, DuplicateLoginPolicy},
+    locale,
+};
+use std::time::Duration;
+
+/// Configuration shared by every session
+pub struct SessionConfig {
+    /// The max size in bytes of a packet that can be read from
+    /// a session before it is closed
+    pub max_packet_size: usize,
+    /// The name of the Quality Of Service region reported to
+    /// clients as the best ping site for sessions
+    pub ping_site: String,
+    /// The locale reported for sessions and telemetry or None
+    /// to report the default locales
+    pub locale: Option<u32>,
+    /// The country reported for sessions that don't have a resolved
+    /// country. Empty when no country is reported
+    pub country: String,
+    /// Whether error responses include a message describing
+    /// the error
+    pub error_messages: bool,
+    /// Whether sessions can login using the session token from
+    /// a previous session
+    pub silent_login: bool,
+    /// The entitlements sent to clients when they list their
+    /// entitlements
+    pub entitlements: Vec<Entitlement>,
+    /// The policy applied to other sessions that are already
+    /// authenticated as a player when the player logs in
+    pub duplicate_login: DuplicateLoginPolicy,
+    /// The max time to wait for writes to a session to complete
+    /// or None if writes don't time out
+    pub write_timeout: Option<Duration>,
+    /// The max time a session can go without sending anything
+    /// before it is closed or None if sessions aren't closed
+    pub idle_timeout: Option<Duration>,
+    /// Whether sessions are allowed to be in multiple games
+    /// at the same time
+    pub multi_game: bool,
+    /// The max number of sessions that can be connected from a
+    /// single IP address or zero for no limit
+    pub max_sessions_per_ip: usize,
+}
+
+impl SessionConfig {
+    /// Loads the session configuration from the environment
+    /// variables falling back to the defaults
+    pub async fn from_env() -> Self {
+        Self {
+            max_packet_size: env::from_env(env::MAX_PACKET_SIZE),
+            ping_site: env::env(env::SESSION_BPS),
+            locale: locale::encode_locale(&env::env(env::SESSION_LOCALE)),
+            country: env::env(env::SESSION_COUNTRY),
+            error_messages: env::from_env(env::ERROR_MESSAGES),
+            silent_login: env::from_env(env::SILENT_LOGIN),
+            entitlements: entitlements::load_entitlements(&env::env(env::ENTITLEMENTS_FILE)).await,
+            duplicate_login: env::from_env(env::DUPLICATE_LOGIN),
+            write_timeout: timeout(env::from_env(env::SESSION_WRITE_TIMEOUT)),
+            idle_timeout: timeout(env::from_env(env::SESSION_IDLE_TIMEOUT)),
+            multi_game: env::from_env(env::MULTI_GAME),
+            max_sessions_per_ip: env::from_env(env::MAX_SESSIONS_PER_IP),
+        }
+    }
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            max_packet_size: env::MAX_PACKET_SIZE.1,
+            ping_site: env::SESSION_BPS.1.to_string(),
+            locale: locale::encode_locale(env::SESSION_LOCALE.1),
+            country: env::SESSION_COUNTRY.1.to_string(),
+            error_messages: env::ERROR_MESSAGES.1,
+            silent_login: env::SILENT_LOGIN.1,
+            entitlements: entitlements::default_entitlements(),
+            duplicate_login: env::DUPLICATE_LOGIN.1,
+            write_timeout: timeout(env::SESSION_WRITE_TIMEOUT.1),
+            idle_timeout: timeout(env::SESSION_IDLE_TIMEOUT.1),
+            multi_game: env::MULTI_GAME.1,
+            max_sessions_per_ip: env::MAX_SESSIONS_PER_IP.1,
+        }
+    }
+}
+
+/// Converts the provided timeout in seconds into a duration. Returns
+/// None if the timeout is disabled
+///
+/// `seconds` The timeout in seconds or zero to disable the timeout
+fn timeout(seconds: u64) -> Option<Duration> {
+    match seconds {
+        0 => None,
+        value => Some(Duration::from_secs(value)),
+    }
+}
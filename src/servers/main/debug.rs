@@ -0,0 +1,100 @@
+//! Module for the configuration of which components are ignored or
+//! minified when debug logging session packets
+
+use crate::utils::{components::Components, env};
+use std::collections::HashSet;
+
+/// Sets of component names that are either ignored completely
+/// or have their contents hidden when debug logging packets.
+/// Component names are in the format Component.Command
+/// (e.g. Util.Ping)
+pub struct DebugComponents {
+    /// Components that are not logged at all
+    ignored: HashSet<String>,
+    /// Components that are logged without their contents
+    minified: HashSet<String>,
+}
+
+impl DebugComponents {
+    /// Creates the debug components from the environment
+    /// variables falling back to the defaults
+    pub fn from_env() -> Self {
+        Self::parse(
+            &env::env(env::DEBUG_IGNORE_COMPONENTS),
+            &env::env(env::DEBUG_MINIFY_COMPONENTS),
+        )
+    }
+
+    /// Parses the debug components from the provided comma
+    /// seperated lists of component names
+    ///
+    /// `ignored`  The list of ignored components
+    /// `minified` The list of minified components
+    pub fn parse(ignored: &str, minified: &str) -> Self {
+        Self {
+            ignored: Self::parse_set(ignored),
+            minified: Self::parse_set(minified),
+        }
+    }
+
+    /// Parses a comma seperated list of component names into a set
+    ///
+    /// `value` The list to parse
+    fn parse_set(value: &str) -> HashSet<String> {
+        value
+            .split(',')
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+            .map(|value| value.to_string())
+            .collect()
+    }
+
+    /// Checks whether the provided `component` is ignored completely
+    /// when debug logging. By default this is packets such as Ping and
+    /// SuspendUserPing where they occur frequently but provide no
+    /// useful data for debugging.
+    ///
+    /// `component` The component to check
+    pub fn is_ignored(&self, component: &Components) -> bool {
+        self.ignored.contains(&component_name(component))
+    }
+
+    /// Checks whether the provided `component` should have its contents
+    /// hidden when being debug printed. Used to hide the contents of
+    /// larger packets.
+    ///
+    /// `component` The component to check
+    pub fn is_minified(&self, component: &Components) -> bool {
+        self.minified.contains(&component_name(component))
+    }
+}
+
+/// Creates the name of the provided component in the format
+/// Component.Command (e.g. Util.Ping)
+///
+/// `component` The component to create the name for
+pub fn component_name(component: &Components) -> String {
+    format!("{:?}", component)
+        .replace('(', ".")
+        .replace(')', "")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{component_name, DebugComponents};
+    use crate::utils::components::{Components, UserSessions, Util};
+
+    #[test]
+    fn test_component_name() {
+        assert_eq!(component_name(&Components::Util(Util::Ping)), "Util.Ping");
+    }
+
+    #[test]
+    fn test_ignored() {
+        let debug = DebugComponents::parse("Util.Ping, UserSessions.ResumeSession", "");
+        assert!(debug.is_ignored(&Components::UserSessions(UserSessions::ResumeSession)));
+        assert!(debug.is_ignored(&Components::Util(Util::Ping)));
+        assert!(!debug.is_ignored(&Components::Util(Util::SuspendUserPing)));
+        assert!(!debug.is_minified(&Components::Util(Util::Ping)));
+    }
+}
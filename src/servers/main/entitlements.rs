@@ -0,0 +1,119 @@
+//! Entitlements that are sent to clients when they list their
+//! entitlements. The list can be replaced by providing a JSON file
+//! through the environment variables which is loaded once when the
+//! server starts.
+
+use super::models::auth::Entitlement;
+use log::{error, info};
+use std::{io, path::Path};
+use tokio::fs;
+
+/// Loads the list of entitlements from the provided entitlements file
+/// falling back to the default entitlements if the file is empty or
+/// could not be loaded
+///
+/// `file` The path of the entitlements file
+pub async fn load_entitlements(file: &str) -> Vec<Entitlement> {
+    if file.is_empty() {
+        return default_entitlements();
+    }
+
+    match read_entitlements(Path::new(file)).await {
+        Ok(list) => {
+            info!("Loaded {} entitlements from {}", list.len(), file);
+            list
+        }
+        Err(err) => {
+            error!("Unable to load entitlements file ({}): {}", file, err);
+            default_entitlements()
+        }
+    }
+}
+
+/// Reads the list of entitlements from the JSON file at the
+/// provided path
+///
+/// `path` The path of the entitlements file
+async fn read_entitlements(path: &Path) -> io::Result<Vec<Entitlement>> {
+    let contents = fs::read(path).await?;
+    let list = serde_json::from_slice(&contents)?;
+    Ok(list)
+}
+
+/// Creates the default list of entitlements
+// Skip formatting these entitlement creations
+#[rustfmt::skip]
+pub fn default_entitlements() -> Vec<Entitlement> {
+    vec![
+        // Project 10 = $10 Multiplayer Pass (Entitlement Required For Online Access)
+        Entitlement::new_pc(0xec5090c43f,"303107",2,"DR:229644400","PROJECT10_CODE_CONSUMED",1),
+        Entitlement::new_pc(0xec3e4d793f,"304141",2,"DR:230773600","PROJECT10_CODE_CONSUMED_LE1",1),
+        Entitlement::new_pc(0xec3e4d793f,"304141",2,"DR:230773600","PROJECT10_CODE_CONSUMED_LE1",1),
+        // Jeeze so many online pass entitlements
+        Entitlement::new_pc(0xec50b255ff,"300241",2,"OFB-MASS:44370","ONLINE_ACCESS",1),
+        Entitlement::new_pc(0xec50a620ff,"300241",2,"OFB-MASS:49465","ONLINE_ACCESS",1),
+        Entitlement::new_pc(0xec508db6ff,"303107",2,"DR:229644400","ONLINE_ACCESS",1),
+        Entitlement::new_pc(0xec3e5393bf,"300241",2,"OFB-EAST:46112","ONLINE_ACCESS",1),
+        Entitlement::new_pc(0xec3e50867f,"304141",2,"DR:230773600","ONLINE_ACCESS",1),
+        Entitlement::new_gen(0xec4495bfff,"303107", 0,"","ONLINE_ACCESS_GAW_PC",1),
+        Entitlement::new_gen(0xea234c3e7f,"303107", 2,"","ONLINE_ACCESS_GAW_XBL2",1),
+        // Singleplayer DLC
+        Entitlement::new_pc(0xec3e62d5ff,"300241",2,"OFB-MASS:51074","ME3_PRC_EXTENDEDCUT",5),
+        Entitlement::new_pc(0xec50b5633f,"300241",2,"OFB-MASS:44370","ME3_PRC_PROTHEAN",5),
+        Entitlement::new_pc(0xec3e56a0ff,"300241",2,"OFB-EAST:46112","ME3_PRC_PROTHEAN",5),
+        Entitlement::new_pc(0xec50b8707f,"300241",2,"OFB-MASS:52001","ME3_PRC_LEVIATHAN",5),
+        Entitlement::new_pc(0xec50ac3b7f,"300241",2,"OFB-MASS:55146","ME3_PRC_OMEGA",5),
+        Entitlement::new_pc(0xec5093d17f,"300241",2,"OFB-EAST:58040","MET_BONUS_CONTENT_DW",5),
+        Entitlement::new_pc(0xec50af48bf,"300241",2,"OFB-EAST:57550","ME3_PRC_CITADEL",5),
+        // Singleplayer Packs
+        Entitlement::new_pc(0xec50bb7dbf,"300241",2,"OFB-MASS:56984","ME3_MTX_APP01",5),
+        Entitlement::new_pc(0xec5099ebff,"300241",2,"OFB-MASS:49032","ME3_MTX_GUN01",5),
+        Entitlement::new_pc(0xec50c1983f,"300241",2,"OFB-MASS:55147","ME3_MTX_GUN02",5),
+        // Multiplayer DLC
+        Entitlement::new_pc(0xec50a0067f,"300241",2,"OFB-MASS:47872","ME3_PRC_RESURGENCE",5),
+        Entitlement::new_pc(0xec50a92e3f,"300241",2,"OFB-MASS:49465","ME3_PRC_REBELLION",5),
+        Entitlement::new_pc(0xec5096debf,"300241",2,"OFB-MASS:51073","ME3_PRC_EARTH",5),
+        Entitlement::new_pc(0xec509cf93f,"300241",2,"OFB-MASS:52000","ME3_PRC_GOBIG",5),
+        Entitlement::new_pc(0xec50a313bf,"300241",2,"OFB-MASS:59712","ME3_PRC_MP5",5),
+        // Other
+        Entitlement::new_pc(0xec81ae023f,"300241",2,"OFB-MASS:46111","ME3_PRO_M90_INDRA",5),
+        Entitlement::new_pc(0xec81aaf4ff,"300241",2,"OFB-MASS:46110","ME3_PRO_AT12_RAIDER_PACK",5),
+        Entitlement::new_pc(0xec81a7e7bf,"300241",2,"OFB-MASS:46033","ME3_PRO_M55_ARGUS",5),
+        Entitlement::new_pc(0xec81a4da7f,"300241",2,"OFB-MASS:46032","ME3_PRO_N7_WARFARE_PACK",5),
+        Entitlement::new_pc(0xec81a1cd3f,"300241",2,"OFB-MASS:46489","ME3_PRO_N7_WARFARE_PACK",5),
+
+        
+        // Collectors Edition
+        Entitlement::new_pc(0xec3e5fc8bf,"300241",2,"OFB-MASS:46484","ME3_MTX_COLLECTORS_EDITION",5),
+        Entitlement::new_pc(0xec3e5cbb7f,"300241",2,"OFB-MASS:46483","ME3_MTX_DIGITAL_ART_BOOKS",5),
+        Entitlement::new_gen(0xec3e59ae3f,"300241",2,"OFB-MASS:46482","ME3_MTX_SOUNDTRACK",5),
+        // Darkhorse Redeem Code (Character boosters and Collector Assault Rifle)
+        Entitlement::new_pc(0xec50be8aff,"300241",2,"OFB-MASS:61524","ME3_PRC_DARKHORSECOMIC",5),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::load_entitlements;
+
+    #[tokio::test]
+    async fn test_custom_entitlements() {
+        let path =
+            std::env::temp_dir().join(format!("pr-entitlements-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"[{"name":"ME3PCOffers","id":1,"pjid":"300241","prca":2,"prid":"OFB-MASS:1","tag":"CUSTOM_PACK","type":5}]"#,
+        )
+        .unwrap();
+
+        let list = load_entitlements(&path.to_string_lossy()).await;
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(list.len(), 1);
+        let entitlement = &list[0];
+        assert_eq!(entitlement.name, "ME3PCOffers");
+        assert_eq!(entitlement.id, 1);
+        assert_eq!(entitlement.tag, "CUSTOM_PACK");
+        assert_eq!(entitlement.ty, 5);
+    }
+}
@@ -1,33 +1,48 @@
-use crate::utils::{components::Components, env};
+use crate::{
+    state::GlobalState,
+    utils::{components::Components, env, geo, net::bind_address},
+};
 use blaze_pk::router::Router;
-use log::{error, info};
+use config::SessionConfig;
+use debug::DebugComponents;
+use log::{debug, error, info, warn};
 use session::Session;
-use std::sync::Arc;
+use std::{io, sync::Arc};
 use tokio::net::TcpListener;
 
+pub mod config;
+mod debug;
+mod entitlements;
 mod models;
+pub mod replay;
 mod routes;
 pub mod session;
+pub mod sessions;
+
+pub use routes::{handled_components, routed_components};
 
 /// Starts the main server which is responsible for a majority of the
 /// game logic such as games, sessions, etc.
-pub async fn start_server() {
+pub async fn start_server() -> io::Result<()> {
     // Initializing the underlying TCP listener
     let listener = {
         let port = env::from_env(env::MAIN_PORT);
-        match TcpListener::bind(("0.0.0.0", port)).await {
+        match TcpListener::bind((bind_address(), port)).await {
             Ok(value) => {
                 info!("Started Main server (Port: {})", port);
                 value
             }
-            Err(_) => {
+            Err(err) => {
                 error!("Failed to bind Main server (Port: {})", port);
-                panic!()
+                return Err(err);
             }
         }
     };
 
     let router: Arc<Router<Components, Session>> = Arc::new(routes::router());
+    let debug: Arc<DebugComponents> = Arc::new(DebugComponents::from_env());
+    let config: Arc<SessionConfig> = Arc::new(SessionConfig::from_env().await);
+    let geolocator = geo::from_env();
     let mut session_id = 1;
     // Accept incoming connections
     loop {
@@ -38,7 +53,31 @@ pub async fn start_server() {
                 continue;
             }
         };
-        Session::spawn(session_id, values, router.clone());
+
+        // Refuse new connections while the server is draining
+        if GlobalState::games().is_draining() {
+            debug!(
+                "Refused Main connection from {}: server is draining",
+                values.1
+            );
+            continue;
+        }
+
+        // Refuse connections from addresses that have too many sessions
+        let ip = values.1.ip();
+        if !GlobalState::sessions().can_connect(ip, config.max_sessions_per_ip) {
+            warn!("Refused Main connection from {ip}: too many sessions");
+            continue;
+        }
+
+        Session::spawn(
+            session_id,
+            values,
+            router.clone(),
+            debug.clone(),
+            config.clone(),
+            geolocator.as_ref(),
+        );
         session_id += 1;
     }
 }
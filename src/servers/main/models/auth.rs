@@ -8,6 +8,7 @@ use blaze_pk::{
     writer::TdfWriter,
 };
 use database::Player;
+use serde::Deserialize;
 use std::borrow::Cow;
 
 /// Different possible authentication request types.
@@ -209,13 +210,15 @@ impl Encodable for ListEntitlementsResponse {
 }
 
 //noinspection SpellCheckingInspection
+#[derive(Clone, Deserialize)]
 pub struct Entitlement {
-    pub name: &'static str,
+    pub name: String,
     pub id: u64,
-    pub pjid: &'static str,
+    pub pjid: String,
     pub prca: u8,
-    pub prid: &'static str,
-    pub tag: &'static str,
+    pub prid: String,
+    pub tag: String,
+    #[serde(rename = "type")]
     pub ty: u8,
 }
 
@@ -223,40 +226,22 @@ impl Entitlement {
     pub const PC_TAG: &'static str = "ME3PCOffers";
     pub const GEN_TAG: &'static str = "ME3GenOffers";
 
-    pub fn new_pc(
-        id: u64,
-        pjid: &'static str,
-        prca: u8,
-        prid: &'static str,
-        tag: &'static str,
-        ty: u8,
-    ) -> Self {
-        Self {
-            name: Self::PC_TAG,
-            id,
-            pjid,
-            prca,
-            prid,
-            tag,
-            ty,
-        }
+    pub fn new_pc(id: u64, pjid: &str, prca: u8, prid: &str, tag: &str, ty: u8) -> Self {
+        Self::new(Self::PC_TAG, id, pjid, prca, prid, tag, ty)
+    }
+
+    pub fn new_gen(id: u64, pjid: &str, prca: u8, prid: &str, tag: &str, ty: u8) -> Self {
+        Self::new(Self::GEN_TAG, id, pjid, prca, prid, tag, ty)
     }
 
-    pub fn new_gen(
-        id: u64,
-        pjid: &'static str,
-        prca: u8,
-        prid: &'static str,
-        tag: &'static str,
-        ty: u8,
-    ) -> Self {
+    fn new(name: &str, id: u64, pjid: &str, prca: u8, prid: &str, tag: &str, ty: u8) -> Self {
         Self {
-            name: Self::GEN_TAG,
+            name: name.to_string(),
             id,
-            pjid,
+            pjid: pjid.to_string(),
             prca,
-            prid,
-            tag,
+            prid: prid.to_string(),
+            tag: tag.to_string(),
             ty,
         }
     }
@@ -266,16 +251,16 @@ impl Encodable for Entitlement {
     fn encode(&self, writer: &mut TdfWriter) {
         writer.tag_str_empty(b"DEVI");
         writer.tag_str(b"GDAY", "2012-12-15T16:15Z");
-        writer.tag_str(b"GNAM", self.name);
+        writer.tag_str(b"GNAM", &self.name);
         writer.tag_u64(b"ID", self.id);
         writer.tag_u8(b"ISCO", 0);
         writer.tag_u8(b"PID", 0);
-        writer.tag_str(b"PJID", self.pjid);
+        writer.tag_str(b"PJID", &self.pjid);
         writer.tag_u8(b"PRCA", self.prca);
-        writer.tag_str(b"PRID", self.prid);
+        writer.tag_str(b"PRID", &self.prid);
         writer.tag_u8(b"STAT", 1);
         writer.tag_u8(b"STRC", 0);
-        writer.tag_str(b"TAG", self.tag);
+        writer.tag_str(b"TAG", &self.tag);
         writer.tag_str_empty(b"TDAY");
         writer.tag_u8(b"TTYPE", self.ty);
         writer.tag_u8(b"UCNT", 0);
@@ -1,4 +1,9 @@
-use blaze_pk::packet::{IntoResponse, Packet};
+use blaze_pk::{
+    codec::Encodable,
+    packet::{IntoResponse, Packet},
+    writer::TdfWriter,
+};
+use std::fmt::Display;
 
 pub type ServerResult<T> = Result<T, ServerError>;
 
@@ -25,14 +30,102 @@ pub enum ServerError {
     FailedNoLoginAction = 0x4004,
     ServerUnavailableNothing = 0x4005,
     ConnectionLost = 0x4007,
+    // Not an official error code used when a client version isn't allowed
+    ClientVersionNotAllowed = 0x4008,
+    // Not an official error code used when the client sends a malformed packet
+    InvalidRequest = 0x4009,
+    // Not an official error code used when a player isn't allowed to modify a game
+    PermissionDenied = 0x400A,
+    // Not an official error code used when the requested game can't be joined
+    GameNotJoinable = 0x400B,
     UnableToUpdateSettings = 0xCB,
     // Errors from suspend
     Suspend12D = 0x12D,
     Suspend12E = 0x12E,
 }
 
+impl ServerError {
+    /// Returns a human readable message describing the error
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::ServerUnavailable => "The server is unavailable",
+            Self::EmailNotFound => "No account exists with the provided email",
+            Self::WrongPassword => "The provided password was incorrect",
+            Self::InvalidSession => "The session is invalid or has expired",
+            Self::EmailAlreadyInUse => "The provided email is already in use",
+            Self::AgeRestriction => "The account does not meet the age restriction",
+            Self::InvalidAccount => "The account is invalid",
+            Self::BannedAccount => "The account has been banned",
+            Self::InvalidInformation => "The provided information was invalid",
+            Self::InvalidEmail => "The provided email was invalid",
+            Self::LegalGuardianRequired => "A legal guardian is required",
+            Self::CodeRequired => "A code is required",
+            Self::KeyCodeAlreadyInUse => "The key code is already in use",
+            Self::InvalidCerberusKey => "The cerberus key is invalid",
+            Self::ServerUnavailableFinal => "The server is unavailable",
+            Self::FailedNoLoginAction => "The session is not authenticated",
+            Self::ServerUnavailableNothing => "The server is unavailable",
+            Self::ConnectionLost => "The connection was lost",
+            Self::ClientVersionNotAllowed => "The client version is not supported by this server",
+            Self::InvalidRequest => "The request was malformed",
+            Self::PermissionDenied => "Only the host and admins can modify the game",
+            Self::GameNotJoinable => "The game no longer exists or can't be joined",
+            Self::UnableToUpdateSettings => "Unable to update the settings",
+            Self::Suspend12D | Self::Suspend12E => "The request was suspended",
+        }
+    }
+}
+
+impl Display for ServerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+/// Error response contents carrying the message for the error. Sessions
+/// remove these unless error messages are enabled as the official client
+/// expects error responses to be empty
+struct ErrorMessage(&'static str);
+
+impl Encodable for ErrorMessage {
+    fn encode(&self, writer: &mut TdfWriter) {
+        writer.tag_str(b"MSG", self.0);
+    }
+}
+
 impl IntoResponse for ServerError {
     fn into_response(self, req: &Packet) -> Packet {
-        req.respond_error_empty(self as u16)
+        req.respond_error(self as u16, ErrorMessage(self.message()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ServerError;
+    use crate::utils::components::{Authentication, Components};
+    use blaze_pk::{
+        codec::Decodable,
+        error::DecodeResult,
+        packet::{IntoResponse, Packet},
+        reader::TdfReader,
+    };
+
+    /// Decoded contents of an error response
+    struct DecodedMessage(String);
+
+    impl Decodable for DecodedMessage {
+        fn decode(reader: &mut TdfReader) -> DecodeResult<Self> {
+            Ok(Self(reader.tag("MSG")?))
+        }
+    }
+
+    #[test]
+    fn test_error_message() {
+        let req = Packet::request_empty(1, Components::Authentication(Authentication::Login));
+        let res = ServerError::InvalidSession.into_response(&req);
+        assert_eq!(res.header.error, ServerError::InvalidSession as u16);
+
+        let message: DecodedMessage = res.decode().unwrap();
+        assert_eq!(message.0, "The session is invalid or has expired");
     }
 }
@@ -47,6 +47,32 @@ impl Encodable for CreateGameResponse {
     }
 }
 
+/// Structure of the request to join a specific game
+pub struct JoinGameRequest {
+    /// The ID of the game to join
+    pub game_id: GameID,
+}
+
+impl Decodable for JoinGameRequest {
+    fn decode(reader: &mut TdfReader) -> DecodeResult<Self> {
+        let game_id: GameID = reader.tag("GID")?;
+        Ok(Self { game_id })
+    }
+}
+
+/// Structure for the response to joining a game which contains
+/// the ID of the game being joined
+pub struct JoinGameResponse {
+    /// The game ID
+    pub game_id: GameID,
+}
+
+impl Encodable for JoinGameResponse {
+    fn encode(&self, writer: &mut TdfWriter) {
+        writer.tag_u32(b"GID", self.game_id);
+    }
+}
+
 /// Structure of request to remove player from a game
 pub struct RemovePlayerRequest {
     /// The ID of the game to remove from
@@ -70,6 +96,23 @@ impl Decodable for RemovePlayerRequest {
     }
 }
 
+/// Structure of request to add or remove a player from the admin
+/// list of a game
+pub struct AdminPlayerRequest {
+    /// The ID of the game
+    pub game_id: GameID,
+    /// The ID of the player to add or remove
+    pub player_id: PlayerID,
+}
+
+impl Decodable for AdminPlayerRequest {
+    fn decode(reader: &mut TdfReader) -> DecodeResult<Self> {
+        let game_id: GameID = reader.tag("GID")?;
+        let player_id: PlayerID = reader.tag("PID")?;
+        Ok(Self { game_id, player_id })
+    }
+}
+
 pub struct GameModifyRequest {
     /// The ID of the game to modify
     pub game_id: GameID,
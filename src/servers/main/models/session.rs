@@ -1,4 +1,10 @@
-use crate::{servers::main::session::Session, utils::types::PlayerID};
+use crate::{
+    servers::main::session::Session,
+    utils::{
+        locale::{self, DEFAULT_SESSION_LOCALE},
+        types::PlayerID,
+    },
+};
 use blaze_pk::{codec::Encodable, tag::TdfType, writer::TdfWriter};
 
 /// Encodes the session details for the provided session using
@@ -8,8 +14,12 @@ use blaze_pk::{codec::Encodable, tag::TdfType, writer::TdfWriter};
 /// `writer`  The writer to encode with
 fn encode_session(session: &Session, writer: &mut TdfWriter) {
     session.net.tag_groups(b"ADDR", writer);
-    writer.tag_str(b"BPS", "ea-sjc");
-    writer.tag_str_empty(b"CTY");
+    writer.tag_str(b"BPS", &session.config.ping_site);
+    locale::tag_country(
+        writer,
+        session.net.country.as_deref(),
+        &session.config.country,
+    );
     writer.tag_var_int_list_empty(b"CVAR");
     {
         writer.tag_map_start(b"DMAP", TdfType::VarInt, TdfType::VarInt, 1);
@@ -17,20 +27,42 @@ fn encode_session(session: &Session, writer: &mut TdfWriter) {
         writer.write_u16(0x409a);
     }
     writer.tag_u16(b"HWFG", session.net.hardware_flags);
-    {
-        // Ping latency to the Quality of service servers
-        writer.tag_list_start(b"PSLM", TdfType::VarInt, 1);
-        0xfff0fff.encode(writer);
-    }
+    // Ping latency to the Quality of service servers
+    session.net.tag_pslm(writer);
     writer.tag_value(b"QDAT", &session.net.qos);
     writer.tag_u8(b"UATT", 0);
-    if let Some(game_id) = &session.game {
-        writer.tag_list_start(b"ULST", TdfType::Triple, 1);
-        (4, 1, *game_id).encode(writer);
+    if !session.games.is_empty() {
+        writer.tag_list_start(b"ULST", TdfType::Triple, session.games.len());
+        for game_id in session.games.ids() {
+            (4, 1, game_id).encode(writer);
+        }
     }
     writer.tag_group_end();
 }
 
+/// Encodes the user details for the player with the provided ID
+/// and display name using the provided writer
+///
+/// `player_id`    The ID of the player
+/// `display_name` The display name of the player
+/// `locale`       The configured locale if any
+/// `writer`       The writer to encode with
+fn encode_user(
+    player_id: PlayerID,
+    display_name: &str,
+    locale: Option<u32>,
+    writer: &mut TdfWriter,
+) {
+    writer.tag_group(b"USER");
+    writer.tag_u32(b"AID", player_id);
+    writer.tag_u32(b"ALOC", locale.unwrap_or(DEFAULT_SESSION_LOCALE));
+    writer.tag_empty_blob(b"EXBB");
+    writer.tag_u8(b"EXID", 0);
+    writer.tag_u32(b"ID", player_id);
+    writer.tag_str(b"NAME", display_name);
+    writer.tag_group_end();
+}
+
 /// Session update for a session other than ourselves
 /// which contains the details for that session
 pub struct SessionUpdate<'a> {
@@ -47,14 +79,30 @@ impl Encodable for SessionUpdate<'_> {
         writer.tag_group(b"DATA");
         encode_session(self.session, writer);
 
-        writer.tag_group(b"USER");
-        writer.tag_u32(b"AID", self.player_id);
-        writer.tag_u32(b"ALOC", 0x64654445);
-        writer.tag_empty_blob(b"EXBB");
-        writer.tag_u8(b"EXID", 0);
-        writer.tag_u32(b"ID", self.player_id);
-        writer.tag_str(b"NAME", self.display_name);
-        writer.tag_group_end();
+        encode_user(
+            self.player_id,
+            self.display_name,
+            self.session.config.locale,
+            writer,
+        );
+    }
+}
+
+/// Presence update for a player other than ourselves which only
+/// contains the details of the player and none of the networking
+/// details of their session
+pub struct PresenceUpdate<'a> {
+    /// The player ID the update is for
+    pub player_id: PlayerID,
+    /// The display name of the player the update is for
+    pub display_name: &'a str,
+    /// The configured locale if any
+    pub locale: Option<u32>,
+}
+
+impl Encodable for PresenceUpdate<'_> {
+    fn encode(&self, writer: &mut TdfWriter) {
+        encode_user(self.player_id, self.display_name, self.locale, writer);
     }
 }
 
@@ -73,3 +121,62 @@ impl Encodable for SetSession<'_> {
         writer.tag_u32(b"USID", self.player_id);
     }
 }
+
+/// The reasons the server can have for forcibly closing a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The player was kicked from the server
+    Kick,
+    /// The server is shutting down
+    Shutdown,
+}
+
+impl DisconnectReason {
+    pub fn value(&self) -> u8 {
+        match self {
+            Self::Kick => 0x1,
+            Self::Shutdown => 0x2,
+        }
+    }
+}
+
+/// Notification sent to a session before the server closes it
+/// containing the reason the session is being closed
+pub struct DisconnectNotify {
+    /// The reason the session is being closed
+    pub reason: DisconnectReason,
+}
+
+impl Encodable for DisconnectNotify {
+    fn encode(&self, writer: &mut TdfWriter) {
+        writer.tag_u8(b"REAS", self.reason.value());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DisconnectNotify, DisconnectReason};
+    use crate::utils::components::{Components, UserSessions};
+    use blaze_pk::{
+        packet::{Packet, PacketComponents},
+        reader::TdfReader,
+    };
+
+    #[test]
+    fn test_disconnect_notify() {
+        let packet = Packet::notify(
+            Components::UserSessions(UserSessions::SessionDisconnected),
+            DisconnectNotify {
+                reason: DisconnectReason::Shutdown,
+            },
+        );
+        assert_eq!(
+            Components::from_header(&packet.header),
+            Components::UserSessions(UserSessions::SessionDisconnected)
+        );
+
+        let mut reader = TdfReader::new(&packet.contents);
+        let reason: u8 = reader.tag("REAS").unwrap();
+        assert_eq!(reason, DisconnectReason::Shutdown.value());
+    }
+}
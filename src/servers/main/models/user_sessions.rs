@@ -3,8 +3,9 @@ use blaze_pk::{
     codec::Decodable,
     error::{DecodeError, DecodeResult},
     reader::TdfReader,
-    types::Union,
+    types::{TdfMap, Union},
 };
+use std::collections::BTreeMap;
 
 /// Structure for a request to resume a session using a session token
 pub struct ResumeSessionRequest {
@@ -26,6 +27,9 @@ pub struct UpdateNetworkRequest {
     pub address: NetGroups,
     /// The client Quality of Service data
     pub qos: QosNetworkData,
+    /// Map of the latency in milliseconds from the client to each
+    /// of the Quality of Service server regions
+    pub latency: BTreeMap<String, u32>,
 }
 
 impl Decodable for UpdateNetworkRequest {
@@ -34,8 +38,16 @@ impl Decodable for UpdateNetworkRequest {
             Union::Set { value, .. } => value,
             Union::Unset => return Err(DecodeError::Other("Client address was unset")),
         };
+        let latency: Option<TdfMap<String, u32>> = reader.try_tag("NLMP")?;
+        let latency = latency
+            .map(|value| value.into_iter().collect())
+            .unwrap_or_default();
         let qos: QosNetworkData = reader.tag("NQOS")?;
-        Ok(Self { address, qos })
+        Ok(Self {
+            address,
+            qos,
+            latency,
+        })
     }
 }
 
@@ -52,3 +64,39 @@ impl Decodable for HardwareFlagRequest {
         Ok(Self { hardware_flag })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::UpdateNetworkRequest;
+    use crate::utils::models::{NetData, QosNetworkData};
+    use blaze_pk::{codec::Decodable, reader::TdfReader, types::TdfMap, writer::TdfWriter};
+
+    #[test]
+    fn test_decode_latency() {
+        let mut writer = TdfWriter::default();
+        let net = NetData {
+            is_set: true,
+            ..Default::default()
+        };
+        net.tag_groups(b"ADDR", &mut writer);
+
+        let mut latency: TdfMap<String, u32> = TdfMap::new();
+        latency.insert("ea-sjc".to_string(), 156);
+        latency.insert("rs-iad".to_string(), 0xFFF0FFF);
+        latency.insert("rs-lhr".to_string(), 48);
+        writer.tag_value(b"NLMP", &latency);
+        writer.tag_value(b"NQOS", &QosNetworkData::default());
+
+        let mut reader = TdfReader::new(&writer.buffer);
+        let request = UpdateNetworkRequest::decode(&mut reader).unwrap();
+        let regions: Vec<(&str, u32)> = request
+            .latency
+            .iter()
+            .map(|(key, value)| (key.as_str(), *value))
+            .collect();
+        assert_eq!(
+            regions,
+            vec![("ea-sjc", 156), ("rs-iad", 0xFFF0FFF), ("rs-lhr", 48)]
+        );
+    }
+}
@@ -1,9 +1,14 @@
-use crate::utils::{constants, models::Port, types::PlayerID};
+use crate::utils::{
+    constants::{self, BLAZE_VERSION, SRC_VERSION},
+    locale::DEFAULT_TELEMETRY_LOCALE,
+    models::Port,
+    types::PlayerID,
+};
 use blaze_pk::{
     codec::{Decodable, Encodable},
     error::DecodeResult,
     reader::TdfReader,
-    tag::TdfType,
+    tag::{Tag, TdfType},
     types::TdfMap,
     writer::TdfWriter,
 };
@@ -27,6 +32,8 @@ const TELEMETRY_KEY: &[u8] = &[
 pub struct TelemetryServer {
     /// The port for the telemetry server
     pub port: u16,
+    /// The configured locale if any
+    pub locale: Option<u32>,
 }
 
 impl Encodable for TelemetryServer {
@@ -37,7 +44,7 @@ impl Encodable for TelemetryServer {
         writer.tag_zero(b"ANON");
         writer.tag_str(b"DISA", TELEMTRY_DISA);
         writer.tag_str(b"FILT", "-UION/****");
-        writer.tag_u32(b"LOC", 1701727834);
+        writer.tag_u32(b"LOC", self.locale.unwrap_or(DEFAULT_TELEMETRY_LOCALE));
         writer.tag_str(b"NOOK", "US,CA,MX");
         // Last known telemetry port: 9988
         writer.tag_u16(b"PORT", self.port);
@@ -74,16 +81,47 @@ impl Encodable for TickerServer {
     }
 }
 
-/// Server SRC version
-pub const SRC_VERSION: &str = "303107";
-pub const BLAZE_VERSION: &str = "Blaze 3.15.08.0 (CL# 1629389)";
 pub const PING_PERIOD: &str = "15s";
 
+/// Structure of the pre-auth request sent by the client containing
+/// details about the client
+pub struct PreAuthRequest {
+    /// The version of the client (CINF.CVER) if the client
+    /// included it
+    pub version: Option<String>,
+}
+
+impl Decodable for PreAuthRequest {
+    fn decode(reader: &mut TdfReader) -> DecodeResult<Self> {
+        let mut version = None;
+        if reader.try_until_tag("CINF", TdfType::Group) {
+            // Only the tags within the client info are read so that a
+            // missing version can't read past the end of the group
+            while reader
+                .buffer
+                .get(reader.cursor)
+                .map_or(false, |value| *value != 0)
+            {
+                let Tag(tag, ty) = reader.read_tag()?;
+                if tag == "CVER" && ty == TdfType::String {
+                    version = Some(reader.read_string()?);
+                } else {
+                    reader.skip_type(&ty)?;
+                }
+            }
+        }
+        Ok(Self { version })
+    }
+}
+
 /// Structure for the response to a pre authentication request
 pub struct PreAuthResponse {
     /// Port for the Quality Of Service server in our case this is
     /// the HTTP server port
     pub qos_port: Port,
+    /// The name of the Quality Of Service region clients should
+    /// report their latency to
+    pub ping_site: String,
 }
 
 impl Encodable for PreAuthResponse {
@@ -105,11 +143,15 @@ impl Encodable for PreAuthResponse {
         {
             writer.tag_group(b"CONF");
             {
-                writer.tag_map_start(b"CONF", TdfType::String, TdfType::String, 3);
+                writer.tag_map_start(b"CONF", TdfType::String, TdfType::String, 4);
 
                 writer.write_str("pingPeriod");
                 writer.write_str(PING_PERIOD);
 
+                // Pocket Relay server version for clients to log
+                writer.write_str("pocketRelayVersion");
+                writer.write_str(constants::VERSION);
+
                 writer.write_str("voipHeadsetUpdateRate");
                 writer.write_str("1000");
 
@@ -151,7 +193,7 @@ impl Encodable for PreAuthResponse {
             // list are later included in a ping list
             {
                 writer.tag_map_start(b"LTPS", TdfType::String, TdfType::Group, 1);
-                writer.write_str("ea-sjc");
+                writer.write_str(&self.ping_site);
                 writer.write_slice(&qoss_group.buffer);
             }
 
@@ -255,6 +297,19 @@ impl Decodable for SuspendPingRequest {
     }
 }
 
+/// Structure for the response to a suspend user ping request
+/// containing the accepted suspend value
+pub struct SuspendPingResponse {
+    /// The accepted suspend ping value
+    pub value: u32,
+}
+
+impl Encodable for SuspendPingResponse {
+    fn encode(&self, writer: &mut TdfWriter) {
+        writer.tag_u32(b"TVAL", self.value);
+    }
+}
+
 /// Structure for the request to update the settings for
 /// the current player
 pub struct SettingsSaveRequest {
@@ -0,0 +1,223 @@
+//! Replaying of captured packets through the packet decoder for
+//! regression testing protocol changes. Captures are JSONL files where
+//! each line contains the direction of the packet and the full encoded
+//! packet as hex:
+//!
+//! {"direction": "client", "packet": "<Encoded packet as hex>"}
+//!
+//! Packets from the client are additionally checked against the session
+//! router without being handled to find requests that have no handler
+
+use super::{debug::component_name, routes::handled_components};
+use crate::utils::{components::Components, packet::append_packet_decoded, packet::read_packet};
+use blaze_pk::packet::{PacketComponents, PacketType};
+use log::{error, info, warn};
+use serde::Deserialize;
+use std::{io, path::Path};
+
+/// Direction value for packets sent by the client
+const DIRECTION_CLIENT: &str = "client";
+
+/// Line from a capture file
+#[derive(Deserialize)]
+struct CaptureLine {
+    /// The direction of the packet either "client" or "server"
+    direction: String,
+    /// The full encoded packet as hex
+    packet: String,
+}
+
+/// Results of replaying a capture
+#[derive(Default)]
+pub struct ReplayReport {
+    /// The number of packets that were replayed
+    pub total: usize,
+    /// The re-stringified output for each packet that decoded
+    pub outputs: Vec<String>,
+    /// The packets that failed to decode
+    pub failures: Vec<ReplayFailure>,
+    /// The client requests that have no handler on the router
+    pub unhandled: Vec<ReplayFailure>,
+}
+
+/// Packet from a capture that failed to replay
+pub struct ReplayFailure {
+    /// The line number of the packet in the capture (Starting at 1)
+    pub line: usize,
+    /// The reason the packet failed
+    pub reason: String,
+}
+
+/// Replays the capture file at the provided path logging the
+/// results of the replay
+///
+/// `path` The path to the capture file
+pub async fn run(path: &Path) -> io::Result<ReplayReport> {
+    let capture = tokio::fs::read_to_string(path).await?;
+    let report = replay(&capture).await;
+    for failure in &report.failures {
+        error!(
+            "Packet failed to decode (Line: {}): {}",
+            failure.line, failure.reason
+        );
+    }
+    for failure in &report.unhandled {
+        warn!(
+            "Request has no handler (Line: {}): {}",
+            failure.line, failure.reason
+        );
+    }
+    info!(
+        "Replayed capture (Packets: {}, Failed: {}, Unhandled: {})",
+        report.total,
+        report.failures.len(),
+        report.unhandled.len()
+    );
+    Ok(report)
+}
+
+/// Replays the provided capture contents decoding each of the packets
+/// and checking client requests against the router
+///
+/// `capture` The contents of the capture file
+pub async fn replay(capture: &str) -> ReplayReport {
+    let handled = handled_components();
+    let mut report = ReplayReport::default();
+
+    for (index, line) in capture.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        report.total += 1;
+
+        let fail = |reason: String| ReplayFailure {
+            line: line_number,
+            reason,
+        };
+
+        let capture: CaptureLine = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(err) => {
+                report
+                    .failures
+                    .push(fail(format!("Invalid capture line: {err}")));
+                continue;
+            }
+        };
+        let Some(bytes) = decode_hex(&capture.packet) else {
+            report
+                .failures
+                .push(fail("Packet is not valid hex".to_string()));
+            continue;
+        };
+        let packet = match read_packet(&mut bytes.as_slice(), usize::MAX).await {
+            Ok(value) => value,
+            Err(err) => {
+                report.failures.push(fail(format!("Invalid packet: {err}")));
+                continue;
+            }
+        };
+
+        let component = Components::from_header(&packet.header);
+        let name = component_name(&component);
+
+        let mut output = format!(
+            "Direction: {}\nComponent: {}\nType: {:?}",
+            capture.direction, name, packet.header.ty
+        );
+        if !append_packet_decoded(&packet, &mut output) {
+            report
+                .failures
+                .push(fail(format!("{name} content was malformed")));
+            continue;
+        }
+        report.outputs.push(output);
+
+        if capture.direction == DIRECTION_CLIENT
+            && packet.header.ty == PacketType::Request
+            && handled.binary_search(&name).is_err()
+        {
+            report.unhandled.push(fail(name));
+        }
+    }
+
+    report
+}
+
+/// Decodes the provided hex string into bytes returning None if
+/// the string isn't valid hex
+///
+/// `value` The hex string
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    let value = value.trim();
+    if value.len() % 2 != 0 || !value.is_ascii() {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&value[index..index + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_hex, run};
+    use crate::{
+        game::models::FetchExtendedData,
+        utils::components::{Components, UserSessions, Util},
+    };
+    use blaze_pk::packet::Packet;
+
+    /// Encodes the provided packet as a capture line
+    async fn capture_line(direction: &str, packet: Packet) -> String {
+        let mut buffer = Vec::new();
+        packet.write_async(&mut buffer).await.unwrap();
+        let hex: String = buffer.iter().map(|byte| format!("{:02X}", byte)).collect();
+        format!(r#"{{"direction": "{direction}", "packet": "{hex}"}}"#)
+    }
+
+    #[test]
+    fn test_decode_hex() {
+        assert_eq!(decode_hex("00FFa1"), Some(vec![0x00, 0xFF, 0xA1]));
+        assert_eq!(decode_hex("0"), None);
+        assert_eq!(decode_hex("ZZ"), None);
+    }
+
+    /// Tests that every line of a small capture decodes and produces
+    /// output when replayed from a file
+    #[tokio::test]
+    async fn test_replay() {
+        let lines = [
+            capture_line(
+                "client",
+                Packet::request_empty(1, Components::Util(Util::Ping)),
+            )
+            .await,
+            capture_line(
+                "server",
+                Packet::notify(
+                    Components::UserSessions(UserSessions::FetchExtendedData),
+                    FetchExtendedData { player_id: 10 },
+                ),
+            )
+            .await,
+        ];
+
+        let path = std::env::temp_dir().join(format!("pr-replay-{}.jsonl", std::process::id()));
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        let report = run(&path).await.unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(report.total, 2);
+        assert!(report.failures.is_empty());
+        assert!(report.unhandled.is_empty());
+        assert_eq!(report.outputs.len(), 2);
+        assert!(report
+            .outputs
+            .iter()
+            .all(|output| output.contains("Content: ")));
+        assert!(report.outputs[1].contains("FetchExtendedData"));
+    }
+}
@@ -1,5 +1,8 @@
+use super::RouteTable;
 use crate::{
+    retriever::RetrieverError,
     servers::main::{
+        config::SessionConfig,
         models::{
             auth::*,
             errors::{ServerError, ServerResult},
@@ -11,14 +14,12 @@ use crate::{
         components::{Authentication as A, Components as C},
         env,
         hashing::{hash_password, verify_password},
+        retry::retry_db,
         types::PlayerID,
         validate::is_email,
     },
 };
-use blaze_pk::{
-    packet::{Request, Response},
-    router::Router,
-};
+use blaze_pk::packet::{Request, Response};
 use database::{DatabaseConnection, Player};
 use log::{debug, error, warn};
 use std::borrow::Cow;
@@ -26,32 +27,58 @@ use std::path::Path;
 use tokio::fs::read_to_string;
 
 /// Routing function for adding all the routes in this file to the
-/// provided router
+/// provided route table
 ///
-/// `router` The router to add to
-pub fn route(router: &mut Router<C, Session>) {
-    router.route(C::Authentication(A::Logout), handle_logout);
-    router.route(C::Authentication(A::SilentLogin), handle_auth_request);
-    router.route(C::Authentication(A::OriginLogin), handle_auth_request);
-    router.route(C::Authentication(A::Login), handle_auth_request);
-    router.route(C::Authentication(A::LoginPersona), handle_login_persona);
-    router.route(
+/// `table` The route table to add to
+pub fn route(table: &mut RouteTable) {
+    route!(table, C::Authentication(A::Logout), handle_logout);
+    route!(
+        table,
+        C::Authentication(A::SilentLogin),
+        handle_auth_request
+    );
+    route!(
+        table,
+        C::Authentication(A::OriginLogin),
+        handle_auth_request
+    );
+    route!(table, C::Authentication(A::Login), handle_auth_request);
+    route!(
+        table,
+        C::Authentication(A::LoginPersona),
+        handle_login_persona
+    );
+    route!(
+        table,
         C::Authentication(A::ListUserEntitlements2),
         handle_list_entitlements,
     );
-    router.route(C::Authentication(A::CreateAccount), handle_create_account);
-    router.route(C::Authentication(A::PasswordForgot), handle_forgot_password);
-    router.route(
+    route!(
+        table,
+        C::Authentication(A::CreateAccount),
+        handle_create_account
+    );
+    route!(
+        table,
+        C::Authentication(A::PasswordForgot),
+        handle_forgot_password
+    );
+    route!(
+        table,
         C::Authentication(A::GetLegalDocsInfo),
         handle_get_legal_docs_info,
     );
-    router.route(C::Authentication(A::GetTermsOfServiceConent), || {
+    route!(table, C::Authentication(A::GetTermsOfServiceConent), || {
         handle_legal_content(LegalType::TermsOfService)
     });
-    router.route(C::Authentication(A::GetPrivacyPolicyContent), || {
+    route!(table, C::Authentication(A::GetPrivacyPolicyContent), || {
         handle_legal_content(LegalType::PrivacyPolicy)
     });
-    router.route(C::Authentication(A::GetAuthToken), handle_get_auth_token);
+    route!(
+        table,
+        C::Authentication(A::GetAuthToken),
+        handle_get_auth_token
+    );
 }
 
 /// This route handles all the different authentication types, Silent, Origin,
@@ -110,7 +137,10 @@ async fn handle_auth_request(
     let silent = req.is_silent();
     let db = GlobalState::database();
     let player: Player = match &req.req {
-        AuthRequest::Silent { token, player_id } => handle_login_token(db, token, *player_id).await,
+        AuthRequest::Silent { token, player_id } => {
+            check_token_login(&session.config)?;
+            handle_login_token(db, token, *player_id).await
+        }
         AuthRequest::Login { email, password } => handle_login_email(db, email, password).await,
         AuthRequest::Origin { token } => handle_login_origin(db, token).await,
     }?;
@@ -126,6 +156,18 @@ async fn handle_auth_request(
     Ok(req.response(res))
 }
 
+/// Checks whether logging in using a session token is allowed. When silent
+/// login is disabled all tokens are treated as invalid so that clients must
+/// login with their credentials for every session
+///
+/// `config` The configuration of the session logging in
+pub fn check_token_login(config: &SessionConfig) -> ServerResult<()> {
+    if !config.silent_login {
+        return Err(ServerError::InvalidSession);
+    }
+    Ok(())
+}
+
 /// Handles finding a player through an authentication token and a player ID
 /// returning the player if found
 ///
@@ -137,7 +179,7 @@ async fn handle_login_token(
     token: &str,
     player_id: PlayerID,
 ) -> ServerResult<Player> {
-    Player::by_id_with_token(db, player_id, token)
+    retry_db(|| Player::by_id_with_token(db, player_id, token))
         .await
         .map_err(|_| ServerError::ServerUnavailable)?
         .ok_or(ServerError::InvalidSession)
@@ -160,7 +202,7 @@ async fn handle_login_email(
     }
 
     // Find a non origin player with that email
-    let player: Player = Player::by_email(db, email, false)
+    let player: Player = retry_db(|| Player::by_email(db, email, false))
         .await
         .map_err(|_| ServerError::ServerUnavailable)?
         .ok_or(ServerError::EmailNotFound)?;
@@ -198,13 +240,23 @@ async fn handle_login_origin(db: &'static DatabaseConnection, token: &str) -> Se
     };
 
     // Authenticate with the official servers
-    let Some(details) = flow.authenticate(token.to_string()).await else {
-        error!("Unable to authenticate Origin: Failed to retrieve details from official server");
-        return Err(ServerError::ServerUnavailable);
+    let details = match flow.authenticate(token.to_string()).await {
+        Ok(value) => value,
+        // The official server responded with an error so the token is not valid
+        Err(RetrieverError::Packet(_)) => {
+            warn!("Unable to authenticate Origin: Token was rejected by official server");
+            return Err(ServerError::InvalidSession);
+        }
+        Err(_) => {
+            error!(
+                "Unable to authenticate Origin: Failed to retrieve details from official server"
+            );
+            return Err(ServerError::ServerUnavailable);
+        }
     };
 
     // Lookup the player details to see if the player exists
-    let player: Option<Player> = Player::by_email(db, &details.email, true)
+    let player: Option<Player> = retry_db(|| Player::by_email(db, &details.email, true))
         .await
         .map_err(|_| ServerError::ServerUnavailable)?;
 
@@ -248,7 +300,7 @@ async fn handle_login_origin(db: &'static DatabaseConnection, token: &str) -> Se
 /// Content: {}
 /// ```
 async fn handle_logout(session: &mut Session) {
-    session.player = None;
+    session.clear_player();
 }
 
 /// Handles list user entitlements 2 responses requests which contains information
@@ -274,60 +326,14 @@ async fn handle_logout(session: &mut Session) {
 /// }
 /// ```
 async fn handle_list_entitlements(
+    session: &mut Session,
     req: ListEntitlementsRequest,
 ) -> Option<ListEntitlementsResponse> {
     let tag: String = req.tag;
     if !tag.is_empty() {
         return None;
     }
-    // Skip formatting these entitlement creations
-    #[rustfmt::skip]
-    let list = vec![
-        // Project 10 = $10 Multiplayer Pass (Entitlement Required For Online Access)
-        Entitlement::new_pc(0xec5090c43f,"303107",2,"DR:229644400","PROJECT10_CODE_CONSUMED",1),
-        Entitlement::new_pc(0xec3e4d793f,"304141",2,"DR:230773600","PROJECT10_CODE_CONSUMED_LE1",1),
-        Entitlement::new_pc(0xec3e4d793f,"304141",2,"DR:230773600","PROJECT10_CODE_CONSUMED_LE1",1),
-        // Jeeze so many online pass entitlements
-        Entitlement::new_pc(0xec50b255ff,"300241",2,"OFB-MASS:44370","ONLINE_ACCESS",1),
-        Entitlement::new_pc(0xec50a620ff,"300241",2,"OFB-MASS:49465","ONLINE_ACCESS",1),
-        Entitlement::new_pc(0xec508db6ff,"303107",2,"DR:229644400","ONLINE_ACCESS",1),
-        Entitlement::new_pc(0xec3e5393bf,"300241",2,"OFB-EAST:46112","ONLINE_ACCESS",1),
-        Entitlement::new_pc(0xec3e50867f,"304141",2,"DR:230773600","ONLINE_ACCESS",1),
-        Entitlement::new_gen(0xec4495bfff,"303107", 0,"","ONLINE_ACCESS_GAW_PC",1),
-        Entitlement::new_gen(0xea234c3e7f,"303107", 2,"","ONLINE_ACCESS_GAW_XBL2",1),
-        // Singleplayer DLC
-        Entitlement::new_pc(0xec3e62d5ff,"300241",2,"OFB-MASS:51074","ME3_PRC_EXTENDEDCUT",5),
-        Entitlement::new_pc(0xec50b5633f,"300241",2,"OFB-MASS:44370","ME3_PRC_PROTHEAN",5),
-        Entitlement::new_pc(0xec3e56a0ff,"300241",2,"OFB-EAST:46112","ME3_PRC_PROTHEAN",5),
-        Entitlement::new_pc(0xec50b8707f,"300241",2,"OFB-MASS:52001","ME3_PRC_LEVIATHAN",5),
-        Entitlement::new_pc(0xec50ac3b7f,"300241",2,"OFB-MASS:55146","ME3_PRC_OMEGA",5),
-        Entitlement::new_pc(0xec5093d17f,"300241",2,"OFB-EAST:58040","MET_BONUS_CONTENT_DW",5),
-        Entitlement::new_pc(0xec50af48bf,"300241",2,"OFB-EAST:57550","ME3_PRC_CITADEL",5),
-        // Singleplayer Packs
-        Entitlement::new_pc(0xec50bb7dbf,"300241",2,"OFB-MASS:56984","ME3_MTX_APP01",5),
-        Entitlement::new_pc(0xec5099ebff,"300241",2,"OFB-MASS:49032","ME3_MTX_GUN01",5),
-        Entitlement::new_pc(0xec50c1983f,"300241",2,"OFB-MASS:55147","ME3_MTX_GUN02",5),
-        // Multiplayer DLC
-        Entitlement::new_pc(0xec50a0067f,"300241",2,"OFB-MASS:47872","ME3_PRC_RESURGENCE",5),
-        Entitlement::new_pc(0xec50a92e3f,"300241",2,"OFB-MASS:49465","ME3_PRC_REBELLION",5),
-        Entitlement::new_pc(0xec5096debf,"300241",2,"OFB-MASS:51073","ME3_PRC_EARTH",5),
-        Entitlement::new_pc(0xec509cf93f,"300241",2,"OFB-MASS:52000","ME3_PRC_GOBIG",5),
-        Entitlement::new_pc(0xec50a313bf,"300241",2,"OFB-MASS:59712","ME3_PRC_MP5",5),
-        // Other
-        Entitlement::new_pc(0xec81ae023f,"300241",2,"OFB-MASS:46111","ME3_PRO_M90_INDRA",5),
-        Entitlement::new_pc(0xec81aaf4ff,"300241",2,"OFB-MASS:46110","ME3_PRO_AT12_RAIDER_PACK",5),
-        Entitlement::new_pc(0xec81a7e7bf,"300241",2,"OFB-MASS:46033","ME3_PRO_M55_ARGUS",5),
-        Entitlement::new_pc(0xec81a4da7f,"300241",2,"OFB-MASS:46032","ME3_PRO_N7_WARFARE_PACK",5),
-        Entitlement::new_pc(0xec81a1cd3f,"300241",2,"OFB-MASS:46489","ME3_PRO_N7_WARFARE_PACK",5),
-
-        
-        // Collectors Edition
-        Entitlement::new_pc(0xec3e5fc8bf,"300241",2,"OFB-MASS:46484","ME3_MTX_COLLECTORS_EDITION",5),
-        Entitlement::new_pc(0xec3e5cbb7f,"300241",2,"OFB-MASS:46483","ME3_MTX_DIGITAL_ART_BOOKS",5),
-        Entitlement::new_gen(0xec3e59ae3f,"300241",2,"OFB-MASS:46482","ME3_MTX_SOUNDTRACK",5),
-        // Darkhorse Redeem Code (Character boosters and Collector Assault Rifle)
-        Entitlement::new_pc(0xec50be8aff,"300241",2,"OFB-MASS:61524","ME3_PRC_DARKHORSECOMIC",5),
-    ];
+    let list = session.config.entitlements.clone();
     Some(ListEntitlementsResponse { list })
 }
 
@@ -443,6 +449,9 @@ async fn handle_create_account(
 
     // Create a default display name from the first 99 chars of the email
     let display_name: String = email.chars().take(99).collect::<String>();
+    if GlobalState::blocked_names().is_blocked(&display_name) {
+        return Err(ServerError::InvalidInformation);
+    }
 
     // Create a new player
     let player: Player =
@@ -562,3 +571,75 @@ async fn handle_get_auth_token(session: &mut Session) -> ServerResult<GetTokenRe
         .ok_or(ServerError::FailedNoLoginAction)?;
     Ok(GetTokenResponse { token })
 }
+
+#[cfg(test)]
+mod test {
+    use super::{check_token_login, handle_login_email};
+    use crate::{
+        servers::main::{config::SessionConfig, models::errors::ServerError},
+        utils::{hashing::hash_password, logging::capture},
+    };
+    use database::{DatabaseType, Player, PoolOptions, TransactionTrait};
+    use std::time::Duration;
+    use tokio::time::sleep;
+
+    /// Tests that tokens are rejected regardless of whether they are
+    /// valid when silent login is disabled
+    #[test]
+    fn test_silent_login_disabled() {
+        assert!(check_token_login(&SessionConfig::default()).is_ok());
+
+        let config = SessionConfig {
+            silent_login: false,
+            ..Default::default()
+        };
+        let result = check_token_login(&config);
+        assert!(matches!(result, Err(ServerError::InvalidSession)));
+    }
+
+    /// Tests that logging in still succeeds when the first attempt to
+    /// find the player fails because no database connection is available
+    #[tokio::test]
+    async fn test_login_after_transient_failure() {
+        capture::install();
+
+        let path = std::env::temp_dir().join(format!("pr-auth-retry-{}.db", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let db = database::connect(
+            DatabaseType::Sqlite(path.display().to_string()),
+            PoolOptions {
+                max_connections: Some(1),
+                min_connections: None,
+                acquire_timeout: Some(Duration::from_millis(200)),
+            },
+        )
+        .await;
+        let password = hash_password("password").unwrap();
+        Player::create(
+            &db,
+            "test@test.com".to_string(),
+            "Test".to_string(),
+            password,
+            false,
+        )
+        .await
+        .unwrap();
+
+        // Hold the only pooled connection until the first attempt has timed out
+        let transaction = db.begin().await.unwrap();
+        let release = async {
+            sleep(Duration::from_millis(250)).await;
+            transaction.rollback().await.unwrap();
+        };
+        let (result, _) = tokio::join!(
+            handle_login_email(&db, "test@test.com", "password"),
+            release
+        );
+
+        std::fs::remove_file(&path).ok();
+        assert_eq!(result.unwrap().email, "test@test.com");
+        assert!(capture::entries("pocket_relay::utils::retry")
+            .iter()
+            .any(|entry| entry.contains("ConnectionAcquire")));
+    }
+}
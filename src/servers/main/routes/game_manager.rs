@@ -1,5 +1,6 @@
+use super::RouteTable;
 use crate::{
-    game::{player::GamePlayer, GameModifyAction, RemovePlayerType},
+    game::{models::AdminListOperation, player::GamePlayer, GameModifyAction, RemovePlayerType},
     servers::main::{
         models::{
             errors::{ServerError, ServerResult},
@@ -13,29 +14,50 @@ use crate::{
         types::GameID,
     },
 };
-use blaze_pk::router::Router;
 use log::info;
 
 /// Routing function for adding all the routes in this file to the
-/// provided router
+/// provided route table
 ///
-/// `router` The router to add to
-pub fn route(router: &mut Router<C, Session>) {
-    router.route(C::GameManager(G::CreateGame), handle_create_game);
-    router.route(C::GameManager(G::AdvanceGameState), handle_game_modify);
-    router.route(C::GameManager(G::SetGameSettings), handle_game_modify);
-    router.route(C::GameManager(G::SetGameAttributes), handle_game_modify);
-    router.route(C::GameManager(G::RemovePlayer), handle_remove_player);
-    router.route(C::GameManager(G::RemovePlayer), handle_remove_player);
-    router.route(
+/// `table` The route table to add to
+pub fn route(table: &mut RouteTable) {
+    route!(table, C::GameManager(G::CreateGame), handle_create_game);
+    route!(table, C::GameManager(G::JoinGame), handle_join_game);
+    route!(
+        table,
+        C::GameManager(G::AdvanceGameState),
+        handle_game_modify
+    );
+    route!(
+        table,
+        C::GameManager(G::SetGameSettings),
+        handle_game_modify
+    );
+    route!(
+        table,
+        C::GameManager(G::SetGameAttributes),
+        handle_game_modify
+    );
+    route!(table, C::GameManager(G::RemovePlayer), handle_remove_player);
+    route!(table, C::GameManager(G::RemovePlayer), handle_remove_player);
+    route!(table, C::GameManager(G::AddAdminPlayer), handle_add_admin);
+    route!(
+        table,
+        C::GameManager(G::RemoveAdminPlayer),
+        handle_remove_admin
+    );
+    route!(
+        table,
         C::GameManager(G::UpdateMeshConnection),
         handle_update_mesh_connection,
     );
-    router.route(
+    route!(
+        table,
         C::GameManager(G::StartMatchmaking),
         handle_start_matchmaking,
     );
-    router.route(
+    route!(
+        table,
         C::GameManager(G::CancelMatchmaking),
         handle_cancel_matchmaking,
     );
@@ -101,10 +123,47 @@ async fn handle_create_game(
         .ok_or(ServerError::FailedNoLoginAction)?;
 
     let games = GlobalState::games();
-    let game_id: GameID = games.create_game(req.attributes, req.setting, player).await;
+    // New games can't be created while the server is draining or
+    // when there are no game IDs left
+    let game_id: GameID = games
+        .create_game(req.attributes, req.setting, player)
+        .await
+        .map_err(|_| ServerError::ServerUnavailable)?;
     Ok(CreateGameResponse { game_id })
 }
 
+/// Handles joining the game with the provided ID. An error is
+/// responded with if the game no longer exists or can't be joined
+///
+/// ```
+/// Route: GameManager(JoinGame)
+/// ID: 58
+/// Content: {
+///     "GID": 1
+/// }
+/// ```
+async fn handle_join_game(
+    session: &mut Session,
+    req: JoinGameRequest,
+) -> ServerResult<JoinGameResponse> {
+    let player: GamePlayer = session
+        .try_into_player()
+        .ok_or(ServerError::FailedNoLoginAction)?;
+
+    info!(
+        "Player {} requested to join game (GID: {})",
+        player.player.display_name, req.game_id
+    );
+
+    if !GlobalState::games().join_game(player, req.game_id).await {
+        return Err(ServerError::GameNotJoinable);
+    }
+
+    Ok(JoinGameResponse {
+        game_id: req.game_id,
+    })
+}
+
 /// Handles changing the state of the game with the provided ID
 ///
 /// ```
@@ -146,9 +205,20 @@ async fn handle_create_game(
 ///     "GID": 1
 /// }
 /// ```
-async fn handle_game_modify(req: GameModifyRequest) {
+async fn handle_game_modify(session: &mut Session, req: GameModifyRequest) -> ServerResult<()> {
+    let player_id = session
+        .player
+        .as_ref()
+        .map(|player| player.id)
+        .ok_or(ServerError::FailedNoLoginAction)?;
     let games = GlobalState::games();
-    games.modify_game(req.game_id, req.action);
+    let allowed = games
+        .modify_game_as(req.game_id, player_id, req.action)
+        .await;
+    if allowed == Some(false) {
+        return Err(ServerError::PermissionDenied);
+    }
+    Ok(())
 }
 
 /// Handles removing a player from a game
@@ -164,12 +234,86 @@ async fn handle_game_modify(req: GameModifyRequest) {
 ///     "REAS": 6
 /// }
 /// ```
-async fn handle_remove_player(req: RemovePlayerRequest) {
+async fn handle_remove_player(session: &mut Session, req: RemovePlayerRequest) -> ServerResult<()> {
+    let player_id = session
+        .player
+        .as_ref()
+        .map(|player| player.id)
+        .ok_or(ServerError::FailedNoLoginAction)?;
     let games = GlobalState::games();
-    games.remove_player(
-        req.game_id,
-        RemovePlayerType::Player(req.player_id, req.reason),
-    );
+    let ty = RemovePlayerType::Player(req.player_id, req.reason);
+
+    // Players are always allowed to remove themselves
+    if req.player_id == player_id {
+        games.remove_player(req.game_id, ty);
+        return Ok(());
+    }
+
+    let allowed = games.remove_player_as(req.game_id, player_id, ty).await;
+    if allowed == Some(false) {
+        return Err(ServerError::PermissionDenied);
+    }
+    Ok(())
+}
+
+/// Handles adding a player to the admin list of a game. Only the host
+/// and existing admins are allowed to add admins when admins are enforced
+///
+/// ```
+/// Route: GameManager(AddAdminPlayer)
+/// ID: 20
+/// Content: {
+///     "GID": 1,
+///     "PID": 2
+/// }
+/// ```
+async fn handle_add_admin(session: &mut Session, req: AdminPlayerRequest) -> ServerResult<()> {
+    modify_admin_list(session, req, AdminListOperation::Add).await
+}
+
+/// Handles removing a player from the admin list of a game. Only the
+/// host and existing admins are allowed to remove admins when admins
+/// are enforced
+///
+/// ```
+/// Route: GameManager(RemoveAdminPlayer)
+/// ID: 21
+/// Content: {
+///     "GID": 1,
+///     "PID": 2
+/// }
+/// ```
+async fn handle_remove_admin(session: &mut Session, req: AdminPlayerRequest) -> ServerResult<()> {
+    modify_admin_list(session, req, AdminListOperation::Remove).await
+}
+
+/// Applies the provided admin list operation to the game on behalf of
+/// the player of the provided session
+///
+/// `session`   The session modifying the admin list
+/// `req`       The admin player request
+/// `operation` The operation to apply
+async fn modify_admin_list(
+    session: &Session,
+    req: AdminPlayerRequest,
+    operation: AdminListOperation,
+) -> ServerResult<()> {
+    let player_id = session
+        .player
+        .as_ref()
+        .map(|player| player.id)
+        .ok_or(ServerError::FailedNoLoginAction)?;
+    let allowed = GlobalState::games()
+        .modify_game_as(
+            req.game_id,
+            player_id,
+            GameModifyAction::ModifyAdminList(req.player_id, operation),
+        )
+        .await;
+    if allowed == Some(false) {
+        return Err(ServerError::PermissionDenied);
+    }
+    Ok(())
 }
 
 /// Handles updating mesh connections
@@ -355,3 +499,89 @@ async fn handle_start_matchmaking(
 async fn handle_cancel_matchmaking(session: &mut Session) {
     session.remove_games();
 }
+
+#[cfg(test)]
+mod test {
+    use super::handle_remove_player;
+    use crate::{
+        game::{models::RemoveReason, player::GamePlayer},
+        servers::main::{
+            models::{errors::ServerError, game_manager::RemovePlayerRequest},
+            session::{Session, SessionAddr, SessionMessage},
+        },
+        state::GlobalState,
+        utils::models::NetData,
+    };
+    use blaze_pk::types::TdfMap;
+    use database::Player;
+    use tokio::sync::mpsc;
+
+    /// Creates a player with the provided ID for testing
+    fn test_player(id: u32) -> Player {
+        Player {
+            id,
+            email: format!("test{id}@test.com"),
+            display_name: format!("Test{id}"),
+            session_token: None,
+            origin: false,
+            password: String::new(),
+            created_at: None,
+        }
+    }
+
+    /// Creates a game player with the provided ID for testing
+    fn game_player(id: u32) -> (GamePlayer, mpsc::UnboundedReceiver<SessionMessage>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let player = GamePlayer::new(
+            test_player(id),
+            NetData::default(),
+            SessionAddr::new(id, sender),
+            Default::default(),
+        );
+        (player, receiver)
+    }
+
+    /// Tests that only players within a game are allowed to remove
+    /// other players from it
+    #[tokio::test]
+    async fn test_remove_player_permission() {
+        GlobalState::init_test().await;
+        let games = GlobalState::games();
+
+        let (host, _host_session) = game_player(1);
+        let game_id = games.create_game(TdfMap::default(), 0, host).await.unwrap();
+        let (player, _player_session) = game_player(2);
+        assert!(games.join_game(player, game_id).await);
+
+        // Players outside the game are rejected
+        let (mut session, _receiver, _client) = Session::new_test(3).await;
+        session.player = Some(test_player(3));
+        let result = handle_remove_player(
+            &mut session,
+            RemovePlayerRequest {
+                game_id,
+                player_id: 2,
+                reason: RemoveReason::Kick,
+            },
+        )
+        .await;
+        assert!(matches!(result, Err(ServerError::PermissionDenied)));
+        let snapshot = games.snapshot_id(game_id).await.unwrap();
+        assert_eq!(snapshot.players.len(), 2);
+
+        // The host is allowed to kick players
+        session.player = Some(test_player(1));
+        let result = handle_remove_player(
+            &mut session,
+            RemovePlayerRequest {
+                game_id,
+                player_id: 2,
+                reason: RemoveReason::Kick,
+            },
+        )
+        .await;
+        assert!(result.is_ok());
+        let snapshot = games.snapshot_id(game_id).await.unwrap();
+        assert_eq!(snapshot.players.len(), 1);
+    }
+}
@@ -1,18 +1,24 @@
+use super::RouteTable;
 use crate::{
     servers::main::{models::messaging::*, session::Session},
+    state::GlobalState,
     utils::{
         components::{Components as C, Messaging as M},
         constants, env,
     },
 };
-use blaze_pk::{packet::Packet, router::Router};
+use blaze_pk::packet::Packet;
+use chrono::Local;
+use log::warn;
+use std::{path::Path, sync::Mutex, time::SystemTime};
+use tokio::fs;
 
 /// Routing function for adding all the routes in this file to the
-/// provided router
+/// provided route table
 ///
-/// `router` The router to add to
-pub fn route(router: &mut Router<C, Session>) {
-    router.route(C::Messaging(M::FetchMessages), handle_fetch_messages);
+/// `table` The route table to add to
+pub fn route(table: &mut RouteTable) {
+    route!(table, C::Messaging(M::FetchMessages), handle_fetch_messages);
 }
 
 /// Handles requests from the client to fetch the server messages. The initial response contains
@@ -40,7 +46,8 @@ async fn handle_fetch_messages(session: &mut Session) -> FetchMessageResponse {
         // Not authenticated return empty count
         return  FetchMessageResponse { count: 0 };
     };
-    let message = get_menu_message(session, &player.display_name);
+    let ip = session.socket_addr.to_string();
+    let message = get_menu_message(ip, &player.display_name).await;
     let notify = Packet::notify(
         C::Messaging(M::SendMessage),
         MessageNotify {
@@ -53,6 +60,79 @@ async fn handle_fetch_messages(session: &mut Session) -> FetchMessageResponse {
     FetchMessageResponse { count: 1 }
 }
 
+/// Values that can be substituted into the menu message
+struct MenuVariables<'a> {
+    /// The display name of the player
+    player_name: &'a str,
+    /// The IP address of the session
+    ip: String,
+    /// The number of connected sessions
+    online: usize,
+    /// The number of active games
+    games: usize,
+    /// The current date
+    date: String,
+}
+
+/// Cache for the contents of the menu message file which is only
+/// read again when the file is modified
+struct MenuMessageFile {
+    /// The modified time and length of the file along with the
+    /// contents that were read
+    cached: Mutex<Option<(SystemTime, u64, String)>>,
+}
+
+/// The cached menu message file
+static MENU_MESSAGE_FILE: MenuMessageFile = MenuMessageFile::new();
+
+impl MenuMessageFile {
+    /// Creates a new empty menu message file cache
+    const fn new() -> Self {
+        Self {
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Loads the contents of the file at the provided path using the
+    /// cached contents unless the file has been modified since it was
+    /// last read
+    ///
+    /// `path` The path of the menu message file
+    async fn load(&self, path: &Path) -> std::io::Result<String> {
+        let metadata = fs::metadata(path).await?;
+        let modified = metadata.modified()?;
+        let length = metadata.len();
+
+        if let Ok(cached) = self.cached.lock() {
+            if let Some((cached_modified, cached_length, contents)) = &*cached {
+                if *cached_modified == modified && *cached_length == length {
+                    return Ok(contents.clone());
+                }
+            }
+        }
+
+        let contents = fs::read_to_string(path).await?;
+        let contents = contents.trim_end().to_string();
+        if let Ok(mut cached) = self.cached.lock() {
+            *cached = Some((modified, length, contents.clone()));
+        }
+        Ok(contents)
+    }
+}
+
+/// Retrieves the menu message from the menu message file if one is
+/// set otherwise from the environment variables
+async fn load_menu_message() -> String {
+    let file = env::env(env::MENU_MESSAGE_FILE);
+    if !file.is_empty() {
+        match MENU_MESSAGE_FILE.load(Path::new(&file)).await {
+            Ok(message) => return message,
+            Err(err) => warn!("Unable to read menu message file ({}): {}", file, err),
+        }
+    }
+    env::env(env::MENU_MESSAGE)
+}
+
 /// Retrieves the menu message from the environment variables and replaces
 /// any variables inside the message with the correct values for this session
 ///
@@ -60,18 +140,101 @@ async fn handle_fetch_messages(session: &mut Session) -> FetchMessageResponse {
 /// - {v} = Server Version
 /// - {n} = Player Display Name
 /// - {ip} = Session IP Address
-fn get_menu_message(session: &Session, player_name: &str) -> String {
-    let mut message = env::env(env::MENU_MESSAGE);
+/// - {online} = Number of connected sessions
+/// - {games} = Number of active games
+/// - {date} = The current date (YYYY-MM-DD)
+///
+/// `ip`          The IP address of the session
+/// `player_name` The display name of the player
+async fn get_menu_message(ip: String, player_name: &str) -> String {
+    let message = load_menu_message().await;
+    let variables = MenuVariables {
+        player_name,
+        ip,
+        online: GlobalState::sessions().count(),
+        games: GlobalState::games().count().await,
+        date: Local::now().format("%Y-%m-%d").to_string(),
+    };
+    let mut message = replace_menu_variables(message, &variables);
+    // Line terminator for the end of the message
+    message.push(char::from(0x0A));
+    message
+}
+
+/// Replaces the variables in the provided message with the values
+/// from the provided variables. Escaped new lines (\n) are replaced
+/// with actual new lines to allow multi-line messages from the
+/// environment variables
+///
+/// `message`   The message to replace the variables in
+/// `variables` The variable values
+fn replace_menu_variables(mut message: String, variables: &MenuVariables) -> String {
+    if message.contains("\\n") {
+        message = message.replace("\\n", "\n");
+    }
     if message.contains("{v}") {
         message = message.replace("{v}", constants::VERSION);
     }
     if message.contains("{n}") {
-        message = message.replace("{n}", player_name);
+        message = message.replace("{n}", variables.player_name);
     }
     if message.contains("{ip}") {
-        message = message.replace("{ip}", &session.socket_addr.to_string());
+        message = message.replace("{ip}", &variables.ip);
+    }
+    if message.contains("{online}") {
+        message = message.replace("{online}", &variables.online.to_string());
+    }
+    if message.contains("{games}") {
+        message = message.replace("{games}", &variables.games.to_string());
+    }
+    if message.contains("{date}") {
+        message = message.replace("{date}", &variables.date);
     }
-    // Line terminator for the end of the message
-    message.push(char::from(0x0A));
     message
 }
+
+#[cfg(test)]
+mod test {
+    use super::{replace_menu_variables, MenuMessageFile, MenuVariables};
+
+    #[test]
+    fn test_menu_variables() {
+        let variables = MenuVariables {
+            player_name: "Test",
+            ip: "127.0.0.1:3659".to_string(),
+            online: 12,
+            games: 3,
+            date: "2023-01-20".to_string(),
+        };
+
+        let message = replace_menu_variables(
+            "{n} {ip} Online: {online} Games: {games} Date: {date}".to_string(),
+            &variables,
+        );
+        assert_eq!(
+            message,
+            "Test 127.0.0.1:3659 Online: 12 Games: 3 Date: 2023-01-20"
+        );
+
+        let message = replace_menu_variables("Line 1\\nLine 2".to_string(), &variables);
+        assert_eq!(message, "Line 1\nLine 2");
+    }
+
+    #[tokio::test]
+    async fn test_menu_message_file() {
+        let path = std::env::temp_dir().join(format!("pr-menu-{}.txt", std::process::id()));
+        let file = MenuMessageFile::new();
+
+        std::fs::write(&path, "First message\n").unwrap();
+        assert_eq!(file.load(&path).await.unwrap(), "First message");
+
+        // Changes to the file are reflected on the next load. The new
+        // contents have a different length so the change is detected even
+        // when the modified time hasn't changed
+        std::fs::write(&path, "The second message").unwrap();
+        assert_eq!(file.load(&path).await.unwrap(), "The second message");
+
+        std::fs::remove_file(&path).ok();
+        assert!(file.load(&path).await.is_err());
+    }
+}
@@ -2,7 +2,17 @@ use blaze_pk::router::Router;
 
 use crate::utils::components::Components;
 
-use super::session::Session;
+use super::{debug::component_name, session::Session};
+
+/// Adds the provided handler to the router of the provided route
+/// table and records the component as handled
+macro_rules! route {
+    ($table:expr, $component:expr, $handler:expr) => {{
+        let component = $component;
+        $table.handled.push(component.clone());
+        $table.router.route(component, $handler);
+    }};
+}
 
 mod auth;
 mod game_manager;
@@ -12,15 +22,60 @@ mod stats;
 mod user_sessions;
 mod util;
 
+/// Router for sessions along with the names of the components that
+/// have handlers registered. Packets for any other components fall
+/// through to the missing handler error
+pub struct RouteTable {
+    /// The underlying router
+    router: Router<Components, Session>,
+    /// The components with handlers
+    handled: Vec<Components>,
+}
+
+/// Creates the route table with all the routes added
+fn route_table() -> RouteTable {
+    let mut table = RouteTable {
+        router: Router::new(),
+        handled: Vec::new(),
+    };
+    auth::route(&mut table);
+    game_manager::route(&mut table);
+    stats::route(&mut table);
+    util::route(&mut table);
+    messaging::route(&mut table);
+    user_sessions::route(&mut table);
+    other::route(&mut table);
+    table
+}
+
 /// Function which creates a router for sessions to use
 pub fn router() -> Router<Components, Session> {
-    let mut router = Router::new();
-    auth::route(&mut router);
-    game_manager::route(&mut router);
-    stats::route(&mut router);
-    util::route(&mut router);
-    messaging::route(&mut router);
-    user_sessions::route(&mut router);
-    other::route(&mut router);
-    router
+    route_table().router
+}
+
+/// Obtains the sorted names of all the components that have handlers
+/// registered on the session router
+pub fn handled_components() -> Vec<String> {
+    let mut handled: Vec<String> = route_table().handled.iter().map(component_name).collect();
+    handled.sort();
+    handled.dedup();
+    handled
+}
+
+/// Obtains all the components that have handlers registered on the
+/// session router
+pub fn routed_components() -> Vec<Components> {
+    route_table().handled
+}
+
+#[cfg(test)]
+mod test {
+    use super::handled_components;
+
+    #[test]
+    fn test_handled_components() {
+        let handled = handled_components();
+        assert!(handled.contains(&"UserSessions.ResumeSession".to_string()));
+        assert!(!handled.contains(&"Util.UserSettingsLoad".to_string()));
+    }
 }
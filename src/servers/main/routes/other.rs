@@ -1,19 +1,21 @@
+use super::RouteTable;
 use crate::{
     servers::main::{models::other::*, session::Session},
     utils::components::{AssociationLists as A, Components as C, GameReporting as G},
 };
-use blaze_pk::{packet::Packet, router::Router};
+use blaze_pk::packet::Packet;
 
 /// Routing function for adding all the routes in this file to the
-/// provided router
+/// provided route table
 ///
-/// `router` The router to add to
-pub fn route(router: &mut Router<C, Session>) {
-    router.route(
+/// `table` The route table to add to
+pub fn route(table: &mut RouteTable) {
+    route!(
+        table,
         C::GameReporting(G::SubmitOfflineGameReport),
         handle_submit_offline,
     );
-    router.route(C::AssociationLists(A::GetLists), handle_get_lists);
+    route!(table, C::AssociationLists(A::GetLists), handle_get_lists);
 }
 
 /// Handles submission of offline game reports from clients.
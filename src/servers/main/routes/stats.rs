@@ -1,31 +1,37 @@
+use super::RouteTable;
 use crate::{
     leaderboard::{models::*, LeaderboardQuery},
-    servers::main::{models::stats::*, session::Session},
+    servers::main::models::stats::*,
     state::GlobalState,
     utils::components::{Components as C, Stats as S},
 };
-use blaze_pk::router::Router;
 use log::error;
 
 /// Routing function for adding all the routes in this file to the
-/// provided router
+/// provided route table
 ///
-/// `router` The router to add to
-pub fn route(router: &mut Router<C, Session>) {
-    router.route(
+/// `table` The route table to add to
+pub fn route(table: &mut RouteTable) {
+    route!(
+        table,
         C::Stats(S::GetLeaderboardEntityCount),
         handle_leaderboard_entity_count,
     );
-    router.route(C::Stats(S::GetLeaderboard), |req: LeaderboardRequest| {
-        handle_leaderboard_query(
-            req.name,
-            LeaderboardQuery::Normal {
-                start: req.start,
-                count: req.count,
-            },
-        )
-    });
-    router.route(
+    route!(
+        table,
+        C::Stats(S::GetLeaderboard),
+        |req: LeaderboardRequest| {
+            handle_leaderboard_query(
+                req.name,
+                LeaderboardQuery::Normal {
+                    start: req.start,
+                    count: req.count,
+                },
+            )
+        }
+    );
+    route!(
+        table,
         C::Stats(S::GetCenteredLeaderboard),
         |req: CenteredLeaderboardRequest| {
             handle_leaderboard_query(
@@ -37,13 +43,18 @@ pub fn route(router: &mut Router<C, Session>) {
             )
         },
     );
-    router.route(
+    route!(
+        table,
         C::Stats(S::GetFilteredLeaderboard),
         |req: FilteredLeaderboardRequest| {
             handle_leaderboard_query(req.name, LeaderboardQuery::Filtered { player_id: req.id })
         },
     );
-    router.route(C::Stats(S::GetLeaderboardGroup), handle_leaderboard_group);
+    route!(
+        table,
+        C::Stats(S::GetLeaderboardGroup),
+        handle_leaderboard_group
+    );
 }
 
 /// Handles returning the number of leaderboard objects present.
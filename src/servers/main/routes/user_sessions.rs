@@ -1,3 +1,4 @@
+use super::{auth::check_token_login, RouteTable};
 use crate::{
     servers::main::{
         models::{
@@ -8,23 +9,32 @@ use crate::{
         session::Session,
     },
     state::GlobalState,
-    utils::components::{Components as C, UserSessions as U},
-};
-use blaze_pk::{
-    packet::{Request, Response},
-    router::Router,
+    utils::{
+        components::{Components as C, UserSessions as U},
+        retry::retry_db,
+    },
 };
-use database::Player;
+use blaze_pk::packet::{Request, Response};
+use database::{DatabaseConnection, Player};
 use log::error;
 
 /// Routing function for adding all the routes in this file to the
-/// provided router
+/// provided route table
 ///
-/// `router` The router to add to
-pub fn route(router: &mut Router<C, Session>) {
-    router.route(C::UserSessions(U::ResumeSession), handle_resume_session);
-    router.route(C::UserSessions(U::UpdateNetworkInfo), handle_update_network);
-    router.route(
+/// `table` The route table to add to
+pub fn route(table: &mut RouteTable) {
+    route!(
+        table,
+        C::UserSessions(U::ResumeSession),
+        handle_resume_session
+    );
+    route!(
+        table,
+        C::UserSessions(U::UpdateNetworkInfo),
+        handle_update_network
+    );
+    route!(
+        table,
         C::UserSessions(U::UpdateHardwareFlags),
         handle_update_hardware_flag,
     );
@@ -44,20 +54,11 @@ async fn handle_resume_session(
     session: &mut Session,
     req: Request<ResumeSessionRequest>,
 ) -> ServerResult<Response> {
+    check_token_login(&session.config)?;
     let db = GlobalState::database();
 
     // Find the player that the token is for
-    let player: Player = match Player::by_token(db, &req.session_token).await {
-        // Valid session token
-        Ok(Some(player)) => player,
-        // Session that was attempted to resume is expired
-        Ok(None) => return Err(ServerError::InvalidSession),
-        // Error occurred while looking up token
-        Err(err) => {
-            error!("Error while attempt to resume session: {err:?}");
-            return Err(ServerError::ServerUnavailable);
-        }
-    };
+    let player: Player = find_token_player(db, &req.session_token).await?;
 
     let (player, session_token) = session.set_player(player).await?;
 
@@ -66,8 +67,31 @@ async fn handle_resume_session(
         session_token,
         silent: true,
     };
+    let response = req.response(res);
+
+    // Place the player back into the game they were in if their
+    // previous session was lost while in a game
+    session.rejoin_game();
 
-    Ok(req.response(res))
+    Ok(response)
+}
+
+/// Finds the player that the provided session token belongs to
+///
+/// `db`    The database connection
+/// `token` The session token
+async fn find_token_player(db: &DatabaseConnection, token: &str) -> ServerResult<Player> {
+    match retry_db(|| Player::by_token(db, token)).await {
+        // Valid session token
+        Ok(Some(player)) => Ok(player),
+        // Session that was attempted to resume is expired
+        Ok(None) => Err(ServerError::InvalidSession),
+        // Error occurred while looking up token
+        Err(err) => {
+            error!("Error while attempt to resume session: {err:?}");
+            Err(ServerError::ServerUnavailable)
+        }
+    }
 }
 
 /// Handles updating the stored networking information for the current session
@@ -100,7 +124,7 @@ async fn handle_resume_session(
 /// }
 /// ```
 async fn handle_update_network(session: &mut Session, req: UpdateNetworkRequest) {
-    session.set_network_info(req.address, req.qos);
+    session.set_network_info(req.address, req.qos, req.latency);
 }
 
 /// Handles updating the stored hardware flag with the client provided hardware flag
@@ -115,3 +139,179 @@ async fn handle_update_network(session: &mut Session, req: UpdateNetworkRequest)
 async fn handle_update_hardware_flag(session: &mut Session, req: HardwareFlagRequest) {
     session.set_hardware_flag(req.hardware_flag);
 }
+
+#[cfg(test)]
+mod test {
+    use super::handle_resume_session;
+    use crate::{
+        game::{player::GamePlayer, GameSnapshot},
+        servers::main::{
+            models::{errors::ServerError, user_sessions::ResumeSessionRequest},
+            session::{GameRole, Session, SessionAddr},
+        },
+        state::GlobalState,
+        utils::{
+            components::{Components as C, UserSessions as U},
+            models::NetData,
+            random::generate_random_string,
+            types::GameID,
+        },
+    };
+    use blaze_pk::{
+        packet::{Packet, Request},
+        types::TdfMap,
+    };
+    use database::Player;
+    use std::{collections::BTreeMap, time::Duration};
+    use tokio::{sync::mpsc, time::sleep};
+
+    /// Creates a resume session request for the provided token
+    fn resume_request(session_token: &str) -> Request<ResumeSessionRequest> {
+        Request {
+            req: ResumeSessionRequest {
+                session_token: session_token.to_string(),
+            },
+            header: Packet::request_empty(1, C::UserSessions(U::ResumeSession)).header,
+        }
+    }
+
+    /// Tests that sessions can't be resumed using a token that has
+    /// been replaced by rotating the token
+    #[tokio::test]
+    async fn test_rotated_token() {
+        GlobalState::init_test().await;
+        let db = GlobalState::database();
+
+        let player = Player::create(
+            db,
+            format!("rotate{}@test.com", std::process::id()),
+            "PlayerRotate".to_string(),
+            String::new(),
+            false,
+        )
+        .await
+        .unwrap();
+
+        let (player, old_token) = player.with_token(db, generate_random_string).await.unwrap();
+
+        let (mut session, _receiver, _client) = Session::new_test(7001).await;
+        assert!(
+            handle_resume_session(&mut session, resume_request(&old_token))
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            session.player.as_ref().map(|player| player.id),
+            Some(player.id)
+        );
+
+        let (player, new_token) = player
+            .rotate_token(db, generate_random_string)
+            .await
+            .unwrap();
+        assert_ne!(old_token, new_token);
+
+        let (mut session, _receiver, _client) = Session::new_test(7002).await;
+        let result = handle_resume_session(&mut session, resume_request(&old_token)).await;
+        assert!(matches!(result, Err(ServerError::InvalidSession)));
+        assert!(session.player.is_none());
+
+        assert!(
+            handle_resume_session(&mut session, resume_request(&new_token))
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            session.player.as_ref().map(|player| player.id),
+            Some(player.id)
+        );
+    }
+
+    /// Waits for the game with the provided ID to have the provided
+    /// number of players returning the snapshot of the game
+    async fn wait_players(game_id: GameID, count: usize) -> GameSnapshot {
+        for _ in 0..100 {
+            let snapshot = GlobalState::games().snapshot_id(game_id).await.unwrap();
+            if snapshot.players.len() == count {
+                return snapshot;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+        panic!("Game didn't reach {count} players");
+    }
+
+    /// Tests that a player who loses their session while in a game is
+    /// placed back into the same game after resuming their session and
+    /// sending their networking information
+    #[tokio::test]
+    async fn test_resume_rejoin() {
+        GlobalState::init_test().await;
+        let db = GlobalState::database();
+        let games = GlobalState::games();
+
+        let (sender, _host_receiver) = mpsc::unbounded_channel();
+        let host = GamePlayer::new(
+            Player {
+                id: u32::MAX,
+                email: "rejoinhost@test.com".to_string(),
+                display_name: "RejoinHost".to_string(),
+                session_token: None,
+                origin: false,
+                password: String::new(),
+                created_at: None,
+            },
+            NetData::default(),
+            SessionAddr::new(7101, sender),
+            Default::default(),
+        );
+        let game_id = games.create_game(TdfMap::default(), 0, host).await.unwrap();
+
+        let player = Player::create(
+            db,
+            format!("rejoin{}@test.com", std::process::id()),
+            "PlayerRejoin".to_string(),
+            String::new(),
+            false,
+        )
+        .await
+        .unwrap();
+        let (player, token) = player.with_token(db, generate_random_string).await.unwrap();
+
+        // The player joins the game then loses their session
+        let (mut session, _receiver, _client) = Session::new_test(7102).await;
+        handle_resume_session(&mut session, resume_request(&token))
+            .await
+            .unwrap();
+        session.set_network_info(Default::default(), Default::default(), BTreeMap::new());
+        assert!(
+            games
+                .join_game(session.try_into_player().unwrap(), game_id)
+                .await
+        );
+        session.games.set(game_id, GameRole::Member);
+        wait_players(game_id, 2).await;
+        drop(session);
+        wait_players(game_id, 1).await;
+
+        // The player isn't placed back until the networking information is known
+        let (mut session, _receiver, _client) = Session::new_test(7103).await;
+        handle_resume_session(&mut session, resume_request(&token))
+            .await
+            .unwrap();
+        sleep(Duration::from_millis(50)).await;
+        let snapshot = games.snapshot_id(game_id).await.unwrap();
+        assert_eq!(snapshot.players.len(), 1);
+
+        session.set_network_info(Default::default(), Default::default(), BTreeMap::new());
+        let snapshot = wait_players(game_id, 2).await;
+        let rejoined = snapshot
+            .players
+            .iter()
+            .find(|value| value.player_id == player.id)
+            .expect("Player wasn't placed back into the game");
+        assert_eq!(rejoined.session_id, 7103);
+        assert!(rejoined.net.is_set);
+
+        games.close_game(game_id).await;
+    }
+}
@@ -1,3 +1,4 @@
+use super::RouteTable;
 use crate::{
     servers::main::{
         models::{
@@ -17,10 +18,10 @@ use crate::{
 };
 
 use base64ct::{Base64, Encoding};
-use blaze_pk::{router::Router, types::TdfMap};
-use database::PlayerData;
+use blaze_pk::types::TdfMap;
+use database::{DatabaseConnection, Player, PlayerData};
 use flate2::{write::ZlibEncoder, Compression};
-use log::{error, warn};
+use log::{debug, error, warn};
 use rust_embed::RustEmbed;
 use std::{
     io::Write,
@@ -31,19 +32,31 @@ use std::{
 use tokio::fs::read;
 
 /// Routing function for adding all the routes in this file to the
-/// provided router
+/// provided route table
 ///
-/// `router` The router to add to
-pub fn route(router: &mut Router<C, Session>) {
-    router.route(C::Util(U::PreAuth), handle_pre_auth);
-    router.route(C::Util(U::PostAuth), handle_post_auth);
-    router.route(C::Util(U::Ping), handle_ping);
-    router.route(C::Util(U::FetchClientConfig), handle_fetch_client_config);
-    router.route(C::Util(U::SuspendUserPing), handle_suspend_user_ping);
-    router.route(C::Util(U::UserSettingsSave), handle_user_settings_save);
-    router.route(C::Util(U::GetTelemetryServer), handle_get_telemetry_server);
-    router.route(C::Util(U::GetTickerServer), handle_get_ticker_server);
-    router.route(C::Util(U::UserSettingsLoadAll), handle_load_settings);
+/// `table` The route table to add to
+pub fn route(table: &mut RouteTable) {
+    route!(table, C::Util(U::PreAuth), handle_pre_auth);
+    route!(table, C::Util(U::PostAuth), handle_post_auth);
+    route!(table, C::Util(U::Ping), handle_ping);
+    route!(
+        table,
+        C::Util(U::FetchClientConfig),
+        handle_fetch_client_config
+    );
+    route!(table, C::Util(U::SuspendUserPing), handle_suspend_user_ping);
+    route!(
+        table,
+        C::Util(U::UserSettingsSave),
+        handle_user_settings_save
+    );
+    route!(
+        table,
+        C::Util(U::GetTelemetryServer),
+        handle_get_telemetry_server
+    );
+    route!(table, C::Util(U::GetTickerServer), handle_get_ticker_server);
+    route!(table, C::Util(U::UserSettingsLoadAll), handle_load_settings);
 }
 
 /// Handles retrieving the details about the telemetry server
@@ -54,9 +67,10 @@ pub fn route(router: &mut Router<C, Session>) {
 /// Content: {}
 /// ```
 ///
-async fn handle_get_telemetry_server() -> TelemetryServer {
+async fn handle_get_telemetry_server(session: &mut Session) -> TelemetryServer {
     TelemetryServer {
         port: env::from_env(env::TELEMETRY_PORT),
+        locale: session.config.locale,
     }
 }
 
@@ -105,9 +119,50 @@ async fn handle_get_ticker_server() -> TickerServer {
 ///     }
 /// }
 /// ```
-async fn handle_pre_auth() -> PreAuthResponse {
+async fn handle_pre_auth(
+    session: &mut Session,
+    req: PreAuthRequest,
+) -> ServerResult<PreAuthResponse> {
+    check_client_version(req.version.as_deref(), &env::env(env::CLIENT_VERSIONS))?;
+
     let qos_port: Port = env::from_env(env::HTTP_PORT);
-    PreAuthResponse { qos_port }
+    Ok(PreAuthResponse {
+        qos_port,
+        ping_site: session.config.ping_site.clone(),
+    })
+}
+
+/// Rejects clients with versions that aren't within the comma seperated
+/// list of allowed versions. Clients that didn't include their version
+/// are allowed as their version can't be checked
+///
+/// `version` The client version if the client included it
+/// `allowed` The comma seperated list of allowed versions
+fn check_client_version(version: Option<&str>, allowed: &str) -> ServerResult<()> {
+    let Some(version) = version else {
+        debug!("Client didn't include its version, skipping version check");
+        return Ok(());
+    };
+    if !is_version_allowed(version, allowed) {
+        warn!("Rejected client with disallowed version: {}", version);
+        return Err(ServerError::ClientVersionNotAllowed);
+    }
+    Ok(())
+}
+
+/// Checks whether the provided client version is within the comma
+/// seperated list of allowed versions. All versions are allowed if
+/// the list is empty
+///
+/// `version` The client version
+/// `allowed` The comma seperated list of allowed versions
+fn is_version_allowed(version: &str, allowed: &str) -> bool {
+    let mut allowed = allowed
+        .split(',')
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .peekable();
+    allowed.peek().is_none() || allowed.any(|value| value == version)
 }
 
 /// Handles post authentication requests. This provides information about other
@@ -130,6 +185,7 @@ async fn handle_post_auth(session: &mut Session) -> ServerResult<PostAuthRespons
     Ok(PostAuthResponse {
         telemetry: TelemetryServer {
             port: env::from_env(env::TELEMETRY_PORT),
+            locale: session.config.locale,
         },
         ticker: TickerServer { port: TICKER_PORT },
         player_id,
@@ -500,8 +556,9 @@ fn data_config() -> TdfMap<String, String> {
     config
 }
 
-/// Handles suspend user ping packets. The usage of this is unknown and needs
-/// further research
+/// Handles suspend user ping packets. The client requests that pings are
+/// suspended for the provided duration (microseconds) and is responded to
+/// with the accepted duration which is limited by the configured max.
 ///
 /// ```
 /// Route: Util(SuspendUserPing)
@@ -510,11 +567,23 @@ fn data_config() -> TdfMap<String, String> {
 ///     "TVAL": 90000000
 /// }
 /// ```
-async fn handle_suspend_user_ping(req: SuspendPingRequest) -> ServerResult<()> {
-    match req.value {
+async fn handle_suspend_user_ping(req: SuspendPingRequest) -> ServerResult<SuspendPingResponse> {
+    suspend_ping(req.value, env::from_env(env::MAX_SUSPEND_PING))
+}
+
+/// Determines the response to a suspend user ping request. The values the
+/// official server rejects are rejected with the same errors otherwise the
+/// requested value is accepted clamped to the provided max
+///
+/// `value` The requested suspend duration
+/// `max`   The max suspend duration
+fn suspend_ping(value: u32, max: u32) -> ServerResult<SuspendPingResponse> {
+    match value {
         20000000 => Err(ServerError::Suspend12D),
         90000000 => Err(ServerError::Suspend12E),
-        _ => Ok(()),
+        value => Ok(SuspendPingResponse {
+            value: value.min(max),
+        }),
     }
 }
 
@@ -538,13 +607,7 @@ async fn handle_user_settings_save(
         .as_ref()
         .ok_or(ServerError::FailedNoLoginAction)?;
 
-    let db = GlobalState::database();
-    if let Err(err) = player.set_data(db, req.key, req.value).await {
-        warn!("Failed to update player data: {err:?}");
-        Err(ServerError::ServerUnavailable)
-    } else {
-        Ok(())
-    }
+    save_setting(GlobalState::database(), player, req).await
 }
 
 /// Handles loading all the user details for the current account and sending them to the
@@ -561,8 +624,37 @@ async fn handle_load_settings(session: &mut Session) -> ServerResult<SettingsRes
         .as_ref()
         .ok_or(ServerError::FailedNoLoginAction)?;
 
-    let db = GlobalState::database();
+    load_settings(GlobalState::database(), player).await
+}
 
+/// Stores the provided setting as player data for the provided player
+/// replacing any existing value for the same key. The player data table
+/// is the store for user settings as the class and character settings
+/// saved by the client are read from it by the leaderboards and the
+/// Galaxy at War and player data APIs
+///
+/// `db`     The database connection
+/// `player` The player to store the setting for
+/// `req`    The setting save request
+async fn save_setting(
+    db: &DatabaseConnection,
+    player: &Player,
+    req: SettingsSaveRequest,
+) -> ServerResult<()> {
+    if let Err(err) = player.set_data(db, req.key, req.value).await {
+        warn!("Failed to update player data: {err:?}");
+        Err(ServerError::ServerUnavailable)
+    } else {
+        Ok(())
+    }
+}
+
+/// Loads all the stored player data for the provided player as
+/// the settings map sent to the client
+///
+/// `db`     The database connection
+/// `player` The player to load the settings for
+async fn load_settings(db: &DatabaseConnection, player: &Player) -> ServerResult<SettingsResponse> {
     // Load the player data from the database
     let data: Vec<PlayerData> = match player.all_data(db).await {
         Ok(value) => value,
@@ -580,3 +672,157 @@ async fn handle_load_settings(session: &mut Session) -> ServerResult<SettingsRes
     settings.order();
     Ok(SettingsResponse { settings })
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        check_client_version, is_version_allowed, load_settings, save_setting, suspend_ping,
+    };
+    use crate::servers::main::models::{
+        errors::ServerError,
+        util::{PreAuthRequest, SettingsSaveRequest, SuspendPingRequest},
+    };
+    use blaze_pk::{codec::Decodable, reader::TdfReader, writer::TdfWriter};
+    use database::Player;
+
+    #[test]
+    fn test_version_allowed() {
+        assert!(is_version_allowed("05427.124", ""));
+        assert!(is_version_allowed("05427.124", "05427.124"));
+        assert!(is_version_allowed("05427.124", "05400.100, 05427.124"));
+        assert!(!is_version_allowed("05400.100", "05427.124"));
+    }
+
+    /// Tests that decoded suspend requests are accepted with their
+    /// duration clamped to the max
+    #[test]
+    fn test_suspend_ping() {
+        let mut writer = TdfWriter::default();
+        writer.tag_u32(b"TVAL", 500000000);
+        let mut reader = TdfReader::new(&writer.buffer);
+        let request = SuspendPingRequest::decode(&mut reader).unwrap();
+        assert_eq!(request.value, 500000000);
+
+        let response = suspend_ping(request.value, 60000000).unwrap();
+        assert_eq!(response.value, 60000000);
+        let response = suspend_ping(30000000, 60000000).unwrap();
+        assert_eq!(response.value, 30000000);
+
+        // Values rejected by the official server are still rejected
+        assert!(matches!(
+            suspend_ping(90000000, 60000000),
+            Err(ServerError::Suspend12E)
+        ));
+    }
+
+    /// Tests that the pre-auth request sent by the official client
+    /// decodes with the groups following the client info
+    #[test]
+    fn test_decode_pre_auth() {
+        let mut writer = TdfWriter::default();
+        writer.tag_group(b"CDAT");
+        writer.tag_zero(b"IITO");
+        writer.tag_u32(b"LANG", 0x656e4e5a);
+        writer.tag_str(b"SVCN", "masseffect-3-pc");
+        writer.tag_zero(b"TYPE");
+        writer.tag_group_end();
+        writer.tag_group(b"CINF");
+        writer.tag_str(b"BSDK", "3.15.6.0");
+        writer.tag_str(b"BTIM", "Dec 21 2012 12:46:51");
+        writer.tag_str(b"CLNT", "MassEffect3-pc");
+        writer.tag_str(b"CSKU", "134845");
+        writer.tag_str(b"CVER", "05427.124");
+        writer.tag_str(b"DSDK", "8.14.7.1");
+        writer.tag_str(b"ENV", "prod");
+        writer.tag_u32(b"LOC", 0x656e4e5a);
+        writer.tag_str(b"MAC", "7c:10:c9:28:33:35");
+        writer.tag_str(b"PLAT", "Windows");
+        writer.tag_group_end();
+        writer.tag_group(b"FCCR");
+        writer.tag_str(b"CFID", "BlazeSDK");
+        writer.tag_group_end();
+
+        let mut reader = TdfReader::new(&writer.buffer);
+        let request = PreAuthRequest::decode(&mut reader).unwrap();
+        assert_eq!(request.version.as_deref(), Some("05427.124"));
+    }
+
+    /// Tests that the version is optional and that a missing version
+    /// doesn't read past the client info
+    #[test]
+    fn test_decode_pre_auth_without_version() {
+        let mut writer = TdfWriter::default();
+        writer.tag_group(b"CINF");
+        writer.tag_str(b"CLNT", "MassEffect3-pc");
+        writer.tag_group_end();
+        writer.tag_group(b"FCCR");
+        writer.tag_str(b"CFID", "BlazeSDK");
+        writer.tag_group_end();
+        let mut reader = TdfReader::new(&writer.buffer);
+        let request = PreAuthRequest::decode(&mut reader).unwrap();
+        assert_eq!(request.version, None);
+
+        let writer = TdfWriter::default();
+        let mut reader = TdfReader::new(&writer.buffer);
+        let request = PreAuthRequest::decode(&mut reader).unwrap();
+        assert_eq!(request.version, None);
+    }
+
+    #[test]
+    fn test_check_client_version() {
+        let allowed = "05427.124";
+        assert!(matches!(
+            check_client_version(Some("05400.100"), allowed),
+            Err(ServerError::ClientVersionNotAllowed)
+        ));
+        assert!(check_client_version(Some("05427.124"), allowed).is_ok());
+        // Clients that don't include their version aren't rejected
+        assert!(check_client_version(None, allowed).is_ok());
+    }
+
+    /// Tests that saved settings are loaded back for the same player
+    /// and not for other players
+    #[tokio::test]
+    async fn test_settings_round_trip() {
+        let db = database::connect_memory().await;
+
+        let player = Player::create(
+            &db,
+            "a@test.com".to_string(),
+            "PlayerA".to_string(),
+            String::new(),
+            false,
+        )
+        .await
+        .unwrap();
+        let other = Player::create(
+            &db,
+            "b@test.com".to_string(),
+            "PlayerB".to_string(),
+            String::new(),
+            false,
+        )
+        .await
+        .unwrap();
+
+        let request = |value: &str| SettingsSaveRequest {
+            key: "class1".to_string(),
+            value: value.to_string(),
+        };
+        save_setting(&db, &player, request("20;4;Adept;20;0.0000;50"))
+            .await
+            .unwrap();
+        save_setting(&db, &player, request("20;4;Adept;21;0.0000;50"))
+            .await
+            .unwrap();
+
+        let response = load_settings(&db, &player).await.unwrap();
+        assert_eq!(
+            response.settings.get("class1"),
+            Some(&"20;4;Adept;21;0.0000;50".to_string())
+        );
+
+        let response = load_settings(&db, &other).await.unwrap();
+        assert_eq!(response.settings.get("class1"), None);
+    }
+}
@@ -1,29 +1,42 @@
 //! Sessions are client connections to the main server with associated
 //! data such as player data for when they become authenticated and
 //! networking data.
-use super::models::{
-    errors::{ServerError, ServerResult},
-    session::{SessionUpdate, SetSession},
+use super::{
+    config::SessionConfig,
+    debug::DebugComponents,
+    models::{
+        errors::{ServerError, ServerResult},
+        messaging::MessageNotify,
+        session::{DisconnectNotify, DisconnectReason, PresenceUpdate, SessionUpdate, SetSession},
+    },
 };
 use crate::{
-    game::{player::GamePlayer, RemovePlayerType},
+    game::{models::FetchExtendedData, player::GamePlayer, GameModifyAction, RemovePlayerType},
     state::GlobalState,
     utils::{
-        components::{self, Components, UserSessions},
+        components::{Components, Messaging, UserSessions},
+        geo::Geolocator,
         models::{NetData, NetGroups, QosNetworkData, UpdateExtDataAttr},
-        packet::append_packet_decoded,
+        packet::{append_packet_decoded, append_packet_raw, read_packet, write_packets_timeout},
         random::generate_random_string,
         types::{GameID, SessionID},
     },
 };
 use blaze_pk::{
-    packet::{Packet, PacketComponents, PacketType},
-    router::{Router, State},
+    packet::{IntoResponse, Packet, PacketComponents, PacketType},
+    router::{HandleError, Router, State},
 };
 use database::Player;
-use log::{debug, error, log_enabled};
-use std::{collections::VecDeque, io, net::SocketAddr, sync::Arc};
-use tokio::{net::TcpStream, select, sync::mpsc};
+use log::{debug, error, info, log_enabled, warn};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    future::pending,
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{net::TcpStream, select, sync::mpsc, time::sleep_until};
 
 /// Structure for storing a client session. This includes the
 /// network stream for the client along with global state and
@@ -45,12 +58,19 @@ pub struct Session {
     /// Networking information
     pub net: NetData,
 
-    /// The id of the game if connected to one
-    pub game: Option<GameID>,
+    /// The games the session is connected to
+    pub games: SessionGames,
 
     /// The queue of packets that need to be written
     queue: VecDeque<Packet>,
 
+    /// The time the last packet was received from the client
+    last_activity: Instant,
+
+    /// The number of packets read from the client used when
+    /// generating trace IDs
+    trace_count: u64,
+
     /// State determining whether the session has a flush message
     /// already queued in the reciever
     flush_queued: bool,
@@ -58,8 +78,22 @@ pub struct Session {
     /// Arc to router to use for routing
     router: Arc<Router<Components, Session>>,
 
+    /// Arc to the debug logging component configuration
+    debug: Arc<DebugComponents>,
+
+    /// Arc to the configuration shared by every session
+    pub config: Arc<SessionConfig>,
+
+    /// Whether every packet for this session is logged in full
+    /// regardless of the debug logging configuration
+    traced: bool,
+
     /// Internal address used for routing can be cloned and used elsewhere
     addr: SessionAddr,
+
+    /// Whether the player should be placed back into their previous
+    /// game once the client has sent its networking information
+    rejoin_pending: bool,
 }
 
 impl State for Session {}
@@ -75,18 +109,166 @@ pub struct SessionAddr {
 }
 
 impl SessionAddr {
-    /// Writes a new packet ot the session
+    /// Creates a new session address from the provided ID
+    /// and message sender
+    ///
+    /// `id`     The ID of the session
+    /// `sender` The sender for sending messages to the session
+    pub fn new(id: SessionID, sender: mpsc::UnboundedSender<SessionMessage>) -> Self {
+        Self { id, sender }
+    }
+
+    /// Writes a new packet ot the session. Returns false if the
+    /// session has been closed and the packet couldn't be sent
     ///
     /// `packet` The packet to write
-    pub fn push(&self, packet: Packet) {
-        self.sender.send(SessionMessage::Write(packet)).ok();
+    pub fn push(&self, packet: Packet) -> bool {
+        self.sender.send(SessionMessage::Write(packet)).is_ok()
+    }
+
+    /// Writes a batch of packets to the session using a single message
+    /// so the session only queues one flush for the whole batch. Returns
+    /// false if the session has been closed and the packets couldn't be sent
+    ///
+    /// `packets` The packets to write
+    pub fn push_many(&self, packets: Vec<Packet>) -> bool {
+        self.sender.send(SessionMessage::WriteMany(packets)).is_ok()
+    }
+
+    /// Checks whether the session this address is for has been closed
+    /// and is no longer able to receive messages
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+
+    /// Sets the role of the session within the provided game adding
+    /// the game to the session games if its not already present
+    ///
+    /// `game_id` The ID of the game
+    /// `role`    The role of the session in the game
+    pub fn set_game(&self, game_id: GameID, role: GameRole) {
+        self.sender
+            .send(SessionMessage::SetGame(game_id, role))
+            .ok();
     }
 
-    /// Sets the game that the session is apart of
+    /// Removes the provided game from the session games
     ///
-    /// `game` The game
-    pub fn set_game(&self, game: Option<GameID>) {
-        self.sender.send(SessionMessage::SetGame(game)).ok();
+    /// `game_id` The ID of the game
+    pub fn remove_game(&self, game_id: GameID) {
+        self.sender.send(SessionMessage::RemoveGame(game_id)).ok();
+    }
+
+    /// Sends a message to the session to be displayed on the client.
+    /// Returns whether the message was sent to the session.
+    ///
+    /// `message` The message to send
+    pub fn send_message(&self, message: String) -> bool {
+        self.sender.send(SessionMessage::Message(message)).is_ok()
+    }
+
+    /// Updates the display name of the player authenticated on
+    /// the session
+    ///
+    /// `display_name` The new display name
+    pub fn set_display_name(&self, display_name: String) {
+        self.sender
+            .send(SessionMessage::SetDisplayName(display_name))
+            .ok();
+    }
+
+    /// Notifies the session of the reason it is being closed then
+    /// closes the session. Returns whether the session was open
+    ///
+    /// `reason` The reason the session is being closed
+    pub fn disconnect(&self, reason: DisconnectReason) -> bool {
+        self.sender.send(SessionMessage::Disconnect(reason)).is_ok()
+    }
+
+    /// Sets whether every packet for the session should be logged in
+    /// full. Returns whether the session was open
+    ///
+    /// `traced` Whether the session should be traced
+    pub fn set_traced(&self, traced: bool) -> bool {
+        self.sender.send(SessionMessage::SetTraced(traced)).is_ok()
+    }
+}
+
+/// The role a session has within a game that it is connected to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameRole {
+    /// The session is the host of the game
+    Host,
+    /// The session is a player in a game hosted by another player
+    Player,
+}
+
+/// Collection of the games that a session is connected to along with
+/// the role of the session in each game. Sessions can only be in a
+/// single game unless multiple games are enabled
+pub struct SessionGames {
+    /// Whether the session is allowed to be in multiple games
+    multi: bool,
+    /// The connected game IDs and the role in each game
+    values: Vec<(GameID, GameRole)>,
+}
+
+impl SessionGames {
+    /// Creates a new empty collection of session games
+    ///
+    /// `multi` Whether the session is allowed to be in multiple games
+    pub fn new(multi: bool) -> Self {
+        Self {
+            multi,
+            values: Vec::new(),
+        }
+    }
+
+    /// Sets the role for the provided game adding the game if it is
+    /// not already present. When multiple games are not allowed any
+    /// other games are replaced and their IDs are returned so that
+    /// the session can be removed from them.
+    ///
+    /// `game_id` The ID of the game
+    /// `role`    The role of the session in the game
+    pub fn set(&mut self, game_id: GameID, role: GameRole) -> Vec<GameID> {
+        if let Some(value) = self.values.iter_mut().find(|value| value.0 == game_id) {
+            value.1 = role;
+            return Vec::new();
+        }
+
+        let replaced = if self.multi { Vec::new() } else { self.take() };
+        self.values.push((game_id, role));
+        replaced
+    }
+
+    /// Removes the provided game returning whether it was present
+    ///
+    /// `game_id` The ID of the game
+    pub fn remove(&mut self, game_id: GameID) -> bool {
+        let length = self.values.len();
+        self.values.retain(|value| value.0 != game_id);
+        self.values.len() != length
+    }
+
+    /// Returns an iterator over the IDs of the connected games
+    pub fn ids(&self) -> impl Iterator<Item = GameID> + '_ {
+        self.values.iter().map(|value| value.0)
+    }
+
+    /// Returns the number of connected games
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns whether the session isn't connected to any games
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Removes all the games returning their IDs
+    pub fn take(&mut self) -> Vec<GameID> {
+        self.values.drain(..).map(|value| value.0).collect()
     }
 }
 
@@ -94,14 +276,32 @@ impl SessionAddr {
 /// session in order to change it in different ways
 #[derive(Debug)]
 pub enum SessionMessage {
-    /// Changes the active game value
-    SetGame(Option<GameID>),
+    /// Sets the role of the session in a game
+    SetGame(GameID, GameRole),
+
+    /// Removes a game from the session
+    RemoveGame(GameID),
 
     /// Writes a new packet to the outbound queue
     Write(Packet),
 
+    /// Writes a batch of packets to the outbound queue
+    WriteMany(Vec<Packet>),
+
     /// Flushes the outbound queue
     Flush,
+
+    /// Sends a message to be displayed on the client
+    Message(String),
+
+    /// Updates the display name of the authenticated player
+    SetDisplayName(String),
+
+    /// Notifies the client of the reason and closes the session
+    Disconnect(DisconnectReason),
+
+    /// Sets whether all packets for the session are logged in full
+    SetTraced(bool),
 }
 
 impl Session {
@@ -109,9 +309,19 @@ impl Session {
         id: SessionID,
         values: (TcpStream, SocketAddr),
         router: Arc<Router<Components, Session>>,
+        debug: Arc<DebugComponents>,
+        config: Arc<SessionConfig>,
+        geolocator: &dyn Geolocator,
     ) {
         let (sender, receiver) = mpsc::unbounded_channel();
-        let session = Session::new(id, values.0, values.1, sender, router);
+        let mut session = Session::new(id, values.0, values.1, sender, router, debug, config);
+        session.net.country = geolocator.locate(values.1.ip());
+        if let Some(country) = &session.net.country {
+            debug!(
+                "Resolved session country (SID: {}, Country: {})",
+                id, country
+            );
+        }
         tokio::spawn(session.process(receiver));
     }
 
@@ -120,39 +330,86 @@ impl Session {
     /// `id`             The unique session ID
     /// `values`         The networking TcpStream and address
     /// `message_sender` The message sender for session messages
+    /// `router`         The router for routing packets
+    /// `debug`          The debug logging component configuration
+    /// `config`         The configuration shared by every session
     fn new(
         id: SessionID,
         stream: TcpStream,
         addr: SocketAddr,
         sender: mpsc::UnboundedSender<SessionMessage>,
         router: Arc<Router<Components, Session>>,
+        debug: Arc<DebugComponents>,
+        config: Arc<SessionConfig>,
     ) -> Self {
+        let session_addr = SessionAddr::new(id, sender);
+        GlobalState::sessions().add_session(session_addr.clone(), addr.ip());
         Self {
             id,
             stream,
             socket_addr: addr,
             queue: VecDeque::new(),
+            last_activity: Instant::now(),
+            trace_count: 0,
             player: None,
             net: NetData::default(),
-            game: None,
+            games: SessionGames::new(config.multi_game),
             flush_queued: false,
             router,
-            addr: SessionAddr { id, sender },
+            debug,
+            config,
+            traced: false,
+            addr: session_addr,
+            rejoin_pending: false,
         }
     }
 
+    /// Creates a new session for tests connected to a local client
+    /// stream. Returns the session along with the receiver for its
+    /// messages and the client end of the stream
+    ///
+    /// `id` The unique session ID
+    #[cfg(test)]
+    pub async fn new_test(
+        id: SessionID,
+    ) -> (Self, mpsc::UnboundedReceiver<SessionMessage>, TcpStream) {
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (stream, addr) = listener.accept().await.unwrap();
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let session = Session::new(
+            id,
+            stream,
+            addr,
+            sender,
+            Arc::new(super::routes::router()),
+            Arc::new(DebugComponents::parse("", "")),
+            Arc::new(SessionConfig::default()),
+        );
+        (session, receiver, client)
+    }
+
     /// Processing function which handles recieving messages, flush notifications,
     /// reading packets, and handling safe shutdowns for this session. This function
     /// owns the session.
     ///
     /// `message` The receiver for receiving session messages
     async fn process(mut self, mut receiver: mpsc::UnboundedReceiver<SessionMessage>) {
+        let idle_timeout = self.config.idle_timeout;
+
         loop {
+            let last_activity = self.last_activity;
             select! {
                 // Recieve session instruction messages
                 message = receiver.recv() => {
                     if let Some(message) = message {
-                        self.handle_message(message).await;
+                        if !self.handle_message(message).await {
+                            break;
+                        }
                     }
                 }
                 // Handle packet reads
@@ -161,6 +418,11 @@ impl Session {
                         break;
                     }
                 }
+                // Close sessions that haven't sent anything
+                _ = wait_idle(last_activity, idle_timeout) => {
+                    debug!("Closing idle session (SID: {})", self.id);
+                    break;
+                }
             };
         }
     }
@@ -169,7 +431,12 @@ impl Session {
     /// if this session is not authenticated
     pub fn try_into_player(&self) -> Option<GamePlayer> {
         let player = self.player.clone()?;
-        Some(GamePlayer::new(player, self.net.clone(), self.addr.clone()))
+        Some(GamePlayer::new(
+            player,
+            self.net.clone(),
+            self.addr.clone(),
+            self.config.clone(),
+        ))
     }
 
     /// Handles processing a recieved packet from the `process` function.
@@ -179,30 +446,92 @@ impl Session {
     /// `component` The component of the packet for routing
     /// `packet`    The packet itself
     async fn handle_packet(&mut self, packet: Packet) -> io::Result<()> {
-        self.debug_log_packet("Read", &packet);
+        self.last_activity = Instant::now();
+        self.trace_count += 1;
+        let trace = trace_id(self.id, packet.header.id, self.trace_count);
+        self.debug_log_packet("Read", &packet, Some(&trace));
         let router = self.router.clone();
 
-        match router.handle(self, packet).await {
-            Ok(packet) => {
-                self.write(packet).await?;
-            }
+        let component = Components::from_header(&packet.header);
+        // Copy of the request header for responding to routing errors
+        // as the packet itself is consumed by the router
+        let request =
+            Packet::request_empty(packet.header.id, Components::from_header(&packet.header));
+        let start = Instant::now();
+
+        let result = router.handle(self, packet).await;
+        // Only the handling time is measured so slow writes don't skew it
+        let elapsed = start.elapsed();
+
+        let mut response = match result {
+            Ok(packet) => packet,
             Err(err) => {
-                error!("Error occurred while decoding packet: {:?}", err);
+                error!(
+                    "Error occurred while routing packet (Trace: {}): {:?}",
+                    trace, err
+                );
+                handle_error(&err).into_response(&request)
             }
+        };
+        let is_error = response.header.ty == PacketType::Error;
+
+        // Recorded before writing so packets are counted even when
+        // the write fails
+        GlobalState::metrics()
+            .packets
+            .record(component, elapsed, is_error);
+
+        if is_error && !self.config.error_messages {
+            response.contents.clear();
         }
-        self.flush().await;
-        Ok(())
+        self.write(response, &trace).await?;
+        self.flush().await
     }
 
     /// Handles a message recieved for the session
+    /// Returns false if the session should be closed.
     ///
     /// `message` The message that was recieved
-    async fn handle_message(&mut self, message: SessionMessage) {
+    async fn handle_message(&mut self, message: SessionMessage) -> bool {
         match message {
-            SessionMessage::SetGame(game) => self.set_game(game),
+            SessionMessage::SetGame(game_id, role) => self.set_game(game_id, role),
+            SessionMessage::RemoveGame(game_id) => self.remove_game(game_id),
             SessionMessage::Write(packet) => self.push(packet),
-            SessionMessage::Flush => self.flush().await,
+            SessionMessage::WriteMany(packets) => self.push_many(packets),
+            SessionMessage::Flush => {
+                if self.flush().await.is_err() {
+                    return false;
+                }
+            }
+            SessionMessage::Message(message) => self.send_message(message),
+            SessionMessage::SetDisplayName(display_name) => self.set_display_name(display_name),
+            SessionMessage::SetTraced(traced) => {
+                debug!("Set session trace (SID: {}, Traced: {})", self.id, traced);
+                self.traced = traced;
+            }
+            SessionMessage::Disconnect(reason) => {
+                self.disconnect_with(reason).await;
+                return false;
+            }
         }
+        true
+    }
+
+    /// Sends the client a notification containing the reason the
+    /// session is being closed and flushes it so that it is written
+    /// before the session is closed
+    ///
+    /// `reason` The reason the session is being closed
+    async fn disconnect_with(&mut self, reason: DisconnectReason) {
+        debug!(
+            "Disconnecting session (SID: {}, Reason: {:?})",
+            self.id, reason
+        );
+        self.push(Packet::notify(
+            Components::UserSessions(UserSessions::SessionDisconnected),
+            DisconnectNotify { reason },
+        ));
+        self.flush().await.ok();
     }
 
     /// Pushes a new packet to the back of the packet buffer
@@ -213,13 +542,31 @@ impl Session {
         self.queue.push_back(packet);
         self.queue_flush();
     }
+
+    /// Pushes all the provided packets to the back of the packet
+    /// buffer sending a single flush notification
+    ///
+    /// `packets` The packets to push to the buffer
+    pub fn push_many(&mut self, packets: Vec<Packet>) {
+        self.queue.extend(packets);
+        self.queue_flush();
+    }
     /// Writes the provided packet directly to the underlying stream
     /// rather than pushing to the buffer. Only use when handling
     /// responses will cause long blocks because will wait for all
     /// the data to be written.
-    async fn write(&mut self, packet: Packet) -> io::Result<()> {
-        packet.write_async(&mut self.stream).await?;
-        self.debug_log_packet("Wrote", &packet);
+    ///
+    /// `packet` The packet to write
+    /// `trace`  The trace ID of the request this packet is for
+    async fn write(&mut self, packet: Packet, trace: &str) -> io::Result<()> {
+        write_packets_timeout(
+            &mut self.stream,
+            std::slice::from_ref(&packet),
+            self.config.write_timeout,
+        )
+        .await
+        .map_err(|err| self.log_write_error(err))?;
+        self.debug_log_packet("Wrote", &packet, Some(trace));
         Ok(())
     }
 
@@ -229,79 +576,37 @@ impl Session {
     /// `action` The name of the action this packet is undergoing.
     ///          (e.g. Writing or Reading)
     /// `packet` The packet that is being logged
-    fn debug_log_packet(&self, action: &str, packet: &Packet) {
-        // Skip if debug logging is disabled
-        if !log_enabled!(log::Level::Debug) {
-            return;
-        }
-
-        let header = &packet.header;
-        let component = Components::from_header(header);
-        if Self::is_debug_ignored(&component) {
+    /// `trace`  The trace ID of the request this packet is for if
+    ///          the packet is part of a request
+    fn debug_log_packet(&self, action: &str, packet: &Packet, trace: Option<&str>) {
+        let component = Components::from_header(&packet.header);
+        let Some(decoded) = packet_log_contents(
+            &self.debug,
+            &component,
+            self.traced,
+            log_enabled!(log::Level::Debug),
+        ) else {
             return;
-        }
-
-        let mut message = String::new();
-        message.push_str("\nSession ");
-        message.push_str(action);
-        message.push_str(" Packet");
-
-        {
-            message.push_str("\nInfo: (");
-
-            if let Some(player) = self.player.as_ref() {
-                message.push_str("Name: ");
-                message.push_str(&player.display_name);
-                message.push_str(", ID: ");
-                message.push_str(&player.id.to_string());
-                message.push_str(", SID: ");
-                message.push_str(&self.id.to_string());
-            } else {
-                message.push_str("SID: ");
-                message.push_str(&self.id.to_string());
-            }
-
-            message.push(')');
-        }
-
-        message.push_str(&format!("\nComponent: {:?}", component));
-        message.push_str(&format!("\nType: {:?}", header.ty));
-        if header.ty != PacketType::Notify {
-            message.push_str("\nID: ");
-            message.push_str(&header.id.to_string());
-        }
+        };
 
-        if header.ty == PacketType::Error {
-            message.push_str("\nERROR: ");
-            message.push_str(&header.error.to_string());
+        let mut info = String::new();
+        if let Some(player) = self.player.as_ref() {
+            info.push_str("Name: ");
+            info.push_str(&player.display_name);
+            info.push_str(", ID: ");
+            info.push_str(&player.id.to_string());
+            info.push_str(", ");
         }
+        info.push_str("SID: ");
+        info.push_str(&self.id.to_string());
 
-        if !Self::is_debug_minified(&component) {
-            append_packet_decoded(packet, &mut message);
+        let mut message = packet_log_message(action, &info, trace, packet, decoded);
+        if self.traced {
+            append_packet_raw(packet, &mut message);
+            info!("{}", message);
+        } else {
+            debug!("{}", message);
         }
-
-        debug!("{}", message);
-    }
-
-    /// Checks whether the provided `component` is ignored completely
-    /// when debug logging. This is for packets such as Ping and SuspendUserPing
-    /// where they occur frequently but provide no useful data for debugging.
-    ///
-    /// `component` The component to check
-    fn is_debug_ignored(component: &Components) -> bool {
-        Components::Util(components::Util::Ping).eq(component)
-            || Components::Util(components::Util::SuspendUserPing).eq(component)
-    }
-
-    /// Checks whether the provided `component` should have its contents
-    /// hidden when being debug printed. Used to hide the contents of
-    /// larger packets.
-    ///
-    /// `component` The component to check
-    fn is_debug_minified(component: &Components) -> bool {
-        Components::Authentication(components::Authentication::ListUserEntitlements2).eq(component)
-            || Components::Util(components::Util::FetchClientConfig).eq(component)
-            || Components::Util(components::Util::UserSettingsLoadAll).eq(component)
     }
 
     /// Queues a new flush if there is not already one queued
@@ -312,62 +617,142 @@ impl Session {
         }
     }
 
-    /// Flushes the output buffer
-    async fn flush(&mut self) {
+    /// Flushes the output buffer. All the queued packets are written
+    /// to the stream using a single write. Returns an error if the
+    /// write failed or timed out in which case the session should
+    /// be closed
+    async fn flush(&mut self) -> io::Result<()> {
         self.flush_queued = false;
 
-        // Counter for the number of items written
-        let mut write_count = 0usize;
-        while let Some(item) = self.queue.pop_front() {
-            self.debug_log_packet("Wrote", &item);
-            match item.write_async(&mut self.stream).await {
-                Ok(_) => {
-                    write_count += 1;
-                }
-                Err(err) => {
-                    error!(
-                        "Error occurred while flushing session (SID: {}): {:?}",
-                        self.id, err
-                    );
-                    return;
-                }
-            }
+        if self.queue.is_empty() {
+            return Ok(());
         }
 
-        if write_count > 0 {
-            debug!("Flushed session (SID: {}, Count: {})", self.id, write_count);
+        let packets: Vec<Packet> = self.queue.drain(..).collect();
+        for packet in &packets {
+            self.debug_log_packet("Wrote", packet, None);
         }
+
+        write_packets_timeout(&mut self.stream, &packets, self.config.write_timeout)
+            .await
+            .map_err(|err| self.log_write_error(err))?;
+
+        debug!(
+            "Flushed session (SID: {}, Count: {})",
+            self.id,
+            packets.len()
+        );
+        Ok(())
+    }
+
+    /// Logs an error that occurred while writing to the stream
+    /// returning the error
+    ///
+    /// `err` The write error
+    fn log_write_error(&self, err: io::Error) -> io::Error {
+        if err.kind() == io::ErrorKind::TimedOut {
+            warn!("Closing session that timed out writing (SID: {})", self.id);
+        } else {
+            error!(
+                "Error occurred while writing to session (SID: {}): {:?}",
+                self.id, err
+            );
+        }
+        err
     }
 
     /// Reads a packet from the stream and then passes the packet
     /// onto `handle_packet` awaiting the result of that
     async fn read(&mut self) -> io::Result<()> {
-        let packet: Packet = Packet::read_async(&mut self.stream).await?;
+        let max_size = self.config.max_packet_size;
+        let packet: Packet = match read_packet(&mut self.stream, max_size).await {
+            Ok(value) => value,
+            Err(err) => {
+                if err.kind() == io::ErrorKind::InvalidData {
+                    warn!(
+                        "Closing session that sent invalid packet (SID: {}): {}",
+                        self.id, err
+                    );
+                }
+                return Err(err);
+            }
+        };
         self.handle_packet(packet).await
     }
 
-    /// Sets the game details for the current session and updates
-    /// the client with the new sesion details
+    /// Sets the role of the session in the provided game and updates
+    /// the client with the new session details. When multiple games are
+    /// disabled the session is removed from any game it was previously in
     ///
-    /// `game` The game the player has joined.
-    /// `slot` The slot in the game the player is in.
-    fn set_game(&mut self, game: Option<GameID>) {
-        self.game = game;
+    /// `game_id` The ID of the game
+    /// `role`    The role of the session in the game
+    fn set_game(&mut self, game_id: GameID, role: GameRole) {
+        let replaced = self.games.set(game_id, role);
+        if !replaced.is_empty() {
+            let games = GlobalState::games();
+            for game_id in replaced {
+                games.remove_player(game_id, RemovePlayerType::Session(self.id));
+            }
+        }
         self.update_client();
     }
 
+    /// Removes the provided game from the session games and updates
+    /// the client with the new session details
+    ///
+    /// `game_id` The ID of the game
+    fn remove_game(&mut self, game_id: GameID) {
+        if self.games.remove(game_id) {
+            self.update_client();
+        }
+    }
+
     /// Updates the networking information for this session making
     /// it a set and setting the ext and groups. Updating the client
     /// with the new session details
     ///
-    /// `groups` The networking groups
-    /// `ext`    The networking ext
-    pub fn set_network_info(&mut self, groups: NetGroups, ext: QosNetworkData) {
+    /// `groups`  The networking groups
+    /// `ext`     The networking ext
+    /// `latency` The latency to each of the Quality of Service regions
+    pub fn set_network_info(
+        &mut self,
+        mut groups: NetGroups,
+        ext: QosNetworkData,
+        latency: BTreeMap<String, u32>,
+    ) {
+        // Include the IPv6 address for clients connected over IPv6
+        if let IpAddr::V6(addr) = self.socket_addr.ip() {
+            if addr.to_ipv4_mapped().is_none() {
+                groups.external.2 = Some(addr);
+            }
+        }
+
         let net = &mut &mut self.net;
         net.is_set = true;
         net.qos = ext;
         net.groups = groups;
+        net.pslm = latency.get(&self.config.ping_site).copied();
+        GlobalState::sessions().set_latency(self.id, latency);
         self.update_client();
+
+        if self.rejoin_pending {
+            self.rejoin_game();
+        }
+    }
+
+    /// Places the player back into the game they were in before their
+    /// previous session was lost. Other players need the networking
+    /// information to connect to the player so this waits until the
+    /// client has sent it.
+    pub fn rejoin_game(&mut self) {
+        if !self.net.is_set {
+            self.rejoin_pending = true;
+            return;
+        }
+        self.rejoin_pending = false;
+        if let Some(player) = self.try_into_player() {
+            GlobalState::games().try_rejoin(player);
+        }
     }
 
     /// Updates the hardware flag for this session and
@@ -394,7 +779,18 @@ impl Session {
     }
 
     pub async fn set_player(&mut self, player: Player) -> ServerResult<(&Player, String)> {
-        // Obtain a token associated to this player
+        // Claim the player applying the policy for players that are
+        // already logged in on another session
+        if !GlobalState::sessions().claim_player(self.id, player.id, self.config.duplicate_login) {
+            info!(
+                "Rejected duplicate login (SID: {}, PID: {})",
+                self.id, player.id
+            );
+            return Err(ServerError::InvalidAccount);
+        }
+
+        // Obtain a token associated to this player only once the
+        // player has been claimed
         let (player, session_token) = match player
             .with_token(GlobalState::database(), generate_random_string)
             .await
@@ -402,15 +798,102 @@ impl Session {
             Ok(value) => value,
             Err(err) => {
                 error!("Unable to create session token for player: {err:?}");
+                GlobalState::sessions().set_player(self.id, None);
                 return Err(ServerError::ServerUnavailable);
             }
         };
 
         // Update the player value
+        GlobalState::sessions().set_display_name(self.id, player.display_name.clone());
+        self.notify_online(&player);
         let player = self.player.insert(player);
         Ok((player, session_token))
     }
 
+    /// Removes the authenticated player from this session
+    pub fn clear_player(&mut self) {
+        self.notify_offline();
+        GlobalState::sessions().set_player(self.id, None);
+        self.player = None;
+        self.rejoin_pending = false;
+    }
+
+    /// Notifies the other authenticated sessions that the provided
+    /// player has come online if presence is enabled
+    ///
+    /// `player` The player that authenticated
+    fn notify_online(&self, player: &Player) {
+        let sessions = GlobalState::sessions();
+        if !sessions.is_presence_enabled() {
+            return;
+        }
+        // Only the player details are sent as the other sessions
+        // don't need the networking details of this session
+        let packet = Packet::notify(
+            Components::UserSessions(UserSessions::SessionDetails),
+            PresenceUpdate {
+                player_id: player.id,
+                display_name: &player.display_name,
+                locale: self.config.locale,
+            },
+        );
+        sessions.notify_presence(self.id, &packet);
+    }
+
+    /// Notifies the other authenticated sessions that the player
+    /// on this session has gone offline if presence is enabled
+    fn notify_offline(&self) {
+        let sessions = GlobalState::sessions();
+        if !sessions.is_presence_enabled() {
+            return;
+        }
+        let Some(player) = self.player.as_ref() else {
+            return;
+        };
+        // The player is still online when another session is authenticated
+        // as them such as when this session was taken over by a new login
+        if sessions
+            .find_player_sessions(player.id)
+            .iter()
+            .any(|addr| addr.id != self.id)
+        {
+            return;
+        }
+        // Clients treat this notification as the player being removed
+        let packet = Packet::notify(
+            Components::UserSessions(UserSessions::FetchExtendedData),
+            FetchExtendedData {
+                player_id: player.id,
+            },
+        );
+        sessions.notify_presence(self.id, &packet);
+    }
+
+    /// Updates the display name of the authenticated player and
+    /// updates the client and any other players in the same game
+    ///
+    /// `display_name` The new display name
+    fn set_display_name(&mut self, display_name: String) {
+        let Some(player) = self.player.as_mut() else {
+            return;
+        };
+        player.display_name = display_name.clone();
+        let player_id = player.id;
+        GlobalState::sessions().set_display_name(self.id, display_name.clone());
+        self.push_details();
+
+        let games = GlobalState::games();
+        for game_id in self.games.ids() {
+            games.modify_game(
+                game_id,
+                GameModifyAction::SetDisplayName {
+                    player_id,
+                    display_name: display_name.clone(),
+                },
+            );
+        }
+    }
+
     pub fn push_details(&mut self) {
         let player = match self.player.as_ref() {
             Some(value) => value,
@@ -440,22 +923,504 @@ impl Session {
         self.push(b);
     }
 
-    /// Removes the session from any connected games and the
+    /// Sends a message notification to the client that will be displayed
+    /// to the player. Sessions that aren't authenticated are skipped as
+    /// the message must target a player.
+    ///
+    /// `message` The message to send
+    pub fn send_message(&mut self, mut message: String) {
+        let Some(player) = self.player.as_ref() else {
+            return;
+        };
+        // Line terminator for the end of the message
+        message.push(char::from(0x0A));
+        let packet = Packet::notify(
+            Components::Messaging(Messaging::SendMessage),
+            MessageNotify {
+                message,
+                player_id: player.id,
+            },
+        );
+        self.push(packet);
+    }
+
+    /// Removes the session from all connected games and the
     /// matchmaking queue
     pub fn remove_games(&mut self) {
-        let game = self.game.take();
+        let removed = self.games.take();
         let games = GlobalState::games();
-        if let Some(game_id) = game {
-            games.remove_player(game_id, RemovePlayerType::Session(self.id));
-        } else {
+        if removed.is_empty() {
             games.unqueue_session(self.id);
+            return;
         }
+        for game_id in removed {
+            games.remove_player(game_id, RemovePlayerType::Session(self.id));
+        }
+    }
+}
+
+/// Creates a trace ID for a packet read from a session so that the
+/// logs for a single request can be correlated
+///
+/// `session_id` The ID of the session
+/// `packet_id`  The ID from the packet header
+/// `count`      The number of packets read by the session
+fn trace_id(session_id: SessionID, packet_id: u16, count: u64) -> String {
+    format!("{}-{}-{}", session_id, packet_id, count)
+}
+
+/// Creates the debug log message for a packet along with its
+/// header information and the provided session information
+///
+/// `action`  The name of the action this packet is undergoing
+/// `info`    The session information
+/// `trace`   The trace ID of the request this packet is for
+/// `packet`  The packet that is being logged
+/// `decoded` Whether to include the decoded packet contents
+fn packet_log_message(
+    action: &str,
+    info: &str,
+    trace: Option<&str>,
+    packet: &Packet,
+    decoded: bool,
+) -> String {
+    let header = &packet.header;
+    let component = Components::from_header(header);
+
+    let mut message = String::new();
+    message.push_str("\nSession ");
+    message.push_str(action);
+    message.push_str(" Packet");
+    message.push_str("\nInfo: (");
+    message.push_str(info);
+    message.push(')');
+
+    if let Some(trace) = trace {
+        message.push_str("\nTrace: ");
+        message.push_str(trace);
+    }
+
+    message.push_str(&format!("\nComponent: {:?}", component));
+    message.push_str(&format!("\nType: {:?}", header.ty));
+    if header.ty != PacketType::Notify {
+        message.push_str("\nID: ");
+        message.push_str(&header.id.to_string());
+    }
+
+    if header.ty == PacketType::Error {
+        message.push_str("\nERROR: ");
+        message.push_str(&header.error.to_string());
+    }
+
+    if decoded {
+        append_packet_decoded(packet, &mut message);
+    }
+    message
+}
+
+/// Determines whether a packet for the provided component should be
+/// logged. Returns whether the contents of the packet should be included
+/// or None if the packet shouldn't be logged. Traced sessions log every
+/// packet in full regardless of the debug logging configuration
+///
+/// `debug`         The debug logging component configuration
+/// `component`     The component of the packet
+/// `traced`        Whether the session is traced
+/// `debug_enabled` Whether debug logging is enabled
+fn packet_log_contents(
+    debug: &DebugComponents,
+    component: &Components,
+    traced: bool,
+    debug_enabled: bool,
+) -> Option<bool> {
+    if traced {
+        return Some(true);
+    }
+    if !debug_enabled || debug.is_ignored(component) {
+        return None;
+    }
+    Some(!debug.is_minified(component))
+}
+
+/// Waits until the session has been idle for longer than the provided
+/// timeout. Never completes if there is no timeout.
+///
+/// `last_activity` The time the session was last active
+/// `timeout`       The idle timeout if enabled
+async fn wait_idle(last_activity: Instant, timeout: Option<Duration>) {
+    match timeout {
+        Some(timeout) => sleep_until((last_activity + timeout).into()).await,
+        None => pending().await,
     }
 }
 
 impl Drop for Session {
     fn drop(&mut self) {
+        // Hold the slots of the games the player was in or store the games
+        // so they can rejoin them if their session is resumed
+        if let Some(player) = self.player.as_ref().filter(|_| !self.games.is_empty()) {
+            let games = GlobalState::games();
+            let game_ids: Vec<GameID> = self.games.ids().collect();
+            if games.hold_player(player.id, game_ids.clone(), self.id) {
+                self.games.take();
+            } else {
+                games.set_rejoin(player.id, game_ids);
+            }
+        }
         self.remove_games();
+        self.notify_offline();
+        GlobalState::sessions().remove_session(self.id);
         debug!("Session dropped (SID: {})", self.id);
     }
 }
+
+/// Maps an error from routing a packet to the error sent back to the
+/// client. Decoding errors are caused by the client sending a malformed
+/// packet so they are reported as invalid requests rather than the
+/// server being unavailable
+///
+/// `err` The routing error
+fn handle_error(err: &HandleError) -> ServerError {
+    match err {
+        HandleError::Decoding(_) => ServerError::InvalidRequest,
+        HandleError::MissingHandler(_) => ServerError::ServerUnavailable,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        handle_error, packet_log_contents, packet_log_message, trace_id, wait_idle, GameRole,
+        Session, SessionAddr, SessionGames, SessionMessage,
+    };
+    use crate::{
+        servers::main::{
+            config::SessionConfig,
+            debug::DebugComponents,
+            models::{auth::AuthRequest, errors::ServerError, session::DisconnectReason},
+        },
+        state::GlobalState,
+        utils::{
+            components::{Authentication, Components, UserSessions, Util},
+            env::DuplicateLoginPolicy,
+            packet::read_packet,
+            types::PlayerID,
+        },
+    };
+    use blaze_pk::{
+        packet::{IntoResponse, Packet, PacketComponents, PacketType},
+        reader::TdfReader,
+        router::HandleError,
+        tag::TdfType,
+    };
+    use database::Player;
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+    use tokio::{sync::mpsc, time::timeout};
+
+    /// Finds the trace ID line within the provided log message
+    fn find_trace(message: &str) -> Option<&str> {
+        message
+            .lines()
+            .find_map(|line| line.strip_prefix("Trace: "))
+    }
+
+    /// Tests that the read and write log messages for a single
+    /// request share the same trace ID
+    #[test]
+    fn test_trace_id() {
+        let request = Packet::request_empty(7, Components::Util(Util::GetTelemetryServer));
+        let response = Packet::response_empty(&request);
+
+        let trace = trace_id(3, request.header.id, 12);
+        assert_eq!(trace, "3-7-12");
+
+        let read = packet_log_message("Read", "SID: 3", Some(&trace), &request, false);
+        let wrote = packet_log_message("Wrote", "SID: 3", Some(&trace), &response, false);
+        assert_eq!(find_trace(&read), Some("3-7-12"));
+        assert_eq!(find_trace(&read), find_trace(&wrote));
+
+        // The next request from the same session gets a different trace
+        let next = trace_id(3, request.header.id, 13);
+        assert_ne!(next, trace);
+
+        // Packets queued outside of a request don't have a trace
+        let message = packet_log_message("Wrote", "SID: 3", None, &response, false);
+        assert_eq!(find_trace(&message), None);
+    }
+
+    /// Tests that the previous game is replaced when multiple
+    /// games are disabled
+    #[test]
+    fn test_single_game() {
+        let mut games = SessionGames::new(false);
+        assert!(games.set(1, GameRole::Host).is_empty());
+        assert_eq!(games.set(2, GameRole::Player), vec![1]);
+        assert_eq!(games.ids().collect::<Vec<_>>(), vec![2]);
+    }
+
+    /// Tests that a session can be in multiple games and that all
+    /// the games are removed when the session is cleaned up
+    #[test]
+    fn test_multi_game() {
+        let mut games = SessionGames::new(true);
+        assert!(games.set(1, GameRole::Host).is_empty());
+        assert!(games.set(2, GameRole::Player).is_empty());
+        assert_eq!(games.len(), 2);
+
+        // Updating the role of an existing game doesn't add it again
+        assert!(games.set(2, GameRole::Host).is_empty());
+        assert_eq!(games.len(), 2);
+        assert_eq!(games.ids().collect::<Vec<_>>(), vec![1, 2]);
+
+        assert_eq!(games.take(), vec![1, 2]);
+        assert!(games.is_empty());
+        assert!(!games.remove(1));
+    }
+
+    /// Tests that sessions with no activity past the timeout are
+    /// considered idle
+    #[tokio::test]
+    async fn test_idle_timeout() {
+        let idle_timeout = Some(Duration::from_millis(50));
+
+        // Session which has been inactive past the timeout
+        let last_activity = Instant::now() - Duration::from_secs(1);
+        let result = timeout(
+            Duration::from_millis(10),
+            wait_idle(last_activity, idle_timeout),
+        );
+        assert!(result.await.is_ok());
+
+        // Session which was just active
+        let result = timeout(
+            Duration::from_millis(10),
+            wait_idle(Instant::now(), idle_timeout),
+        );
+        assert!(result.await.is_err());
+
+        // Sessions never time out when disabled
+        let result = timeout(Duration::from_millis(10), wait_idle(last_activity, None));
+        assert!(result.await.is_err());
+    }
+
+    /// Tests that authenticating a session notifies the other
+    /// authenticated sessions with only the details of the player
+    #[tokio::test]
+    async fn test_notify_online() {
+        GlobalState::init_test().await;
+        let sessions = GlobalState::sessions();
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+        // Another session which is already authenticated
+        let (sender, mut observer) = mpsc::unbounded_channel();
+        sessions.add_session(SessionAddr::new(9101, sender), ip);
+        sessions.set_player(9101, Some(PlayerID::MAX));
+
+        let (mut session, _receiver, _client) = Session::new_test(9102).await;
+        sessions.add_session(session.addr.clone(), ip);
+        let player = Player::create(
+            GlobalState::database(),
+            format!("presence{}@test.com", std::process::id()),
+            "PresencePlayer".to_string(),
+            String::new(),
+            false,
+        )
+        .await
+        .unwrap();
+        let player_id = player.id;
+        session.set_player(player).await.unwrap();
+
+        // Other tests may also authenticate so find the packet for this player
+        let packet = std::iter::from_fn(|| observer.try_recv().ok())
+            .find_map(|message| match message {
+                SessionMessage::Write(packet)
+                    if Components::from_header(&packet.header)
+                        == Components::UserSessions(UserSessions::SessionDetails) =>
+                {
+                    let mut reader = TdfReader::new(&packet.contents);
+                    reader.until_tag("USER", TdfType::Group).ok()?;
+                    let id: PlayerID = reader.tag("ID").ok()?;
+                    (id == player_id).then_some(packet)
+                }
+                _ => None,
+            })
+            .expect("Expected presence packet");
+
+        let mut reader = TdfReader::new(&packet.contents);
+        assert!(!reader.try_until_tag("DATA", TdfType::Group));
+        let mut reader = TdfReader::new(&packet.contents);
+        reader.until_tag("USER", TdfType::Group).unwrap();
+        let name: String = reader.tag("NAME").unwrap();
+        assert_eq!(name, "PresencePlayer");
+
+        sessions.remove_session(9101);
+        sessions.remove_session(9102);
+    }
+
+    /// Tests that a session which was taken over by a new login doesn't
+    /// notify the other sessions that the player went offline
+    #[tokio::test]
+    async fn test_takeover_stays_online() {
+        GlobalState::init_test().await;
+        let sessions = GlobalState::sessions();
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let player_id = PlayerID::MAX - 1;
+
+        // Another session which is already authenticated
+        let (sender, mut observer) = mpsc::unbounded_channel();
+        sessions.add_session(SessionAddr::new(9201, sender), ip);
+        sessions.set_player(9201, Some(PlayerID::MAX));
+
+        let (mut session, _receiver, _client) = Session::new_test(9202).await;
+        sessions.add_session(session.addr.clone(), ip);
+        session.player = Some(Player {
+            id: player_id,
+            email: "takeover@test.com".to_string(),
+            display_name: "Takeover".to_string(),
+            session_token: None,
+            origin: false,
+            password: String::new(),
+            created_at: None,
+        });
+        assert!(sessions.claim_player(9202, player_id, DuplicateLoginPolicy::Allow));
+
+        // The player logs in on another session taking over the first
+        let (sender, _receiver) = mpsc::unbounded_channel();
+        sessions.add_session(SessionAddr::new(9203, sender), ip);
+        assert!(sessions.claim_player(9203, player_id, DuplicateLoginPolicy::Takeover));
+        drop(session);
+
+        let offline = std::iter::from_fn(|| observer.try_recv().ok()).any(|message| {
+            let SessionMessage::Write(packet) = message else {
+                return false;
+            };
+            Components::from_header(&packet.header)
+                == Components::UserSessions(UserSessions::FetchExtendedData)
+                && TdfReader::new(&packet.contents)
+                    .tag::<PlayerID>("BUID")
+                    .ok()
+                    == Some(player_id)
+        });
+        assert!(!offline);
+
+        sessions.remove_session(9201);
+        sessions.remove_session(9203);
+    }
+
+    /// Tests that a login rejected by the duplicate login policy doesn't
+    /// issue a session token for the player
+    #[tokio::test]
+    async fn test_rejected_login_keeps_token() {
+        GlobalState::init_test().await;
+        let sessions = GlobalState::sessions();
+        let db = GlobalState::database();
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+        let player = Player::create(
+            db,
+            format!("rejected{}@test.com", std::process::id()),
+            "RejectedPlayer".to_string(),
+            String::new(),
+            false,
+        )
+        .await
+        .unwrap();
+        let player_id = player.id;
+
+        // Another session which is already authenticated as the player
+        let (sender, _observer) = mpsc::unbounded_channel();
+        sessions.add_session(SessionAddr::new(9301, sender), ip);
+        sessions.set_player(9301, Some(player_id));
+
+        let (mut session, _receiver, _client) = Session::new_test(9302).await;
+        session.config = Arc::new(SessionConfig {
+            duplicate_login: DuplicateLoginPolicy::Reject,
+            ..Default::default()
+        });
+        sessions.add_session(session.addr.clone(), ip);
+
+        let result = session.set_player(player).await;
+        assert!(matches!(result, Err(ServerError::InvalidAccount)));
+        assert!(session.player.is_none());
+
+        let player = Player::by_id(db, player_id).await.unwrap().unwrap();
+        assert!(player.session_token.is_none());
+
+        sessions.remove_session(9301);
+        sessions.remove_session(9302);
+    }
+
+    /// Tests that disconnecting a session sends the client the reason
+    /// it was disconnected and ends the session loop
+    #[tokio::test]
+    async fn test_disconnect_ends_session() {
+        GlobalState::init_test().await;
+        let (session, receiver, mut client) = Session::new_test(9001).await;
+        let addr = session.addr.clone();
+        let handle = tokio::spawn(session.process(receiver));
+
+        assert!(addr.disconnect(DisconnectReason::Kick));
+        timeout(Duration::from_secs(5), handle)
+            .await
+            .expect("Session loop did not end")
+            .unwrap();
+
+        let packet = read_packet(&mut client, 1024).await.unwrap();
+        assert_eq!(
+            Components::from_header(&packet.header),
+            Components::UserSessions(UserSessions::SessionDisconnected)
+        );
+        let mut reader = TdfReader::new(&packet.contents);
+        let reason: u8 = reader.tag("REAS").unwrap();
+        assert_eq!(reason, DisconnectReason::Kick.value());
+
+        // The session no longer receives messages once closed
+        assert!(!addr.disconnect(DisconnectReason::Kick));
+    }
+
+    /// Tests that malformed packets are responded to with the invalid
+    /// request error rather than the server being unavailable
+    #[test]
+    fn test_decode_error() {
+        let request = Packet::request_empty(4, Components::Authentication(Authentication::Login));
+        let Err(err) = request.decode::<AuthRequest>() else {
+            panic!("Expected malformed packet to fail decoding");
+        };
+
+        let response = handle_error(&HandleError::Decoding(err)).into_response(&request);
+        assert_eq!(response.header.ty, PacketType::Error);
+        assert_eq!(response.header.error, ServerError::InvalidRequest as u16);
+
+        // Packets without a handler are still the server's fault
+        let missing = Packet::request_empty(4, Components::Authentication(Authentication::Login));
+        let response = handle_error(&HandleError::MissingHandler(missing)).into_response(&request);
+        assert_eq!(response.header.error, ServerError::ServerUnavailable as u16);
+    }
+
+    /// Tests that traced sessions log packets in full even when the
+    /// component is ignored and debug logging is disabled
+    #[test]
+    fn test_traced_logging() {
+        let debug = DebugComponents::parse("Util.Ping", "Util.GetTelemetryServer");
+        let ping = Components::Util(Util::Ping);
+        let telemetry = Components::Util(Util::GetTelemetryServer);
+
+        assert_eq!(packet_log_contents(&debug, &ping, false, true), None);
+        assert_eq!(
+            packet_log_contents(&debug, &telemetry, false, true),
+            Some(false)
+        );
+        assert_eq!(packet_log_contents(&debug, &telemetry, false, false), None);
+
+        assert_eq!(packet_log_contents(&debug, &ping, true, true), Some(true));
+        assert_eq!(packet_log_contents(&debug, &ping, true, false), Some(true));
+        assert_eq!(
+            packet_log_contents(&debug, &telemetry, true, true),
+            Some(true)
+        );
+    }
+}
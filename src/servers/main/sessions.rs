@@ -0,0 +1,589 @@
+//! Module for tracking the sessions that are currently connected
+//! to the main server
+
+use super::{models::session::DisconnectReason, session::SessionAddr};
+use crate::utils::{
+    env::DuplicateLoginPolicy,
+    types::{GameID, PlayerID, SessionID},
+};
+use blaze_pk::packet::Packet;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    net::IpAddr,
+    sync::{Mutex, RwLock},
+    time::SystemTime,
+};
+
+/// Structure for tracking the sessions connected to the
+/// main server
+#[derive(Default)]
+pub struct Sessions {
+    /// Map of the session IDs to the session entries
+    values: RwLock<HashMap<SessionID, SessionEntry>>,
+    /// Map of source IP addresses to the number of sessions
+    /// connected from that address
+    connections: Mutex<HashMap<IpAddr, usize>>,
+    /// Whether presence changes are sent to the other sessions
+    presence: bool,
+}
+
+/// Read-only summary of a connected session
+#[derive(Serialize)]
+pub struct SessionSummary {
+    /// The ID of the session
+    pub id: SessionID,
+    /// The ID of the player authenticated on the session
+    pub player_id: Option<PlayerID>,
+    /// The display name of the player authenticated on the session
+    pub display_name: Option<String>,
+    /// The IP address the session connected from
+    pub ip: IpAddr,
+    /// The IDs of the games the player is in
+    pub game_ids: Vec<GameID>,
+    /// The number of seconds the session has been connected for
+    pub duration: u64,
+}
+
+/// Entry for a connected session
+struct SessionEntry {
+    /// The address of the session
+    addr: SessionAddr,
+    /// The IP address the session connected from
+    ip: IpAddr,
+    /// The ID of the player authenticated on the session
+    player_id: Option<PlayerID>,
+    /// The display name of the player authenticated on the session
+    display_name: Option<String>,
+    /// The time the session connected at
+    connected: SystemTime,
+    /// The latency from the session to each of the Quality of
+    /// Service regions as last reported by the client
+    latency: BTreeMap<String, u32>,
+}
+
+impl Sessions {
+    /// Creates a new set of sessions
+    ///
+    /// `presence` Whether presence changes are sent to the other sessions
+    pub fn new(presence: bool) -> Self {
+        Self {
+            presence,
+            ..Default::default()
+        }
+    }
+
+    /// Checks whether presence changes are sent to the other sessions
+    pub fn is_presence_enabled(&self) -> bool {
+        self.presence
+    }
+
+    /// Adds the provided session address to the connected sessions
+    ///
+    /// `addr` The address of the session
+    /// `ip`   The IP address the session connected from
+    pub fn add_session(&self, addr: SessionAddr, ip: IpAddr) {
+        if let Ok(mut connections) = self.connections.lock() {
+            *connections.entry(ip).or_default() += 1;
+        }
+        if let Ok(mut values) = self.values.write() {
+            values.insert(
+                addr.id,
+                SessionEntry {
+                    addr,
+                    ip,
+                    player_id: None,
+                    display_name: None,
+                    connected: SystemTime::now(),
+                    latency: BTreeMap::new(),
+                },
+            );
+        }
+    }
+
+    /// Removes the session with the provided ID from the connected
+    /// sessions
+    ///
+    /// `id` The ID of the session to remove
+    pub fn remove_session(&self, id: SessionID) {
+        let entry = match self.values.write() {
+            Ok(mut values) => values.remove(&id),
+            Err(_) => return,
+        };
+        let Some(entry) = entry else {
+            return;
+        };
+        if let Ok(mut connections) = self.connections.lock() {
+            if let Some(count) = connections.get_mut(&entry.ip) {
+                *count -= 1;
+                if *count == 0 {
+                    connections.remove(&entry.ip);
+                }
+            }
+        }
+    }
+
+    /// Sets the ID of the player authenticated on the session with
+    /// the provided ID
+    ///
+    /// `id`        The ID of the session
+    /// `player_id` The ID of the authenticated player
+    pub fn set_player(&self, id: SessionID, player_id: Option<PlayerID>) {
+        if let Ok(mut values) = self.values.write() {
+            if let Some(entry) = values.get_mut(&id) {
+                entry.player_id = player_id;
+                if player_id.is_none() {
+                    entry.display_name = None;
+                }
+            }
+        }
+    }
+
+    /// Sets the display name of the player authenticated on the
+    /// session with the provided ID
+    ///
+    /// `id`           The ID of the session
+    /// `display_name` The display name of the player
+    pub fn set_display_name(&self, id: SessionID, display_name: String) {
+        if let Ok(mut values) = self.values.write() {
+            if let Some(entry) = values.get_mut(&id) {
+                entry.display_name = Some(display_name);
+            }
+        }
+    }
+
+    /// Claims the player with the provided ID for the session with the
+    /// provided ID applying the provided policy to any other sessions
+    /// that are already authenticated as that player. Returns whether
+    /// the player was claimed
+    ///
+    /// `id`        The ID of the session
+    /// `player_id` The ID of the authenticated player
+    /// `policy`    The policy for existing sessions of the player
+    pub fn claim_player(
+        &self,
+        id: SessionID,
+        player_id: PlayerID,
+        policy: DuplicateLoginPolicy,
+    ) -> bool {
+        let Ok(mut values) = self.values.write() else {
+            return false;
+        };
+        if policy != DuplicateLoginPolicy::Allow {
+            let mut existing = values
+                .values_mut()
+                .filter(|entry| entry.addr.id != id && entry.player_id == Some(player_id))
+                .peekable();
+            if existing.peek().is_some() {
+                if policy == DuplicateLoginPolicy::Reject {
+                    return false;
+                }
+                for entry in existing {
+                    entry.player_id = None;
+                    entry.display_name = None;
+                    entry.addr.disconnect(DisconnectReason::Kick);
+                }
+            }
+        }
+        if let Some(entry) = values.get_mut(&id) {
+            entry.player_id = Some(player_id);
+        }
+        true
+    }
+
+    /// Sets the latency map reported by the session with the
+    /// provided ID
+    ///
+    /// `id`      The ID of the session
+    /// `latency` The latency to each of the regions
+    pub fn set_latency(&self, id: SessionID, latency: BTreeMap<String, u32>) {
+        if let Ok(mut values) = self.values.write() {
+            if let Some(entry) = values.get_mut(&id) {
+                entry.latency = latency;
+            }
+        }
+    }
+
+    /// Finds the latency map reported by a session authenticated as
+    /// the player with the provided ID
+    ///
+    /// `player_id` The ID of the player
+    pub fn find_player_latency(&self, player_id: PlayerID) -> Option<BTreeMap<String, u32>> {
+        let values = self.values.read().ok()?;
+        values
+            .values()
+            .find(|entry| entry.player_id == Some(player_id))
+            .map(|entry| entry.latency.clone())
+    }
+
+    /// Finds the addresses of all the sessions that are authenticated
+    /// as the player with the provided ID
+    ///
+    /// `player_id` The ID of the player
+    pub fn find_player_sessions(&self, player_id: PlayerID) -> Vec<SessionAddr> {
+        let Ok(values) = self.values.read() else {
+            return Vec::new();
+        };
+        values
+            .values()
+            .filter(|entry| entry.player_id == Some(player_id))
+            .map(|entry| entry.addr.clone())
+            .collect()
+    }
+
+    /// Sends the provided presence packet to all the authenticated
+    /// sessions other than the session the presence change is for.
+    /// Returns the number of sessions the packet was sent to
+    ///
+    /// `source` The ID of the session whose presence changed
+    /// `packet` The presence packet to send
+    pub fn notify_presence(&self, source: SessionID, packet: &Packet) -> usize {
+        let Ok(values) = self.values.read() else {
+            return 0;
+        };
+        let mut count = 0;
+        for entry in values.values() {
+            if entry.addr.id == source || entry.player_id.is_none() {
+                continue;
+            }
+            entry.addr.push(packet.clone());
+            count += 1;
+        }
+        count
+    }
+
+    /// Checks whether another session is allowed to connect from the
+    /// provided IP address by checking that the number of sessions
+    /// connected from the address is below the provided limit. Loopback
+    /// addresses and a limit of zero are not limited.
+    ///
+    /// `ip`    The IP address of the new connection
+    /// `limit` The maximum number of sessions per IP address
+    pub fn can_connect(&self, ip: IpAddr, limit: usize) -> bool {
+        if limit == 0 || ip.is_loopback() {
+            return true;
+        }
+        self.connections
+            .lock()
+            .map(|connections| connections.get(&ip).copied().unwrap_or(0) < limit)
+            .unwrap_or(true)
+    }
+
+    /// Sets whether the session with the provided ID is traced. Returns
+    /// whether the session was found
+    ///
+    /// `id`     The ID of the session
+    /// `traced` Whether the session should be traced
+    pub fn set_traced(&self, id: SessionID, traced: bool) -> bool {
+        let Ok(values) = self.values.read() else {
+            return false;
+        };
+        values
+            .get(&id)
+            .map(|entry| entry.addr.set_traced(traced))
+            .unwrap_or(false)
+    }
+
+    /// Creates summaries of all the connected sessions sorted by the
+    /// session ID. The game IDs of the summaries are left unset
+    pub fn summaries(&self) -> Vec<SessionSummary> {
+        let Ok(values) = self.values.read() else {
+            return Vec::new();
+        };
+        let mut summaries: Vec<SessionSummary> = values
+            .values()
+            .map(|entry| SessionSummary {
+                id: entry.addr.id,
+                player_id: entry.player_id,
+                display_name: entry.display_name.clone(),
+                ip: entry.ip,
+                game_ids: Vec::new(),
+                duration: entry.connected.elapsed().unwrap_or_default().as_secs(),
+            })
+            .collect();
+        summaries.sort_by_key(|summary| summary.id);
+        summaries
+    }
+
+    /// Obtains the number of currently connected sessions
+    pub fn count(&self) -> usize {
+        self.values.read().map(|values| values.len()).unwrap_or(0)
+    }
+
+    /// Obtains the number of unique players that are authenticated
+    /// on the currently connected sessions
+    pub fn player_count(&self) -> usize {
+        let Ok(values) = self.values.read() else {
+            return 0;
+        };
+        values
+            .values()
+            .filter_map(|entry| entry.player_id)
+            .collect::<BTreeSet<_>>()
+            .len()
+    }
+
+    /// Sends the provided message to all of the connected sessions
+    /// returning the number of sessions the message was sent to
+    ///
+    /// `message` The message to send
+    pub fn broadcast(&self, message: &str) -> usize {
+        let Ok(values) = self.values.read() else {
+            return 0;
+        };
+        values
+            .values()
+            .filter(|entry| entry.addr.send_message(message.to_string()))
+            .count()
+    }
+
+    /// Disconnects all the connected sessions notifying them of the
+    /// provided reason. Returns the number of sessions disconnected
+    ///
+    /// `reason` The reason the sessions are being closed
+    fn disconnect_all(&self, reason: DisconnectReason) -> usize {
+        let Ok(values) = self.values.read() else {
+            return 0;
+        };
+        values
+            .values()
+            .filter(|entry| entry.addr.disconnect(reason))
+            .count()
+    }
+
+    /// Disconnects all the sessions authenticated as the player with the
+    /// provided ID notifying them that they were kicked. Returns the
+    /// number of sessions disconnected
+    ///
+    /// `player_id` The ID of the player
+    pub fn kick_player(&self, player_id: PlayerID) -> usize {
+        self.find_player_sessions(player_id)
+            .iter()
+            .filter(|addr| addr.disconnect(DisconnectReason::Kick))
+            .count()
+    }
+
+    /// Disconnects all the connected sessions notifying them that the
+    /// server is shutting down. Returns the number of sessions disconnected
+    pub fn shutdown(&self) -> usize {
+        self.disconnect_all(DisconnectReason::Shutdown)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Sessions;
+    use crate::{
+        env::DuplicateLoginPolicy,
+        game::models::FetchExtendedData,
+        servers::main::{
+            models::session::DisconnectReason,
+            session::{SessionAddr, SessionMessage},
+        },
+        utils::components::{Components, UserSessions},
+    };
+    use blaze_pk::packet::{Packet, PacketComponents};
+    use std::{
+        collections::BTreeMap,
+        net::{IpAddr, Ipv4Addr},
+    };
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_broadcast() {
+        let sessions = Sessions::default();
+
+        let (sender_a, mut receiver_a) = mpsc::unbounded_channel();
+        let (sender_b, mut receiver_b) = mpsc::unbounded_channel();
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        sessions.add_session(SessionAddr::new(1, sender_a), ip);
+        sessions.add_session(SessionAddr::new(2, sender_b), ip);
+
+        assert_eq!(sessions.count(), 2);
+        assert_eq!(sessions.broadcast("Hello"), 2);
+
+        for receiver in [&mut receiver_a, &mut receiver_b] {
+            match receiver.try_recv() {
+                Ok(SessionMessage::Message(message)) => assert_eq!(message, "Hello"),
+                _ => panic!("Expected message"),
+            }
+        }
+
+        sessions.remove_session(1);
+        assert_eq!(sessions.count(), 1);
+        assert_eq!(sessions.broadcast("Hello"), 1);
+        assert!(receiver_a.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_player_latency() {
+        let sessions = Sessions::default();
+        let (sender, _receiver) = mpsc::unbounded_channel();
+        sessions.add_session(SessionAddr::new(1, sender), IpAddr::V4(Ipv4Addr::LOCALHOST));
+        sessions.set_player(1, Some(5));
+        assert_eq!(sessions.find_player_latency(5), Some(BTreeMap::new()));
+
+        let latency: BTreeMap<String, u32> =
+            [("ea-sjc", 156), ("rs-iad", 0xFFF0FFF), ("rs-lhr", 48)]
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect();
+        sessions.set_latency(1, latency.clone());
+        assert_eq!(sessions.find_player_latency(5), Some(latency));
+        assert_eq!(sessions.find_player_latency(6), None);
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let sessions = Sessions::default();
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        sessions.add_session(SessionAddr::new(1, sender), IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        // Closed sessions aren't counted
+        let (sender, _) = mpsc::unbounded_channel();
+        sessions.add_session(SessionAddr::new(2, sender), IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        assert_eq!(sessions.shutdown(), 1);
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(SessionMessage::Disconnect(DisconnectReason::Shutdown))
+        ));
+    }
+
+    #[test]
+    fn test_connection_limit() {
+        let sessions = Sessions::default();
+        let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 10));
+        let other = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 11));
+
+        for id in 1..=3 {
+            assert!(sessions.can_connect(ip, 3));
+            let (sender, _) = mpsc::unbounded_channel();
+            sessions.add_session(SessionAddr::new(id, sender), ip);
+        }
+
+        // The fourth connection from the same address is refused
+        assert!(!sessions.can_connect(ip, 3));
+        assert!(sessions.can_connect(other, 3));
+        assert!(sessions.can_connect(ip, 0));
+
+        // Loopback addresses are exempt from the limit
+        let loopback = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        for id in 4..=6 {
+            let (sender, _) = mpsc::unbounded_channel();
+            sessions.add_session(SessionAddr::new(id, sender), loopback);
+        }
+        assert!(sessions.can_connect(loopback, 3));
+
+        // Closing a session frees up a slot for the address
+        sessions.remove_session(1);
+        assert!(sessions.can_connect(ip, 3));
+    }
+
+    #[test]
+    fn test_notify_presence() {
+        let sessions = Sessions::default();
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+        let (sender_a, mut receiver_a) = mpsc::unbounded_channel();
+        let (sender_b, mut receiver_b) = mpsc::unbounded_channel();
+        let (sender_c, mut receiver_c) = mpsc::unbounded_channel();
+        sessions.add_session(SessionAddr::new(1, sender_a), ip);
+        sessions.add_session(SessionAddr::new(2, sender_b), ip);
+        sessions.add_session(SessionAddr::new(3, sender_c), ip);
+
+        // The second session is authenticated while the third isn't
+        sessions.set_player(2, Some(20));
+
+        // The first session authenticates
+        sessions.set_player(1, Some(10));
+        let packet = Packet::notify(
+            Components::UserSessions(UserSessions::FetchExtendedData),
+            FetchExtendedData { player_id: 10 },
+        );
+        assert_eq!(sessions.notify_presence(1, &packet), 1);
+
+        match receiver_b.try_recv() {
+            Ok(SessionMessage::Write(packet)) => assert_eq!(
+                Components::from_header(&packet.header),
+                Components::UserSessions(UserSessions::FetchExtendedData)
+            ),
+            _ => panic!("Expected presence packet"),
+        }
+        assert!(receiver_a.try_recv().is_err());
+        assert!(receiver_c.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_summaries() {
+        let sessions = Sessions::default();
+        let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 10));
+
+        let (sender_a, _receiver_a) = mpsc::unbounded_channel();
+        let (sender_b, _receiver_b) = mpsc::unbounded_channel();
+        sessions.add_session(
+            SessionAddr::new(2, sender_b),
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+        );
+        sessions.add_session(SessionAddr::new(1, sender_a), ip);
+        assert!(sessions.claim_player(1, 10, DuplicateLoginPolicy::Reject));
+        sessions.set_display_name(1, "Test10".to_string());
+
+        let summaries = sessions.summaries();
+        assert_eq!(summaries.len(), 2);
+
+        let summary = &summaries[0];
+        assert_eq!(summary.id, 1);
+        assert_eq!(summary.player_id, Some(10));
+        assert_eq!(summary.display_name.as_deref(), Some("Test10"));
+        assert_eq!(summary.ip, ip);
+        assert!(summary.game_ids.is_empty());
+        assert_eq!(summary.duration, 0);
+
+        // Unauthenticated sessions have no player details
+        assert_eq!(summaries[1].id, 2);
+        assert_eq!(summaries[1].player_id, None);
+        assert_eq!(summaries[1].display_name, None);
+
+        // Logging out clears the player details
+        sessions.set_player(1, None);
+        assert_eq!(sessions.summaries()[0].display_name, None);
+    }
+
+    #[test]
+    fn test_duplicate_login() {
+        let sessions = Sessions::default();
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+        let (sender_a, mut receiver_a) = mpsc::unbounded_channel();
+        let (sender_b, _receiver_b) = mpsc::unbounded_channel();
+        sessions.add_session(SessionAddr::new(1, sender_a), ip);
+        sessions.add_session(SessionAddr::new(2, sender_b), ip);
+        assert!(sessions.claim_player(1, 10, DuplicateLoginPolicy::Reject));
+
+        // Re-authenticating on the same session is always allowed
+        assert!(sessions.claim_player(1, 10, DuplicateLoginPolicy::Reject));
+
+        // Rejection leaves the first session intact
+        assert!(!sessions.claim_player(2, 10, DuplicateLoginPolicy::Reject));
+        let found = sessions.find_player_sessions(10);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, 1);
+        assert!(receiver_a.try_recv().is_err());
+
+        // Takeover disconnects the first session
+        assert!(sessions.claim_player(2, 10, DuplicateLoginPolicy::Takeover));
+        let found = sessions.find_player_sessions(10);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, 2);
+        assert!(matches!(
+            receiver_a.try_recv(),
+            Ok(SessionMessage::Disconnect(DisconnectReason::Kick))
+        ));
+
+        // Allowing leaves both sessions authenticated
+        assert!(sessions.claim_player(1, 10, DuplicateLoginPolicy::Allow));
+        assert_eq!(sessions.find_player_sessions(10).len(), 2);
+        assert!(receiver_a.try_recv().is_err());
+    }
+}
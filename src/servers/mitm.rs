@@ -4,7 +4,7 @@
 use crate::{
     retriever::Retriever,
     state::GlobalState,
-    utils::{components::Components, env, packet::append_packet_decoded},
+    utils::{components::Components, env, net::bind_address, packet::append_packet_decoded},
 };
 use blaze_pk::packet::{Packet, PacketType};
 use blaze_ssl_async::stream::BlazeStream;
@@ -20,24 +20,27 @@ use tokio::{
 /// proxy between this server and the official servers. All packets send and
 /// recieved by this server are forwarded to the official servers and are logged
 /// using the debug logging.
-pub async fn start_server() {
+pub async fn start_server() -> io::Result<()> {
     // MITM server is unable to start if the retriever is disabled or fails to connect
     let Some(retriever) = GlobalState::retriever() else {
         error!("Server is in MITM mode but was unable to connect to the official servers. Stopping server.");
-        panic!();
+        return Err(io::Error::new(
+            io::ErrorKind::NotConnected,
+            "Unable to connect to the official servers",
+        ));
     };
 
     // Initializing the underlying TCP listener
     let listener = {
         let port = env::from_env(env::MAIN_PORT);
-        match TcpListener::bind(("0.0.0.0", port)).await {
+        match TcpListener::bind((bind_address(), port)).await {
             Ok(value) => {
                 info!("Started MITM server (Port: {})", port);
                 value
             }
-            Err(_) => {
+            Err(err) => {
                 error!("Failed to bind MITM server (Port: {})", port);
-                panic!()
+                return Err(err);
             }
         }
     };
@@ -1,23 +1,29 @@
 //! Module for the Redirector server which handles redirecting the clients
 //! to the correct address for the main server.
 
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+};
 
-use crate::{env, utils::net::public_address};
+use crate::{
+    env,
+    utils::net::{bind_address, public_address},
+};
 use log::{error, info};
 use tokio::net::UdpSocket;
 
-pub async fn start_server() {
+pub async fn start_server() -> io::Result<()> {
     let socket = {
         let port = env::from_env(env::QOS_PORT);
-        match UdpSocket::bind(("0.0.0.0", port)).await {
+        match UdpSocket::bind((bind_address(), port)).await {
             Ok(value) => {
                 info!("Started QOS server (Port: {})", port);
                 value
             }
-            Err(_) => {
+            Err(err) => {
                 error!("Failed to bind QOS server (Port: {})", port);
-                panic!()
+                return Err(err);
             }
         }
     };
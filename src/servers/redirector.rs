@@ -7,6 +7,7 @@ use crate::{
         components::{Components, Redirector},
         constants,
         models::{InstanceDetails, InstanceNet},
+        net::bind_address,
     },
 };
 use blaze_pk::packet::Packet;
@@ -18,18 +19,18 @@ use tokio::{io::AsyncWriteExt, select, time::sleep};
 /// Starts the Redirector server this server is what the Mass Effect 3 game
 /// client initially reaches out to. This server is responsible for telling
 /// the client where the server is and whether it should use SSLv3 to connect.
-pub async fn start_server() {
+pub async fn start_server() -> io::Result<()> {
     // Initializing the underlying TCP listener
     let listener = {
         let port = env::from_env(env::REDIRECTOR_PORT);
-        match BlazeListener::bind(("0.0.0.0", port)).await {
+        match BlazeListener::bind((bind_address(), port)).await {
             Ok(value) => {
                 info!("Started Redirector server (Port: {})", port);
                 value
             }
-            Err(_) => {
+            Err(err) => {
                 error!("Failed to bind Redirector server (Port: {})", port);
-                panic!()
+                return Err(err);
             }
         }
     };
@@ -1,7 +1,10 @@
 //! Module for the Redirector server which handles redirecting the clients
 //! to the correct address for the main server.
 
-use crate::env;
+use crate::{
+    env,
+    utils::{models::Port, net::bind_address},
+};
 use log::{debug, error, info};
 use std::{collections::HashMap, io};
 use tokio::{
@@ -9,19 +12,26 @@ use tokio::{
     net::{TcpListener, TcpStream},
 };
 
-pub async fn start_server() {
+/// Starts the telemetry server on the port from the environment
+/// variables
+pub async fn start_server() -> io::Result<()> {
+    serve(env::from_env(env::TELEMETRY_PORT)).await
+}
+
+/// Binds the telemetry server to the provided port and handles
+/// reading the messages from the accepted connections
+///
+/// `port` The port to bind to
+async fn serve(port: Port) -> io::Result<()> {
     // Initializing the underlying TCP listener
-    let listener = {
-        let port = env::from_env(env::TELEMETRY_PORT);
-        match TcpListener::bind(("0.0.0.0", port)).await {
-            Ok(value) => {
-                info!("Started Telemetry server (Port: {})", port);
-                value
-            }
-            Err(_) => {
-                error!("Failed to bind Telemetry server (Port: {})", port);
-                panic!()
-            }
+    let listener = match TcpListener::bind((bind_address(), port)).await {
+        Ok(value) => {
+            info!("Started Telemetry server (Port: {})", port);
+            value
+        }
+        Err(err) => {
+            error!("Failed to bind Telemetry server (Port: {})", port);
+            return Err(err);
         }
     };
 
@@ -130,3 +140,22 @@ fn decode_tlm3(slice: &mut [u8]) -> String {
         format!("{slice:?}")
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::serve;
+    use std::time::Duration;
+    use tokio::{net::TcpListener, time::timeout};
+
+    /// Tests that failing to bind the server returns an error
+    /// rather than panicking
+    #[tokio::test]
+    async fn test_bind_error() {
+        // Occupy a port so that binding the server fails
+        let listener = TcpListener::bind(("0.0.0.0", 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let result = timeout(Duration::from_secs(5), serve(port)).await;
+        assert!(matches!(result, Ok(Err(_))));
+    }
+}
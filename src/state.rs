@@ -1,5 +1,17 @@
-use crate::{env, game::manager::Games, leaderboard::Leaderboard, retriever::Retriever};
-use database::{self, DatabaseConnection, DatabaseType};
+use crate::{
+    env,
+    game::{config::GameConfig, manager::Games},
+    leaderboard::Leaderboard,
+    metrics::Metrics,
+    retriever::Retriever,
+    servers::main::sessions::Sessions,
+    utils::validate::BlockedNames,
+};
+use database::{self, DatabaseConnection, DatabaseType, PoolOptions};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use tokio::join;
 
 /// Global state that is shared throughout the application this
@@ -8,10 +20,14 @@ use tokio::join;
 pub enum GlobalState {
     Unset,
     Set {
-        games: Games,
+        games: Arc<Games>,
         db: DatabaseConnection,
         retriever: Option<Retriever>,
         leaderboard: Leaderboard,
+        metrics: Metrics,
+        sessions: Sessions,
+        blocked_names: BlockedNames,
+        started_at: Instant,
     },
 }
 
@@ -23,10 +39,19 @@ impl GlobalState {
     /// the option GLOBAL_STATE after everything is
     /// initialized.
     pub async fn init() {
+        let started_at = Instant::now();
         let (db, retriever) = join!(Self::init_database(), Retriever::new());
 
-        let games: Games = Games::default();
+        let games: Arc<Games> = Arc::new(Games::new(
+            env::from_env(env::GAME_ID_BASE),
+            env::from_env(env::GAME_ID_RANGE),
+            env::from_env(env::MAX_QUEUE_LENGTH),
+            GameConfig::from_env(),
+        ));
         let leaderboard: Leaderboard = Leaderboard::default();
+        let metrics: Metrics = Metrics::new();
+        let sessions: Sessions = Sessions::new(env::from_env(env::PRESENCE));
+        let blocked_names: BlockedNames = BlockedNames::from_env();
 
         unsafe {
             GLOBAL_STATE = GlobalState::Set {
@@ -34,10 +59,49 @@ impl GlobalState {
                 games,
                 retriever,
                 leaderboard,
+                metrics,
+                sessions,
+                blocked_names,
+                started_at,
             };
         }
     }
 
+    /// Initializes the global state for tests using the default
+    /// configuration and a database file in the temporary directory.
+    /// The state is only initialized once and is shared between tests
+    #[cfg(test)]
+    pub async fn init_test() {
+        static INIT: tokio::sync::OnceCell<()> = tokio::sync::OnceCell::const_new();
+        INIT.get_or_init(|| async {
+            let file =
+                std::env::temp_dir().join(format!("pocket-relay-test-{}.db", std::process::id()));
+            std::fs::remove_file(&file).ok();
+            // Tests run on separate runtimes so the database is stored in
+            // a file rather than memory to keep it when connections close
+            let db = database::connect(
+                DatabaseType::Sqlite(file.to_string_lossy().to_string()),
+                PoolOptions::new(1, 0, 0),
+            )
+            .await;
+
+            unsafe {
+                GLOBAL_STATE = GlobalState::Set {
+                    db,
+                    games: Arc::new(Games::default()),
+                    retriever: None,
+                    leaderboard: Leaderboard::default(),
+                    metrics: Metrics::new(),
+                    // Presence is enabled so that it can be tested
+                    sessions: Sessions::new(true),
+                    blocked_names: BlockedNames::default(),
+                    started_at: Instant::now(),
+                };
+            }
+        })
+        .await;
+    }
+
     /// Initializes the connection with the database using the url or file
     /// from the environment variables
     async fn init_database() -> DatabaseConnection {
@@ -50,7 +114,12 @@ impl GlobalState {
             );
             DatabaseType::MySQL(url)
         };
-        database::connect(ty).await
+        let pool = PoolOptions::new(
+            env::from_env(env::DATABASE_MAX_CONNECTIONS),
+            env::from_env(env::DATABASE_MIN_CONNECTIONS),
+            env::from_env(env::DATABASE_ACQUIRE_TIMEOUT),
+        );
+        database::connect(ty, pool).await
     }
 
     /// Obtains a static reference to the database connection
@@ -66,7 +135,7 @@ impl GlobalState {
 
     /// Obtains a static reference to the games manager stored
     /// on the global state
-    pub fn games() -> &'static Games {
+    pub fn games() -> &'static Arc<Games> {
         unsafe {
             match &GLOBAL_STATE {
                 GlobalState::Set { games, .. } => games,
@@ -96,4 +165,48 @@ impl GlobalState {
             }
         }
     }
+
+    /// Obtains a static reference to the metrics stored on
+    /// the global state
+    pub fn metrics() -> &'static Metrics {
+        unsafe {
+            match &GLOBAL_STATE {
+                GlobalState::Set { metrics, .. } => metrics,
+                GlobalState::Unset => panic!("Global state not initialized"),
+            }
+        }
+    }
+
+    /// Obtains a static reference to the sessions stored on
+    /// the global state
+    pub fn sessions() -> &'static Sessions {
+        unsafe {
+            match &GLOBAL_STATE {
+                GlobalState::Set { sessions, .. } => sessions,
+                GlobalState::Unset => panic!("Global state not initialized"),
+            }
+        }
+    }
+
+    /// Obtains a static reference to the blocked display names
+    /// stored on the global state
+    pub fn blocked_names() -> &'static BlockedNames {
+        unsafe {
+            match &GLOBAL_STATE {
+                GlobalState::Set { blocked_names, .. } => blocked_names,
+                GlobalState::Unset => panic!("Global state not initialized"),
+            }
+        }
+    }
+
+    /// Obtains the amount of time that has passed since the global
+    /// state was initialized when the server started
+    pub fn uptime() -> Duration {
+        unsafe {
+            match &GLOBAL_STATE {
+                GlobalState::Set { started_at, .. } => started_at.elapsed(),
+                GlobalState::Unset => panic!("Global state not initialized"),
+            }
+        }
+    }
 }
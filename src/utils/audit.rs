@@ -0,0 +1,50 @@
+//! Audit logging for game lifecycle events. Audit entries are logged
+//! to a separate logging target which is written to its own log file
+//! as key=value pairs so that they can be parsed by moderation tools.
+
+use log::info;
+use std::fmt::{Display, Write};
+
+/// The logging target used for audit entries
+pub const TARGET: &str = "audit";
+
+/// Logs an audit entry for the provided event with the provided
+/// list of key value fields
+///
+/// `event`  The name of the event
+/// `fields` The key value fields for the event
+pub fn log_event(event: &str, fields: &[(&str, &dyn Display)]) {
+    info!(target: TARGET, "{}", format_entry(event, fields));
+}
+
+/// Formats an audit entry as a list of key=value pairs starting with
+/// the event name. Values containing spaces are quoted.
+///
+/// `event`  The name of the event
+/// `fields` The key value fields for the event
+fn format_entry(event: &str, fields: &[(&str, &dyn Display)]) -> String {
+    let mut output = format!("event={event}");
+    for (key, value) in fields {
+        let value = value.to_string();
+        if value.contains(' ') {
+            let _ = write!(output, " {key}={value:?}");
+        } else {
+            let _ = write!(output, " {key}={value}");
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::format_entry;
+
+    #[test]
+    fn test_format_entry() {
+        let entry = format_entry("player_removed", &[("game_id", &1), ("reason", &"Kick")]);
+        assert_eq!(entry, "event=player_removed game_id=1 reason=Kick");
+
+        let entry = format_entry("game_created", &[("name", &"My Game")]);
+        assert_eq!(entry, "event=game_created name=\"My Game\"");
+    }
+}
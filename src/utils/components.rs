@@ -263,6 +263,7 @@ define_components! {
             SessionDetails (0x2)
             UpdateExtendedDataAttribute (0x5)
             FetchExtendedData (0x3)
+            SessionDisconnected (0x4)
         }
     }
 }
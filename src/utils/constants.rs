@@ -1,5 +1,12 @@
 /// The server version extracted from the Cargo.toml
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The git commit hash the server was built from if it was
+/// provided through the GIT_HASH variable at build time
+pub const GIT_HASH: Option<&str> = option_env!("GIT_HASH");
+/// Server SRC version
+pub const SRC_VERSION: &str = "303107";
+/// Blaze version reported to clients
+pub const BLAZE_VERSION: &str = "Blaze 3.15.08.0 (CL# 1629389)";
 /// The external address of the server. This address is whats used in
 /// the system hosts file as a redirect so theres no need to use any
 /// other address.
@@ -0,0 +1,36 @@
+//! Background job that periodically applies the galaxy at war decay to
+//! all players so that the galaxy at war values reflect inactivity even
+//! for players that don't log in
+
+use crate::{state::GlobalState, utils::env};
+use database::GalaxyAtWar;
+use log::{debug, error};
+use std::time::Duration;
+use tokio::time::interval;
+
+/// The number of galaxy at war entries to decay at a time
+const BATCH_SIZE: u64 = 100;
+
+/// Spawns the task that periodically applies the configured galaxy at
+/// war decay to all players. Nothing is spawned when the decay interval
+/// or the daily decay are zero
+pub fn start_decay_job() {
+    let period = Duration::from_secs(env::from_env(env::GAW_DECAY_INTERVAL));
+    let decay: f32 = env::from_env(env::GAW_DAILY_DECAY);
+    if period.is_zero() || decay <= 0.0 {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = interval(period);
+        loop {
+            interval.tick().await;
+            let db = GlobalState::database();
+            match GalaxyAtWar::decay_all(db, decay, BATCH_SIZE).await {
+                Ok(0) => {}
+                Ok(count) => debug!("Applied galaxy at war decay (Count: {})", count),
+                Err(err) => error!("Failed to apply galaxy at war decay: {:?}", err),
+            }
+        }
+    });
+}
@@ -1,39 +1,130 @@
 use log::LevelFilter;
 use std::str::FromStr;
 
-use super::models::Port;
+use super::{models::Port, net};
 
-pub const REDIRECTOR_PORT: (&str, Port) = ("PR_REDIRECTOR_PORT", 42127);
-pub const MAIN_PORT: (&str, Port) = ("PR_MAIN_PORT", 14219);
-pub const HTTP_PORT: (&str, Port) = ("PR_HTTP_PORT", 80);
-pub const TELEMETRY_PORT: (&str, Port) = ("PR_TELEMETRY_PORT", 9988);
-pub const QOS_PORT: (&str, Port) = ("PR_QOS_PORT", 17499);
+/// Defines the constants for the environment variables along with a
+/// list of every variable and the function for checking whether a value
+/// set for it is valid. Variables without a validation function are
+/// checked by parsing the value as their type
+macro_rules! env_vars {
+    (@valid $ty:ty, $valid:expr) => {
+        $valid
+    };
+    (@valid $ty:ty) => {
+        <$ty as EnvValue>::is_valid
+    };
+    ($($name:ident: $ty:ty = ($key:literal, $default:expr) $(=> $valid:expr)?;)*) => {
+        $(pub const $name: (&str, $ty) = ($key, $default);)*
 
-pub const MENU_MESSAGE: (&str, &str) = (
-    "PR_MENU_MESSAGE",
-    "<font color='#B2B2B2'>Pocket Relay</font> - <font color='#FFFF66'>Logged as: {n}</font>",
-);
+        /// The keys of every variable along with the function for
+        /// checking whether a value for the variable is valid
+        const VARIABLES: &[(&str, fn(&str) -> bool)] = &[
+            $(($key, env_vars!(@valid $ty $(, $valid)?)),)*
+        ];
+    };
+}
 
-pub const DATABASE_FILE: (&str, &str) = ("PR_DATABASE_FILE", "data/app.db");
-pub const DATABASE_URL: &str = "PR_DATABASE_URL";
+env_vars! {
+    BIND_ADDRESS: &str = ("PR_BIND_ADDRESS", "0.0.0.0") => net::is_valid_bind_address;
+    PUBLIC_ADDRESS: &str = ("PR_PUBLIC_ADDRESS", "") => net::is_valid_public_address;
+    PUBLIC_IP_FALLBACK: &str = ("PR_PUBLIC_IP_FALLBACK", "") => net::is_valid_public_address;
+
+    REDIRECTOR_PORT: Port = ("PR_REDIRECTOR_PORT", 42127);
+    MAIN_PORT: Port = ("PR_MAIN_PORT", 14219);
+    HTTP_PORT: Port = ("PR_HTTP_PORT", 80);
+    TELEMETRY_PORT: Port = ("PR_TELEMETRY_PORT", 9988);
+    QOS_PORT: Port = ("PR_QOS_PORT", 17499);
+
+    MAX_PACKET_SIZE: usize = ("PR_MAX_PACKET_SIZE", 1024 * 1024);
+    MAX_SESSIONS_PER_IP: usize = ("PR_MAX_SESSIONS_PER_IP", 0);
+    ERROR_MESSAGES: bool = ("PR_ERROR_MESSAGES", false);
+    SESSION_IDLE_TIMEOUT: u64 = ("PR_SESSION_IDLE_TIMEOUT", 0);
+    SESSION_WRITE_TIMEOUT: u64 = ("PR_SESSION_WRITE_TIMEOUT", 0);
+    PRESENCE: bool = ("PR_PRESENCE", false);
+    SILENT_LOGIN: bool = ("PR_SILENT_LOGIN", true);
+    MAX_SUSPEND_PING: u32 = ("PR_MAX_SUSPEND_PING", 90000000);
+    DUPLICATE_LOGIN: DuplicateLoginPolicy = ("PR_DUPLICATE_LOGIN", DuplicateLoginPolicy::Allow);
+    SESSION_BPS: &str = ("PR_SESSION_BPS", "ea-sjc");
+    SESSION_COUNTRY: &str = ("PR_SESSION_COUNTRY", "");
+    SESSION_LOCALE: &str = ("PR_SESSION_LOCALE", "");
+    GEOLOCATION_FILE: &str = ("PR_GEOLOCATION_FILE", "");
+    CLIENT_VERSIONS: &str = ("PR_CLIENT_VERSIONS", "");
+
+    MENU_MESSAGE: &str = (
+        "PR_MENU_MESSAGE",
+        "<font color='#B2B2B2'>Pocket Relay</font> - <font color='#FFFF66'>Logged as: {n}</font>"
+    );
+    MENU_MESSAGE_FILE: &str = ("PR_MENU_MESSAGE_FILE", "");
+    ENTITLEMENTS_FILE: &str = ("PR_ENTITLEMENTS_FILE", "");
+    BLOCKED_NAMES: &str = ("PR_BLOCKED_NAMES", "");
+    BLOCKED_NAMES_FILE: &str = ("PR_BLOCKED_NAMES_FILE", "");
+
+    DATABASE_FILE: &str = ("PR_DATABASE_FILE", "data/app.db");
+    DATABASE_MAX_CONNECTIONS: u32 = ("PR_DATABASE_MAX_CONNECTIONS", 0);
+    DATABASE_MIN_CONNECTIONS: u32 = ("PR_DATABASE_MIN_CONNECTIONS", 0);
+    DATABASE_ACQUIRE_TIMEOUT: u64 = ("PR_DATABASE_ACQUIRE_TIMEOUT", 0);
+    DATABASE_RETRY_ATTEMPTS: u32 = ("PR_DATABASE_RETRY_ATTEMPTS", 3);
+    DATABASE_RETRY_BACKOFF: u64 = ("PR_DATABASE_RETRY_BACKOFF", 100);
+
+    GAW_DAILY_DECAY: f32 = ("PR_GAW_DAILY_DECAY", 0.0);
+    GAW_DECAY_INTERVAL: u64 = ("PR_GAW_DECAY_INTERVAL", 0);
+    GAW_PROMOTIONS: bool = ("PR_GAW_PROMOTIONS", true);
+
+    GAME_SETTING_MASK: u16 = ("PR_GAME_SETTING_MASK", u16::MAX);
+    GAME_MAX_ATTRIBUTES: usize = ("PR_GAME_MAX_ATTRIBUTES", 64);
+    GAME_MAX_ATTRIBUTE_KEY_LENGTH: usize = ("PR_GAME_MAX_ATTRIBUTE_KEY_LENGTH", 64);
+    GAME_MAX_ATTRIBUTE_VALUE_LENGTH: usize = ("PR_GAME_MAX_ATTRIBUTE_VALUE_LENGTH", 256);
+    GAME_MAX_PLAYERS: usize = ("PR_GAME_MAX_PLAYERS", 4);
+    GAME_LABEL_ATTRIBUTE: &str = ("PR_GAME_LABEL_ATTRIBUTE", "name");
+    NAT_CHECK: bool = ("PR_NAT_CHECK", false);
+    MULTI_GAME: bool = ("PR_MULTI_GAME", false);
+    ENFORCE_GAME_ADMINS: bool = ("PR_ENFORCE_GAME_ADMINS", false);
+    REJOIN_WINDOW: u64 = ("PR_REJOIN_WINDOW", 60);
+    RECONNECT_GRACE: u64 = ("PR_RECONNECT_GRACE", 0);
+    EMPTY_GAME_TIMEOUT: u64 = ("PR_EMPTY_GAME_TIMEOUT", 300);
+    GAME_ID_BASE: u32 = ("PR_GAME_ID_BASE", 1);
+    GAME_ID_RANGE: u32 = ("PR_GAME_ID_RANGE", 0);
+    MAX_QUEUE_LENGTH: usize = ("PR_MAX_QUEUE_LENGTH", 0);
+    QUEUE_ON_ATTRIBUTE_CHANGE: bool = ("PR_QUEUE_ON_ATTRIBUTE_CHANGE", true);
+    SNAPSHOT_CONCURRENCY: usize = ("PR_SNAPSHOT_CONCURRENCY", 32);
+    DRAIN_TIMEOUT: u64 = ("PR_DRAIN_TIMEOUT", 3600);
+
+    RETRIEVER: bool = ("PR_RETRIEVER", true);
+
+    ORIGIN_FETCH: bool = ("PR_ORIGIN_FETCH", true);
+    ORIGIN_FETCH_DATA: bool = ("PR_ORIGIN_FETCH_DATA", true);
 
-pub const GAW_DAILY_DECAY: (&str, f32) = ("PR_GAW_DAILY_DECAY", 0.0);
-pub const GAW_PROMOTIONS: (&str, bool) = ("PR_GAW_PROMOTIONS", true);
+    MITM_ENABLED: bool = ("PR_MITM_ENABLED", false);
 
-pub const RETRIEVER: (&str, bool) = ("PR_RETRIEVER", true);
+    LOGGING_LEVEL: LevelFilter = ("PR_LOG_LEVEL", LevelFilter::Info);
+    LOGGING_DIR: &str = ("PR_LOGGING_DIR", "data/logs");
+    LOG_COMPRESSION: bool = ("PR_LOG_COMPRESSION", true);
+    AUDIT_LOGGING_LEVEL: LevelFilter = ("PR_AUDIT_LOG_LEVEL", LevelFilter::Info);
+    DEBUG_IGNORE_COMPONENTS: &str = (
+        "PR_DEBUG_IGNORE_COMPONENTS",
+        "Util.Ping,Util.SuspendUserPing"
+    );
+    DEBUG_MINIFY_COMPONENTS: &str = (
+        "PR_DEBUG_MINIFY_COMPONENTS",
+        "Authentication.ListUserEntitlements2,Util.FetchClientConfig,Util.UserSettingsLoadAll"
+    );
 
-pub const ORIGIN_FETCH: (&str, bool) = ("PR_ORIGIN_FETCH", true);
-pub const ORIGIN_FETCH_DATA: (&str, bool) = ("PR_ORIGIN_FETCH_DATA", true);
+    API: bool = ("PR_API", false);
+    API_USERNAME: &str = ("PR_API_USERNAME", "admin");
+    API_PASSWORD: &str = ("PR_API_PASSWORD", "admin");
+    HTTP_COMPRESSION: bool = ("PR_HTTP_COMPRESSION", true);
+    HTTP_COMPRESSION_THRESHOLD: u64 = ("PR_HTTP_COMPRESSION_THRESHOLD", 1024);
+    API_ACCESS_LOG: bool = ("PR_API_ACCESS_LOG", false);
+    API_TLS_CERT: &str = ("PR_API_TLS_CERT", "");
+    API_TLS_KEY: &str = ("PR_API_TLS_KEY", "");
 
-pub const MITM_ENABLED: (&str, bool) = ("PR_MITM_ENABLED", false);
+    VALIDATE_ENV: bool = ("PR_VALIDATE_ENV", true);
 
-pub const LOGGING_LEVEL: (&str, LevelFilter) = ("PR_LOG_LEVEL", LevelFilter::Info);
-pub const LOGGING_DIR: (&str, &str) = ("PR_LOGGING_DIR", "data/logs");
-pub const LOG_COMPRESSION: (&str, bool) = ("PR_LOG_COMPRESSION", true);
+    METRICS_IGNORE_PINGS: bool = ("PR_METRICS_IGNORE_PINGS", true);
+}
 
-pub const API: (&str, bool) = ("PR_API", false);
-pub const API_USERNAME: (&str, &str) = ("PR_API_USERNAME", "admin");
-pub const API_PASSWORD: (&str, &str) = ("PR_API_PASSWORD", "admin");
+pub const DATABASE_URL: &str = "PR_DATABASE_URL";
 
 #[inline]
 pub fn env(pair: (&str, &str)) -> String {
@@ -50,8 +141,110 @@ pub fn from_env<F: FromStr>(pair: (&str, F)) -> F {
     pair.1
 }
 
+/// Checks that all the environment variables that have been set can be
+/// parsed as their expected types. Returns a list of the problems found
+/// which will be empty if the configuration is valid
+pub fn validate() -> Vec<String> {
+    validate_with(|key| std::env::var(key).ok())
+}
+
+/// Validates the environment variables using the provided function to
+/// lookup the values of the variables
+///
+/// `lookup` Function for looking up the value of a variable
+fn validate_with(lookup: impl Fn(&str) -> Option<String>) -> Vec<String> {
+    let mut problems: Vec<String> = VARIABLES
+        .iter()
+        .filter_map(|(key, valid)| {
+            let value = lookup(key)?;
+            (!valid(&value)).then(|| format!("{key} has an invalid value \"{value}\""))
+        })
+        .collect();
+
+    // The TLS certificate and key must be provided together
+    let tls_cert = lookup(API_TLS_CERT.0).filter(|value| !value.is_empty());
+    let tls_key = lookup(API_TLS_KEY.0).filter(|value| !value.is_empty());
+    if tls_cert.is_some() != tls_key.is_some() {
+        problems.push(format!(
+            "{} and {} must be set together",
+            API_TLS_CERT.0, API_TLS_KEY.0
+        ));
+    }
+
+    problems
+}
+
+/// Policy for handling a player logging in while they are already
+/// authenticated on another session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateLoginPolicy {
+    /// The new login is accepted leaving the existing sessions
+    /// authenticated as the same player
+    Allow,
+    /// The new login is rejected leaving the existing session intact
+    Reject,
+    /// The existing sessions are disconnected and the new login is
+    /// accepted
+    Takeover,
+}
+
+impl FromStr for DuplicateLoginPolicy {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "allow" => Ok(Self::Allow),
+            "reject" => Ok(Self::Reject),
+            "takeover" => Ok(Self::Takeover),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Trait implemented by the types of environment variables for checking
+/// whether a value set for a variable of that type is valid
+trait EnvValue {
+    /// Checks whether the provided value is valid for this type
+    ///
+    /// `value` The value to check
+    fn is_valid(value: &str) -> bool;
+}
+
+/// Text variables accept any value
+impl EnvValue for &str {
+    fn is_valid(_value: &str) -> bool {
+        true
+    }
+}
+
+/// Implements [`EnvValue`] for types that are valid when the value can
+/// be parsed as the type
+macro_rules! parsed_env_value {
+    ($($ty:ty),*) => {
+        $(
+            impl EnvValue for $ty {
+                fn is_valid(value: &str) -> bool {
+                    <$ty>::from_str(value).is_ok()
+                }
+            }
+        )*
+    };
+}
+
+parsed_env_value!(
+    u16,
+    u32,
+    u64,
+    usize,
+    bool,
+    f32,
+    LevelFilter,
+    DuplicateLoginPolicy
+);
+
 #[cfg(test)]
 mod test {
+    use super::{validate_with, MAIN_PORT, QOS_PORT};
     use crate::env::from_env;
 
     #[test]
@@ -71,4 +264,44 @@ mod test {
         std::env::set_var("TEST", "12");
         assert_eq!(from_env(("TEST", 0)), 12);
     }
+
+    /// Tests that invalid variables are reported by the validator while
+    /// unset and valid variables are not
+    #[test]
+    fn test_validate() {
+        let problems = validate_with(|_| None);
+        assert!(problems.is_empty());
+
+        let problems = validate_with(|key| match key {
+            "PR_MAIN_PORT" => Some("14219".to_string()),
+            "PR_QOS_PORT" => Some("not a port".to_string()),
+            "PR_NAT_CHECK" => Some("yes".to_string()),
+            _ => None,
+        });
+        assert_eq!(problems.len(), 2);
+        assert!(problems.iter().any(|value| value.contains(QOS_PORT.0)));
+        assert!(problems.iter().all(|value| !value.contains(MAIN_PORT.0)));
+        assert!(problems.iter().any(|value| value.contains("PR_NAT_CHECK")));
+    }
+
+    /// Tests that the address variables are validated using the same
+    /// rules they are parsed with
+    #[test]
+    fn test_validate_addresses() {
+        let problems = validate_with(|key| match key {
+            "PR_BIND_ADDRESS" => Some("::".to_string()),
+            "PR_PUBLIC_ADDRESS" => Some(String::new()),
+            "PR_PUBLIC_IP_FALLBACK" => Some("203.0.113.7".to_string()),
+            _ => None,
+        });
+        assert!(problems.is_empty());
+
+        let problems = validate_with(|key| match key {
+            "PR_BIND_ADDRESS" => Some("localhost".to_string()),
+            "PR_PUBLIC_ADDRESS" => Some("::1".to_string()),
+            "PR_PUBLIC_IP_FALLBACK" => Some("not an address".to_string()),
+            _ => None,
+        });
+        assert_eq!(problems.len(), 3);
+    }
 }
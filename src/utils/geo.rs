@@ -0,0 +1,109 @@
+//! Geolocation of session addresses so that sessions can report the
+//! country they are connecting from
+
+use crate::utils::env;
+use log::{error, info};
+use std::net::{IpAddr, Ipv4Addr};
+
+/// Provider for resolving the country that an address is located in
+pub trait Geolocator: Send + Sync {
+    /// Resolves the two letter country code for the provided address
+    /// returning None if the country is not known
+    ///
+    /// `ip` The address to resolve
+    fn locate(&self, ip: IpAddr) -> Option<String>;
+}
+
+/// Geolocator that doesn't resolve any addresses. Used when
+/// geolocation is not configured
+pub struct NoopGeolocator;
+
+impl Geolocator for NoopGeolocator {
+    fn locate(&self, _ip: IpAddr) -> Option<String> {
+        None
+    }
+}
+
+/// Geolocator using a list of IPv4 address ranges and the country
+/// each range is located in
+#[derive(Default)]
+pub struct RangeGeolocator {
+    /// The inclusive start and end of each range with its country
+    ranges: Vec<(u32, u32, String)>,
+}
+
+impl RangeGeolocator {
+    /// Parses the provided list of ranges with one range per line in
+    /// the format start,end,country (e.g. 1.0.0.0,1.0.0.255,AU)
+    /// skipping any lines that aren't valid
+    ///
+    /// `value` The list of ranges
+    pub fn parse(value: &str) -> Self {
+        let ranges = value
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split(',').map(|value| value.trim());
+                let start: Ipv4Addr = parts.next()?.parse().ok()?;
+                let end: Ipv4Addr = parts.next()?.parse().ok()?;
+                let country = parts.next().filter(|value| !value.is_empty())?;
+                Some((u32::from(start), u32::from(end), country.to_string()))
+            })
+            .collect();
+        Self { ranges }
+    }
+}
+
+impl Geolocator for RangeGeolocator {
+    fn locate(&self, ip: IpAddr) -> Option<String> {
+        let IpAddr::V4(ip) = ip else {
+            return None;
+        };
+        let ip = u32::from(ip);
+        self.ranges
+            .iter()
+            .find(|(start, end, _)| (*start..=*end).contains(&ip))
+            .map(|(_, _, country)| country.clone())
+    }
+}
+
+/// Creates the geolocator configured by the environment variables.
+/// When a geolocation file is set the ranges are loaded from the file
+/// otherwise addresses are not resolved
+pub fn from_env() -> Box<dyn Geolocator> {
+    let file = env::env(env::GEOLOCATION_FILE);
+    if file.is_empty() {
+        return Box::new(NoopGeolocator);
+    }
+    match std::fs::read_to_string(&file) {
+        Ok(contents) => {
+            let geolocator = RangeGeolocator::parse(&contents);
+            info!(
+                "Loaded {} geolocation ranges from {}",
+                geolocator.ranges.len(),
+                file
+            );
+            Box::new(geolocator)
+        }
+        Err(err) => {
+            error!("Unable to read geolocation file ({}): {}", file, err);
+            Box::new(NoopGeolocator)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Geolocator, RangeGeolocator};
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn test_range_geolocator() {
+        let geolocator =
+            RangeGeolocator::parse("1.0.0.0,1.0.0.255,AU\ninvalid\n8.8.8.0, 8.8.8.255, US\n");
+        let locate = |ip: [u8; 4]| geolocator.locate(IpAddr::V4(Ipv4Addr::from(ip)));
+        assert_eq!(locate([1, 0, 0, 12]), Some("AU".to_string()));
+        assert_eq!(locate([8, 8, 8, 8]), Some("US".to_string()));
+        assert_eq!(locate([127, 0, 0, 1]), None);
+        assert_eq!(geolocator.locate(IpAddr::V6(Ipv6Addr::LOCALHOST)), None);
+    }
+}
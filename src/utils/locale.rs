@@ -0,0 +1,51 @@
+//! Country and locale codes reported to clients for sessions and
+//! the telemetry server
+
+use blaze_pk::writer::TdfWriter;
+
+/// Locale reported for sessions when no locale is configured (deDE)
+pub const DEFAULT_SESSION_LOCALE: u32 = 0x64654445;
+/// Locale reported for telemetry when no locale is configured (enNZ)
+pub const DEFAULT_TELEMETRY_LOCALE: u32 = 0x656e4e5a;
+
+/// Encodes a locale string such as "enUS" into the numeric form used
+/// by clients which is the four characters as a big endian integer.
+/// Returns None if the value isn't four ASCII characters
+///
+/// `value` The locale string
+pub fn encode_locale(value: &str) -> Option<u32> {
+    let bytes: [u8; 4] = value.as_bytes().try_into().ok()?;
+    if !bytes.is_ascii() {
+        return None;
+    }
+    Some(u32::from_be_bytes(bytes))
+}
+
+/// Writes the country code as the CTY tag. The resolved country is used
+/// when present otherwise the configured country is used. The tag is left
+/// empty when no country is configured
+///
+/// `writer`     The writer to write the tag with
+/// `resolved`   The country resolved for the session
+/// `configured` The configured country
+pub fn tag_country(writer: &mut TdfWriter, resolved: Option<&str>, configured: &str) {
+    let country = resolved.unwrap_or(configured);
+    if country.is_empty() {
+        writer.tag_str_empty(b"CTY");
+    } else {
+        writer.tag_str(b"CTY", country);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encode_locale, DEFAULT_SESSION_LOCALE, DEFAULT_TELEMETRY_LOCALE};
+
+    #[test]
+    fn test_encode_locale() {
+        assert_eq!(encode_locale("deDE"), Some(DEFAULT_SESSION_LOCALE));
+        assert_eq!(encode_locale("enNZ"), Some(DEFAULT_TELEMETRY_LOCALE));
+        assert_eq!(encode_locale(""), None);
+        assert_eq!(encode_locale("enUSA"), None);
+    }
+}
@@ -1,4 +1,8 @@
-use crate::{env, utils::net::public_address};
+use crate::{
+    env,
+    servers::http::middleware::access,
+    utils::{audit, net::public_address},
+};
 use log::{info, LevelFilter};
 use log4rs::{
     append::{
@@ -17,6 +21,11 @@ use log4rs::{
 
 /// The pattern to use when logging
 const LOGGING_PATTERN: &str = "[{d} {h({l})} {M}] {m}{n}";
+/// The pattern to use when logging audit entries
+const AUDIT_LOGGING_PATTERN: &str = "{d} {m}{n}";
+/// The pattern to use when logging access entries. Entries are JSON
+/// objects which already include their timestamp
+const ACCESS_LOGGING_PATTERN: &str = "{m}{n}";
 /// Max logging file size before rolling over to the next log file. (5mb)
 const LOGGING_MAX_SIZE: u64 = 1024 * 1024 * 5;
 /// The max number of logging files to keep before deleting
@@ -59,17 +68,37 @@ pub fn setup() {
         .build(latest_path, Box::new(compound_policy))
         .expect("Unable to create logging file appender");
 
+    // Audit and access entries are written to their own rolling files
+    let audit_appender =
+        entry_file_appender(&logging_path, "audit", AUDIT_LOGGING_PATTERN, compression);
+    let access_appender =
+        entry_file_appender(&logging_path, "access", ACCESS_LOGGING_PATTERN, compression);
+
     const APPENDERS: [&str; 2] = ["stdout", "file"];
 
     let config = Config::builder()
         .appender(Appender::builder().build("stdout", Box::new(stdout_appender)))
         .appender(Appender::builder().build("file", Box::new(file_appender)))
+        .appender(Appender::builder().build("audit", Box::new(audit_appender)))
+        .appender(Appender::builder().build("access", Box::new(access_appender)))
         .logger(
             Logger::builder()
                 .appenders(APPENDERS)
                 .additive(false)
                 .build("pocket_relay", logging_level),
         )
+        .logger(
+            Logger::builder()
+                .appender("audit")
+                .additive(false)
+                .build(audit::TARGET, env::from_env(env::AUDIT_LOGGING_LEVEL)),
+        )
+        .logger(
+            Logger::builder()
+                .appender("access")
+                .additive(false)
+                .build(access::TARGET, LevelFilter::Info),
+        )
         .build(
             Root::builder()
                 .appenders(APPENDERS)
@@ -80,6 +109,39 @@ pub fn setup() {
     init_config(config).expect("Unable to initialize logger");
 }
 
+/// Creates a rolling file appender for writing entries such as audit
+/// or access entries to their own log file
+///
+/// `logging_path` The directory to store the log files in
+/// `name`         The name of the log file
+/// `pattern`      The pattern to log the entries with
+/// `compression`  Whether to compress the rolled log files
+fn entry_file_appender(
+    logging_path: &str,
+    name: &str,
+    pattern: &str,
+    compression: bool,
+) -> RollingFileAppender {
+    let mut file_pattern = format!("{}/{}-{{}}.log", logging_path, name);
+    if compression {
+        file_pattern.push_str(".gz")
+    }
+
+    let roller = FixedWindowRoller::builder()
+        .build(&file_pattern, LOGGING_MAX_FILES)
+        .expect("Unable to create fixed window entry log roller");
+
+    let policy = CompoundPolicy::new(
+        Box::new(SizeTrigger::new(LOGGING_MAX_SIZE)),
+        Box::new(roller),
+    );
+
+    RollingFileAppender::builder()
+        .encoder(Box::new(PatternEncoder::new(pattern)))
+        .build(format!("{}/{}.log", logging_path, name), Box::new(policy))
+        .expect("Unable to create entry logging file appender")
+}
+
 /// Prints a list of possible urls that can be used to connect to
 /// this Pocket relay server
 pub async fn log_connection_urls() {
@@ -116,3 +178,52 @@ pub async fn log_connection_urls() {
 
     info!("Connection URLS ({output})");
 }
+
+/// Logger used by tests to capture logged entries so that they can
+/// be checked. Only one logger can be set so tests share this one.
+#[cfg(test)]
+pub mod capture {
+    use log::{LevelFilter, Log, Metadata, Record};
+    use std::sync::Mutex;
+
+    /// Logger storing the target and message of each logged entry
+    struct CaptureLogger(Mutex<Vec<(String, String)>>);
+
+    impl Log for CaptureLogger {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            if let Ok(mut entries) = self.0.lock() {
+                entries.push((record.target().to_string(), record.args().to_string()));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CaptureLogger = CaptureLogger(Mutex::new(Vec::new()));
+
+    /// Sets the capture logger as the global logger if it hasn't
+    /// already been set
+    pub fn install() {
+        log::set_logger(&LOGGER).ok();
+        log::set_max_level(LevelFilter::Info);
+    }
+
+    /// Returns the messages of all the captured entries for the
+    /// provided target
+    ///
+    /// `target` The logging target to get the entries for
+    pub fn entries(target: &str) -> Vec<String> {
+        LOGGER
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(entry_target, _)| entry_target == target)
+            .map(|(_, message)| message.clone())
+            .collect()
+    }
+}
@@ -1,14 +1,20 @@
+pub mod audit;
 #[allow(clippy::enum_variant_names)]
 pub mod components;
 pub mod constants;
+pub mod decay;
 pub mod dmap;
 pub mod env;
+pub mod geo;
 pub mod hashing;
+pub mod locale;
 pub mod logging;
 pub mod models;
 pub mod net;
 pub mod packet;
 pub mod parsing;
 pub mod random;
+pub mod retry;
+pub mod time;
 pub mod types;
 pub mod validate;
@@ -1,9 +1,9 @@
 use crate::utils::types::PlayerID;
 use blaze_pk::{
     codec::{Decodable, Encodable},
-    error::DecodeResult,
+    error::{DecodeError, DecodeResult},
     reader::TdfReader,
-    tag::TdfType,
+    tag::{Tag, TdfType},
     types::Union,
     value_type,
     writer::TdfWriter,
@@ -11,7 +11,7 @@ use blaze_pk::{
 use serde::{ser::SerializeStruct, Serialize};
 use std::{
     fmt::{Debug, Display},
-    net::Ipv4Addr,
+    net::{Ipv4Addr, Ipv6Addr},
 };
 
 /// Networking information for an instance. Contains the
@@ -122,7 +122,7 @@ impl Decodable for InstanceDetails {
         let net: InstanceNet = match reader.tag::<Union<InstanceNet>>("ADDR")? {
             Union::Set { value, .. } => value,
             Union::Unset => {
-                return Err(blaze_pk::error::DecodeError::MissingTag {
+                return Err(DecodeError::MissingTag {
                     tag: "ADDR".to_string(),
                     ty: TdfType::Union,
                 })
@@ -196,6 +196,49 @@ pub struct QosNetworkData {
     pub ubps: u16,
 }
 
+/// Category for the quality of a connection derived from the
+/// bandwidth and NAT type reported by the client
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionQuality {
+    Good,
+    Fair,
+    Poor,
+    /// The client hasn't reported its network details
+    Unknown,
+}
+
+impl QosNetworkData {
+    /// Upstream bandwidth at or above which the bandwidth is
+    /// considered high
+    const HIGH_BANDWIDTH: u16 = 500;
+    /// Upstream bandwidth at or above which the bandwidth is
+    /// considered moderate
+    const MODERATE_BANDWIDTH: u16 = 100;
+
+    /// Scores the quality of the connection from the NAT type and the
+    /// upstream bandwidth. Each contributes up to two points where an
+    /// open NAT and high bandwidth are worth the most. Three or more
+    /// points are good, two points are fair and anything lower is poor
+    pub fn quality(&self) -> ConnectionQuality {
+        let nat_score = match self.natt {
+            NatType::Open => 2,
+            NatType::Moderate => 1,
+            _ => 0,
+        };
+        let bandwidth_score = match self.ubps {
+            value if value >= Self::HIGH_BANDWIDTH => 2,
+            value if value >= Self::MODERATE_BANDWIDTH => 1,
+            _ => 0,
+        };
+        match nat_score + bandwidth_score {
+            3.. => ConnectionQuality::Good,
+            2 => ConnectionQuality::Fair,
+            _ => ConnectionQuality::Poor,
+        }
+    }
+}
+
 //
 #[derive(Debug, Copy, Clone, Serialize)]
 pub enum NatType {
@@ -280,6 +323,14 @@ pub struct NetData {
     pub qos: QosNetworkData,
     pub hardware_flags: u16,
     pub is_set: bool,
+    /// The latency to the configured ping site if the client
+    /// has reported it
+    #[serde(skip)]
+    pub pslm: Option<u32>,
+    /// The country the session is connecting from if it was
+    /// resolved using geolocation
+    #[serde(skip)]
+    pub country: Option<String>,
 }
 
 #[derive(Debug, Default, Clone, Serialize)]
@@ -312,6 +363,28 @@ impl Decodable for NetGroups {
 value_type!(NetGroups, TdfType::Group);
 
 impl NetData {
+    /// The ping site latency used when the client hasn't reported
+    /// its latency
+    const UNKNOWN_PSLM: u32 = 0xfff0fff;
+
+    /// Obtains the quality of the connection from the reported network
+    /// details. Unknown if the network details haven't been reported
+    pub fn quality(&self) -> ConnectionQuality {
+        if !self.is_set {
+            return ConnectionQuality::Unknown;
+        }
+        self.qos.quality()
+    }
+
+    /// Writes the ping site latency list with the latency to the
+    /// configured ping site
+    ///
+    /// `writer` The writer to write to
+    pub fn tag_pslm(&self, writer: &mut TdfWriter) {
+        writer.tag_list_start(b"PSLM", TdfType::VarInt, 1);
+        writer.write_u32(self.pslm.unwrap_or(Self::UNKNOWN_PSLM));
+    }
+
     pub fn tag_groups(&self, tag: &[u8], writer: &mut TdfWriter) {
         if !self.is_set {
             writer.tag_union_unset(tag);
@@ -322,13 +395,19 @@ impl NetData {
 }
 
 /// Structure for a networking group which consists of a
-/// networking address and port value
+/// networking address and port value along with an optional
+/// IPv6 address for players connecting over IPv6
 #[derive(Debug, Clone, Default, Eq, PartialEq)]
-pub struct NetGroup(pub NetAddress, pub Port);
+pub struct NetGroup(pub NetAddress, pub Port, pub Option<Ipv6Addr>);
 
 impl Encodable for NetGroup {
     fn encode(&self, writer: &mut TdfWriter) {
         writer.tag_value(b"IP", &self.0);
+        // The IPv6 address is only included when present so that
+        // the group matches what older clients expect
+        if let Some(ipv6) = &self.2 {
+            writer.tag_str(b"IPV6", &ipv6.to_string());
+        }
         writer.tag_u16(b"PORT", self.1);
         writer.tag_group_end();
     }
@@ -337,9 +416,31 @@ impl Encodable for NetGroup {
 impl Decodable for NetGroup {
     fn decode(reader: &mut TdfReader) -> DecodeResult<Self> {
         let ip: NetAddress = reader.tag("IP")?;
-        let port: u16 = reader.tag("PORT")?;
+        let mut port: Option<u16> = None;
+        let mut ipv6: Option<String> = None;
+
+        // The remaining tags are read up to the end of the group so that
+        // searching for the optional IPv6 address can't read past the group
+        while reader
+            .buffer
+            .get(reader.cursor)
+            .map_or(false, |value| *value != 0)
+        {
+            let Tag(tag, ty) = reader.read_tag()?;
+            match (tag.as_str(), &ty) {
+                ("PORT", TdfType::VarInt) => port = Some(reader.read_u16()?),
+                ("IPV6", TdfType::String) => ipv6 = Some(reader.read_string()?),
+                _ => reader.skip_type(&ty)?,
+            }
+        }
         reader.read_byte()?;
-        Ok(Self(ip, port))
+
+        let port = port.ok_or_else(|| DecodeError::MissingTag {
+            tag: "PORT".to_string(),
+            ty: TdfType::VarInt,
+        })?;
+        let ipv6 = ipv6.and_then(|value| value.parse().ok());
+        Ok(Self(ip, port, ipv6))
     }
 }
 
@@ -350,9 +451,14 @@ impl Serialize for NetGroup {
     where
         S: serde::Serializer,
     {
-        let mut s = serializer.serialize_struct("NetGroup", 2)?;
+        let mut s = serializer.serialize_struct("NetGroup", 3)?;
         s.serialize_field("address", &self.0)?;
         s.serialize_field("port", &self.1)?;
+        if let Some(ipv6) = &self.2 {
+            s.serialize_field("ipv6", ipv6)?;
+        } else {
+            s.skip_field("ipv6")?;
+        }
         s.end()
     }
 }
@@ -414,3 +520,83 @@ impl Serialize for NetAddress {
         serializer.serialize_str(&value)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{ConnectionQuality, NatType, NetAddress, NetGroup, NetGroups, QosNetworkData};
+    use blaze_pk::{reader::TdfReader, writer::TdfWriter};
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    /// Encodes the provided groups and decodes them again
+    fn round_trip(groups: &NetGroups) -> NetGroups {
+        let mut writer = TdfWriter::default();
+        writer.tag_value(b"ADDR", groups);
+        let mut reader = TdfReader::new(&writer.buffer);
+        reader.tag("ADDR").unwrap()
+    }
+
+    #[test]
+    fn test_ipv6_group() {
+        let groups = NetGroups {
+            internal: NetGroup(NetAddress(Ipv4Addr::new(192, 168, 0, 2)), 3659, None),
+            external: NetGroup(
+                NetAddress(Ipv4Addr::UNSPECIFIED),
+                3659,
+                Some(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+            ),
+        };
+
+        let decoded = round_trip(&groups);
+        assert_eq!(decoded.internal, groups.internal);
+        assert_eq!(decoded.external, groups.external);
+    }
+
+    /// Tests that a group without an IPv6 address decodes when it is
+    /// followed by other groups and tags
+    #[test]
+    fn test_group_without_ipv6() {
+        let groups = NetGroups {
+            internal: NetGroup(
+                NetAddress(Ipv4Addr::new(192, 168, 0, 2)),
+                3659,
+                Some(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2)),
+            ),
+            external: NetGroup(NetAddress(Ipv4Addr::new(203, 0, 113, 5)), 3658, None),
+        };
+
+        let mut writer = TdfWriter::default();
+        writer.tag_value(b"ADDR", &groups);
+        writer.tag_u16(b"PORT", 9000);
+        writer.tag_str(b"TEXT", "After");
+
+        let mut reader = TdfReader::new(&writer.buffer);
+        let decoded: NetGroups = reader.tag("ADDR").unwrap();
+        assert_eq!(decoded.external, groups.external);
+        assert_eq!(decoded.internal, groups.internal);
+
+        // Tags following the groups are still readable
+        let port: u16 = reader.tag("PORT").unwrap();
+        assert_eq!(port, 9000);
+        let text: String = reader.tag("TEXT").unwrap();
+        assert_eq!(text, "After");
+    }
+
+    #[test]
+    fn test_connection_quality() {
+        let quality = |natt, ubps| {
+            QosNetworkData {
+                dbps: 0,
+                natt,
+                ubps,
+            }
+            .quality()
+        };
+        assert_eq!(quality(NatType::Open, 800), ConnectionQuality::Good);
+        assert_eq!(quality(NatType::Moderate, 500), ConnectionQuality::Good);
+        assert_eq!(quality(NatType::Open, 50), ConnectionQuality::Fair);
+        assert_eq!(quality(NatType::Moderate, 200), ConnectionQuality::Fair);
+        assert_eq!(quality(NatType::Strict, 800), ConnectionQuality::Fair);
+        assert_eq!(quality(NatType::Sequential, 200), ConnectionQuality::Poor);
+        assert_eq!(quality(NatType::Strict, 50), ConnectionQuality::Poor);
+    }
+}
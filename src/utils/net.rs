@@ -1,12 +1,65 @@
 use std::{
-    net::{IpAddr, Ipv4Addr},
+    net::{AddrParseError, IpAddr, Ipv4Addr},
+    sync::atomic::{AtomicBool, Ordering},
     time::{Duration, SystemTime},
 };
 
+use crate::env;
+use log::warn;
 use reqwest;
 use serde::Deserialize;
 use tokio::sync::RwLock;
 
+/// Obtains the address that the servers should bind to from the
+/// environment variables.
+pub fn bind_address() -> IpAddr {
+    parse_bind_address(&env::env(env::BIND_ADDRESS))
+}
+
+/// Checks whether the provided value is a valid bind address
+///
+/// `value` The address to check
+pub fn is_valid_bind_address(value: &str) -> bool {
+    value.parse::<IpAddr>().is_ok()
+}
+
+/// Checks whether the provided value is a valid public address. Empty
+/// values are valid as the address is optional
+///
+/// `value` The address to check
+pub fn is_valid_public_address(value: &str) -> bool {
+    parse_public_address(value).is_ok()
+}
+
+/// Parses the provided public address returning None if the value is
+/// empty or an error if the address is invalid
+///
+/// `value` The address to parse
+fn parse_public_address(value: &str) -> Result<Option<Ipv4Addr>, AddrParseError> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value.parse().map(Some)
+}
+
+/// Parses the provided bind address. Invalid addresses are logged
+/// and the default address is used instead.
+///
+/// `value` The address to parse
+fn parse_bind_address(value: &str) -> IpAddr {
+    match value.parse() {
+        Ok(value) => value,
+        Err(_) => {
+            warn!(
+                "Invalid bind address \"{}\" falling back to {}",
+                value,
+                env::BIND_ADDRESS.1
+            );
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        }
+    }
+}
+
 /// Caching structure for the public address value
 enum PublicAddrCache {
     /// The value hasn't yet been computed
@@ -26,10 +79,15 @@ static PUBLIC_ADDR_CACHE: RwLock<PublicAddrCache> = RwLock::const_new(PublicAddr
 /// Cache public address for 2 hours
 const ADDR_CACHE_TIME: Duration = Duration::from_secs(60 * 60 * 2);
 
-/// Retrieves the public address of the server either using the cached
-/// value if its not expired or fetching the new value from the API using
-/// `fetch_public_addr`
+/// Retrieves the public address of the server. The address from the
+/// environment variables is used if one is configured otherwise the
+/// cached value is used if its not expired or the new value is fetched
+/// from the API
 pub async fn public_address() -> Option<Ipv4Addr> {
+    if let Some(value) = configured_public_address() {
+        return Some(value);
+    }
+
     {
         let cached = &*PUBLIC_ADDR_CACHE.read().await;
         match cached {
@@ -60,15 +118,7 @@ pub async fn public_address() -> Option<Ipv4Addr> {
         }
     }
 
-    // If we couldn't connect to any IP services its likely
-    // we don't have internet lets try using our local address
-    if value.is_none() {
-        if let Ok(IpAddr::V4(addr)) = local_ip_address::local_ip() {
-            value = Some(addr)
-        }
-    }
-
-    let value = value?;
+    let value = fallback_address(value, configured_fallback_address, local_address)?;
 
     // Update cached value with the new address
     {
@@ -82,6 +132,74 @@ pub async fn public_address() -> Option<Ipv4Addr> {
     Some(value)
 }
 
+/// Whether the warning for the public address lookup failing has
+/// already been logged
+static LOOKUP_FAILED_WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Chooses the public address to use from the result of the lookup. When
+/// the lookup failed the configured fallback address is used and a warning
+/// is logged the first time. If there is no fallback configured then its
+/// likely we don't have internet so the local address is used instead
+///
+/// `lookup`   The address from the lookup if it succeeded
+/// `fallback` Function for obtaining the configured fallback address
+/// `local`    Function for obtaining the local address
+fn fallback_address(
+    lookup: Option<Ipv4Addr>,
+    fallback: impl FnOnce() -> Option<Ipv4Addr>,
+    local: impl FnOnce() -> Option<Ipv4Addr>,
+) -> Option<Ipv4Addr> {
+    if lookup.is_some() {
+        return lookup;
+    }
+    let fallback = fallback();
+    if !LOOKUP_FAILED_WARNED.swap(true, Ordering::Relaxed) {
+        match fallback {
+            Some(value) => warn!("Failed to lookup public address using fallback {}", value),
+            None => warn!(
+                "Failed to lookup public address and no fallback is configured (See {}), \
+                 players outside your network may be unable to connect",
+                env::PUBLIC_IP_FALLBACK.0
+            ),
+        }
+    }
+    fallback.or_else(local)
+}
+
+/// Obtains the local IPv4 address of the machine
+fn local_address() -> Option<Ipv4Addr> {
+    match local_ip_address::local_ip() {
+        Ok(IpAddr::V4(addr)) => Some(addr),
+        _ => None,
+    }
+}
+
+/// Obtains the fallback public address from the environment variables.
+/// Invalid addresses are logged and ignored.
+fn configured_fallback_address() -> Option<Ipv4Addr> {
+    let value = env::env(env::PUBLIC_IP_FALLBACK);
+    match parse_public_address(&value) {
+        Ok(value) => value,
+        Err(_) => {
+            warn!("Invalid public address fallback \"{}\" ignoring", value);
+            None
+        }
+    }
+}
+
+/// Obtains the public address override from the environment variables.
+/// Invalid addresses are logged and ignored.
+fn configured_public_address() -> Option<Ipv4Addr> {
+    let value = env::env(env::PUBLIC_ADDRESS);
+    match parse_public_address(&value) {
+        Ok(value) => value,
+        Err(_) => {
+            warn!("Invalid public address \"{}\" ignoring override", value);
+            None
+        }
+    }
+}
+
 /// Structure for the lookup responses from the google DNS API
 ///
 /// # Structure
@@ -168,3 +286,41 @@ async fn lookup_tokio(value: &str) -> Option<String> {
 
     Some(format!("{}", ip))
 }
+
+#[cfg(test)]
+mod test {
+    use super::{fallback_address, parse_bind_address};
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[test]
+    fn test_bind_address() {
+        assert_eq!(
+            parse_bind_address("127.0.0.1"),
+            IpAddr::V4(Ipv4Addr::LOCALHOST)
+        );
+        assert!(parse_bind_address("::1").is_ipv6());
+        assert_eq!(
+            parse_bind_address("not an address"),
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        );
+    }
+
+    /// Tests that the configured fallback is used when the lookup fails
+    /// before falling back to the local address
+    #[test]
+    fn test_fallback_address() {
+        let public = Ipv4Addr::new(203, 0, 113, 5);
+        let fallback = Ipv4Addr::new(198, 51, 100, 7);
+        let local = Ipv4Addr::new(192, 168, 0, 10);
+
+        assert_eq!(
+            fallback_address(Some(public), || Some(fallback), || Some(local)),
+            Some(public)
+        );
+        assert_eq!(
+            fallback_address(None, || Some(fallback), || Some(local)),
+            Some(fallback)
+        );
+        assert_eq!(fallback_address(None, || None, || Some(local)), Some(local));
+    }
+}
@@ -1,13 +1,21 @@
 use blaze_pk::{packet::Packet, reader::TdfReader};
+use std::{io, time::Duration};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    time::timeout,
+};
+
+/// The size of the fixed portion of a packet header
+const HEADER_SIZE: usize = 12;
 
 /// Decodes the provided packet into its string representation and appends
 /// the value to the provided output prefixed by Content: if an error
 /// occurs while decoding the raw values and decoding error will
-/// be appended to the output
+/// be appended to the output. Returns whether the contents decoded
 ///
 /// `packet` The packet to decode
 /// `output` The output to append to
-pub fn append_packet_decoded(packet: &Packet, output: &mut String) {
+pub fn append_packet_decoded(packet: &Packet, output: &mut String) -> bool {
     let mut reader = TdfReader::new(&packet.contents);
     let mut out = String::new();
     out.push_str("{\n");
@@ -19,7 +27,7 @@ pub fn append_packet_decoded(packet: &Packet, output: &mut String) {
         output.push_str(&out);
 
         output.push_str(&format!("\nRaw: {:?}", &packet.contents));
-        return;
+        return false;
     }
     if out.len() == 2 {
         // Remove new line if nothing else was appended
@@ -28,4 +36,237 @@ pub fn append_packet_decoded(packet: &Packet, output: &mut String) {
     out.push('}');
     output.push_str("\nContent: ");
     output.push_str(&out);
+    true
+}
+
+/// Reads a packet from the provided input ensuring that the length of the
+/// packet contents doesn't exceed the provided maximum size. The length
+/// is checked using the header before the contents are read so that
+/// oversized packets are never buffered.
+///
+/// `input`    The input to read from
+/// `max_size` The maximum allowed length of the packet contents
+pub async fn read_packet<R: AsyncRead + Unpin>(
+    input: &mut R,
+    max_size: usize,
+) -> io::Result<Packet> {
+    let mut buffer = vec![0u8; HEADER_SIZE];
+    input.read_exact(&mut buffer).await?;
+
+    let mut length = u16::from_be_bytes([buffer[0], buffer[1]]) as usize;
+
+    // Packets with the extended length flag have an additional
+    // two bytes of length after the header
+    if buffer[9] & 0x10 == 0x10 {
+        let mut ext = [0u8; 2];
+        input.read_exact(&mut ext).await?;
+        length += (u16::from_be_bytes(ext) as usize) << 16;
+        buffer.extend_from_slice(&ext);
+    }
+
+    if length > max_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Packet length {length} exceeds maximum of {max_size}"),
+        ));
+    }
+
+    let offset = buffer.len();
+    buffer.resize(offset + length, 0);
+    input.read_exact(&mut buffer[offset..]).await?;
+
+    Packet::read_async(&mut buffer.as_slice()).await
+}
+
+/// Writes all the provided packets to the output using a single write.
+/// The packets are encoded in order into one buffer before writing to
+/// reduce the number of writes to the underlying stream.
+///
+/// `output`  The output to write to
+/// `packets` The packets to write
+pub async fn write_packets<W: AsyncWrite + Unpin>(
+    output: &mut W,
+    packets: &[Packet],
+) -> io::Result<()> {
+    let mut buffer: Vec<u8> = Vec::new();
+    for packet in packets {
+        packet.write_async(&mut buffer).await?;
+    }
+    output.write_all(&buffer).await
+}
+
+/// Appends the raw contents of the provided packet to the output
+/// as hex prefixed by Raw:
+///
+/// `packet` The packet to append
+/// `output` The output to append to
+pub fn append_packet_raw(packet: &Packet, output: &mut String) {
+    output.push_str("\nRaw: ");
+    for byte in packet.contents.iter() {
+        output.push_str(&format!("{:02X}", byte));
+    }
+}
+
+/// Writes the provided packets using `write_packets` failing with a
+/// timed out error if the write doesn't complete within the provided
+/// duration. No timeout is applied when the duration is None
+///
+/// `output`   The output to write to
+/// `packets`  The packets to write
+/// `duration` The max time to wait for the write
+pub async fn write_packets_timeout<W: AsyncWrite + Unpin>(
+    output: &mut W,
+    packets: &[Packet],
+    duration: Option<Duration>,
+) -> io::Result<()> {
+    let Some(duration) = duration else {
+        return write_packets(output, packets).await;
+    };
+    match timeout(duration, write_packets(output, packets)).await {
+        Ok(result) => result,
+        Err(_) => Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "Timed out while writing packets",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_packet, write_packets, write_packets_timeout};
+    use std::{
+        io,
+        pin::Pin,
+        task::{Context, Poll},
+        time::Duration,
+    };
+    use tokio::io::AsyncWrite;
+
+    /// Creates the raw bytes for a request packet with the
+    /// provided number of bytes of contents
+    fn create_packet(length: u16) -> Vec<u8> {
+        let mut output = Vec::new();
+        // Length
+        output.extend_from_slice(&length.to_be_bytes());
+        // Component, command and error
+        output.extend_from_slice(&[0x0, 0x9, 0x0, 0x2, 0x0, 0x0]);
+        // Type, flags and ID
+        output.extend_from_slice(&[0x0, 0x0, 0x0, 0x1]);
+        // Contents
+        output.extend(std::iter::repeat(0u8).take(length as usize));
+        output
+    }
+
+    #[tokio::test]
+    async fn test_read_packet() {
+        let bytes = create_packet(32);
+        let packet = read_packet(&mut bytes.as_slice(), 64).await.unwrap();
+        assert_eq!(packet.contents.len(), 32);
+    }
+
+    #[tokio::test]
+    async fn test_read_packet_too_large() {
+        let bytes = create_packet(128);
+        let err = read_packet(&mut bytes.as_slice(), 64).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    /// Writer which counts the number of writes made to it
+    #[derive(Default)]
+    struct CountingWriter {
+        /// The number of writes
+        writes: usize,
+        /// The bytes that were written
+        output: Vec<u8>,
+    }
+
+    impl AsyncWrite for CountingWriter {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.writes += 1;
+            self.output.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_packets() {
+        let mut packets = Vec::new();
+        let mut total_length = 0;
+        for length in [8, 16, 32] {
+            let bytes = create_packet(length);
+            total_length += bytes.len();
+            packets.push(read_packet(&mut bytes.as_slice(), 64).await.unwrap());
+        }
+
+        let mut writer = CountingWriter::default();
+        write_packets(&mut writer, &packets).await.unwrap();
+        assert_eq!(writer.writes, 1);
+        assert_eq!(writer.output.len(), total_length);
+
+        // Packets must be written in order
+        let mut output = writer.output.as_slice();
+        for length in [8, 16, 32] {
+            let packet = read_packet(&mut output, 64).await.unwrap();
+            assert_eq!(packet.contents.len(), length);
+        }
+    }
+
+    /// Writer which never accepts any bytes like a client that has
+    /// stopped reading from its connection
+    struct BlockedWriter;
+
+    impl AsyncWrite for BlockedWriter {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            _buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            Poll::Pending
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Pending
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Pending
+        }
+    }
+
+    /// Tests that writes to a blocked writer fail once the timeout
+    /// has passed
+    #[tokio::test]
+    async fn test_write_timeout() {
+        let packets = vec![read_packet(&mut create_packet(8).as_slice(), 64)
+            .await
+            .unwrap()];
+
+        let err = write_packets_timeout(
+            &mut BlockedWriter,
+            &packets,
+            Some(Duration::from_millis(10)),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        // Writers that aren't blocked are unaffected
+        let mut writer = CountingWriter::default();
+        write_packets_timeout(&mut writer, &packets, Some(Duration::from_millis(10)))
+            .await
+            .unwrap();
+        assert_eq!(writer.writes, 1);
+    }
 }
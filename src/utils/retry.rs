@@ -0,0 +1,120 @@
+//! Retrying for database queries on the hot paths such as authentication
+//! so that transient connection errors don't immediately fail the request
+
+use crate::utils::env;
+use database::{DbErr, DbResult, RuntimeErr, SqlxError};
+use log::warn;
+use std::{future::Future, time::Duration};
+use tokio::time::sleep;
+
+/// Runs the provided database query retrying it when it fails with a
+/// connection level error using the retry attempts and backoff from
+/// the environment variables
+///
+/// `query` Function creating the query future for each attempt
+pub async fn retry_db<F, Fut, T>(query: F) -> DbResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = DbResult<T>>,
+{
+    let attempts = env::from_env(env::DATABASE_RETRY_ATTEMPTS);
+    let backoff = Duration::from_millis(env::from_env(env::DATABASE_RETRY_BACKOFF));
+    retry_with(query, attempts, backoff).await
+}
+
+/// Runs the provided database query retrying it up to the provided
+/// number of attempts when it fails with a connection level error.
+/// The time waited between each attempt increases by the backoff
+/// after each attempt. Other errors are returned without retrying.
+///
+/// `query`    Function creating the query future for each attempt
+/// `attempts` The max number of attempts
+/// `backoff`  The base time to wait between attempts
+async fn retry_with<F, Fut, T>(mut query: F, attempts: u32, backoff: Duration) -> DbResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = DbResult<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match query().await {
+            Err(err) if attempt < attempts && is_connection_error(&err) => {
+                warn!(
+                    "Database query failed (Attempt {}/{}): {:?}",
+                    attempt, attempts, err
+                );
+                sleep(backoff * attempt).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Checks whether the provided error is a connection level error
+/// that could succeed if the query was attempted again. This includes
+/// failing to acquire a pooled connection and queries that failed due
+/// to IO errors or the pool timing out.
+///
+/// `err` The error to check
+fn is_connection_error(err: &DbErr) -> bool {
+    match err {
+        DbErr::Conn(_) | DbErr::ConnectionAcquire => true,
+        DbErr::Query(RuntimeErr::SqlxError(err)) | DbErr::Exec(RuntimeErr::SqlxError(err)) => {
+            matches!(
+                err,
+                SqlxError::Io(_) | SqlxError::PoolTimedOut | SqlxError::PoolClosed
+            )
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_connection_error, retry_with};
+    use database::{DbErr, RuntimeErr, SqlxError};
+    use std::{
+        cell::Cell,
+        io::{Error, ErrorKind},
+        time::Duration,
+    };
+
+    #[test]
+    fn test_connection_errors() {
+        let io = || SqlxError::Io(Error::new(ErrorKind::ConnectionReset, "Connection reset"));
+        assert!(is_connection_error(&DbErr::ConnectionAcquire));
+        assert!(is_connection_error(&DbErr::Query(RuntimeErr::SqlxError(
+            io()
+        ))));
+        assert!(is_connection_error(&DbErr::Exec(RuntimeErr::SqlxError(
+            SqlxError::PoolTimedOut
+        ))));
+
+        // Errors from the query itself are not connection errors
+        assert!(!is_connection_error(&DbErr::Query(RuntimeErr::SqlxError(
+            SqlxError::RowNotFound
+        ))));
+        assert!(!is_connection_error(&DbErr::Query(RuntimeErr::Internal(
+            "Syntax error".to_string()
+        ))));
+    }
+
+    #[tokio::test]
+    async fn test_retry_other_error() {
+        let calls = Cell::new(0);
+        let result: Result<(), DbErr> = retry_with(
+            || async {
+                calls.set(calls.get() + 1);
+                Err(DbErr::RecordNotFound("Player".to_string()))
+            },
+            3,
+            Duration::from_millis(1),
+        )
+        .await;
+
+        // Logical errors are not retried
+        assert_eq!(calls.get(), 1);
+        assert!(result.is_err());
+    }
+}
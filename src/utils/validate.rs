@@ -1,3 +1,5 @@
+use crate::utils::env;
+use log::{error, info};
 use regex::Regex;
 
 /// Validates an email checking it against the email regex
@@ -8,3 +10,106 @@ pub fn is_email(email: &str) -> bool {
     .unwrap();
     regex.is_match(email)
 }
+
+/// Validates a display name ensuring it is between 1 and 32 characters
+/// long and only contains letters, numbers, spaces, underscores, dashes
+/// and periods. Leading and trailing spaces are not allowed.
+pub fn is_display_name(display_name: &str) -> bool {
+    let length = display_name.chars().count();
+    (1..=32).contains(&length)
+        && display_name.trim() == display_name
+        && display_name
+            .chars()
+            .all(|value| value.is_ascii_alphanumeric() || matches!(value, ' ' | '_' | '-' | '.'))
+}
+
+/// List of lowercase substrings that aren't allowed to be used
+/// within display names
+#[derive(Default)]
+pub struct BlockedNames {
+    /// The blocked substrings
+    words: Vec<String>,
+}
+
+impl BlockedNames {
+    /// Creates the blocked names from the provided comma seperated list
+    ///
+    /// `value` The list of blocked names
+    pub fn new(value: &str) -> Self {
+        Self {
+            words: Self::parse(value, ','),
+        }
+    }
+
+    /// Loads the blocked names from the comma seperated list in the
+    /// environment variables along with the blocked names file which
+    /// contains one blocked name per line if one is set
+    pub fn from_env() -> Self {
+        let mut words = Self::parse(&env::env(env::BLOCKED_NAMES), ',');
+
+        let file = env::env(env::BLOCKED_NAMES_FILE);
+        if !file.is_empty() {
+            match std::fs::read_to_string(&file) {
+                Ok(contents) => {
+                    let file_words = Self::parse(&contents, '\n');
+                    info!("Loaded {} blocked names from {}", file_words.len(), file);
+                    words.extend(file_words);
+                }
+                Err(err) => error!("Unable to read blocked names file ({}): {}", file, err),
+            }
+        }
+
+        Self { words }
+    }
+
+    /// Parses the blocked names from the provided list seperated by
+    /// the provided seperator ignoring empty values
+    ///
+    /// `value`     The list to parse
+    /// `seperator` The seperator between the names
+    fn parse(value: &str, seperator: char) -> Vec<String> {
+        value
+            .split(seperator)
+            .map(|value| value.trim().to_lowercase())
+            .filter(|value| !value.is_empty())
+            .collect()
+    }
+
+    /// Checks whether the provided display name contains any of the
+    /// blocked names ignoring case
+    ///
+    /// `display_name` The display name to check
+    pub fn is_blocked(&self, display_name: &str) -> bool {
+        if self.words.is_empty() {
+            return false;
+        }
+        let display_name = display_name.to_lowercase();
+        self.words
+            .iter()
+            .any(|word| display_name.contains(word.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_display_name, BlockedNames};
+
+    #[test]
+    fn test_display_name() {
+        assert!(is_display_name("Jacobtread"));
+        assert!(is_display_name("Test_Player-1.2"));
+        assert!(!is_display_name(""));
+        assert!(!is_display_name(" Padded "));
+        assert!(!is_display_name("Invalid<Name>"));
+        assert!(!is_display_name(&"a".repeat(33)));
+    }
+
+    #[test]
+    fn test_blocked_names() {
+        let blocked = BlockedNames::new("badword, Rude ,");
+        assert!(blocked.is_blocked("Player_BadWord_1"));
+        assert!(blocked.is_blocked("rUdE"));
+        assert!(!blocked.is_blocked("Jacobtread"));
+        assert!(!BlockedNames::default().is_blocked("badword"));
+    }
+}